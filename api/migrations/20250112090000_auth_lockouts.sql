@@ -0,0 +1,14 @@
+-- Add migration script here
+CREATE TABLE auth_lockouts (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    scope VARCHAR(20) NOT NULL,  -- account or ip
+    identifier VARCHAR(255) NOT NULL,  -- user id or ip address
+    failed_attempts INTEGER NOT NULL DEFAULT 0,
+    last_failed_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    locked_until TIMESTAMP WITH TIME ZONE,
+    UNIQUE(scope, identifier)
+);
+
+CREATE INDEX idx_auth_logs_user_id ON auth_logs(user_id);
+CREATE INDEX idx_auth_logs_ip_address ON auth_logs(ip_address);
+CREATE INDEX idx_auth_logs_created_at ON auth_logs(created_at);
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- set when a signed-in user starts linking a provider, the callback must come from the same user
+ALTER TABLE oauth_states ADD COLUMN link_user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+-- github accounts become identities, users.github_id is kept as a profile field only
+INSERT INTO user_identities (user_id, provider, subject, email, linked_at)
+SELECT id, 'github', github_id, email, created_at
+FROM users
+WHERE github_id IS NOT NULL
+ON CONFLICT (provider, subject) DO NOTHING;
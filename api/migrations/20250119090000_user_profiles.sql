@@ -0,0 +1,25 @@
+-- Add migration script here
+ALTER TABLE users
+ADD COLUMN display_name VARCHAR(100),
+ADD COLUMN pending_email VARCHAR(255);
+
+-- issues and comments of deleted accounts are handed to this placeholder. it has no
+-- password, no identities and an undeliverable, unverified address, so nobody can sign in as it
+INSERT INTO users (id, email, username, is_email_verified, display_name)
+VALUES (
+    '00000000-0000-0000-0000-000000000000',
+    'deleted-user@issueapp.invalid',
+    '[deleted]',
+    FALSE,
+    'Deleted user'
+)
+ON CONFLICT DO NOTHING;
+
+-- deleting a user must never take the issues and comments they wrote with it
+ALTER TABLE issues
+DROP CONSTRAINT issues_creator_id_fkey,
+ADD CONSTRAINT issues_creator_id_fkey FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE RESTRICT;
+
+ALTER TABLE comments
+DROP CONSTRAINT comments_creator_id_fkey,
+ADD CONSTRAINT comments_creator_id_fkey FOREIGN KEY (creator_id) REFERENCES users(id) ON DELETE RESTRICT;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- the same person can be invited to several orgs, but only once per org
+ALTER TABLE org_invites DROP CONSTRAINT IF EXISTS org_invites_email_key;
+ALTER TABLE org_invites ADD CONSTRAINT org_invites_org_id_email_key UNIQUE (org_id, email);
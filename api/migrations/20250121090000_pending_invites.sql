@@ -0,0 +1,11 @@
+-- Add migration script here
+-- an invite is tied to an account once its owner has proven the address, the email alone is not enough
+ALTER TABLE org_invites ADD COLUMN user_id UUID REFERENCES users(id) ON DELETE CASCADE;
+
+-- invites so far could only be sent to existing accounts
+UPDATE org_invites oi
+SET user_id = u.id
+FROM users u
+WHERE lower(u.email) = lower(oi.email);
+
+CREATE INDEX IF NOT EXISTS org_invites_user_id_idx ON org_invites(user_id);
@@ -0,0 +1,42 @@
+-- Add migration script here
+CREATE TYPE member_join_method AS ENUM ('CREATED', 'INVITE', 'INVITE_LINK', 'DOMAIN');
+
+CREATE TABLE IF NOT EXISTS org_invite_links (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    token text NOT NULL UNIQUE,
+    role member_role NOT NULL DEFAULT 'MEMBER',
+    max_uses integer CHECK (max_uses > 0),
+    use_count integer NOT NULL DEFAULT 0,
+    expires_at timestamp with time zone,
+    created_by uuid NOT NULL REFERENCES users(id),
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+    revoked_at timestamp with time zone
+);
+
+CREATE INDEX IF NOT EXISTS org_invite_links_org_id_idx ON org_invite_links(org_id);
+
+CREATE TABLE IF NOT EXISTS org_domains (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    domain text NOT NULL,
+    created_by uuid NOT NULL REFERENCES users(id),
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+
+    CONSTRAINT org_domains_org_id_domain_key UNIQUE (org_id, domain)
+);
+
+CREATE INDEX IF NOT EXISTS org_domains_domain_idx ON org_domains(domain);
+
+-- every membership records how it came about
+ALTER TABLE org_members ADD COLUMN join_method member_join_method NOT NULL DEFAULT 'INVITE';
+ALTER TABLE org_members ADD COLUMN invite_link_id uuid REFERENCES org_invite_links(id) ON DELETE SET NULL;
+
+UPDATE org_members SET join_method = 'CREATED' WHERE invited_by = user_id AND role = 'OWNER';
+
+-- links can be redeemed concurrently, so a second row for the same user has to be impossible
+DELETE FROM org_members a
+USING org_members b
+WHERE a.org_id = b.org_id AND a.user_id = b.user_id AND a.ctid > b.ctid;
+
+ALTER TABLE org_members ADD CONSTRAINT org_members_org_id_user_id_key UNIQUE (org_id, user_id);
@@ -0,0 +1,43 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS teams (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    name text NOT NULL,
+    key text NOT NULL,
+    lead_id uuid,
+    created_at timestamp with time zone DEFAULT now(),
+    updated_at timestamp with time zone DEFAULT now(),
+
+    CONSTRAINT teams_org_id_key_key UNIQUE (org_id, key),
+    CONSTRAINT valid_team_key CHECK (key ~ '^[A-Z][A-Z0-9]{1,9}$')
+);
+
+-- a team member has to be a member of the org, leaving the org leaves its teams
+CREATE TABLE IF NOT EXISTS team_members (
+    team_id uuid NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    org_id uuid NOT NULL,
+    user_id uuid NOT NULL,
+    created_at timestamp with time zone DEFAULT now(),
+
+    PRIMARY KEY (team_id, user_id),
+    FOREIGN KEY (org_id, user_id) REFERENCES org_members(org_id, user_id) ON DELETE CASCADE
+);
+
+-- the lead is always one of the team members
+ALTER TABLE teams ADD CONSTRAINT teams_lead_fkey
+    FOREIGN KEY (id, lead_id) REFERENCES team_members(team_id, user_id) ON DELETE SET NULL (lead_id);
+
+CREATE TRIGGER update_teams_updated_at
+    BEFORE UPDATE ON teams
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE INDEX IF NOT EXISTS teams_org_id_idx ON teams(org_id);
+CREATE INDEX IF NOT EXISTS team_members_org_id_user_id_idx ON team_members(org_id, user_id);
+
+-- issues of a team are numbered separately under the team key
+ALTER TABLE issues ADD COLUMN team_id uuid REFERENCES teams(id) ON DELETE RESTRICT;
+ALTER TABLE issues DROP CONSTRAINT unique_issue_number_per_org;
+ALTER TABLE issues ADD CONSTRAINT unique_issue_number_per_team UNIQUE NULLS NOT DISTINCT (org_id, team_id, number);
+
+CREATE INDEX IF NOT EXISTS issues_team_id_idx ON issues(team_id);
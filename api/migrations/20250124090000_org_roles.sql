@@ -0,0 +1,24 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS org_roles (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    name text NOT NULL,
+    permissions text[] NOT NULL DEFAULT '{}',
+    created_at timestamp with time zone DEFAULT now(),
+    updated_at timestamp with time zone DEFAULT now(),
+
+    CONSTRAINT org_roles_org_id_name_key UNIQUE (org_id, name),
+    CONSTRAINT org_roles_org_id_id_key UNIQUE (org_id, id)
+);
+
+CREATE TRIGGER update_org_roles_updated_at
+    BEFORE UPDATE ON org_roles
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+-- a custom role replaces the defaults of the member role, it can only come from the same org
+ALTER TABLE org_members ADD COLUMN custom_role_id uuid;
+ALTER TABLE org_members ADD CONSTRAINT org_members_custom_role_fkey
+    FOREIGN KEY (org_id, custom_role_id) REFERENCES org_roles(org_id, id) ON DELETE SET NULL (custom_role_id);
+
+CREATE INDEX IF NOT EXISTS org_members_custom_role_id_idx ON org_members(custom_role_id);
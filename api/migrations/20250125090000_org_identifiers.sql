@@ -0,0 +1,59 @@
+-- Add migration script here
+-- identifiers used to be the first three characters of the name, which allowed spaces and duplicates
+CREATE TEMP TABLE org_identifier_backfill AS
+SELECT
+    id,
+    created_at,
+    COALESCE(
+        substring(regexp_replace(upper(name), '[^A-Z0-9]', '', 'g') from '[A-Z][A-Z0-9]{1,2}'),
+        'ORG'
+    ) as prefix,
+    NULL::text as custom_id
+FROM org;
+
+-- the oldest org keeps the bare prefix, these are unique among themselves
+UPDATE org_identifier_backfill b
+SET custom_id = b.prefix
+WHERE b.id = (
+    SELECT f.id FROM org_identifier_backfill f
+    WHERE f.prefix = b.prefix
+    ORDER BY f.created_at, f.id
+    LIMIT 1
+);
+
+-- the rest are numbered, skipping numbers another org already got as its prefix (AB2 Corp)
+DO $$
+DECLARE
+    duplicate record;
+    n integer;
+BEGIN
+    FOR duplicate IN
+        SELECT id, prefix FROM org_identifier_backfill WHERE custom_id IS NULL ORDER BY created_at, id
+    LOOP
+        n := 2;
+        WHILE EXISTS (SELECT 1 FROM org_identifier_backfill WHERE custom_id = duplicate.prefix || n) LOOP
+            n := n + 1;
+        END LOOP;
+
+        UPDATE org_identifier_backfill SET custom_id = duplicate.prefix || n WHERE id = duplicate.id;
+    END LOOP;
+END $$;
+
+UPDATE org
+SET custom_id = b.custom_id
+FROM org_identifier_backfill b
+WHERE org.id = b.id;
+
+DROP TABLE org_identifier_backfill;
+
+ALTER TABLE org ADD CONSTRAINT org_custom_id_key UNIQUE (custom_id);
+ALTER TABLE org ADD CONSTRAINT valid_custom_id CHECK (custom_id ~ '^[A-Z][A-Z0-9]{1,9}$');
+
+-- issue keys keep resolving after a rename, and nobody else can take a retired identifier
+CREATE TABLE IF NOT EXISTS org_previous_identifiers (
+    custom_id text PRIMARY KEY,
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    retired_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS org_previous_identifiers_org_id_idx ON org_previous_identifiers(org_id);
@@ -0,0 +1,9 @@
+-- Add migration script here
+-- old slugs keep resolving to the org, and nobody else can take them
+CREATE TABLE IF NOT EXISTS org_previous_slugs (
+    slug text PRIMARY KEY,
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    retired_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS org_previous_slugs_org_id_idx ON org_previous_slugs(org_id);
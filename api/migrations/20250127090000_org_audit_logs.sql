@@ -0,0 +1,19 @@
+-- Add migration script here
+-- security relevant changes inside an org, kept when the actor or target goes away
+CREATE TABLE IF NOT EXISTS org_audit_logs (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    actor_id uuid REFERENCES users(id) ON DELETE SET NULL,
+    action text NOT NULL,
+    target_type text NOT NULL,
+    target_id uuid,
+    ip_address varchar(45),
+    user_agent text,
+    before jsonb,
+    after jsonb,
+    created_at timestamp with time zone NOT NULL DEFAULT now()
+);
+
+CREATE INDEX IF NOT EXISTS org_audit_logs_org_created_idx ON org_audit_logs(org_id, created_at DESC);
+CREATE INDEX IF NOT EXISTS org_audit_logs_org_action_idx ON org_audit_logs(org_id, action);
+CREATE INDEX IF NOT EXISTS org_audit_logs_org_actor_idx ON org_audit_logs(org_id, actor_id);
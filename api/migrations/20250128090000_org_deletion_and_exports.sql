@@ -0,0 +1,30 @@
+-- Add migration script here
+-- deleting an org only schedules it, the data is purged once purge_at has passed
+ALTER TABLE org
+    ADD COLUMN IF NOT EXISTS deletion_requested_at timestamp with time zone,
+    ADD COLUMN IF NOT EXISTS deletion_requested_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS purge_at timestamp with time zone;
+
+CREATE INDEX IF NOT EXISTS org_purge_at_idx ON org(purge_at) WHERE purge_at IS NOT NULL;
+
+CREATE TYPE org_export_status AS ENUM ('PENDING', 'RUNNING', 'COMPLETED', 'FAILED');
+
+CREATE TABLE IF NOT EXISTS org_exports (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    requested_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    status org_export_status NOT NULL DEFAULT 'PENDING',
+    -- zip archive, kept until expires_at
+    archive bytea,
+    archive_size bigint,
+    error text,
+    created_at timestamp with time zone NOT NULL DEFAULT now(),
+    completed_at timestamp with time zone,
+    expires_at timestamp with time zone
+);
+
+CREATE INDEX IF NOT EXISTS org_exports_org_id_idx ON org_exports(org_id, created_at DESC);
+
+-- one export at a time per org
+CREATE UNIQUE INDEX IF NOT EXISTS org_exports_active_idx ON org_exports(org_id)
+    WHERE status IN ('PENDING', 'RUNNING');
@@ -0,0 +1,79 @@
+-- Add migration script here
+CREATE TABLE IF NOT EXISTS labels (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    name text NOT NULL,
+    color text,
+    description text,
+    created_at timestamp with time zone DEFAULT now(),
+    updated_at timestamp with time zone DEFAULT now(),
+
+    CONSTRAINT labels_org_id_name_key UNIQUE (org_id, name)
+);
+
+CREATE TRIGGER update_labels_updated_at
+    BEFORE UPDATE ON labels
+    FOR EACH ROW
+    EXECUTE FUNCTION update_updated_at_column();
+
+CREATE TABLE IF NOT EXISTS issue_labels (
+    issue_id uuid NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+    label_id uuid NOT NULL REFERENCES labels(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (issue_id, label_id)
+);
+
+-- an assignee has to be a member of the org, leaving the org unassigns them
+CREATE TABLE IF NOT EXISTS issue_assignees (
+    issue_id uuid NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+    org_id uuid NOT NULL,
+    user_id uuid NOT NULL,
+    created_at timestamp with time zone DEFAULT now(),
+
+    PRIMARY KEY (issue_id, user_id),
+    FOREIGN KEY (org_id, user_id) REFERENCES org_members(org_id, user_id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS issue_labels_label_id_idx ON issue_labels(label_id);
+CREATE INDEX IF NOT EXISTS issue_assignees_org_id_user_id_idx ON issue_assignees(org_id, user_id);
+
+CREATE TYPE import_source AS ENUM ('GITHUB');
+CREATE TYPE import_status AS ENUM ('PENDING', 'RUNNING', 'COMPLETED', 'FAILED');
+
+CREATE TABLE IF NOT EXISTS org_imports (
+    id uuid PRIMARY KEY DEFAULT gen_random_uuid(),
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    requested_by uuid REFERENCES users(id) ON DELETE SET NULL,
+    source import_source NOT NULL,
+    repository text NOT NULL,  -- owner/name on github
+    team_id uuid REFERENCES teams(id) ON DELETE CASCADE,
+    preserve_numbers boolean NOT NULL DEFAULT false,
+    open_status issue_status NOT NULL,
+    closed_status issue_status NOT NULL,
+    access_token text,  -- kept to resume the import, cleared once it completes
+    status import_status NOT NULL DEFAULT 'PENDING',
+    next_page integer NOT NULL DEFAULT 1,  -- page of the source issue list to continue from
+    total_issues integer,
+    imported_issues integer NOT NULL DEFAULT 0,
+    imported_comments integer NOT NULL DEFAULT 0,
+    renumbered_issues integer NOT NULL DEFAULT 0,
+    error text,
+    created_at timestamp with time zone DEFAULT now(),
+    updated_at timestamp with time zone DEFAULT now(),  -- doubles as the heartbeat of a running import
+    completed_at timestamp with time zone
+);
+
+CREATE INDEX IF NOT EXISTS org_imports_org_id_idx ON org_imports(org_id, created_at DESC);
+
+-- one import of a repository into an org at a time
+CREATE UNIQUE INDEX IF NOT EXISTS org_imports_active_idx ON org_imports(org_id, repository)
+    WHERE status IN ('PENDING', 'RUNNING');
+
+-- source issues that were already imported, a resumed import skips them
+CREATE TABLE IF NOT EXISTS org_import_items (
+    import_id uuid NOT NULL REFERENCES org_imports(id) ON DELETE CASCADE,
+    external_id bigint NOT NULL,
+    issue_id uuid NOT NULL REFERENCES issues(id) ON DELETE CASCADE,
+
+    PRIMARY KEY (import_id, external_id)
+);
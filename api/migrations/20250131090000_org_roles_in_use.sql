@@ -0,0 +1,6 @@
+-- Add migration script here
+-- a role still held by members can't be deleted, dropping back to the defaults of the
+-- member role could hand out more than whoever deletes the role may grant
+ALTER TABLE org_members DROP CONSTRAINT org_members_custom_role_fkey;
+ALTER TABLE org_members ADD CONSTRAINT org_members_custom_role_fkey
+    FOREIGN KEY (org_id, custom_role_id) REFERENCES org_roles(org_id, id);
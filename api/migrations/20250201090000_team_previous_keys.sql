@@ -0,0 +1,13 @@
+-- Add migration script here
+-- like org identifiers, issue keys of a team keep resolving after its key changes and
+-- no other team of the org can take a retired key
+CREATE TABLE IF NOT EXISTS team_previous_keys (
+    org_id uuid NOT NULL REFERENCES org(id) ON DELETE CASCADE,
+    key text NOT NULL,
+    team_id uuid NOT NULL REFERENCES teams(id) ON DELETE CASCADE,
+    retired_at timestamp with time zone NOT NULL DEFAULT now(),
+
+    PRIMARY KEY (org_id, key)
+);
+
+CREATE INDEX IF NOT EXISTS team_previous_keys_team_id_idx ON team_previous_keys(team_id);
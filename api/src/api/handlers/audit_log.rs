@@ -0,0 +1,36 @@
+use actix_web::{http::header, web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{app_state::AppState, errors::CustomError, models::audit_log::ListAuditLogQuery};
+
+pub async fn list_audit_log(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    query: web::Query<ListAuditLogQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let logs = state
+        .audit_log_service
+        .list_logs(path.into_inner(), query.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(logs))
+}
+
+pub async fn export_audit_log(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    query: web::Query<ListAuditLogQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let org_id = path.into_inner();
+    let export = state
+        .audit_log_service
+        .export_logs(org_id, query.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"audit-log-{}.jsonl\"", org_id),
+        ))
+        .body(export))
+}
@@ -3,8 +3,12 @@ use actix_web::{web, HttpRequest, HttpResponse};
 use crate::{
     app_state::AppState,
     errors::CustomError,
-    models::auth::{LoginRequest, RegisterRequest},
-    utils::{context::get_context_user_id, urls::extract_query_params},
+    models::{
+        auth::{LoginRequest, RegisterRequest},
+        magic_link::{MagicLinkLoginRequest, MagicLinkRequest},
+        oauth::{OauthAuthorizeQuery, OauthAuthorizeResponse, OauthCallbackQuery},
+    },
+    utils::context::{get_client_info, get_context_user_id},
 };
 
 pub async fn register(
@@ -20,12 +24,42 @@ pub async fn register(
 }
 
 pub async fn login(
+    req: HttpRequest,
     login_data: web::Json<LoginRequest>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
     let login_res = state
         .auth_service
-        .login_user(login_data.into_inner())
+        .login_user(login_data.into_inner(), client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(login_res))
+}
+
+pub async fn request_magic_link(
+    req: HttpRequest,
+    request_data: web::Json<MagicLinkRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let magic_link_res = state
+        .auth_service
+        .request_magic_link(request_data.into_inner(), client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(magic_link_res))
+}
+
+pub async fn login_with_magic_link(
+    req: HttpRequest,
+    login_data: web::Json<MagicLinkLoginRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let login_res = state
+        .auth_service
+        .login_with_magic_link(login_data.into_inner(), client)
         .await?;
 
     Ok(HttpResponse::Ok().json(login_res))
@@ -55,33 +89,80 @@ pub async fn session(
 
     Ok(HttpResponse::Ok().json(session))
 }
-#[derive(serde::Serialize)]
-struct GhResponse {
-    url: reqwest::Url,
-}
 
-pub async fn init_github(state: web::Data<AppState>) -> Result<HttpResponse, CustomError> {
-    let gh_url = state.oauth_service.init_github_link().await;
-    Ok(HttpResponse::Ok().json(GhResponse { url: gh_url }))
+pub async fn init_github(
+    query: web::Query<OauthAuthorizeQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let gh_url = state
+        .oauth_service
+        .init_github_link(query.into_inner().redirect_to)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(OauthAuthorizeResponse { url: gh_url }))
 }
 
 pub async fn github_callback(
-    req: HttpRequest,
+    query: web::Query<OauthCallbackQuery>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
-    let code = extract_query_params(req, "code".into())?;
-    let redirect_url = format!("{}?code={}", state.config.github_redirect_url, code);
+    let query = query.into_inner();
+    let mut redirect_url = reqwest::Url::parse(&state.config.github_redirect_url)
+        .map_err(|_| CustomError::InternalServerError)?;
+    redirect_url
+        .query_pairs_mut()
+        .append_pair("code", &query.code)
+        .append_pair("state", &query.state);
 
     Ok(HttpResponse::Found()
-        .append_header(("Location", redirect_url))
+        .append_header(("Location", redirect_url.to_string()))
         .finish())
 }
 
 pub async fn login_with_github(
     req: HttpRequest,
+    query: web::Query<OauthCallbackQuery>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
-    let code = extract_query_params(req, "code".into())?;
-    let user_res = state.oauth_service.handle_github_callback(code).await?;
+    let client = get_client_info(&req);
+    let query = query.into_inner();
+    let user_res = state
+        .oauth_service
+        .handle_github_callback(query.code, query.state, client)
+        .await?;
+
     Ok(HttpResponse::Ok().json(user_res))
 }
+
+pub async fn list_oidc_providers(state: web::Data<AppState>) -> Result<HttpResponse, CustomError> {
+    Ok(HttpResponse::Ok().json(state.oidc_service.list_providers()))
+}
+
+pub async fn init_oidc(
+    provider: web::Path<String>,
+    query: web::Query<OauthAuthorizeQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let url = state
+        .oidc_service
+        .init_login(&provider, query.into_inner().redirect_to)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(OauthAuthorizeResponse { url }))
+}
+
+pub async fn login_with_oidc(
+    req: HttpRequest,
+    provider: web::Path<String>,
+    query: web::Query<OauthCallbackQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let query = query.into_inner();
+    let login_res = state
+        .oidc_service
+        .handle_callback(&provider, query.code, query.state, client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(login_res))
+}
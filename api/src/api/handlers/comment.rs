@@ -4,8 +4,11 @@ use uuid::Uuid;
 use crate::{
     app_state::AppState,
     errors::CustomError,
-    models::comment::{CommentRequest, UpdateCommentRequest},
-    utils::context::get_context_user_id,
+    models::{
+        comment::{CommentRequest, UpdateCommentRequest},
+        role::Permission,
+    },
+    utils::context::{ensure_permission, get_context_user_id, has_permission},
 };
 
 pub async fn create_comment(
@@ -14,6 +17,7 @@ pub async fn create_comment(
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::CommentCreate)?;
     let user_id = get_context_user_id(req).await?;
 
     let comment = state
@@ -33,11 +37,11 @@ pub async fn update_comment(
     let user_id = get_context_user_id(req).await?;
 
     // 1 org_id, 2 comment_id
-    let (_, comment_id) = path.into_inner();
+    let (org_id, comment_id) = path.into_inner();
 
     let comment = state
         .comment_service
-        .update_comment(comment_id, user_id, comment_data.into_inner())
+        .update_comment(org_id, comment_id, user_id, comment_data.into_inner())
         .await?;
 
     Ok(HttpResponse::Ok().json(comment))
@@ -48,14 +52,15 @@ pub async fn delete_comment(
     state: web::Data<AppState>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, CustomError> {
+    let can_moderate = has_permission(&req, Permission::CommentModerate);
     let user_id = get_context_user_id(req).await?;
 
     // 1 org_id, 2 comment_id
-    let (_, comment_id) = path.into_inner();
+    let (org_id, comment_id) = path.into_inner();
 
     state
         .comment_service
-        .delete_comment(comment_id, user_id)
+        .delete_comment(org_id, comment_id, user_id, can_moderate)
         .await?;
 
     Ok(HttpResponse::Ok().finish())
@@ -0,0 +1,86 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::oauth::{OauthAuthorizeQuery, OauthAuthorizeResponse, OauthCallbackQuery},
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+// github is built in, every other provider id refers to a configured oidc provider
+const GITHUB_PROVIDER: &str = "github";
+
+pub async fn list_identities(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let identities = state.identity_service.list_identities(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(identities))
+}
+
+pub async fn init_identity_link(
+    req: HttpRequest,
+    provider: web::Path<String>,
+    query: web::Query<OauthAuthorizeQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let redirect_to = query.into_inner().redirect_to;
+
+    let url = if provider.as_str() == GITHUB_PROVIDER {
+        state
+            .oauth_service
+            .init_github_identity_link(user_id, redirect_to)
+            .await?
+    } else {
+        state
+            .oidc_service
+            .init_identity_link(&provider, user_id, redirect_to)
+            .await?
+    };
+
+    Ok(HttpResponse::Ok().json(OauthAuthorizeResponse { url }))
+}
+
+pub async fn link_identity(
+    req: HttpRequest,
+    provider: web::Path<String>,
+    link_data: web::Json<OauthCallbackQuery>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let link_data = link_data.into_inner();
+
+    let identity = if provider.as_str() == GITHUB_PROVIDER {
+        state
+            .oauth_service
+            .link_github_identity(user_id, link_data.code, link_data.state, client)
+            .await?
+    } else {
+        state
+            .oidc_service
+            .link_identity(&provider, user_id, link_data.code, link_data.state, client)
+            .await?
+    };
+
+    Ok(HttpResponse::Created().json(identity))
+}
+
+pub async fn unlink_identity(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    state
+        .identity_service
+        .unlink_identity(user_id, path.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,100 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::{
+        import::{FileImportRequest, GithubImportRequest},
+        oauth::{OauthAuthorizeQuery, OauthAuthorizeResponse},
+    },
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+pub async fn authorize_github_import(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    query: web::Query<OauthAuthorizeQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+
+    let url = state
+        .import_service
+        .authorize_github_import(user_id, query.into_inner().redirect_to)
+        .await?;
+    Ok(HttpResponse::Ok().json(OauthAuthorizeResponse { url }))
+}
+
+pub async fn start_github_import(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    data: web::Json<GithubImportRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let import = state
+        .import_service
+        .start_github_import(path.into_inner(), user_id, data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(import))
+}
+
+pub async fn list_imports(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let imports = state.import_service.list_imports(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(imports))
+}
+
+pub async fn get_import(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, import_id) = path.into_inner();
+
+    let import = state.import_service.get_import(org_id, import_id).await?;
+    Ok(HttpResponse::Ok().json(import))
+}
+
+pub async fn resume_import(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, import_id) = path.into_inner();
+
+    let import = state
+        .import_service
+        .resume_import(org_id, import_id, user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(import))
+}
+
+pub async fn import_file(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    data: web::Json<FileImportRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let data = data.into_inner();
+    let dry_run = data.dry_run.unwrap_or(false);
+    let result = state
+        .file_import_service
+        .import_file(path.into_inner(), user_id, data, &client)
+        .await?;
+
+    if dry_run {
+        return Ok(HttpResponse::Ok().json(result));
+    }
+    Ok(HttpResponse::Created().json(result))
+}
@@ -1,11 +1,17 @@
-use actix_web::{web, HttpRequest, HttpResponse};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
 use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
     errors::CustomError,
-    models::issue::{IssueRequest, UpdateIssueRequest},
-    utils::context::{get_context_org, get_context_user_id},
+    models::{
+        issue::{IssueListQuery, IssueRequest, UpdateIssueRequest},
+        issue_csv::{CsvColumnsRequest, CsvImportRequest},
+        role::Permission,
+    },
+    utils::context::{
+        ensure_permission, get_client_info, get_context_org, get_context_user_id, has_permission,
+    },
 };
 
 pub async fn create_issue(
@@ -13,6 +19,7 @@ pub async fn create_issue(
     state: web::Data<AppState>,
     payload: web::Json<IssueRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::IssueCreate)?;
     let user_id = get_context_user_id(req.clone()).await?;
     let org = get_context_org(req).await?;
 
@@ -34,25 +41,44 @@ pub async fn get_issue(
     Ok(HttpResponse::Ok().json(issue))
 }
 
+pub async fn get_issue_by_key(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, String)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, key) = path.into_inner();
+
+    let issue = state.issue_service.get_issue_by_key(org_id, &key).await?;
+    Ok(HttpResponse::Ok().json(issue))
+}
+
 pub async fn delete_issue(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, CustomError> {
-    let (_org_id, issue_id) = path.into_inner();
+    let can_delete_any = has_permission(&req, Permission::IssueDelete);
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, issue_id) = path.into_inner();
 
-    state.issue_service.delete_issue(issue_id).await?;
+    state
+        .issue_service
+        .delete_issue(org_id, issue_id, user_id, can_delete_any, &client)
+        .await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
 pub async fn update_issue(
+    req: HttpRequest,
     state: web::Data<AppState>,
     payload: web::Json<UpdateIssueRequest>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, CustomError> {
-    let (_org_id, issue_id) = path.into_inner();
+    ensure_permission(&req, Permission::IssueUpdate)?;
+    let (org_id, issue_id) = path.into_inner();
     let issue = state
         .issue_service
-        .update_issue(issue_id, payload.into_inner())
+        .update_issue(org_id, issue_id, payload.into_inner())
         .await?;
     Ok(HttpResponse::Ok().json(issue))
 }
@@ -60,8 +86,64 @@ pub async fn update_issue(
 pub async fn get_issues(
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
+    query: web::Query<IssueListQuery>,
 ) -> Result<HttpResponse, CustomError> {
     let org_id = path.into_inner();
-    let issues = state.issue_service.get_all_by_org_id(org_id).await?;
+    let issues = state
+        .issue_service
+        .get_all_by_org_id(org_id, query.into_inner())
+        .await?;
     Ok(HttpResponse::Ok().json(issues))
 }
+
+pub async fn export_issues(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    query: web::Query<IssueListQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let org_id = path.into_inner();
+    let export = state
+        .issue_csv_service
+        .export_issues(org_id, query.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/csv; charset=utf-8")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"issues-{}.csv\"", org_id),
+        ))
+        .body(export))
+}
+
+pub async fn get_csv_columns(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    payload: web::Json<CsvColumnsRequest>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::OrgImport)?;
+    let columns = state.issue_csv_service.csv_columns(payload.into_inner())?;
+    Ok(HttpResponse::Ok().json(columns))
+}
+
+pub async fn import_issues_csv(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    payload: web::Json<CsvImportRequest>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::OrgImport)?;
+    let user_id = get_context_user_id(req.clone()).await?;
+    let org = get_context_org(req).await?;
+
+    let data = payload.into_inner();
+    let dry_run = data.dry_run.unwrap_or(false);
+    let result = state
+        .issue_csv_service
+        .import_issues(org.id, user_id, data)
+        .await?;
+
+    if dry_run {
+        return Ok(HttpResponse::Ok().json(result));
+    }
+    Ok(HttpResponse::Created().json(result))
+}
@@ -1,5 +1,15 @@
+pub mod audit_log;
 pub mod auth;
 pub mod comment;
+pub mod identity;
+pub mod import;
 pub mod issue;
 pub mod org;
+pub mod org_export;
+pub mod personal_access_token;
+pub mod role;
+pub mod team;
+pub mod two_factor;
+pub mod user;
 pub mod user_preferences;
+pub mod webhook;
@@ -4,11 +4,18 @@ use uuid::Uuid;
 use crate::{
     app_state::AppState,
     errors::CustomError,
-    models::org::{
-        CreateOrgRequest, InviteOrgMemberRequest, MemberRole, UpdateOrgMemberRequest,
-        UpdateOrgRequest,
+    models::{
+        org::{
+            AddOrgDomainRequest, CreateInviteLinkRequest, CreateOrgRequest, InviteOrgMemberRequest,
+            ListOrgMembersQuery, MemberStatus, TransferOwnershipRequest, UpdateOrgMemberRequest,
+            UpdateOrgRequest,
+        },
+        role::{Permission, SessionPermissionsResponse},
+    },
+    utils::context::{
+        allows_org, ensure_owner, ensure_permission, ensure_unrestricted, get_client_info,
+        get_context_org, get_context_permissions, get_context_role, get_context_user_id,
     },
-    utils::context::{get_context_org, get_context_user_id},
 };
 
 pub async fn create_org(
@@ -16,6 +23,7 @@ pub async fn create_org(
     org_data: web::Json<CreateOrgRequest>,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
+    ensure_unrestricted(&req)?;
     let user_id = get_context_user_id(req).await?;
 
     let org = state
@@ -30,9 +38,15 @@ pub async fn list_organizations(
     req: HttpRequest,
     state: web::Data<AppState>,
 ) -> Result<HttpResponse, CustomError> {
-    let user_id = get_context_user_id(req).await?;
+    let user_id = get_context_user_id(req.clone()).await?;
 
-    let orgs = state.org_service.list_user_orgs(user_id).await?;
+    let orgs: Vec<_> = state
+        .org_service
+        .list_user_orgs(user_id)
+        .await?
+        .into_iter()
+        .filter(|org| allows_org(&req, org.id))
+        .collect();
     Ok(HttpResponse::Ok().json(orgs))
 }
 
@@ -51,80 +65,291 @@ pub async fn accept_invite(
     state: web::Data<AppState>,
     path: web::Path<(String, Uuid)>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
     let user_id = get_context_user_id(req).await?;
 
     state
         .org_service
-        .accept_org_invite(path.1, user_id, path.0.clone())
+        .accept_org_invite(path.1, user_id, path.0.clone(), &client)
         .await?;
 
     Ok(HttpResponse::NoContent().finish())
 }
 
 pub async fn cancel_invite(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
     state
         .org_service
-        .decline_org_invite(path.into_inner())
+        .decline_org_invite(user_id, path.into_inner())
         .await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
+pub async fn preview_invite_link(
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let preview = state
+        .org_service
+        .preview_invite_link(&path.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(preview))
+}
+
+pub async fn redeem_invite_link(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let org = state
+        .org_service
+        .redeem_invite_link(user_id, &path.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(org))
+}
+
+pub async fn list_joinable_orgs(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+
+    let orgs = state.org_service.list_joinable_orgs(user_id).await?;
+    Ok(HttpResponse::Ok().json(orgs))
+}
+
+pub async fn join_by_domain(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let org = state
+        .org_service
+        .join_by_domain(user_id, path.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(org))
+}
+
 pub async fn get_org(req: HttpRequest) -> Result<HttpResponse, CustomError> {
     let org = get_context_org(req).await?;
     Ok(HttpResponse::Ok().json(org))
 }
 
 pub async fn update_org(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
     payload: web::Json<UpdateOrgRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::OrgSettings)?;
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
     let org = state
         .org_service
-        .update_org(path.into_inner(), payload.into_inner())
+        .update_org(path.into_inner(), payload.into_inner(), user_id, &client)
         .await?;
     Ok(HttpResponse::Ok().json(org))
 }
 
 pub async fn delete_org(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse, CustomError> {
-    state.org_service.delete_org(path.into_inner()).await?;
-    Ok(HttpResponse::NoContent().finish())
+    ensure_permission(&req, Permission::OrgDelete)?;
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let org = state
+        .org_service
+        .delete_org(path.into_inner(), user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(org))
 }
 
-pub async fn invite_user(
+pub async fn restore_org(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_owner(&req)?;
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let org = state
+        .org_service
+        .restore_org(path.into_inner(), user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(org))
+}
+
+pub async fn invite_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
     payload: web::Json<InviteOrgMemberRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let org = get_context_org(req.clone()).await?;
     let user_id = get_context_user_id(req).await?;
 
     let invite = state
         .org_service
-        .invite_user_to_org(
-            path.into_inner(),
-            payload.into_inner(),
-            MemberRole::Member,
-            user_id,
-        )
+        .invite_user_to_org(&org, payload.into_inner(), user_id, &client)
         .await?;
 
     Ok(HttpResponse::Created().json(invite))
 }
 
+pub async fn resend_invite(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let org = get_context_org(req.clone()).await?;
+    let user_id = get_context_user_id(req).await?;
+    let (_, invite_id) = path.into_inner();
+
+    let invite = state
+        .org_service
+        .resend_invite(&org, invite_id, user_id)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(invite))
+}
+
+pub async fn revoke_invite(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, invite_id) = path.into_inner();
+
+    state
+        .org_service
+        .revoke_invite(org_id, invite_id, user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn create_invite_link(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateInviteLinkRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let link = state
+        .org_service
+        .create_invite_link(path.into_inner(), user_id, payload.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Created().json(link))
+}
+
+pub async fn list_invite_links(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let links = state
+        .org_service
+        .list_invite_links(path.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(links))
+}
+
+pub async fn revoke_invite_link(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, link_id) = path.into_inner();
+
+    state
+        .org_service
+        .revoke_invite_link(org_id, link_id, user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn add_domain(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<AddOrgDomainRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let domain = state
+        .org_service
+        .add_domain(path.into_inner(), user_id, payload.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Created().json(domain))
+}
+
+pub async fn list_domains(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let domains = state.org_service.list_domains(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(domains))
+}
+
+pub async fn remove_domain(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, domain_id) = path.into_inner();
+
+    state
+        .org_service
+        .remove_domain(org_id, user_id, domain_id, &client)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
 pub async fn list_org_members(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
+    query: web::Query<ListOrgMembersQuery>,
 ) -> Result<HttpResponse, CustomError> {
+    ensure_permission(&req, Permission::MemberView)?;
+    let status = query.into_inner().status.unwrap_or(MemberStatus::Active);
+    // suspended members are only listed for those who can suspend them
+    if status != MemberStatus::Active {
+        ensure_permission(&req, Permission::MemberSuspend)?;
+    }
+
     let members = state
         .org_service
-        .list_org_members(path.into_inner())
+        .list_org_members(path.into_inner(), status)
         .await?;
     Ok(HttpResponse::Ok().json(members))
 }
@@ -141,37 +366,128 @@ pub async fn list_org_invites(
 }
 
 pub async fn update_member_role(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<(Uuid, Uuid)>,
     payload: web::Json<UpdateOrgMemberRequest>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
     let (org_id, member_id) = path.into_inner();
 
     state
         .org_service
-        .change_member_role(org_id, member_id, payload.into_inner().role.unwrap())
+        .ensure_can_manage(org_id, user_id, member_id)
+        .await?;
+    state
+        .org_service
+        .change_member_role(
+            org_id,
+            user_id,
+            member_id,
+            payload.into_inner().role,
+            &client,
+        )
         .await?;
     Ok(HttpResponse::NoContent().finish())
 }
 
 pub async fn remove_member(
+    req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<(Uuid, Uuid)>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
     let (org_id, member_id) = path.into_inner();
-    state.org_service.remove_member(org_id, member_id).await?;
+
+    state
+        .org_service
+        .ensure_can_manage(org_id, user_id, member_id)
+        .await?;
+    state
+        .org_service
+        .remove_member(org_id, user_id, member_id, &client)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn transfer_ownership(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<TransferOwnershipRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let org = get_context_org(req.clone()).await?;
+    let user_id = get_context_user_id(req).await?;
+    let (_, member_id) = path.into_inner();
+
+    state
+        .org_service
+        .transfer_ownership(&org, user_id, member_id, payload.into_inner(), &client)
+        .await?;
+
     Ok(HttpResponse::NoContent().finish())
 }
 
-pub async fn get_session_role(
+pub async fn leave_org(
     req: HttpRequest,
     state: web::Data<AppState>,
     path: web::Path<Uuid>,
 ) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
     let user_id = get_context_user_id(req).await?;
-    let org_id = path.into_inner();
+    state
+        .org_service
+        .leave_org(path.into_inner(), user_id, &client)
+        .await?;
 
-    let role = state.org_service.check_user_role(org_id, user_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
 
+pub async fn suspend_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, member_id) = path.into_inner();
+
+    state
+        .org_service
+        .suspend_member(org_id, user_id, member_id, &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn reactivate_member(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, member_id) = path.into_inner();
+
+    state
+        .org_service
+        .reactivate_member(org_id, user_id, member_id, &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_session_role(req: HttpRequest) -> Result<HttpResponse, CustomError> {
+    let role = get_context_role(req).await?;
     Ok(HttpResponse::Ok().json(role))
 }
+
+pub async fn get_session_permissions(req: HttpRequest) -> Result<HttpResponse, CustomError> {
+    let role = get_context_role(req.clone()).await?;
+    let permissions = get_context_permissions(req).await?;
+
+    Ok(HttpResponse::Ok().json(SessionPermissionsResponse { role, permissions }))
+}
@@ -0,0 +1,78 @@
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    utils::context::{ensure_owner, get_client_info, get_context_user_id},
+};
+
+pub async fn request_export(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_owner(&req)?;
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let export = state
+        .org_export_service
+        .request_export(path.into_inner(), user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(export))
+}
+
+pub async fn list_exports(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_owner(&req)?;
+
+    let exports = state
+        .org_export_service
+        .list_exports(path.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(exports))
+}
+
+pub async fn get_export(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_owner(&req)?;
+    let (org_id, export_id) = path.into_inner();
+
+    let export = state
+        .org_export_service
+        .get_export(org_id, export_id)
+        .await?;
+    Ok(HttpResponse::Ok().json(export))
+}
+
+pub async fn download_export(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    ensure_owner(&req)?;
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, export_id) = path.into_inner();
+
+    let archive = state
+        .org_export_service
+        .download_export(org_id, export_id, user_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/zip")
+        .insert_header((
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"org-export-{}.zip\"", export_id),
+        ))
+        .body(archive))
+}
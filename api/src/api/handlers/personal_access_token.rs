@@ -0,0 +1,52 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::personal_access_token::CreatePersonalAccessTokenRequest,
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+pub async fn create_access_token(
+    req: HttpRequest,
+    token_data: web::Json<CreatePersonalAccessTokenRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let token_res = state
+        .personal_access_token_service
+        .create_token(user_id, token_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Created().json(token_res))
+}
+
+pub async fn list_access_tokens(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let tokens = state
+        .personal_access_token_service
+        .list_tokens(user_id)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+pub async fn revoke_access_token(
+    req: HttpRequest,
+    path: web::Path<Uuid>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    state
+        .personal_access_token_service
+        .revoke_token(user_id, path.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,113 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::role::{AssignOrgRoleRequest, CreateOrgRoleRequest, UpdateOrgRoleRequest},
+    utils::context::{get_client_info, get_context_permissions, get_context_user_id},
+};
+
+pub async fn list_roles(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let roles = state.role_service.list_roles(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(roles))
+}
+
+pub async fn create_role(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateOrgRoleRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let permissions = get_context_permissions(req.clone()).await?;
+    let user_id = get_context_user_id(req).await?;
+
+    let role = state
+        .role_service
+        .create_role(
+            path.into_inner(),
+            user_id,
+            &permissions,
+            payload.into_inner(),
+            &client,
+        )
+        .await?;
+
+    Ok(HttpResponse::Created().json(role))
+}
+
+pub async fn update_role(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateOrgRoleRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let permissions = get_context_permissions(req.clone()).await?;
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, role_id) = path.into_inner();
+
+    let role = state
+        .role_service
+        .update_role(
+            org_id,
+            role_id,
+            user_id,
+            &permissions,
+            payload.into_inner(),
+            &client,
+        )
+        .await?;
+
+    Ok(HttpResponse::Ok().json(role))
+}
+
+pub async fn delete_role(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, role_id) = path.into_inner();
+
+    state
+        .role_service
+        .delete_role(org_id, user_id, role_id, &client)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn assign_role(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<AssignOrgRoleRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let permissions = get_context_permissions(req.clone()).await?;
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, member_id) = path.into_inner();
+
+    state
+        .org_service
+        .ensure_can_manage(org_id, user_id, member_id)
+        .await?;
+    state
+        .role_service
+        .assign_role(
+            org_id,
+            member_id,
+            payload.into_inner().role_id,
+            user_id,
+            &permissions,
+            &client,
+        )
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
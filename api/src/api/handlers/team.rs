@@ -0,0 +1,93 @@
+use actix_web::{web, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::team::{AddTeamMemberRequest, CreateTeamRequest, UpdateTeamRequest},
+};
+
+pub async fn create_team(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateTeamRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let team = state
+        .team_service
+        .create_team(path.into_inner(), payload.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Created().json(team))
+}
+
+pub async fn list_teams(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let teams = state.team_service.list_teams(path.into_inner()).await?;
+    Ok(HttpResponse::Ok().json(teams))
+}
+
+pub async fn get_team(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, team_id) = path.into_inner();
+
+    let team = state.team_service.get_team(org_id, team_id).await?;
+    Ok(HttpResponse::Ok().json(team))
+}
+
+pub async fn update_team(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateTeamRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, team_id) = path.into_inner();
+
+    let team = state
+        .team_service
+        .update_team(org_id, team_id, payload.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(team))
+}
+
+pub async fn delete_team(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, team_id) = path.into_inner();
+
+    state.team_service.delete_team(org_id, team_id).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn add_team_member(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<AddTeamMemberRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, team_id) = path.into_inner();
+
+    state
+        .team_service
+        .add_team_member(org_id, team_id, payload.into_inner().user_id)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn remove_team_member(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, team_id, user_id) = path.into_inner();
+
+    state
+        .team_service
+        .remove_team_member(org_id, team_id, user_id)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
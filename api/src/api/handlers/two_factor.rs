@@ -0,0 +1,87 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::two_factor::{TwoFactorCodeRequest, TwoFactorLoginRequest},
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+pub async fn get_two_factor_status(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let status = state.two_factor_service.get_status(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(status))
+}
+
+pub async fn enroll_two_factor(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let enroll_res = state.two_factor_service.enroll(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(enroll_res))
+}
+
+pub async fn confirm_two_factor(
+    req: HttpRequest,
+    code_data: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let recovery_codes = state
+        .two_factor_service
+        .confirm(user_id, code_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(recovery_codes))
+}
+
+pub async fn disable_two_factor(
+    req: HttpRequest,
+    code_data: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    state
+        .two_factor_service
+        .disable(user_id, code_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn regenerate_recovery_codes(
+    req: HttpRequest,
+    code_data: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let recovery_codes = state
+        .two_factor_service
+        .regenerate_recovery_codes(user_id, code_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(recovery_codes))
+}
+
+pub async fn login_with_two_factor(
+    req: HttpRequest,
+    login_data: web::Json<TwoFactorLoginRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let login_res = state
+        .auth_service
+        .login_with_two_factor(login_data.into_inner(), client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(login_res))
+}
@@ -0,0 +1,108 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::user::{
+        ChangeEmailRequest, DeleteAccountRequest, UpdateProfileRequest, VerifyEmailRequest,
+    },
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+pub async fn get_profile(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let profile = state.user_service.get_profile(user_id).await?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+pub async fn update_profile(
+    req: HttpRequest,
+    profile_data: web::Json<UpdateProfileRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let profile = state
+        .user_service
+        .update_profile(user_id, profile_data.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+pub async fn change_email(
+    req: HttpRequest,
+    email_data: web::Json<ChangeEmailRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let response = state
+        .user_service
+        .request_email_change(user_id, email_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(response))
+}
+
+pub async fn verify_email(
+    req: HttpRequest,
+    verify_data: web::Json<VerifyEmailRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let profile = state
+        .user_service
+        .verify_email(verify_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(profile))
+}
+
+pub async fn resend_email_verification(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    state
+        .user_service
+        .resend_email_verification(user_id)
+        .await?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+pub async fn delete_account(
+    req: HttpRequest,
+    delete_data: web::Json<DeleteAccountRequest>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    state
+        .user_service
+        .delete_account(user_id, delete_data.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn unlock_user(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    state
+        .auth_service
+        .unlock_account(path.into_inner(), user_id, client)
+        .await?;
+
+    Ok(HttpResponse::NoContent().finish())
+}
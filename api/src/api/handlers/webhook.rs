@@ -0,0 +1,132 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    errors::CustomError,
+    models::webhook::{CreateWebhookRequest, ListWebhookDeliveriesQuery, UpdateWebhookRequest},
+    utils::context::{get_client_info, get_context_user_id},
+};
+
+pub async fn list_webhooks(
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+) -> Result<HttpResponse, CustomError> {
+    let webhooks = state
+        .webhook_service
+        .list_webhooks(path.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(webhooks))
+}
+
+pub async fn create_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<Uuid>,
+    payload: web::Json<CreateWebhookRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+
+    let webhook = state
+        .webhook_service
+        .create_webhook(path.into_inner(), user_id, payload.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Created().json(webhook))
+}
+
+pub async fn get_webhook(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let webhook = state
+        .webhook_service
+        .get_webhook(org_id, webhook_id)
+        .await?;
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+pub async fn update_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    payload: web::Json<UpdateWebhookRequest>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, webhook_id) = path.into_inner();
+
+    let webhook = state
+        .webhook_service
+        .update_webhook(org_id, user_id, webhook_id, payload.into_inner(), &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+pub async fn delete_webhook(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, webhook_id) = path.into_inner();
+
+    state
+        .webhook_service
+        .delete_webhook(org_id, user_id, webhook_id, &client)
+        .await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn rotate_webhook_secret(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let client = get_client_info(&req);
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, webhook_id) = path.into_inner();
+
+    let webhook = state
+        .webhook_service
+        .rotate_secret(org_id, user_id, webhook_id, &client)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(webhook))
+}
+
+// the delivery is queued, its outcome shows up in the delivery log
+pub async fn send_webhook_test_event(
+    req: HttpRequest,
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, CustomError> {
+    let user_id = get_context_user_id(req).await?;
+    let (org_id, webhook_id) = path.into_inner();
+
+    let delivery = state
+        .webhook_service
+        .send_test_event(org_id, user_id, webhook_id)
+        .await?;
+
+    Ok(HttpResponse::Accepted().json(delivery))
+}
+
+pub async fn list_webhook_deliveries(
+    state: web::Data<AppState>,
+    path: web::Path<(Uuid, Uuid)>,
+    query: web::Query<ListWebhookDeliveriesQuery>,
+) -> Result<HttpResponse, CustomError> {
+    let (org_id, webhook_id) = path.into_inner();
+
+    let deliveries = state
+        .webhook_service
+        .list_deliveries(org_id, webhook_id, query.into_inner())
+        .await?;
+    Ok(HttpResponse::Ok().json(deliveries))
+}
@@ -1,5 +1,6 @@
 pub mod authentication_guard;
 pub mod logger;
 pub mod org_guard;
-pub mod role_guard;
+pub mod permission_guard;
+pub mod scope_guard;
 pub mod token_extractor;
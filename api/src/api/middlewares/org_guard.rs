@@ -1,16 +1,21 @@
 use actix_web::{
-    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    dev::{Path, Service, ServiceRequest, ServiceResponse, Transform, Url},
     web, Error, HttpMessage,
 };
 use futures::Future;
 use std::pin::Pin;
 use std::rc::Rc;
 use std::task::{Context, Poll};
+use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
     errors::CustomError,
-    models::context::{OrgContext, UserContext},
+    models::{
+        context::{OrgContext, UserContext},
+        org::{MemberRole, MemberStatus},
+        role::Permission,
+    },
 };
 
 #[derive(Debug, Clone)]
@@ -51,13 +56,13 @@ where
         self.service.poll_ready(cx)
     }
 
-    fn call(&self, req: ServiceRequest) -> Self::Future {
+    fn call(&self, mut req: ServiceRequest) -> Self::Future {
         let service = self.service.clone();
 
         Box::pin(async move {
             let state = req
                 .app_data::<web::Data<AppState>>()
-                .ok_or_else(|| CustomError::InternalServerError)?;
+                .ok_or(CustomError::InternalServerError)?;
 
             let user_context = req
                 .extensions()
@@ -65,35 +70,93 @@ where
                 .ok_or(CustomError::Unauthorized)?
                 .clone();
 
-            let org_id = req
+            let org_ref = req
                 .match_info()
                 .get("org_id")
                 .ok_or_else(|| CustomError::NotFound("Organization ID not found".to_string()))?
                 .to_string();
-            let org_id = uuid::Uuid::parse_str(&org_id).map_err(|_| CustomError::BadRequest)?;
 
             let user_id = user_context
                 .user_id
                 .as_ref()
                 .ok_or(CustomError::Unauthorized)?;
 
-            let user = state.auth_service.get_session(user_id.clone()).await?;
+            let user = state.auth_service.get_session(*user_id).await?;
 
-            let org = state
+            let membership = state
                 .org_service
-                .get_org(org_id, user.id)
-                .await
-                .map_err(|_| CustomError::NotFound("Organization not found".to_string()))?;
+                .get_membership(&org_ref, user.id)
+                .await?
+                .ok_or_else(|| CustomError::NotFound("Organization not found".to_string()))?;
+
+            match membership.status {
+                MemberStatus::Active => {}
+                // suspended members get told so instead of the org seeming to vanish
+                MemberStatus::Disabled => return Err(CustomError::MembershipSuspended.into()),
+                MemberStatus::Invited => {
+                    return Err(CustomError::NotFound("Organization not found".to_string()).into())
+                }
+            }
+
+            let org = membership.org;
+
+            if let Some(access_token) = &user_context.access_token {
+                if !access_token.allows_org(org.id) {
+                    return Err(CustomError::Forbidden.into());
+                }
+            }
 
-            let org_context = OrgContext { org: Some(org) };
+            // only owners can still reach an org scheduled for deletion, to restore or export it
+            if org.purge_at.is_some() && membership.role != MemberRole::Owner {
+                return Err(CustomError::Conflict(
+                    "This organization is scheduled for deletion".to_string(),
+                    "ORG_017".to_string(),
+                )
+                .into());
+            }
 
-            if let Some(org_id) = org_context.org.as_ref().map(|o| &o.id) {
-                log::info!("OrgContext: {}", org_id);
+            if org.require_two_factor && !state.two_factor_service.is_enabled(user.id).await? {
+                return Err(CustomError::TwoFactorRequired.into());
             }
 
+            let org_id = org.id;
+            let permissions =
+                Permission::resolve(&membership.role, membership.custom_permissions.as_deref());
+
+            let org_context = OrgContext {
+                org: Some(org),
+                role: Some(membership.role),
+                permissions,
+            };
+
+            log::info!("OrgContext: {}", org_id);
+
             req.extensions_mut().insert(org_context);
 
-            service.call(req).await.map_err(Into::into)
+            if org_ref != org_id.to_string() {
+                replace_org_id(req.match_info_mut(), org_id);
+            }
+
+            service.call(req).await
         })
     }
 }
+
+// handlers read {org_id} as a uuid, so a slug is swapped for the id of the org,
+// routes matched further down keep appending after the rewritten segments
+fn replace_org_id(path: &mut Path<Url>, org_id: Uuid) {
+    let consumed = path.as_str().len() - path.unprocessed().len();
+    let segments = path
+        .iter()
+        .map(|(name, value)| match name {
+            "org_id" => (name.to_string(), org_id.to_string()),
+            _ => (name.to_string(), value.to_string()),
+        })
+        .collect::<Vec<_>>();
+
+    path.reset();
+    path.skip(consumed as u16);
+    for (name, value) in segments {
+        path.add_static(name, value);
+    }
+}
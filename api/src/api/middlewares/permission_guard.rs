@@ -0,0 +1,84 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    Error, HttpMessage,
+};
+use futures::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+
+use crate::{
+    errors::CustomError,
+    models::{context::OrgContext, role::Permission},
+};
+
+// relies on the permissions the org guard resolved for the member
+pub struct PermissionGuard {
+    permission: Permission,
+}
+
+impl PermissionGuard {
+    pub fn new(permission: Permission) -> Self {
+        Self { permission }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for PermissionGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = PermissionMiddleware<S>;
+    type InitError = ();
+    type Future = futures::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        futures::future::ready(Ok(PermissionMiddleware {
+            service: Rc::new(service),
+            permission: self.permission,
+        }))
+    }
+}
+
+pub struct PermissionMiddleware<S> {
+    service: Rc<S>,
+    permission: Permission,
+}
+
+impl<S, B> Service<ServiceRequest> for PermissionMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = self.service.clone();
+        let permission = self.permission;
+
+        Box::pin(async move {
+            let allowed = {
+                let extensions = req.extensions();
+                let org_context = extensions
+                    .get::<OrgContext>()
+                    .ok_or(CustomError::Unauthorized)?;
+                org_context.has_permission(permission)
+            };
+
+            if !allowed {
+                log::info!("Missing permission: {}", permission.as_str());
+                return Err(CustomError::Forbidden.into());
+            }
+
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,202 @@
+use actix_web::{
+    dev::{Service, ServiceRequest, ServiceResponse, Transform},
+    http::Method,
+    Error, HttpMessage,
+};
+use futures::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use crate::{
+    errors::CustomError,
+    models::{context::UserContext, personal_access_token::TokenScope},
+};
+
+// restricts what personal access tokens can reach, session tokens pass through untouched
+#[derive(Debug, Clone)]
+pub struct ScopeGuard {
+    read_scope: Option<TokenScope>,
+    write_scope: Option<TokenScope>,
+}
+
+impl ScopeGuard {
+    pub fn new(read_scope: TokenScope, write_scope: TokenScope) -> Self {
+        Self {
+            read_scope: Some(read_scope),
+            write_scope: Some(write_scope),
+        }
+    }
+
+    pub fn session_only() -> Self {
+        Self {
+            read_scope: None,
+            write_scope: None,
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for ScopeGuard
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = ScopeMiddleware<S>;
+    type InitError = ();
+    type Future = futures::future::Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        futures::future::ready(Ok(ScopeMiddleware {
+            service,
+            read_scope: self.read_scope,
+            write_scope: self.write_scope,
+        }))
+    }
+}
+
+pub struct ScopeMiddleware<S> {
+    service: S,
+    read_scope: Option<TokenScope>,
+    write_scope: Option<TokenScope>,
+}
+
+impl<S, B> Service<ServiceRequest> for ScopeMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.service.poll_ready(cx)
+    }
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let access_token = req
+            .extensions()
+            .get::<UserContext>()
+            .and_then(|context| context.access_token.clone());
+
+        if let Some(access_token) = access_token {
+            let required_scope = match *req.method() {
+                Method::GET | Method::HEAD => self.read_scope,
+                _ => self.write_scope,
+            };
+
+            let allowed = required_scope.is_some_and(|scope| access_token.has_scope(scope));
+            if !allowed {
+                return Box::pin(async move { Err(CustomError::Forbidden.into()) });
+            }
+        }
+
+        let fut = self.service.call(req);
+        Box::pin(fut)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        dev::Service as _, http::StatusCode, test, web, App, HttpMessage as _, HttpResponse,
+    };
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::models::context::AccessTokenContext;
+
+    // stands in for the authentication guard, scopes of none mean a session token
+    async fn status(scopes: Option<Vec<TokenScope>>, method: Method, path: &str) -> StatusCode {
+        let app = test::init_service(
+            App::new()
+                .service(
+                    web::scope("/issues")
+                        .wrap(ScopeGuard::new(
+                            TokenScope::IssuesRead,
+                            TokenScope::IssuesWrite,
+                        ))
+                        .default_service(web::to(HttpResponse::Ok)),
+                )
+                .service(
+                    web::scope("/access-tokens")
+                        .wrap(ScopeGuard::session_only())
+                        .default_service(web::to(HttpResponse::Ok)),
+                )
+                .wrap_fn(move |req, srv| {
+                    req.extensions_mut().insert(UserContext {
+                        user_id: Some(Uuid::new_v4()),
+                        access_token: scopes.clone().map(|scopes| AccessTokenContext {
+                            token_id: Uuid::new_v4(),
+                            scopes,
+                            org_ids: None,
+                        }),
+                    });
+                    srv.call(req)
+                }),
+        )
+        .await;
+
+        let req = test::TestRequest::default()
+            .method(method)
+            .uri(path)
+            .to_request();
+        match test::try_call_service(&app, req).await {
+            Ok(res) => res.status(),
+            Err(e) => e.error_response().status(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn tokens_without_the_scope_are_forbidden() {
+        let read_only = Some(vec![TokenScope::IssuesRead]);
+
+        assert_eq!(
+            status(read_only.clone(), Method::GET, "/issues").await,
+            StatusCode::OK
+        );
+        assert_eq!(
+            status(read_only.clone(), Method::POST, "/issues").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(read_only, Method::DELETE, "/issues").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(Some(vec![TokenScope::OrgAdmin]), Method::POST, "/issues").await,
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    #[actix_web::test]
+    async fn write_scope_implies_read() {
+        let write = Some(vec![TokenScope::IssuesWrite]);
+
+        assert_eq!(
+            status(write.clone(), Method::POST, "/issues").await,
+            StatusCode::OK
+        );
+        assert_eq!(status(write, Method::GET, "/issues").await, StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn session_only_routes_turn_away_every_token() {
+        let all = Some(vec![
+            TokenScope::IssuesRead,
+            TokenScope::IssuesWrite,
+            TokenScope::OrgAdmin,
+        ]);
+
+        assert_eq!(
+            status(all, Method::GET, "/access-tokens").await,
+            StatusCode::FORBIDDEN
+        );
+        assert_eq!(
+            status(None, Method::POST, "/access-tokens").await,
+            StatusCode::OK
+        );
+        assert_eq!(status(None, Method::POST, "/issues").await, StatusCode::OK);
+    }
+}
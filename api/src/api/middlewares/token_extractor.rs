@@ -1,4 +1,7 @@
-use crate::{app_state::AppState, models::context::UserContext};
+use crate::{
+    app_state::AppState, models::context::UserContext,
+    services::personal_access_token::TOKEN_PREFIX,
+};
 use actix_web::{
     dev::{Service, ServiceRequest, ServiceResponse, Transform},
     web, Error, HttpMessage,
@@ -57,10 +60,21 @@ where
                     if auth_str.starts_with("Bearer ") {
                         let token = auth_str.trim_start_matches("Bearer ").trim();
                         if let Some(state) = state {
-                            let token_service = &state.token_service;
-                            let user_id = token_service.extract_user_id(token).await;
-                            if let Ok(user_id) = user_id {
-                                context.user_id = Some(user_id);
+                            if token.starts_with(TOKEN_PREFIX) {
+                                let authenticated = state
+                                    .personal_access_token_service
+                                    .authenticate(token)
+                                    .await;
+                                if let Ok(Some((user_id, access_token))) = authenticated {
+                                    context.user_id = Some(user_id);
+                                    context.access_token = Some(access_token);
+                                }
+                            } else {
+                                let token_service = &state.token_service;
+                                let user_id = token_service.extract_user_id(token).await;
+                                if let Ok(user_id) = user_id {
+                                    context.user_id = Some(user_id);
+                                }
                             }
                         }
                     }
@@ -1,3 +1,3 @@
+pub mod handlers;
 pub mod middlewares;
 pub mod routes;
-pub mod handlers;
@@ -1,14 +1,36 @@
-use crate::api::{handlers::auth::*, middlewares::authentication_guard::AuthenticationGuard};
+use crate::api::{
+    handlers::{auth::*, two_factor::*},
+    middlewares::{authentication_guard::AuthenticationGuard, scope_guard::ScopeGuard},
+};
 use actix_web::web;
 pub fn configure_auth_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/auth")
             .route("/register", web::post().to(register))
             .route("/login", web::post().to(login))
+            .route("/2fa/login", web::post().to(login_with_two_factor))
+            .route("/magic-link", web::post().to(request_magic_link))
+            .route("/magic-link/login", web::post().to(login_with_magic_link))
             .route("/refresh", web::post().to(refresh_token))
             .route("/oauth/github", web::get().to(init_github))
             .route("/oauth/github/callback", web::get().to(github_callback))
             .route("/oauth/github/login", web::get().to(login_with_github))
+            .route("/oauth/oidc", web::get().to(list_oidc_providers))
+            .route("/oauth/oidc/{provider}", web::get().to(init_oidc))
+            .route(
+                "/oauth/oidc/{provider}/login",
+                web::get().to(login_with_oidc),
+            )
+            .service(
+                web::scope("/2fa")
+                    .wrap(AuthenticationGuard)
+                    .wrap(ScopeGuard::session_only())
+                    .route("", web::get().to(get_two_factor_status))
+                    .route("/enroll", web::post().to(enroll_two_factor))
+                    .route("/confirm", web::post().to(confirm_two_factor))
+                    .route("/disable", web::post().to(disable_two_factor))
+                    .route("/recovery-codes", web::post().to(regenerate_recovery_codes)),
+            )
             .service(
                 web::scope("")
                     .wrap(AuthenticationGuard)
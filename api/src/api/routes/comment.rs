@@ -1,13 +1,22 @@
 use actix_web::web;
 
-use crate::api::{
-    handlers::comment::*,
-    middlewares::{authentication_guard::AuthenticationGuard, org_guard::OrgGuard},
+use crate::{
+    api::{
+        handlers::comment::*,
+        middlewares::{
+            authentication_guard::AuthenticationGuard, org_guard::OrgGuard, scope_guard::ScopeGuard,
+        },
+    },
+    models::personal_access_token::TokenScope,
 };
 
 pub fn configure_comment_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/comments/{org_id}")
+            .wrap(ScopeGuard::new(
+                TokenScope::IssuesRead,
+                TokenScope::IssuesWrite,
+            ))
             .wrap(AuthenticationGuard)
             .wrap(OrgGuard)
             .route("", web::post().to(create_comment))
@@ -0,0 +1,18 @@
+use actix_web::web;
+
+use crate::api::{
+    handlers::identity::*,
+    middlewares::{authentication_guard::AuthenticationGuard, scope_guard::ScopeGuard},
+};
+
+pub fn configure_identity_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/identities")
+            .wrap(AuthenticationGuard)
+            .wrap(ScopeGuard::session_only())
+            .route("", web::get().to(list_identities))
+            .route("/{provider}/link", web::get().to(init_identity_link))
+            .route("/{provider}/link", web::post().to(link_identity))
+            .route("/{identity_id}", web::delete().to(unlink_identity)),
+    );
+}
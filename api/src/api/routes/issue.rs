@@ -1,19 +1,45 @@
 use actix_web::web;
 
-use crate::api::{
-    handlers::issue::*,
-    middlewares::{authentication_guard::AuthenticationGuard, org_guard::OrgGuard},
+use crate::{
+    api::{
+        handlers::issue::*,
+        middlewares::{
+            authentication_guard::AuthenticationGuard, org_guard::OrgGuard, scope_guard::ScopeGuard,
+        },
+    },
+    models::personal_access_token::TokenScope,
 };
 
 pub fn configure_issue_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/issues/{org_id}")
+            .wrap(ScopeGuard::new(
+                TokenScope::IssuesRead,
+                TokenScope::IssuesWrite,
+            ))
             .wrap(AuthenticationGuard)
             .wrap(OrgGuard)
             .route("", web::post().to(create_issue))
             .route("", web::get().to(get_issues))
+            .route("/export", web::get().to(export_issues))
+            .service(
+                web::resource("/import/csv/columns")
+                    .app_data(csv_json_config())
+                    .route(web::post().to(get_csv_columns)),
+            )
+            .service(
+                web::resource("/import/csv")
+                    .app_data(csv_json_config())
+                    .route(web::post().to(import_issues_csv)),
+            )
+            .route("/key/{key}", web::get().to(get_issue_by_key))
             .route("/{issue_id}", web::get().to(get_issue))
             .route("/{issue_id}", web::patch().to(update_issue))
             .route("/{issue_id}", web::delete().to(delete_issue)),
     );
 }
+
+// the file is sent whole inside the json body
+fn csv_json_config() -> web::JsonConfig {
+    web::JsonConfig::default().limit(16 * 1024 * 1024)
+}
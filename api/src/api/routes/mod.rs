@@ -2,8 +2,12 @@ use actix_web::web;
 
 mod auth;
 mod comment;
+mod identity;
 mod issue;
 mod org;
+mod personal_access_token;
+mod team;
+mod user;
 mod user_preferences;
 
 pub fn configure_routes(cfg: &mut web::ServiceConfig) {
@@ -12,7 +16,11 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
             .configure(auth::configure_auth_routes)
             .configure(org::configure_organization_routes)
             .configure(issue::configure_issue_routes)
+            .configure(team::configure_team_routes)
             .configure(user_preferences::configure_user_preferences_routes)
-            .configure(comment::configure_comment_routes),
+            .configure(comment::configure_comment_routes)
+            .configure(personal_access_token::configure_personal_access_token_routes)
+            .configure(identity::configure_identity_routes)
+            .configure(user::configure_user_routes),
     );
 }
@@ -1,35 +1,144 @@
 use crate::api::{
-    handlers::org::*,
+    handlers::{audit_log::*, import::*, org::*, org_export::*, role::*, webhook::*},
     middlewares::{
-        authentication_guard::AuthenticationGuard, org_guard::OrgGuard, role_guard::RoleGuard,
+        authentication_guard::AuthenticationGuard, org_guard::OrgGuard,
+        permission_guard::PermissionGuard, scope_guard::ScopeGuard,
     },
 };
-use crate::models::org::MemberRole;
+use crate::models::{personal_access_token::TokenScope, role::Permission};
 use actix_web::web;
 
 pub fn configure_organization_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/org")
+            .wrap(ScopeGuard::new(
+                TokenScope::IssuesRead,
+                TokenScope::OrgAdmin,
+            ))
             .wrap(AuthenticationGuard)
             .service(
                 web::scope("/{org_id}")
                     .wrap(OrgGuard)
                     .route("", web::get().to(get_org))
+                    // settings and deletion share the path, their handlers check the permission
+                    .route("", web::patch().to(update_org))
+                    .route("", web::delete().to(delete_org))
+                    // restoring and exports are for owners only, checked in the handlers
+                    .route("/restore", web::post().to(restore_org))
                     .route("/session-role", web::get().to(get_session_role))
+                    .route(
+                        "/session-permissions",
+                        web::get().to(get_session_permissions),
+                    )
+                    .route("/leave", web::post().to(leave_org))
+                    .route("/roles", web::get().to(list_roles))
+                    .service(
+                        web::scope("/roles")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::RoleManage))
+                            .route("", web::post().to(create_role))
+                            .route("/{role_id}", web::patch().to(update_role))
+                            .route("/{role_id}", web::delete().to(delete_role)),
+                    )
                     .service(
                         web::scope("/member")
-                            .wrap(RoleGuard::new(vec![MemberRole::Owner]))
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::MemberManage))
                             .route("/{member_id}", web::patch().to(update_member_role))
-                            .route("/{member_id}", web::delete().to(remove_member)),
+                            .route("/{member_id}", web::delete().to(remove_member))
+                            .route("/{member_id}/custom-role", web::put().to(assign_role))
+                            .route(
+                                "/{member_id}/transfer-ownership",
+                                web::post().to(transfer_ownership),
+                            ),
+                    )
+                    .service(
+                        web::scope("/domains")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::OrgDomains))
+                            .route("", web::post().to(add_domain))
+                            .route("", web::get().to(list_domains))
+                            .route("/{domain_id}", web::delete().to(remove_domain)),
+                    )
+                    .service(
+                        web::scope("/invites")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::MemberInvite))
+                            .route("", web::post().to(invite_user))
+                            .route("", web::get().to(list_org_invites))
+                            .route("/{invite_id}/resend", web::post().to(resend_invite))
+                            .route("/{invite_id}", web::delete().to(revoke_invite)),
+                    )
+                    .service(
+                        web::scope("/invite-links")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::MemberInvite))
+                            .route("", web::post().to(create_invite_link))
+                            .route("", web::get().to(list_invite_links))
+                            .route("/{link_id}", web::delete().to(revoke_invite_link)),
                     )
                     .service(
-                        web::scope("")
-                            .wrap(RoleGuard::new(vec![MemberRole::Admin, MemberRole::Owner]))
-                            .route("", web::patch().to(update_org))
-                            .route("", web::delete().to(delete_org))
-                            .route("/invites", web::post().to(invite_user))
-                            .route("/invites", web::get().to(list_org_invites))
-                            .route("/members", web::get().to(list_org_members)),
+                        web::scope("/exports")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .route("", web::post().to(request_export))
+                            .route("", web::get().to(list_exports))
+                            .route("/{export_id}", web::get().to(get_export))
+                            .route("/{export_id}/download", web::get().to(download_export)),
+                    )
+                    .service(
+                        web::scope("/imports")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::OrgImport))
+                            .route("", web::get().to(list_imports))
+                            .route("/github/authorize", web::get().to(authorize_github_import))
+                            .route("/github", web::post().to(start_github_import))
+                            .service(
+                                web::resource("/file")
+                                    .app_data(import_file_json_config())
+                                    .route(web::post().to(import_file)),
+                            )
+                            .route("/{import_id}", web::get().to(get_import))
+                            .route("/{import_id}/resume", web::post().to(resume_import)),
+                    )
+                    .service(
+                        web::scope("/audit-log")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::AuditView))
+                            .route("", web::get().to(list_audit_log))
+                            .route("/export", web::get().to(export_audit_log)),
+                    )
+                    .service(
+                        web::scope("/webhooks")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .wrap(PermissionGuard::new(Permission::WebhookManage))
+                            .route("", web::get().to(list_webhooks))
+                            .route("", web::post().to(create_webhook))
+                            .route("/{webhook_id}", web::get().to(get_webhook))
+                            .route("/{webhook_id}", web::patch().to(update_webhook))
+                            .route("/{webhook_id}", web::delete().to(delete_webhook))
+                            .route(
+                                "/{webhook_id}/secret",
+                                web::post().to(rotate_webhook_secret),
+                            )
+                            .route(
+                                "/{webhook_id}/test",
+                                web::post().to(send_webhook_test_event),
+                            )
+                            .route(
+                                "/{webhook_id}/deliveries",
+                                web::get().to(list_webhook_deliveries),
+                            ),
+                    )
+                    .service(
+                        web::scope("/members")
+                            .wrap(ScopeGuard::new(TokenScope::OrgAdmin, TokenScope::OrgAdmin))
+                            .route("", web::get().to(list_org_members))
+                            .service(
+                                web::scope("/{member_id}")
+                                    .wrap(PermissionGuard::new(Permission::MemberSuspend))
+                                    .route("/suspend", web::post().to(suspend_member))
+                                    .route("/reactivate", web::post().to(reactivate_member)),
+                            ),
                     ),
             )
             .route("", web::post().to(create_org))
@@ -39,8 +148,24 @@ pub fn configure_organization_routes(cfg: &mut web::ServiceConfig) {
     cfg.service(
         web::scope("/invites")
             .wrap(AuthenticationGuard)
+            .wrap(ScopeGuard::session_only())
             .route("", web::get().to(list_user_invites))
             .route("/{token}/{org_id}/accept", web::post().to(accept_invite))
             .route("/{token}", web::delete().to(cancel_invite)),
     );
+
+    cfg.service(
+        web::scope("/join")
+            .wrap(AuthenticationGuard)
+            .wrap(ScopeGuard::session_only())
+            .route("/links/{token}", web::get().to(preview_invite_link))
+            .route("/links/{token}", web::post().to(redeem_invite_link))
+            .route("/domains", web::get().to(list_joinable_orgs))
+            .route("/domains/{org_id}", web::post().to(join_by_domain)),
+    );
+}
+
+// the file is sent whole inside the json body and parsed in memory
+fn import_file_json_config() -> web::JsonConfig {
+    web::JsonConfig::default().limit(16 * 1024 * 1024)
 }
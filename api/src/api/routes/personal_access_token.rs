@@ -0,0 +1,17 @@
+use actix_web::web;
+
+use crate::api::{
+    handlers::personal_access_token::*,
+    middlewares::{authentication_guard::AuthenticationGuard, scope_guard::ScopeGuard},
+};
+
+pub fn configure_personal_access_token_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/access-tokens")
+            .wrap(AuthenticationGuard)
+            .wrap(ScopeGuard::session_only())
+            .route("", web::post().to(create_access_token))
+            .route("", web::get().to(list_access_tokens))
+            .route("/{token_id}", web::delete().to(revoke_access_token)),
+    );
+}
@@ -0,0 +1,38 @@
+use actix_web::web;
+
+use crate::{
+    api::{
+        handlers::team::*,
+        middlewares::{
+            authentication_guard::AuthenticationGuard, org_guard::OrgGuard,
+            permission_guard::PermissionGuard, scope_guard::ScopeGuard,
+        },
+    },
+    models::{personal_access_token::TokenScope, role::Permission},
+};
+
+pub fn configure_team_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/teams/{org_id}")
+            .wrap(ScopeGuard::new(
+                TokenScope::IssuesRead,
+                TokenScope::OrgAdmin,
+            ))
+            .wrap(AuthenticationGuard)
+            .wrap(OrgGuard)
+            .route("", web::get().to(list_teams))
+            .route("/{team_id}", web::get().to(get_team))
+            .service(
+                web::scope("")
+                    .wrap(PermissionGuard::new(Permission::TeamManage))
+                    .route("", web::post().to(create_team))
+                    .route("/{team_id}", web::patch().to(update_team))
+                    .route("/{team_id}", web::delete().to(delete_team))
+                    .route("/{team_id}/members", web::post().to(add_team_member))
+                    .route(
+                        "/{team_id}/members/{user_id}",
+                        web::delete().to(remove_team_member),
+                    ),
+            ),
+    );
+}
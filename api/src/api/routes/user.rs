@@ -0,0 +1,33 @@
+use actix_web::web;
+
+use crate::api::{
+    handlers::user::*,
+    middlewares::{authentication_guard::AuthenticationGuard, scope_guard::ScopeGuard},
+};
+
+pub fn configure_user_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(
+        web::scope("/users")
+            // followed from the confirmation email, possibly in another browser
+            .route("/verify-email", web::post().to(verify_email))
+            .service(
+                web::scope("/me")
+                    .wrap(AuthenticationGuard)
+                    .wrap(ScopeGuard::session_only())
+                    .route("", web::get().to(get_profile))
+                    .route("", web::patch().to(update_profile))
+                    .route("", web::delete().to(delete_account))
+                    .route("/email", web::post().to(change_email))
+                    .route(
+                        "/email/verification",
+                        web::post().to(resend_email_verification),
+                    ),
+            )
+            .service(
+                web::scope("/{user_id}")
+                    .wrap(AuthenticationGuard)
+                    .wrap(ScopeGuard::session_only())
+                    .route("/unlock", web::post().to(unlock_user)),
+            ),
+    );
+}
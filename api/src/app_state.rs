@@ -5,8 +5,28 @@ use sqlx::PgPool;
 use crate::{
     config::Config,
     services::{
-        auth::AuthService, comment::CommentService, issue::IssueService, oauth::OauthService,
-        org::OrgService, token::TokenService, user_preferences::UserPreferencesService,
+        audit_log::AuditLogService,
+        auth::AuthService,
+        comment::CommentService,
+        file_import::FileImportService,
+        identity::IdentityService,
+        import::ImportService,
+        issue::IssueService,
+        issue_csv::IssueCsvService,
+        lockout::LockoutService,
+        mailer::{mailer_from_config, Mailer},
+        oauth::OauthService,
+        oidc::OidcService,
+        org::OrgService,
+        org_export::OrgExportService,
+        personal_access_token::PersonalAccessTokenService,
+        role::RoleService,
+        team::TeamService,
+        token::TokenService,
+        two_factor::TwoFactorService,
+        user::UserService,
+        user_preferences::UserPreferencesService,
+        webhook::WebhookService,
     },
 };
 
@@ -14,32 +34,102 @@ use crate::{
 pub struct AppState {
     pub auth_service: Arc<AuthService>,
     pub token_service: Arc<TokenService>,
+    pub two_factor_service: Arc<TwoFactorService>,
+    pub personal_access_token_service: Arc<PersonalAccessTokenService>,
     pub org_service: Arc<OrgService>,
+    pub org_export_service: Arc<OrgExportService>,
+    pub import_service: Arc<ImportService>,
+    pub file_import_service: Arc<FileImportService>,
     pub issue_service: Arc<IssueService>,
+    pub issue_csv_service: Arc<IssueCsvService>,
+    pub team_service: Arc<TeamService>,
+    pub role_service: Arc<RoleService>,
+    pub audit_log_service: Arc<AuditLogService>,
+    pub user_service: Arc<UserService>,
     pub user_preferences_service: Arc<UserPreferencesService>,
     pub comment_service: Arc<CommentService>,
+    pub webhook_service: Arc<WebhookService>,
+    pub identity_service: Arc<IdentityService>,
     pub oauth_service: Arc<OauthService>,
+    pub oidc_service: Arc<OidcService>,
+    pub mailer: Arc<dyn Mailer>,
     pub config: Config,
 }
 
 impl AppState {
     pub async fn new(pool: PgPool, config: &Config) -> Result<Self, Box<dyn std::error::Error>> {
-        let token_service = Arc::new(TokenService::new().unwrap());
+        let token_service = Arc::new(TokenService::new(&config.token_config)?);
 
-        let oauth_service = Arc::new(OauthService::new(
+        let mailer = mailer_from_config(&config.mailer_config)?;
+
+        let lockout_service = Arc::new(LockoutService::new(pool.clone(), config));
+        let two_factor_service = Arc::new(TwoFactorService::new(
+            pool.clone(),
+            config,
+            lockout_service.clone(),
+        ));
+        let user_service = Arc::new(UserService::new(
             pool.clone(),
-            &config,
+            config,
+            two_factor_service.clone(),
+            lockout_service.clone(),
+            mailer.clone(),
+        ));
+
+        let auth_service = Arc::new(AuthService::new(
+            pool.clone(),
+            config,
             token_service.clone(),
+            two_factor_service.clone(),
+            user_service.clone(),
+            lockout_service,
+            mailer.clone(),
+        ));
+
+        let identity_service = Arc::new(IdentityService::new(pool.clone()));
+
+        let oauth_service = Arc::new(OauthService::new(
+            pool.clone(),
+            config,
+            auth_service.clone(),
+            two_factor_service.clone(),
+            identity_service.clone(),
         )?);
 
+        let oidc_service = Arc::new(OidcService::new(
+            pool.clone(),
+            config,
+            auth_service.clone(),
+            two_factor_service.clone(),
+            identity_service.clone(),
+        ));
+
         Ok(AppState {
-            token_service: token_service.clone(),
-            auth_service: Arc::new(AuthService::new(pool.clone(), token_service)),
-            org_service: Arc::new(OrgService::new(pool.clone())),
+            token_service,
+            auth_service,
+            two_factor_service,
+            personal_access_token_service: Arc::new(PersonalAccessTokenService::new(pool.clone())),
+            org_service: Arc::new(OrgService::new(pool.clone(), config, mailer.clone())),
+            org_export_service: Arc::new(OrgExportService::new(pool.clone())),
+            import_service: Arc::new(ImportService::new(
+                pool.clone(),
+                config,
+                oauth_service.clone(),
+            )),
+            file_import_service: Arc::new(FileImportService::new(pool.clone())),
+            user_service,
             user_preferences_service: Arc::new(UserPreferencesService::new(pool.clone())),
             issue_service: Arc::new(IssueService::new(pool.clone())),
+            issue_csv_service: Arc::new(IssueCsvService::new(pool.clone())),
+            team_service: Arc::new(TeamService::new(pool.clone())),
+            role_service: Arc::new(RoleService::new(pool.clone())),
+            audit_log_service: Arc::new(AuditLogService::new(pool.clone())),
             comment_service: Arc::new(CommentService::new(pool.clone())),
+            webhook_service: Arc::new(WebhookService::new(pool.clone(), config)?),
+            identity_service,
             oauth_service,
+            oidc_service,
+            mailer,
             config: config.clone(),
         })
     }
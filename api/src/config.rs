@@ -1,4 +1,7 @@
-use std::env;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ipnet::IpNet;
+use std::{collections::HashSet, env, fmt, fs, net::IpAddr, str::FromStr};
+use uuid::Uuid;
 
 use crate::errors::CustomError;
 
@@ -9,6 +12,135 @@ pub struct Config {
     pub github_client_id: String,
     pub github_client_secret: String,
     pub github_redirect_url: String,
+    pub github_api_url: String,
+    pub app_url: String,
+    pub oauth_redirect_allowlist: Vec<String>,
+    // forwarded client addresses are only believed when they come through these
+    pub trusted_proxies: Vec<IpNet>,
+    // lets webhooks reach loopback and private networks, for self-hosted setups
+    pub webhook_allow_private_targets: bool,
+    pub oidc_providers: Vec<OidcProviderConfig>,
+    pub password_policy: PasswordPolicy,
+    pub lockout_policy: LockoutPolicy,
+    pub totp_issuer: String,
+    pub token_config: TokenConfig,
+    pub mailer_config: MailerConfig,
+    // deleted orgs can be restored by an owner until the grace period is over
+    pub org_deletion_grace_days: i64,
+    // accounts that may act across the whole instance, like lifting an account lockout
+    pub instance_admins: Vec<Uuid>,
+}
+
+#[derive(Clone, Debug)]
+pub struct TokenConfig {
+    // the first key signs new tokens unless PASETO_ACTIVE_KEY_ID says otherwise,
+    // the rest are only kept around to verify tokens issued before a rotation
+    pub keys: Vec<TokenKey>,
+    pub active_key_id: String,
+    pub access_token_expiration: i64,
+    pub refresh_token_expiration: i64,
+    pub magic_link_expiration: i64,
+}
+
+#[derive(Clone)]
+pub struct TokenKey {
+    pub id: String,
+    pub key: [u8; 32],
+}
+
+impl fmt::Debug for TokenKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TokenKey")
+            .field("id", &self.id)
+            .field("key", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct MailerConfig {
+    pub from: String,
+    // without an smtp server emails are only written to the log, which is
+    // enough for local development
+    pub smtp: Option<SmtpConfig>,
+}
+
+#[derive(Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+}
+
+impl fmt::Debug for SmtpConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SmtpConfig")
+            .field("host", &self.host)
+            .field("port", &self.port)
+            .field("username", &self.username)
+            .field("password", &self.password.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct OidcProviderConfig {
+    pub id: String,
+    pub display_name: String,
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+    pub scopes: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub require_uppercase: bool,
+    pub require_lowercase: bool,
+    pub require_digit: bool,
+    pub require_special: bool,
+    pub reject_common: bool,
+    pub reject_user_info: bool,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            max_length: 128,
+            require_uppercase: true,
+            require_lowercase: true,
+            require_digit: true,
+            require_special: true,
+            reject_common: true,
+            reject_user_info: true,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LockoutPolicy {
+    pub max_account_attempts: i32,
+    pub max_ip_attempts: i32,
+    pub attempt_window_secs: i64,
+    pub base_lockout_secs: i64,
+    pub max_lockout_secs: i64,
+}
+
+impl Default for LockoutPolicy {
+    fn default() -> Self {
+        Self {
+            max_account_attempts: 5,
+            max_ip_attempts: 20,
+            attempt_window_secs: 15 * 60,
+            base_lockout_secs: 60,
+            max_lockout_secs: 24 * 60 * 60,
+        }
+    }
 }
 
 impl Config {
@@ -38,12 +170,377 @@ impl Config {
             CustomError::ConfigError("GITHUB_REDIRECT_URL environment variable not set".to_string())
         })?;
 
+        // overridable so imports can run against a mock of the github api
+        let github_api_url = env_or("GITHUB_API_URL", "https://api.github.com".to_string())?
+            .trim_end_matches('/')
+            .to_string();
+
+        // base url of the frontend, used to build links sent by email
+        let app_url = env_or("APP_URL", "http://localhost:5173".to_string())?
+            .trim_end_matches('/')
+            .to_string();
+
+        // origins the frontend may send users back to after an oauth login,
+        // relative paths are always allowed
+        let oauth_redirect_allowlist = env::var("OAUTH_REDIRECT_ALLOWLIST")
+            .map(|origins| {
+                origins
+                    .split(',')
+                    .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                    .filter(|origin| !origin.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // addresses or networks of the reverse proxies in front of the api, comma separated
+        let trusted_proxies = env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+            .map(|proxy| {
+                proxy
+                    .parse::<IpNet>()
+                    .or_else(|_| proxy.parse::<IpAddr>().map(IpNet::from))
+                    .map_err(|_| {
+                        CustomError::ConfigError(format!("Invalid trusted proxy {}", proxy))
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let webhook_allow_private_targets = env_or("WEBHOOK_ALLOW_PRIVATE_TARGETS", false)?;
+
+        let oidc_providers = OidcProviderConfig::from_env()?;
+        let password_policy = PasswordPolicy::from_env()?;
+        let lockout_policy = LockoutPolicy::from_env()?;
+        let totp_issuer = env_or("TOTP_ISSUER", "IssueApp".to_string())?;
+        let token_config = TokenConfig::from_env()?;
+        let mailer_config = MailerConfig::from_env()?;
+
+        let org_deletion_grace_days = env_or("ORG_DELETION_GRACE_DAYS", 30)?;
+        if org_deletion_grace_days < 0 {
+            return Err(CustomError::ConfigError(
+                "ORG_DELETION_GRACE_DAYS must not be negative".to_string(),
+            ));
+        }
+
+        // user ids, comma separated
+        let instance_admins = env::var("INSTANCE_ADMINS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(|id| {
+                id.parse::<Uuid>().map_err(|_| {
+                    CustomError::ConfigError(format!("Invalid instance admin user id {}", id))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
         Ok(Config {
             port,
             database_url,
             github_client_id,
             github_client_secret,
             github_redirect_url,
+            github_api_url,
+            app_url,
+            oauth_redirect_allowlist,
+            trusted_proxies,
+            webhook_allow_private_targets,
+            oidc_providers,
+            password_policy,
+            lockout_policy,
+            totp_issuer,
+            token_config,
+            mailer_config,
+            org_deletion_grace_days,
+            instance_admins,
+        })
+    }
+}
+
+// services wired up for tests, nothing is read from the environment
+#[cfg(test)]
+impl Config {
+    pub fn for_tests() -> Self {
+        Config {
+            port: 0,
+            database_url: String::new(),
+            github_client_id: "github-client".to_string(),
+            github_client_secret: "github-secret".to_string(),
+            github_redirect_url: "http://localhost:5173/auth/github/callback".to_string(),
+            github_api_url: "https://api.github.com".to_string(),
+            app_url: "http://localhost:5173".to_string(),
+            oauth_redirect_allowlist: Vec::new(),
+            trusted_proxies: Vec::new(),
+            webhook_allow_private_targets: false,
+            oidc_providers: Vec::new(),
+            password_policy: PasswordPolicy::default(),
+            lockout_policy: LockoutPolicy::default(),
+            totp_issuer: "IssueApp".to_string(),
+            token_config: TokenConfig {
+                keys: vec![TokenKey {
+                    id: "test".to_string(),
+                    key: [7; 32],
+                }],
+                active_key_id: "test".to_string(),
+                access_token_expiration: 15,
+                refresh_token_expiration: 7 * 24 * 60,
+                magic_link_expiration: 15,
+            },
+            mailer_config: MailerConfig {
+                from: "IssueApp <no-reply@localhost>".to_string(),
+                smtp: None,
+            },
+            org_deletion_grace_days: 30,
+            instance_admins: Vec::new(),
+        }
+    }
+}
+
+impl OidcProviderConfig {
+    // OIDC_PROVIDERS lists provider ids, each configured through OIDC_<ID>_* variables
+    pub fn from_env() -> Result<Vec<Self>, CustomError> {
+        let ids = env::var("OIDC_PROVIDERS").unwrap_or_default();
+
+        ids.split(',')
+            .map(str::trim)
+            .filter(|id| !id.is_empty())
+            .map(Self::from_env_for)
+            .collect()
+    }
+
+    fn from_env_for(id: &str) -> Result<Self, CustomError> {
+        if !id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            return Err(CustomError::ConfigError(format!(
+                "OIDC provider id {} may only contain lowercase letters, digits and dashes",
+                id
+            )));
+        }
+
+        // identity routes use the provider id as is, github is taken by the built-in provider
+        if id == "github" {
+            return Err(CustomError::ConfigError(
+                "OIDC provider id github is reserved".to_string(),
+            ));
+        }
+
+        let prefix = format!("OIDC_{}", id.to_ascii_uppercase().replace('-', "_"));
+        let required = |name: &str| {
+            let key = format!("{}_{}", prefix, name);
+            env::var(&key).map_err(|_| {
+                CustomError::ConfigError(format!("{} environment variable not set", key))
+            })
+        };
+
+        Ok(Self {
+            id: id.to_string(),
+            display_name: env_or(&format!("{}_DISPLAY_NAME", prefix), id.to_string())?,
+            issuer: required("ISSUER")?,
+            client_id: required("CLIENT_ID")?,
+            client_secret: required("CLIENT_SECRET")?,
+            redirect_url: required("REDIRECT_URL")?,
+            scopes: env_or(
+                &format!("{}_SCOPES", prefix),
+                "openid email profile".to_string(),
+            )?
+            .split_whitespace()
+            .map(str::to_string)
+            .collect(),
+        })
+    }
+}
+
+impl PasswordPolicy {
+    pub fn from_env() -> Result<Self, CustomError> {
+        let default = Self::default();
+
+        let policy = Self {
+            min_length: env_or("PASSWORD_MIN_LENGTH", default.min_length)?,
+            max_length: env_or("PASSWORD_MAX_LENGTH", default.max_length)?,
+            require_uppercase: env_or("PASSWORD_REQUIRE_UPPERCASE", default.require_uppercase)?,
+            require_lowercase: env_or("PASSWORD_REQUIRE_LOWERCASE", default.require_lowercase)?,
+            require_digit: env_or("PASSWORD_REQUIRE_DIGIT", default.require_digit)?,
+            require_special: env_or("PASSWORD_REQUIRE_SPECIAL", default.require_special)?,
+            reject_common: env_or("PASSWORD_REJECT_COMMON", default.reject_common)?,
+            reject_user_info: env_or("PASSWORD_REJECT_USER_INFO", default.reject_user_info)?,
+        };
+
+        if policy.min_length == 0 || policy.min_length > policy.max_length {
+            return Err(CustomError::ConfigError(
+                "PASSWORD_MIN_LENGTH must be between 1 and PASSWORD_MAX_LENGTH".to_string(),
+            ));
+        }
+
+        Ok(policy)
+    }
+}
+
+impl LockoutPolicy {
+    pub fn from_env() -> Result<Self, CustomError> {
+        let default = Self::default();
+
+        let policy = Self {
+            max_account_attempts: env_or(
+                "LOGIN_MAX_ACCOUNT_ATTEMPTS",
+                default.max_account_attempts,
+            )?,
+            max_ip_attempts: env_or("LOGIN_MAX_IP_ATTEMPTS", default.max_ip_attempts)?,
+            attempt_window_secs: env_or("LOGIN_ATTEMPT_WINDOW_SECS", default.attempt_window_secs)?,
+            base_lockout_secs: env_or("LOGIN_BASE_LOCKOUT_SECS", default.base_lockout_secs)?,
+            max_lockout_secs: env_or("LOGIN_MAX_LOCKOUT_SECS", default.max_lockout_secs)?,
+        };
+
+        if policy.max_account_attempts < 1 || policy.max_ip_attempts < 1 {
+            return Err(CustomError::ConfigError(
+                "LOGIN_MAX_ACCOUNT_ATTEMPTS and LOGIN_MAX_IP_ATTEMPTS must be at least 1"
+                    .to_string(),
+            ));
+        }
+
+        Ok(policy)
+    }
+
+    // doubles the lockout for every failed attempt past the limit
+    pub fn lockout_secs(&self, failed_attempts: i32, max_attempts: i32) -> i64 {
+        let exponent = (failed_attempts - max_attempts).clamp(0, 30) as u32;
+        self.base_lockout_secs
+            .saturating_mul(2_i64.saturating_pow(exponent))
+            .min(self.max_lockout_secs)
+    }
+}
+
+impl TokenConfig {
+    // keys are `id:base64key` pairs, comma separated in PASETO_KEYS or one per
+    // line in the file at PASETO_KEYS_FILE
+    pub fn from_env() -> Result<Self, CustomError> {
+        let raw_keys = match (env::var("PASETO_KEYS"), env::var("PASETO_KEYS_FILE")) {
+            (Ok(keys), _) => keys.split(',').map(str::to_string).collect::<Vec<_>>(),
+            (Err(_), Ok(path)) => fs::read_to_string(&path)
+                .map_err(|e| {
+                    CustomError::ConfigError(format!("Failed to read PASETO_KEYS_FILE: {}", e))
+                })?
+                .lines()
+                .map(str::to_string)
+                .collect(),
+            (Err(_), Err(_)) => {
+                return Err(CustomError::ConfigError(
+                    "PASETO_KEYS or PASETO_KEYS_FILE environment variable not set".to_string(),
+                ))
+            }
+        };
+
+        let keys = raw_keys
+            .iter()
+            .map(|entry| entry.trim())
+            .filter(|entry| !entry.is_empty() && !entry.starts_with('#'))
+            .map(TokenKey::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let first_key = keys.first().ok_or(CustomError::ConfigError(
+            "At least one PASETO key must be configured".to_string(),
+        ))?;
+
+        let mut seen_ids = HashSet::new();
+        if !keys.iter().all(|key| seen_ids.insert(key.id.as_str())) {
+            return Err(CustomError::ConfigError(
+                "PASETO key ids must be unique".to_string(),
+            ));
+        }
+
+        let active_key_id = env_or("PASETO_ACTIVE_KEY_ID", first_key.id.clone())?;
+        if !keys.iter().any(|key| key.id == active_key_id) {
+            return Err(CustomError::ConfigError(format!(
+                "PASETO_ACTIVE_KEY_ID {} does not match any configured key",
+                active_key_id
+            )));
+        }
+
+        let access_token_expiration = env_or("ACCESS_TOKEN_EXPIRATION_MINUTES", 15)?;
+        let refresh_token_expiration = env_or("REFRESH_TOKEN_EXPIRATION_MINUTES", 7 * 24 * 60)?;
+        let magic_link_expiration = env_or("MAGIC_LINK_EXPIRATION_MINUTES", 15)?;
+
+        if access_token_expiration < 1 || refresh_token_expiration < access_token_expiration {
+            return Err(CustomError::ConfigError(
+                "ACCESS_TOKEN_EXPIRATION_MINUTES must be at least 1 and not exceed REFRESH_TOKEN_EXPIRATION_MINUTES"
+                    .to_string(),
+            ));
+        }
+
+        if magic_link_expiration < 1 {
+            return Err(CustomError::ConfigError(
+                "MAGIC_LINK_EXPIRATION_MINUTES must be at least 1".to_string(),
+            ));
+        }
+
+        Ok(Self {
+            keys,
+            active_key_id,
+            access_token_expiration,
+            refresh_token_expiration,
+            magic_link_expiration,
+        })
+    }
+}
+
+impl MailerConfig {
+    pub fn from_env() -> Result<Self, CustomError> {
+        let from = env_or("MAIL_FROM", "IssueApp <no-reply@localhost>".to_string())?;
+
+        let smtp = match env::var("SMTP_HOST") {
+            Ok(host) => Some(SmtpConfig {
+                host,
+                port: env_or("SMTP_PORT", 587)?,
+                username: env::var("SMTP_USERNAME").ok(),
+                password: env::var("SMTP_PASSWORD").ok(),
+            }),
+            Err(_) => None,
+        };
+
+        Ok(Self { from, smtp })
+    }
+}
+
+impl TokenKey {
+    fn parse(entry: &str) -> Result<Self, CustomError> {
+        let (id, encoded) = entry.split_once(':').ok_or(CustomError::ConfigError(
+            "PASETO keys must be in the form id:base64key".to_string(),
+        ))?;
+
+        let id = id.trim();
+        if id.is_empty() {
+            return Err(CustomError::ConfigError(
+                "PASETO key id cannot be empty".to_string(),
+            ));
+        }
+
+        let key = STANDARD
+            .decode(encoded.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or(CustomError::ConfigError(format!(
+                "PASETO key {} must be 32 bytes encoded as base64",
+                id
+            )))?;
+
+        Ok(Self {
+            id: id.to_string(),
+            key,
         })
     }
 }
+
+// optional variables fall back to the default, but a value that is set and
+// cannot be parsed is a config error rather than being silently ignored
+fn env_or<T: FromStr>(key: &str, default: T) -> Result<T, CustomError> {
+    match env::var(key) {
+        Ok(value) => value
+            .parse()
+            .map_err(|_| CustomError::ConfigError(format!("Failed to parse {}", key))),
+        Err(_) => Ok(default),
+    }
+}
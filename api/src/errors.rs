@@ -17,7 +17,7 @@ pub enum CustomError {
     #[error("Conflict: {0}, {1}")]
     Conflict(String, String),
     #[error("Not Strong Password")]
-    NotStrongPassword,
+    NotStrongPassword(ValidationErrors),
     #[error("Invalid Confirmation Code")]
     InvalidConfirmationCode,
     #[error("Code Expired")]
@@ -33,7 +33,7 @@ pub enum CustomError {
     #[error("Account Disabled")]
     AccountDisabled,
     #[error("Too Many Failed Attempts")]
-    TooManyAttempts,
+    TooManyAttempts(i64),
     #[error("{0}")]
     InvalidToken(String),
     #[error("Unauthorized")]
@@ -42,10 +42,22 @@ pub enum CustomError {
     Forbidden,
     #[error("Token expired")]
     TokenExpired,
+    #[error("Two-factor authentication required")]
+    TwoFactorRequired,
+    #[error("Reauthentication required")]
+    ReauthenticationRequired,
+    #[error("Membership suspended")]
+    MembershipSuspended,
     #[error("Validation Error")]
     ValidationError(ValidationErrors),
     #[error("External Service Error: {0}")]
     ExternalServiceError(String),
+    #[error("Invalid OAuth state")]
+    OauthStateMismatch,
+    #[error("OAuth state expired")]
+    OauthStateExpired,
+    #[error("Redirect not allowed")]
+    RedirectNotAllowed,
 }
 
 impl ResponseError for CustomError {
@@ -57,7 +69,6 @@ impl ResponseError for CustomError {
                 code: "RES_001".to_string(),
                 message: "The requested resource could not be found".to_string(),
                 validation_errors: None,
-
             }),
 
             // Server Errors - 500
@@ -67,7 +78,6 @@ impl ResponseError for CustomError {
                     code: "SRV_001".to_string(),
                     message: "An unexpected error occurred".to_string(),
                     validation_errors: None,
-
                 })
             }
 
@@ -77,7 +87,6 @@ impl ResponseError for CustomError {
                 code: "BAD_001".to_string(),
                 message: "Bad request".to_string(),
                 validation_errors: None,
-
             }),
 
             // Database Errors - 500
@@ -87,7 +96,6 @@ impl ResponseError for CustomError {
                     code: "DB_001".to_string(),
                     message: message.to_string(),
                     validation_errors: None,
-
                 })
             }
 
@@ -98,37 +106,36 @@ impl ResponseError for CustomError {
                     code: "CFG_001".to_string(),
                     message: message.to_string(),
                     validation_errors: None,
-
                 })
             }
 
-
             // Conflict Errors - 409
             CustomError::Conflict(message, code) => HttpResponse::Conflict().json(ErrorResponse {
                 error: "Conflict".to_string(),
                 code: code.to_string(),
                 message: message.to_string(),
                 validation_errors: None,
-
             }),
 
             // Not Strong Password - 400
-            CustomError::NotStrongPassword => HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Not Strong Password".to_string(),
-                code: "PWD_001".to_string(),
-                message: "Password must be at least 8 characters long and contain at least one uppercase letter, one lowercase letter, one number, and one special character".to_string(),
-                validation_errors: None,
-
-            }),
+            CustomError::NotStrongPassword(errors) => {
+                HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Not Strong Password".to_string(),
+                    code: "PWD_001".to_string(),
+                    message: "The password does not meet the password policy".to_string(),
+                    validation_errors: Some(serde_json::to_value(errors).unwrap()),
+                })
+            }
 
             // Invalid Confirmation Code - 400
-            CustomError::InvalidConfirmationCode => HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Invalid confirmation code".to_string(),
-                code: "CON_002".to_string(),
-                message: "The provided confirmation code is invalid".to_string(),
-                validation_errors: None,
-
-            }),
+            CustomError::InvalidConfirmationCode => {
+                HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Invalid confirmation code".to_string(),
+                    code: "CON_002".to_string(),
+                    message: "The provided confirmation code is invalid".to_string(),
+                    validation_errors: None,
+                })
+            }
 
             // Code Expired - 400
             CustomError::CodeExpired => HttpResponse::BadRequest().json(ErrorResponse {
@@ -136,17 +143,17 @@ impl ResponseError for CustomError {
                 code: "CON_003".to_string(),
                 message: "The confirmation code has expired".to_string(),
                 validation_errors: None,
-
             }),
 
             // Account Already Confirmed - 400
-            CustomError::AccountAlreadyConfirmed => HttpResponse::BadRequest().json(ErrorResponse {
-                error: "Account already confirmed".to_string(),
-                code: "CON_004".to_string(),
-                message: "This account has already been confirmed".to_string(),
-                validation_errors: None,
-
-            }),
+            CustomError::AccountAlreadyConfirmed => {
+                HttpResponse::BadRequest().json(ErrorResponse {
+                    error: "Account already confirmed".to_string(),
+                    code: "CON_004".to_string(),
+                    message: "This account has already been confirmed".to_string(),
+                    validation_errors: None,
+                })
+            }
 
             // Account Not Found - 404
             CustomError::AccountNotFound => HttpResponse::NotFound().json(ErrorResponse {
@@ -154,7 +161,6 @@ impl ResponseError for CustomError {
                 code: "CON_005".to_string(),
                 message: "No account found with the provided email".to_string(),
                 validation_errors: None,
-
             }),
 
             // Invalid Credentials - 401
@@ -163,7 +169,6 @@ impl ResponseError for CustomError {
                 code: "AUTH_001".to_string(),
                 message: "The provided email or password is incorrect".to_string(),
                 validation_errors: None,
-
             }),
 
             // Account Not Verified - 403
@@ -172,7 +177,6 @@ impl ResponseError for CustomError {
                 code: "AUTH_002".to_string(),
                 message: "Please verify your email address before logging in".to_string(),
                 validation_errors: None,
-
             }),
 
             // Account Disabled - 403
@@ -181,26 +185,27 @@ impl ResponseError for CustomError {
                 code: "AUTH_003".to_string(),
                 message: "Your account has been disabled. Please contact support".to_string(),
                 validation_errors: None,
-
             }),
 
             // Too Many Failed Attempts - 429
-            CustomError::TooManyAttempts => HttpResponse::TooManyRequests().json(ErrorResponse {
-                error: "Too many attempts".to_string(),
-                code: "AUTH_004".to_string(),
-                message: "Too many failed login attempts. Please try again later".to_string(),
-                validation_errors: None,
-
-            }),
+            CustomError::TooManyAttempts(retry_after) => HttpResponse::TooManyRequests()
+                .insert_header(("Retry-After", retry_after.to_string()))
+                .json(ErrorResponse {
+                    error: "Too many attempts".to_string(),
+                    code: "AUTH_004".to_string(),
+                    message: "Too many failed login attempts. Please try again later".to_string(),
+                    validation_errors: None,
+                }),
 
             // Invalid Token - 401
-            CustomError::InvalidToken(message) => HttpResponse::Unauthorized().json(ErrorResponse {
-                error: "Invalid token".to_string(),
-                code: "AUTH_005".to_string(),
-                message: message.to_string(),
-                validation_errors: None,
-
-            }),
+            CustomError::InvalidToken(message) => {
+                HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Invalid token".to_string(),
+                    code: "AUTH_005".to_string(),
+                    message: message.to_string(),
+                    validation_errors: None,
+                })
+            }
 
             // Unauthorized - 401
             CustomError::Unauthorized => HttpResponse::Unauthorized().json(ErrorResponse {
@@ -208,7 +213,6 @@ impl ResponseError for CustomError {
                 code: "AUTH_006".to_string(),
                 message: "You are not authorized to access this resource".to_string(),
                 validation_errors: None,
-
             }),
 
             // Forbidden - 403
@@ -217,7 +221,6 @@ impl ResponseError for CustomError {
                 code: "AUTH_007".to_string(),
                 message: "You do not have permission to access this resource".to_string(),
                 validation_errors: None,
-
             }),
 
             // Token Expired - 401
@@ -228,6 +231,33 @@ impl ResponseError for CustomError {
                 validation_errors: None,
             }),
 
+            // Two-Factor Required - 403
+            CustomError::TwoFactorRequired => HttpResponse::Forbidden().json(ErrorResponse {
+                error: "Two-factor authentication required".to_string(),
+                code: "AUTH_009".to_string(),
+                message: "This organization requires two-factor authentication to be enabled"
+                    .to_string(),
+                validation_errors: None,
+            }),
+
+            // Reauthentication Required - 401
+            CustomError::ReauthenticationRequired => {
+                HttpResponse::Unauthorized().json(ErrorResponse {
+                    error: "Reauthentication required".to_string(),
+                    code: "AUTH_010".to_string(),
+                    message: "Please sign in again to confirm this change".to_string(),
+                    validation_errors: None,
+                })
+            }
+
+            // Membership Suspended - 403
+            CustomError::MembershipSuspended => HttpResponse::Forbidden().json(ErrorResponse {
+                error: "Membership suspended".to_string(),
+                code: "ORG_004".to_string(),
+                message: "Your membership in this organization has been suspended".to_string(),
+                validation_errors: None,
+            }),
+
             // Validation Error - 422
             CustomError::ValidationError(errors) => {
                 HttpResponse::BadRequest().json(ErrorResponse {
@@ -247,6 +277,31 @@ impl ResponseError for CustomError {
                     validation_errors: None,
                 })
             }
+
+            // OAuth State Mismatch - 400
+            CustomError::OauthStateMismatch => HttpResponse::BadRequest().json(ErrorResponse {
+                error: "Invalid OAuth state".to_string(),
+                code: "OAUTH_001".to_string(),
+                message: "The login attempt could not be verified. Please start the login again"
+                    .to_string(),
+                validation_errors: None,
+            }),
+
+            // OAuth State Expired - 400
+            CustomError::OauthStateExpired => HttpResponse::BadRequest().json(ErrorResponse {
+                error: "OAuth state expired".to_string(),
+                code: "OAUTH_002".to_string(),
+                message: "The login attempt has expired. Please start the login again".to_string(),
+                validation_errors: None,
+            }),
+
+            // Redirect Not Allowed - 400
+            CustomError::RedirectNotAllowed => HttpResponse::BadRequest().json(ErrorResponse {
+                error: "Redirect not allowed".to_string(),
+                code: "OAUTH_003".to_string(),
+                message: "The requested redirect URL is not allowed".to_string(),
+                validation_errors: None,
+            }),
         }
     }
 }
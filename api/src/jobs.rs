@@ -0,0 +1,57 @@
+use std::time::Duration;
+
+use actix_web::web;
+
+use crate::app_state::AppState;
+
+const MAINTENANCE_INTERVAL_SECS: u64 = 15 * 60;
+const WEBHOOK_DELIVERY_INTERVAL_SECS: u64 = 5;
+
+// runs on the server's runtime, a failed round is logged and retried on the next tick
+pub fn spawn_maintenance(state: web::Data<AppState>) {
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            actix_web::rt::time::interval(Duration::from_secs(MAINTENANCE_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            match state.org_service.purge_due_orgs().await {
+                Ok(purged) => {
+                    for org_id in purged {
+                        log::info!("Purged org {} after its deletion grace period", org_id);
+                    }
+                }
+                Err(e) => log::error!("Failed to purge deleted orgs: {}", e),
+            }
+
+            if let Err(e) = state.org_export_service.clean_up_exports().await {
+                log::error!("Failed to clean up org exports: {}", e);
+            }
+
+            if let Err(e) = state.import_service.resume_stale_imports().await {
+                log::error!("Failed to resume interrupted imports: {}", e);
+            }
+
+            if let Err(e) = state.webhook_service.clean_up_deliveries().await {
+                log::error!("Failed to clean up webhook deliveries: {}", e);
+            }
+        }
+    });
+}
+
+// polls the delivery queue, so events queued by any instance and retries that came due get sent
+pub fn spawn_webhook_deliveries(state: web::Data<AppState>) {
+    actix_web::rt::spawn(async move {
+        let mut interval =
+            actix_web::rt::time::interval(Duration::from_secs(WEBHOOK_DELIVERY_INTERVAL_SECS));
+
+        loop {
+            interval.tick().await;
+
+            if let Err(e) = state.webhook_service.deliver_due().await {
+                log::error!("Failed to deliver webhooks: {}", e);
+            }
+        }
+    });
+}
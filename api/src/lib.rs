@@ -2,6 +2,7 @@ pub mod api;
 pub mod app_state;
 pub mod config;
 pub mod errors;
+pub mod jobs;
 pub mod models;
 pub mod repositories;
 pub mod services;
@@ -7,6 +7,7 @@ use api::{
     },
     app_state::AppState,
     config,
+    jobs::{spawn_maintenance, spawn_webhook_deliveries},
     utils::logger::setup_logger,
 };
 
@@ -15,19 +16,21 @@ use sqlx::PgPool;
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     setup_logger();
-    let cfg =
-        config::Config::new().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+    let cfg = config::Config::new().map_err(std::io::Error::other)?;
 
     let pool = PgPool::connect(&cfg.database_url)
         .await
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .map_err(std::io::Error::other)?;
 
     let state = web::Data::new(
         AppState::new(pool.clone(), &cfg)
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?,
+            .map_err(|e| std::io::Error::other(e.to_string()))?,
     );
 
+    spawn_maintenance(state.clone());
+    spawn_webhook_deliveries(state.clone());
+
     HttpServer::new(move || {
         let cors = Cors::permissive();
         App::new()
@@ -0,0 +1,248 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value as JsonValue};
+use uuid::Uuid;
+use validator_derive::Validate;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEvent {
+    #[serde(rename = "org.updated")]
+    OrgUpdated,
+    #[serde(rename = "org.deletion_scheduled")]
+    OrgDeletionScheduled,
+    #[serde(rename = "org.restored")]
+    OrgRestored,
+    #[serde(rename = "org.export_requested")]
+    OrgExportRequested,
+    #[serde(rename = "org.export_downloaded")]
+    OrgExportDownloaded,
+    #[serde(rename = "org.import_started")]
+    OrgImportStarted,
+    #[serde(rename = "org.import_resumed")]
+    OrgImportResumed,
+    #[serde(rename = "org.file_imported")]
+    OrgFileImported,
+    #[serde(rename = "member.invited")]
+    MemberInvited,
+    #[serde(rename = "member.invite_revoked")]
+    MemberInviteRevoked,
+    #[serde(rename = "member.joined")]
+    MemberJoined,
+    #[serde(rename = "member.removed")]
+    MemberRemoved,
+    #[serde(rename = "member.left")]
+    MemberLeft,
+    // the built-in role or the custom role of a member
+    #[serde(rename = "member.role_changed")]
+    MemberRoleChanged,
+    #[serde(rename = "member.suspended")]
+    MemberSuspended,
+    #[serde(rename = "member.reactivated")]
+    MemberReactivated,
+    #[serde(rename = "org.ownership_transferred")]
+    OwnershipTransferred,
+    #[serde(rename = "invite_link.created")]
+    InviteLinkCreated,
+    #[serde(rename = "invite_link.revoked")]
+    InviteLinkRevoked,
+    #[serde(rename = "domain.added")]
+    DomainAdded,
+    #[serde(rename = "domain.removed")]
+    DomainRemoved,
+    #[serde(rename = "role.created")]
+    RoleCreated,
+    #[serde(rename = "role.updated")]
+    RoleUpdated,
+    #[serde(rename = "role.deleted")]
+    RoleDeleted,
+    #[serde(rename = "webhook.created")]
+    WebhookCreated,
+    // also when its secret is rotated
+    #[serde(rename = "webhook.updated")]
+    WebhookUpdated,
+    #[serde(rename = "webhook.deleted")]
+    WebhookDeleted,
+    #[serde(rename = "issue.deleted")]
+    IssueDeleted,
+    #[serde(rename = "access_token.created")]
+    AccessTokenCreated,
+}
+
+impl AuditEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditEvent::OrgUpdated => "org.updated",
+            AuditEvent::OrgDeletionScheduled => "org.deletion_scheduled",
+            AuditEvent::OrgRestored => "org.restored",
+            AuditEvent::OrgExportRequested => "org.export_requested",
+            AuditEvent::OrgExportDownloaded => "org.export_downloaded",
+            AuditEvent::OrgImportStarted => "org.import_started",
+            AuditEvent::OrgImportResumed => "org.import_resumed",
+            AuditEvent::OrgFileImported => "org.file_imported",
+            AuditEvent::MemberInvited => "member.invited",
+            AuditEvent::MemberInviteRevoked => "member.invite_revoked",
+            AuditEvent::MemberJoined => "member.joined",
+            AuditEvent::MemberRemoved => "member.removed",
+            AuditEvent::MemberLeft => "member.left",
+            AuditEvent::MemberRoleChanged => "member.role_changed",
+            AuditEvent::MemberSuspended => "member.suspended",
+            AuditEvent::MemberReactivated => "member.reactivated",
+            AuditEvent::OwnershipTransferred => "org.ownership_transferred",
+            AuditEvent::InviteLinkCreated => "invite_link.created",
+            AuditEvent::InviteLinkRevoked => "invite_link.revoked",
+            AuditEvent::DomainAdded => "domain.added",
+            AuditEvent::DomainRemoved => "domain.removed",
+            AuditEvent::RoleCreated => "role.created",
+            AuditEvent::RoleUpdated => "role.updated",
+            AuditEvent::RoleDeleted => "role.deleted",
+            AuditEvent::WebhookCreated => "webhook.created",
+            AuditEvent::WebhookUpdated => "webhook.updated",
+            AuditEvent::WebhookDeleted => "webhook.deleted",
+            AuditEvent::IssueDeleted => "issue.deleted",
+            AuditEvent::AccessTokenCreated => "access_token.created",
+        }
+    }
+
+    // what the target id of the entry points at
+    pub fn target_type(&self) -> &'static str {
+        match self {
+            AuditEvent::OrgUpdated | AuditEvent::OrgDeletionScheduled | AuditEvent::OrgRestored => {
+                "org"
+            }
+            AuditEvent::OrgExportRequested | AuditEvent::OrgExportDownloaded => "export",
+            AuditEvent::OrgImportStarted
+            | AuditEvent::OrgImportResumed
+            | AuditEvent::OrgFileImported => "import",
+            AuditEvent::MemberInvited | AuditEvent::MemberInviteRevoked => "invite",
+            AuditEvent::MemberJoined
+            | AuditEvent::MemberRemoved
+            | AuditEvent::MemberLeft
+            | AuditEvent::MemberRoleChanged
+            | AuditEvent::MemberSuspended
+            | AuditEvent::MemberReactivated
+            | AuditEvent::OwnershipTransferred => "user",
+            AuditEvent::InviteLinkCreated | AuditEvent::InviteLinkRevoked => "invite_link",
+            AuditEvent::DomainAdded | AuditEvent::DomainRemoved => "domain",
+            AuditEvent::RoleCreated | AuditEvent::RoleUpdated | AuditEvent::RoleDeleted => "role",
+            AuditEvent::WebhookCreated
+            | AuditEvent::WebhookUpdated
+            | AuditEvent::WebhookDeleted => "webhook",
+            AuditEvent::IssueDeleted => "issue",
+            AuditEvent::AccessTokenCreated => "access_token",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    // kept for display, the actor may have deleted their account since
+    pub actor_username: Option<String>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+    pub created_at: DateTime<Utc>,
+}
+
+// what happened to which target, the actor and client come from the request
+#[derive(Debug)]
+pub struct AuditEntry {
+    pub event: AuditEvent,
+    pub target_id: Option<Uuid>,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+impl AuditEntry {
+    pub fn new(event: AuditEvent, target_id: Option<Uuid>) -> Self {
+        Self {
+            event,
+            target_id,
+            before: None,
+            after: None,
+        }
+    }
+
+    pub fn before(mut self, before: JsonValue) -> Self {
+        self.before = Some(before);
+        self
+    }
+
+    pub fn after(mut self, after: JsonValue) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    // keeps only the top level fields that actually changed
+    pub fn changes(self, before: JsonValue, after: JsonValue) -> Self {
+        match (before, after) {
+            (JsonValue::Object(before), JsonValue::Object(after)) => {
+                let (before, after): (Map<_, _>, Map<_, _>) = before
+                    .into_iter()
+                    .filter_map(|(key, old)| {
+                        let new = after.get(&key).cloned().unwrap_or(JsonValue::Null);
+                        (old != new).then(|| ((key.clone(), old), (key, new)))
+                    })
+                    .unzip();
+                self.before(JsonValue::Object(before))
+                    .after(JsonValue::Object(after))
+            }
+            (before, after) => self.before(before).after(after),
+        }
+    }
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListAuditLogQuery {
+    pub action: Option<AuditEvent>,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[validate(range(min = 1, message = "Page starts at 1"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100, message = "Page size must be between 1 and 100"))]
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AuditLogPageResponse {
+    pub entries: Vec<AuditLog>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateAuditLogData {
+    pub org_id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub target_type: String,
+    pub target_id: Option<Uuid>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub before: Option<JsonValue>,
+    pub after: Option<JsonValue>,
+}
+
+#[derive(Debug)]
+pub struct AuditLogFilter {
+    pub action: Option<String>,
+    pub actor_id: Option<Uuid>,
+    pub target_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
@@ -2,16 +2,22 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 use uuid::Uuid;
+use validator_derive::Validate;
+
+use super::two_factor::TwoFactorChallengeResponse;
+use crate::utils::validation::{validate_email, validate_username};
 
 // --- data models ---
 
-#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct User {
     pub id: Uuid,
     pub email: String,
     pub username: String,
+    #[serde(skip_serializing)]
     pub password_hash: Option<String>,
     pub is_email_verified: bool,
+    #[serde(skip_serializing)]
     pub email_verification_token: Option<String>,
     pub email_verification_expires_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
@@ -20,6 +26,7 @@ pub struct User {
     pub avatar_url: Option<String>,
     pub github_id: Option<String>,
     pub github_url: Option<String>,
+    pub display_name: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
@@ -56,12 +63,97 @@ pub struct AuthLog {
     pub details: Option<JsonValue>,
 }
 
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct AuthLockout {
+    pub id: Uuid,
+    pub scope: String,
+    pub identifier: String,
+    pub failed_attempts: i32,
+    pub last_failed_at: DateTime<Utc>,
+    pub locked_until: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AuthEvent {
+    LoginSuccess,
+    LoginFailed,
+    LoginBlocked,
+    AccountUnlocked,
+    TwoFactorChallenge,
+    TwoFactorEnabled,
+    TwoFactorDisabled,
+    RecoveryCodeUsed,
+    RecoveryCodesRegenerated,
+    AccessTokenCreated,
+    AccessTokenRevoked,
+    IdentityLinked,
+    IdentityUnlinked,
+    MagicLinkRequested,
+    EmailChangeRequested,
+    EmailChanged,
+    EmailVerified,
+    AccountDeleted,
+}
+
+impl AuthEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuthEvent::LoginSuccess => "login_success",
+            AuthEvent::LoginFailed => "login_failed",
+            AuthEvent::LoginBlocked => "login_blocked",
+            AuthEvent::AccountUnlocked => "account_unlocked",
+            AuthEvent::TwoFactorChallenge => "two_factor_challenge",
+            AuthEvent::TwoFactorEnabled => "two_factor_enabled",
+            AuthEvent::TwoFactorDisabled => "two_factor_disabled",
+            AuthEvent::RecoveryCodeUsed => "recovery_code_used",
+            AuthEvent::RecoveryCodesRegenerated => "recovery_codes_regenerated",
+            AuthEvent::AccessTokenCreated => "access_token_created",
+            AuthEvent::AccessTokenRevoked => "access_token_revoked",
+            AuthEvent::IdentityLinked => "identity_linked",
+            AuthEvent::IdentityUnlinked => "identity_unlinked",
+            AuthEvent::MagicLinkRequested => "magic_link_requested",
+            AuthEvent::EmailChangeRequested => "email_change_requested",
+            AuthEvent::EmailChanged => "email_changed",
+            AuthEvent::EmailVerified => "email_verified",
+            AuthEvent::AccountDeleted => "account_deleted",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LockoutScope {
+    Account,
+    Ip,
+}
+
+impl LockoutScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LockoutScope::Account => "account",
+            LockoutScope::Ip => "ip",
+        }
+    }
+}
+
 // --- request/response models ---
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Validate)]
 pub struct RegisterRequest {
+    #[validate(
+        length(
+            min = 3,
+            max = 50,
+            message = "Username must be between 3 and 50 characters"
+        ),
+        custom(function = "validate_username")
+    )]
     pub username: String,
+    #[validate(
+        length(max = 255, message = "Email cannot be longer than 255 characters"),
+        custom(function = "validate_email")
+    )]
     pub email: String,
+    #[validate(length(min = 1, message = "Password cannot be empty"))]
     pub password: String,
 }
 
@@ -72,9 +164,11 @@ pub struct RegisterResponse {
     pub email: String,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, Validate)]
 pub struct LoginRequest {
+    #[validate(custom(function = "validate_email"))]
     pub email: String,
+    #[validate(length(min = 1, message = "Password cannot be empty"))]
     pub password: String,
 }
 
@@ -87,6 +181,16 @@ pub struct LoginResponse {
     pub refresh_token: String,
     pub expires_in_refresh: i64,
     pub expires_in_access: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+}
+
+// password logins of users with 2fa enabled stop at a challenge instead of the token pair
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResult {
+    Tokens(LoginResponse),
+    TwoFactorRequired(TwoFactorChallengeResponse),
 }
 
 // --- repository models ---
@@ -98,13 +202,6 @@ pub struct CreateUserData {
     pub password: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct UpdateUserData {
-    pub email: Option<String>,
-    pub username: Option<String>,
-    pub password: Option<String>,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateRefreshTokenData {
     pub user_id: Uuid,
@@ -123,22 +220,28 @@ pub struct CreateRevokedTokenData {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
-pub struct CreateGithubUser {
-    pub email: String,
-    pub username: String,
-    pub github_id: String,
-    pub github_url: String,
-    pub avatar_url: Option<String>,
+pub struct CreateAuthLogData {
+    pub user_id: Option<Uuid>,
+    pub event_type: String,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub details: Option<JsonValue>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateGithubUser {
-    pub email: Option<String>,
-    pub github_id: Option<String>,
+    pub github_id: String,
     pub github_url: Option<String>,
     pub avatar_url: Option<String>,
 }
 
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum GithubLoginResult {
+    Tokens(Box<UserGithubResponse>),
+    TwoFactorRequired(TwoFactorChallengeResponse),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UserGithubResponse {
     pub user: User,
@@ -146,4 +249,6 @@ pub struct UserGithubResponse {
     pub refresh_token: String,
     pub access_token_expiration: i64,
     pub refresh_token_expiration: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
 }
@@ -1,13 +1,58 @@
 use uuid::Uuid;
 
-use super::org::Org;
+use super::{
+    org::{MemberRole, Org},
+    personal_access_token::TokenScope,
+    role::Permission,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct UserContext {
     pub user_id: Option<Uuid>,
+    // set when the request was authenticated with a personal access token
+    pub access_token: Option<AccessTokenContext>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AccessTokenContext {
+    pub token_id: Uuid,
+    pub scopes: Vec<TokenScope>,
+    pub org_ids: Option<Vec<Uuid>>,
+}
+
+impl AccessTokenContext {
+    // write access implies read access, and org admins can always read the org
+    pub fn has_scope(&self, scope: TokenScope) -> bool {
+        self.scopes.iter().any(|granted| {
+            *granted == scope
+                || (scope == TokenScope::IssuesRead
+                    && matches!(granted, TokenScope::IssuesWrite | TokenScope::OrgAdmin))
+        })
+    }
+
+    pub fn allows_org(&self, org_id: Uuid) -> bool {
+        self.org_ids
+            .as_ref()
+            .is_none_or(|org_ids| org_ids.contains(&org_id))
+    }
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct OrgContext {
     pub org: Option<Org>,
+    pub role: Option<MemberRole>,
+    // resolved once by the org guard so permission checks need no query
+    pub permissions: Vec<Permission>,
+}
+
+impl OrgContext {
+    pub fn has_permission(&self, permission: Permission) -> bool {
+        self.permissions.contains(&permission)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
 }
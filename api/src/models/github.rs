@@ -1,3 +1,4 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 #[derive(Deserialize, Debug)]
@@ -53,3 +54,50 @@ pub struct UserEmail {
     pub verified: bool,
     pub visibility: Option<String>,
 }
+
+// --- import models ---
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubUser {
+    pub id: u64,
+    pub login: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubLabel {
+    pub name: String,
+    pub color: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubIssue {
+    pub id: i64,
+    pub number: i32,
+    pub title: String,
+    pub body: Option<String>,
+    pub state: String,
+    pub state_reason: Option<String>,
+    pub user: Option<GithubUser>,
+    #[serde(default)]
+    pub labels: Vec<GithubLabel>,
+    #[serde(default)]
+    pub assignees: Vec<GithubUser>,
+    pub comments: i32,
+    // the issues endpoint lists pull requests too, they carry this field
+    pub pull_request: Option<serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GithubComment {
+    pub id: i64,
+    pub body: Option<String>,
+    pub user: Option<GithubUser>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct GithubSearchResult {
+    pub total_count: i64,
+}
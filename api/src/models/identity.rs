@@ -0,0 +1,45 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserIdentity {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub linked_at: DateTime<Utc>,
+}
+
+// what a provider told us about the account after a successful authorization
+#[derive(Debug)]
+pub struct ExternalIdentity {
+    pub provider: String,
+    pub subject: String,
+    pub email: Option<String>,
+    pub email_verified: bool,
+    pub username: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateExternalUser {
+    pub email: String,
+    pub username: String,
+    pub avatar_url: Option<String>,
+    pub is_email_verified: bool,
+    pub provider: String,
+    pub subject: String,
+}
+
+#[derive(Debug)]
+pub enum UnlinkIdentityResult {
+    Unlinked(UserIdentity),
+    NotFound,
+    LastLoginMethod,
+}
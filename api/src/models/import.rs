@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use validator::ValidationError;
+use validator_derive::Validate;
+
+use super::issue::{IssuePriority, IssueStatus};
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "import_source", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportSource {
+    Github,
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "import_status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ImportFileFormat {
+    // the xml of a jira issue search
+    JiraXml,
+    JiraCsv,
+    LinearCsv,
+}
+
+// the access token used to read the source never leaves the database
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrgImport {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub requested_by: Option<Uuid>,
+    pub source: ImportSource,
+    pub repository: String,
+    pub team_id: Option<Uuid>,
+    pub preserve_numbers: bool,
+    pub open_status: IssueStatus,
+    pub closed_status: IssueStatus,
+    pub status: ImportStatus,
+    // unknown until the source reported how many issues there are
+    pub total_issues: Option<i32>,
+    pub imported_issues: i32,
+    pub imported_comments: i32,
+    // issues whose original number was already taken and got the next free one
+    pub renumbered_issues: i32,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct GithubImportRequest {
+    // from the callback of the authorization started for the import
+    #[validate(length(min = 1, message = "Code cannot be empty"))]
+    pub code: String,
+    #[validate(length(min = 1, message = "State cannot be empty"))]
+    pub state: String,
+    #[validate(custom(function = "validate_repository"))]
+    pub repository: String,
+    pub team_id: Option<Uuid>,
+    pub preserve_numbers: Option<bool>,
+    pub open_status: Option<IssueStatus>,
+    pub closed_status: Option<IssueStatus>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FileImportRequest {
+    pub format: ImportFileFormat,
+    #[validate(length(min = 1, message = "The file is empty"))]
+    pub file: String,
+    pub team_id: Option<Uuid>,
+    // people of the export as the report lists them, for those whose email, username
+    // or display name does not match a member
+    pub user_mapping: Option<HashMap<String, Uuid>>,
+    // reports what would be imported without creating anything
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum UnmappedKind {
+    Status,
+    Priority,
+    // author of an issue or comment
+    User,
+    Assignee,
+    Parent,
+    Date,
+}
+
+// a value of the export with no counterpart here and what was used instead
+#[derive(Debug, Serialize)]
+pub struct UnmappedValue {
+    pub kind: UnmappedKind,
+    pub value: String,
+    pub occurrences: usize,
+    pub replaced_with: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileImportedIssue {
+    pub external_key: String,
+    pub issue_id: Uuid,
+    pub number: i32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileImportResponse {
+    pub dry_run: bool,
+    pub issues: usize,
+    pub sub_issues: usize,
+    pub comments: usize,
+    pub unmapped: Vec<UnmappedValue>,
+    // empty on a dry run
+    pub imported: Vec<FileImportedIssue>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateImportData {
+    pub org_id: Uuid,
+    pub requested_by: Uuid,
+    pub source: ImportSource,
+    pub repository: String,
+    pub team_id: Option<Uuid>,
+    pub preserve_numbers: bool,
+    pub open_status: IssueStatus,
+    pub closed_status: IssueStatus,
+    pub access_token: String,
+}
+
+// what a running import needs to pick up where it stopped
+#[derive(Debug, Clone)]
+pub struct ImportJob {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub requested_by: Option<Uuid>,
+    pub repository: String,
+    pub team_id: Option<Uuid>,
+    pub preserve_numbers: bool,
+    pub open_status: IssueStatus,
+    pub closed_status: IssueStatus,
+    pub access_token: Option<String>,
+    pub next_page: i32,
+}
+
+#[derive(Debug)]
+pub struct ImportedLabel {
+    pub name: String,
+    pub color: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct ImportedComment {
+    pub creator_id: Uuid,
+    pub content: JsonValue,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct ImportedIssue {
+    pub external_id: i64,
+    // the original number to keep, the next free number is used when it is taken
+    pub number: Option<i32>,
+    pub title: String,
+    pub description: Option<JsonValue>,
+    pub status: IssueStatus,
+    pub creator_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub labels: Vec<ImportedLabel>,
+    pub assignee_ids: Vec<Uuid>,
+    pub comments: Vec<ImportedComment>,
+}
+
+#[derive(Debug)]
+pub struct ImportedFileIssue {
+    // index of the parent among the issues of the same file
+    pub parent: Option<usize>,
+    pub title: String,
+    pub description: Option<JsonValue>,
+    pub status: IssueStatus,
+    pub priority: IssuePriority,
+    pub due_date: Option<DateTime<Utc>>,
+    pub creator_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub labels: Vec<ImportedLabel>,
+    pub assignee_ids: Vec<Uuid>,
+    pub comments: Vec<ImportedComment>,
+}
+
+// --- validators funcs ---
+
+fn validate_repository(repository: &str) -> Result<(), ValidationError> {
+    let repository_rgx = Regex::new(r"^[A-Za-z0-9-]+/[A-Za-z0-9._-]+$").unwrap();
+    if !repository_rgx.is_match(repository) {
+        return Err(ValidationError::new("repository must look like owner/name"));
+    }
+    Ok(())
+}
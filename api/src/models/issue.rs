@@ -31,10 +31,47 @@ pub enum IssueStatus {
     Blocked,
 }
 
+// names as people write them in spreadsheets, "In progress" and "IN_PROGRESS" alike
+impl IssueStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match normalized_name(name).as_str() {
+            "backlog" => Some(IssueStatus::Backlog),
+            "todo" => Some(IssueStatus::Todo),
+            "inprogress" => Some(IssueStatus::InProgress),
+            "inreview" => Some(IssueStatus::InReview),
+            "done" => Some(IssueStatus::Done),
+            "canceled" | "cancelled" => Some(IssueStatus::Canceled),
+            "blocked" => Some(IssueStatus::Blocked),
+            _ => None,
+        }
+    }
+}
+
+impl IssuePriority {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match normalized_name(name).as_str() {
+            "urgent" => Some(IssuePriority::Urgent),
+            "high" => Some(IssuePriority::High),
+            "medium" => Some(IssuePriority::Medium),
+            "low" => Some(IssuePriority::Low),
+            _ => None,
+        }
+    }
+}
+
+pub fn normalized_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
 pub struct Issue {
     pub id: Uuid,
     pub org_id: Uuid,
+    // numbered under the team key instead of the org id when set
+    pub team_id: Option<Uuid>,
     pub creator_id: Uuid,
     pub number: i32,
     pub title: String,
@@ -47,6 +84,18 @@ pub struct Issue {
     pub updated_at: DateTime<Utc>,
 }
 
+// labels are shared by the issues of an org
+#[derive(Debug, Serialize, Deserialize, FromRow, Clone)]
+pub struct Label {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
 // --- request/response models ---
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -54,6 +103,7 @@ pub struct IssueRequest {
     #[validate(length(min = 1, message = "Title cannot be empty"))]
     pub title: String,
     pub description: Option<serde_json::Value>,
+    pub team_id: Option<Uuid>,
 
     #[validate(custom(function = "validate_priority"))]
     pub priority: IssuePriority,
@@ -82,6 +132,22 @@ pub struct UpdateIssueRequest {
     pub due_date: Option<DateTime<Utc>>,
 
     pub remove_due_date: Option<bool>,
+
+    // moving an issue to another team gives it the next number of that team
+    pub team_id: Option<Uuid>,
+    pub remove_team: Option<bool>,
+}
+
+// shared by the issue list and its csv export
+#[derive(Debug, Deserialize)]
+pub struct IssueListQuery {
+    pub team_id: Option<Uuid>,
+    pub status: Option<IssueStatus>,
+    pub priority: Option<IssuePriority>,
+    pub creator_id: Option<Uuid>,
+    pub assignee_id: Option<Uuid>,
+    // label name
+    pub label: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -90,6 +156,8 @@ pub struct IssueResponse {
     pub issue_id: Uuid,
     pub sub_issues: Option<Vec<Issue>>,
     pub comments: Option<Vec<CommentResponse>>,
+    pub labels: Option<Vec<Label>>,
+    pub assignee_ids: Option<Vec<Uuid>>,
 }
 
 // --- validators funcs ---
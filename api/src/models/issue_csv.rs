@@ -0,0 +1,116 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use validator_derive::Validate;
+
+use super::issue::{Issue, IssuePriority, IssueStatus};
+
+// --- data models ---
+
+#[derive(Debug)]
+pub struct IssueCsvRecord {
+    pub key: String,
+    pub number: i32,
+    pub team: Option<String>,
+    pub title: String,
+    pub description: Option<JsonValue>,
+    pub status: IssueStatus,
+    pub priority: IssuePriority,
+    pub due_date: Option<DateTime<Utc>>,
+    pub parent: Option<i32>,
+    pub creator: String,
+    pub assignees: Vec<String>,
+    pub labels: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl IssueCsvRecord {
+    // also the column names the import suggests, so an export can be imported as is
+    pub const HEADERS: [&'static str; 14] = [
+        "key",
+        "number",
+        "team",
+        "title",
+        "description",
+        "status",
+        "priority",
+        "due_date",
+        "parent",
+        "creator",
+        "assignees",
+        "labels",
+        "created_at",
+        "updated_at",
+    ];
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CsvColumnsRequest {
+    #[validate(length(min = 1, message = "The file is empty"))]
+    pub csv: String,
+}
+
+// which column of the file holds each field, unmapped fields get their defaults
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CsvColumnMapping {
+    pub title: Option<String>,
+    // markdown
+    pub description: Option<String>,
+    pub status: Option<String>,
+    pub priority: Option<String>,
+    pub due_date: Option<String>,
+    // number of the parent, either the number column of another row or an existing issue
+    pub parent: Option<String>,
+    // only used to resolve parents within the file, imported issues get new numbers
+    pub number: Option<String>,
+    // email of a member, the importer when empty
+    pub creator: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvColumnsResponse {
+    pub columns: Vec<String>,
+    pub suggested_mapping: CsvColumnMapping,
+    pub rows: usize,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CsvImportRequest {
+    #[validate(length(min = 1, message = "The file is empty"))]
+    pub csv: String,
+    pub mapping: CsvColumnMapping,
+    pub team_id: Option<Uuid>,
+    // validates every row without creating anything
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CsvImportResponse {
+    pub dry_run: bool,
+    pub rows: usize,
+    pub issues: Vec<Issue>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub enum CsvParent {
+    // index of another row of the same file
+    Row(usize),
+    Issue(Uuid),
+}
+
+#[derive(Debug)]
+pub struct CsvIssueRow {
+    pub title: String,
+    pub description: Option<JsonValue>,
+    pub status: IssueStatus,
+    pub priority: IssuePriority,
+    pub due_date: Option<DateTime<Utc>>,
+    pub parent: Option<CsvParent>,
+    pub creator_id: Uuid,
+}
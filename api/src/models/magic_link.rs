@@ -0,0 +1,49 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator_derive::Validate;
+
+use crate::utils::validation::validate_email;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub ip_address: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkRequest {
+    #[validate(custom(function = "validate_email"))]
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct MagicLinkLoginRequest {
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateMagicLinkData {
+    pub user_id: Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+}
@@ -0,0 +1,46 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// --- data models ---
+
+#[derive(Debug)]
+pub struct OauthState {
+    pub id: Uuid,
+    pub provider: String,
+    pub pkce_verifier: String,
+    pub nonce: Option<String>,
+    pub redirect_to: Option<String>,
+    pub link_user_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreateOauthStateData {
+    pub provider: String,
+    pub state_hash: String,
+    pub pkce_verifier: String,
+    pub nonce: Option<String>,
+    pub redirect_to: Option<String>,
+    pub link_user_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize)]
+pub struct OauthAuthorizeQuery {
+    pub redirect_to: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OauthCallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OauthAuthorizeResponse {
+    pub url: reqwest::Url,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+// --- data models ---
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct OidcDiscovery {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OidcTokenResponse {
+    pub id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+    pub preferred_username: Option<String>,
+    pub picture: Option<String>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Serialize)]
+pub struct OidcProviderResponse {
+    pub id: String,
+    pub name: String,
+}
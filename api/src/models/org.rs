@@ -5,7 +5,8 @@ use uuid::Uuid;
 use validator::ValidationError;
 use validator_derive::Validate;
 
-use super::auth::User;
+use super::{auth::User, role::Permission};
+use crate::utils::validation::{validate_email, validate_key_prefix};
 
 // --- data models ---
 
@@ -18,7 +19,42 @@ pub enum MemberRole {
     Member,
 }
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type)]
+impl MemberRole {
+    // inviting someone can never hand out more than the inviter has
+    pub fn can_grant(&self, role: &MemberRole) -> bool {
+        self.level() >= role.level()
+    }
+
+    pub fn default_permissions(&self) -> Vec<Permission> {
+        match self {
+            MemberRole::Owner => Permission::ALL.to_vec(),
+            MemberRole::Admin => Permission::ALL
+                .into_iter()
+                .filter(|permission| {
+                    !matches!(
+                        permission,
+                        Permission::MemberManage | Permission::OrgDomains | Permission::RoleManage
+                    )
+                })
+                .collect(),
+            MemberRole::Member => vec![
+                Permission::IssueCreate,
+                Permission::IssueUpdate,
+                Permission::CommentCreate,
+            ],
+        }
+    }
+
+    fn level(&self) -> u8 {
+        match self {
+            MemberRole::Owner => 2,
+            MemberRole::Admin => 1,
+            MemberRole::Member => 0,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 #[sqlx(type_name = "member_status", rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum MemberStatus {
@@ -27,6 +63,17 @@ pub enum MemberStatus {
     Disabled,
 }
 
+// how a membership came about, kept on the member row for auditing
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "member_join_method", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JoinMethod {
+    Created,
+    Invite,
+    InviteLink,
+    Domain,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Org {
     pub id: Uuid,
@@ -36,6 +83,9 @@ pub struct Org {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub logo_url: Option<String>,
+    pub require_two_factor: bool,
+    // set while the org is scheduled for deletion
+    pub purge_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -46,7 +96,12 @@ pub struct OrgMember {
     pub role: MemberRole,
     pub status: MemberStatus,
     pub joined_at: DateTime<Utc>,
+    // the link creator or the owner who registered the domain for self-service joins
     pub invited_by: Uuid,
+    pub join_method: JoinMethod,
+    pub invite_link_id: Option<Uuid>,
+    // replaces the default permissions of the role, ignored for owners
+    pub custom_role_id: Option<Uuid>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -56,6 +111,8 @@ pub struct OrgMemberInvite {
     pub id: Uuid,
     pub org_id: Uuid,
     pub email: String,
+    // set once the address belongs to a verified account
+    pub user_id: Option<Uuid>,
     pub role: MemberRole,
     pub invited_by: Uuid,
     pub invited_at: DateTime<Utc>,
@@ -63,6 +120,31 @@ pub struct OrgMemberInvite {
     pub token: String,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrgInviteLink {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub token: String,
+    pub role: MemberRole,
+    // no limit when empty
+    pub max_uses: Option<i32>,
+    pub use_count: i32,
+    // never expires when empty
+    pub expires_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrgDomain {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub domain: String,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
 // --- request/response models ---
 
 #[derive(Debug, Deserialize, Clone, Validate)]
@@ -71,6 +153,9 @@ pub struct CreateOrgRequest {
     pub name: String,
     #[validate(custom(function = "validate_logo_url"))]
     pub logo_url: Option<String>,
+    // generated from the name when empty
+    #[validate(custom(function = "validate_slug"))]
+    pub slug: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -79,20 +164,61 @@ pub struct UpdateOrgRequest {
     pub name: Option<String>,
     #[validate(custom(function = "validate_logo_url"))]
     pub logo_url: Option<String>,
+    pub require_two_factor: Option<bool>,
+    // prefix of the issue keys, old keys keep resolving after a change
+    #[validate(custom(function = "validate_key_prefix"))]
+    pub custom_id: Option<String>,
+    // old slugs keep resolving after a change
+    #[validate(custom(function = "validate_slug"))]
+    pub slug: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct InviteOrgMemberRequest {
     #[validate(custom(function = "validate_email"))]
     pub email: String,
+    // defaults to member
+    pub role: Option<MemberRole>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateInviteLinkRequest {
+    // defaults to member, links cannot hand out ownership
+    pub role: Option<MemberRole>,
+    #[validate(range(min = 1, max = 10000, message = "Max uses must be between 1 and 10000"))]
+    pub max_uses: Option<i32>,
+    #[validate(range(min = 1, max = 365, message = "Expiry must be between 1 and 365 days"))]
+    pub expires_in_days: Option<i64>,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct AddOrgDomainRequest {
+    #[validate(
+        length(max = 253, message = "Domain cannot be longer than 253 characters"),
+        custom(function = "validate_domain")
+    )]
+    pub domain: String,
+}
+
+// suspension has its own endpoints, this only changes the role
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateOrgMemberRequest {
-    pub role: Option<MemberRole>,
+    pub role: MemberRole,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ListOrgMembersQuery {
+    // active members unless asked otherwise, so suspended ones stay out of assignee pickers
     pub status: Option<MemberStatus>,
 }
 
+#[derive(Debug, Deserialize, Validate)]
+pub struct TransferOwnershipRequest {
+    // must repeat the org name, ownership cannot be taken back by the previous owner
+    #[validate(length(min = 1, message = "Confirmation cannot be empty"))]
+    pub confirmation: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct MembershipResponse {
     answer: bool,
@@ -112,6 +238,67 @@ pub struct OrgMemberInviteResponse {
     pub invited_by: User,
 }
 
+// shown before an invite link is redeemed
+#[derive(Debug, Serialize)]
+pub struct InviteLinkPreview {
+    pub org_id: Uuid,
+    pub org_name: String,
+    pub org_logo: Option<String>,
+    pub role: MemberRole,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JoinableOrgResponse {
+    pub org_id: Uuid,
+    pub org_name: String,
+    pub org_logo: Option<String>,
+    pub domain: String,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateOrgMemberData {
+    pub org_id: Uuid,
+    pub user_id: Uuid,
+    pub role: MemberRole,
+    pub invited_by: Uuid,
+    pub join_method: JoinMethod,
+    pub invite_link_id: Option<Uuid>,
+}
+
+// everything the org guard needs to know about the requesting member
+#[derive(Debug)]
+pub struct OrgMembership {
+    pub org: Org,
+    pub role: MemberRole,
+    pub status: MemberStatus,
+    pub custom_permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug)]
+pub enum IdentifierChangeResult {
+    Changed,
+    // in use or retired by another org
+    Taken,
+    UsedByTeam,
+}
+
+#[derive(Debug)]
+pub enum MemberChangeResult {
+    Changed,
+    NotFound,
+    LastOwner,
+}
+
+#[derive(Debug)]
+pub enum TransferOwnershipResult {
+    Transferred,
+    NotOwner,
+    MemberNotFound,
+}
+
 // --- validation rules ---
 
 fn validate_logo_url(url: &str) -> Result<(), validator::ValidationError> {
@@ -121,10 +308,24 @@ fn validate_logo_url(url: &str) -> Result<(), validator::ValidationError> {
     Ok(())
 }
 
-fn validate_email(email: &str) -> Result<(), validator::ValidationError> {
-    let email_rgx = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
-    if !email_rgx.is_match(email) {
-        return Err(ValidationError::new("email must be a valid email address"));
+// slugs share the path segment with org ids, so they must never look like one
+fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    let slug_rgx = Regex::new(r"^[a-z0-9]+(-[a-z0-9]+)*$").unwrap();
+    if slug.len() < 3 || slug.len() > 60 || !slug_rgx.is_match(slug) {
+        return Err(ValidationError::new(
+            "slug must be 3 to 60 lowercase letters, digits or single hyphens",
+        ));
+    }
+    if Uuid::parse_str(slug).is_ok() {
+        return Err(ValidationError::new("slug cannot be an id"));
+    }
+    Ok(())
+}
+
+fn validate_domain(domain: &str) -> Result<(), ValidationError> {
+    let domain_rgx = Regex::new(r"^(?i)([a-z0-9]([a-z0-9-]*[a-z0-9])?\.)+[a-z]{2,}$").unwrap();
+    if !domain_rgx.is_match(domain) {
+        return Err(ValidationError::new("domain must be a valid domain name"));
     }
     Ok(())
 }
@@ -0,0 +1,77 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "org_export_status", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Completed,
+    Failed,
+}
+
+// the archive itself is only loaded for the download
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrgExport {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub requested_by: Option<Uuid>,
+    pub status: ExportStatus,
+    pub archive_size: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// one json file per entity in the archive
+#[derive(Debug, Clone, Copy)]
+pub enum ExportEntity {
+    Org,
+    Members,
+    Roles,
+    Teams,
+    TeamMembers,
+    Labels,
+    Issues,
+    Comments,
+    Invites,
+    Domains,
+    AuditLog,
+}
+
+impl ExportEntity {
+    pub const ALL: [ExportEntity; 11] = [
+        ExportEntity::Org,
+        ExportEntity::Members,
+        ExportEntity::Roles,
+        ExportEntity::Teams,
+        ExportEntity::TeamMembers,
+        ExportEntity::Labels,
+        ExportEntity::Issues,
+        ExportEntity::Comments,
+        ExportEntity::Invites,
+        ExportEntity::Domains,
+        ExportEntity::AuditLog,
+    ];
+
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            ExportEntity::Org => "org.json",
+            ExportEntity::Members => "members.json",
+            ExportEntity::Roles => "roles.json",
+            ExportEntity::Teams => "teams.json",
+            ExportEntity::TeamMembers => "team_members.json",
+            ExportEntity::Labels => "labels.json",
+            ExportEntity::Issues => "issues.json",
+            ExportEntity::Comments => "comments.json",
+            ExportEntity::Invites => "invites.json",
+            ExportEntity::Domains => "domains.json",
+            ExportEntity::AuditLog => "audit_log.json",
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+use validator_derive::Validate;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum TokenScope {
+    #[serde(rename = "issues:read")]
+    IssuesRead,
+    #[serde(rename = "issues:write")]
+    IssuesWrite,
+    #[serde(rename = "org:admin")]
+    OrgAdmin,
+}
+
+impl TokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenScope::IssuesRead => "issues:read",
+            TokenScope::IssuesWrite => "issues:write",
+            TokenScope::OrgAdmin => "org:admin",
+        }
+    }
+}
+
+impl FromStr for TokenScope {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "issues:read" => Ok(TokenScope::IssuesRead),
+            "issues:write" => Ok(TokenScope::IssuesWrite),
+            "org:admin" => Ok(TokenScope::OrgAdmin),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PersonalAccessToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub org_ids: Option<Vec<Uuid>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct CreatePersonalAccessTokenData {
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub token_hash: String,
+    pub scopes: Vec<String>,
+    pub org_ids: Option<Vec<Uuid>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreatePersonalAccessTokenRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    #[validate(range(min = 1, max = 365, message = "Expiry must be between 1 and 365 days"))]
+    pub expires_in_days: Option<i64>,
+    #[validate(length(min = 1, message = "At least one scope is required"))]
+    pub scopes: Vec<TokenScope>,
+    #[validate(length(min = 1, message = "Org restriction cannot be empty"))]
+    pub org_ids: Option<Vec<Uuid>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatePersonalAccessTokenResponse {
+    // the plain token is only ever returned here, it cannot be recovered later
+    pub token: String,
+    #[serde(flatten)]
+    pub personal_access_token: PersonalAccessToken,
+}
@@ -0,0 +1,187 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator_derive::Validate;
+
+use super::org::MemberRole;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    #[serde(rename = "issue.create")]
+    IssueCreate,
+    #[serde(rename = "issue.update")]
+    IssueUpdate,
+    // authors can always delete their own issues
+    #[serde(rename = "issue.delete")]
+    IssueDelete,
+    #[serde(rename = "comment.create")]
+    CommentCreate,
+    // delete comments of other members
+    #[serde(rename = "comment.moderate")]
+    CommentModerate,
+    #[serde(rename = "member.view")]
+    MemberView,
+    #[serde(rename = "member.invite")]
+    MemberInvite,
+    // suspend and reactivate members
+    #[serde(rename = "member.suspend")]
+    MemberSuspend,
+    // change roles, remove members and transfer ownership
+    #[serde(rename = "member.manage")]
+    MemberManage,
+    #[serde(rename = "team.manage")]
+    TeamManage,
+    #[serde(rename = "org.settings")]
+    OrgSettings,
+    #[serde(rename = "org.domains")]
+    OrgDomains,
+    #[serde(rename = "org.delete")]
+    OrgDelete,
+    #[serde(rename = "role.manage")]
+    RoleManage,
+    #[serde(rename = "audit.view")]
+    AuditView,
+    // bring issues in from other tools
+    #[serde(rename = "org.import")]
+    OrgImport,
+    #[serde(rename = "webhook.manage")]
+    WebhookManage,
+}
+
+impl Permission {
+    pub const ALL: [Permission; 17] = [
+        Permission::IssueCreate,
+        Permission::IssueUpdate,
+        Permission::IssueDelete,
+        Permission::CommentCreate,
+        Permission::CommentModerate,
+        Permission::MemberView,
+        Permission::MemberInvite,
+        Permission::MemberSuspend,
+        Permission::MemberManage,
+        Permission::TeamManage,
+        Permission::OrgSettings,
+        Permission::OrgDomains,
+        Permission::OrgDelete,
+        Permission::RoleManage,
+        Permission::AuditView,
+        Permission::OrgImport,
+        Permission::WebhookManage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::IssueCreate => "issue.create",
+            Permission::IssueUpdate => "issue.update",
+            Permission::IssueDelete => "issue.delete",
+            Permission::CommentCreate => "comment.create",
+            Permission::CommentModerate => "comment.moderate",
+            Permission::MemberView => "member.view",
+            Permission::MemberInvite => "member.invite",
+            Permission::MemberSuspend => "member.suspend",
+            Permission::MemberManage => "member.manage",
+            Permission::TeamManage => "team.manage",
+            Permission::OrgSettings => "org.settings",
+            Permission::OrgDomains => "org.domains",
+            Permission::OrgDelete => "org.delete",
+            Permission::RoleManage => "role.manage",
+            Permission::AuditView => "audit.view",
+            Permission::OrgImport => "org.import",
+            Permission::WebhookManage => "webhook.manage",
+        }
+    }
+
+    // owners always hold every permission so an org can never lock itself out,
+    // a custom role replaces the defaults of the built-in role
+    pub fn resolve(role: &MemberRole, custom_permissions: Option<&[String]>) -> Vec<Permission> {
+        match (role, custom_permissions) {
+            (MemberRole::Owner, _) => Permission::ALL.to_vec(),
+            (_, Some(custom_permissions)) => custom_permissions
+                .iter()
+                .filter_map(|permission| permission.parse().ok())
+                .collect(),
+            (_, None) => role.default_permissions(),
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Permission::ALL
+            .into_iter()
+            .find(|permission| permission.as_str() == value)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OrgRole {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub name: String,
+    pub permissions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateOrgRoleRequest {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Name must be between 1 and 50 characters"
+    ))]
+    pub name: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateOrgRoleRequest {
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Name must be between 1 and 50 characters"
+    ))]
+    pub name: Option<String>,
+    pub permissions: Option<Vec<Permission>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AssignOrgRoleRequest {
+    // back to the defaults of the built-in role when empty
+    pub role_id: Option<Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BuiltInRoleResponse {
+    pub role: MemberRole,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrgRolesResponse {
+    pub built_in: Vec<BuiltInRoleResponse>,
+    pub custom: Vec<OrgRole>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionPermissionsResponse {
+    pub role: MemberRole,
+    pub permissions: Vec<Permission>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub enum RoleWriteResult {
+    Written(OrgRole),
+    NameTaken,
+}
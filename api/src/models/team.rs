@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator_derive::Validate;
+
+use super::auth::User;
+use crate::utils::validation::validate_key_prefix;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Team {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub name: String,
+    // prefix of the issue numbers of the team, e.g. ENG-12
+    pub key: String,
+    pub lead_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TeamMember {
+    pub team_id: Uuid,
+    pub user_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateTeamRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    #[validate(custom(function = "validate_key_prefix"))]
+    pub key: String,
+    // added to the team when not a member yet
+    pub lead_id: Option<Uuid>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateTeamRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "Name must be between 1 and 100 characters"
+    ))]
+    pub name: Option<String>,
+    #[validate(custom(function = "validate_key_prefix"))]
+    pub key: Option<String>,
+    pub lead_id: Option<Uuid>,
+    pub remove_lead: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AddTeamMemberRequest {
+    pub user_id: Uuid,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamMemberResponse {
+    pub user: User,
+    pub team_member: TeamMember,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamResponse {
+    pub team: Team,
+    pub members: Vec<TeamMemberResponse>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub enum TeamWriteResult {
+    Written(Team),
+    KeyTaken,
+    // the lead is not an active member of the org
+    LeadNotMember,
+}
+
+#[derive(Debug)]
+pub enum AddTeamMemberResult {
+    Added,
+    AlreadyMember,
+    NotOrgMember,
+}
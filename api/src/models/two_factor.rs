@@ -0,0 +1,71 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator_derive::Validate;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, sqlx::FromRow)]
+pub struct UserTwoFactor {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub last_used_step: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TwoFactorCodeRequest {
+    #[validate(length(
+        min = 6,
+        max = 16,
+        message = "Code must be between 6 and 16 characters"
+    ))]
+    pub code: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct TwoFactorLoginRequest {
+    #[validate(length(min = 1, message = "Challenge token cannot be empty"))]
+    pub challenge_token: String,
+    #[validate(length(
+        min = 6,
+        max = 16,
+        message = "Code must be between 6 and 16 characters"
+    ))]
+    pub code: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorEnrollResponse {
+    pub secret: String,
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    pub enabled_at: Option<DateTime<Utc>>,
+    pub remaining_recovery_codes: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    pub message: String,
+    pub user_id: String,
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_to: Option<String>,
+}
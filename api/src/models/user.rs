@@ -0,0 +1,141 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+use validator::ValidationError;
+use validator_derive::Validate;
+
+use super::auth::User;
+use crate::utils::validation::{validate_email, validate_username};
+
+// issues and comments of deleted accounts are reassigned to this placeholder user
+pub const DELETED_USER_ID: Uuid = Uuid::nil();
+
+// --- request/response models ---
+
+#[derive(Debug, Serialize)]
+pub struct UserProfileResponse {
+    pub id: Uuid,
+    pub email: String,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+    pub github_url: Option<String>,
+    pub is_email_verified: bool,
+    pub pending_email: Option<String>,
+    pub has_password: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_login_at: Option<DateTime<Utc>>,
+}
+
+impl UserProfileResponse {
+    pub fn new(user: User, pending_email: Option<String>) -> Self {
+        Self {
+            id: user.id,
+            email: user.email,
+            username: user.username,
+            display_name: user.display_name,
+            avatar_url: user.avatar_url,
+            github_url: user.github_url,
+            is_email_verified: user.is_email_verified,
+            pending_email,
+            has_password: user.password_hash.is_some(),
+            created_at: user.created_at,
+            last_login_at: user.last_login_at,
+        }
+    }
+}
+
+// fields left out are not changed, an empty display name or avatar url clears it
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateProfileRequest {
+    #[validate(
+        length(
+            min = 3,
+            max = 50,
+            message = "Username must be between 3 and 50 characters"
+        ),
+        custom(function = "validate_username")
+    )]
+    pub username: Option<String>,
+    #[validate(length(
+        max = 100,
+        message = "Display name cannot be longer than 100 characters"
+    ))]
+    pub display_name: Option<String>,
+    #[validate(
+        length(
+            max = 2048,
+            message = "Avatar url cannot be longer than 2048 characters"
+        ),
+        custom(function = "validate_avatar_url")
+    )]
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ChangeEmailRequest {
+    #[validate(
+        length(max = 255, message = "Email cannot be longer than 255 characters"),
+        custom(function = "validate_email")
+    )]
+    pub email: String,
+    // required when the account has a password
+    pub password: Option<String>,
+    // required when two-factor authentication is enabled
+    pub code: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct VerifyEmailRequest {
+    #[validate(length(min = 1, message = "Token cannot be empty"))]
+    pub token: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChangeEmailResponse {
+    pub message: String,
+    pub pending_email: String,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct DeleteAccountRequest {
+    // must repeat the username so an account is not deleted by accident
+    #[validate(length(min = 1, message = "Confirmation cannot be empty"))]
+    pub confirmation: String,
+    // required when the account has a password
+    pub password: Option<String>,
+    // required when two-factor authentication is enabled
+    pub code: Option<String>,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct UpdateProfileData {
+    pub username: Option<String>,
+    pub display_name: Option<String>,
+    pub avatar_url: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PendingEmailChange {
+    pub user_id: Uuid,
+    pub email: String,
+    // the same as email when a new account confirms its address
+    pub pending_email: String,
+}
+
+#[derive(Debug)]
+pub enum DeleteAccountResult {
+    Deleted,
+    // names of the orgs the user is the only active owner of
+    SoleOwner(Vec<String>),
+}
+
+// an empty value is allowed so the avatar can be removed
+fn validate_avatar_url(url: &str) -> Result<(), ValidationError> {
+    if !url.is_empty() && !url.starts_with("https://") {
+        return Err(ValidationError::new("avatar_url must be a valid URL"));
+    }
+    Ok(())
+}
@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use uuid::Uuid;
+use validator::ValidationError;
+use validator_derive::Validate;
+
+// --- data models ---
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum WebhookEvent {
+    #[serde(rename = "issue.created")]
+    IssueCreated,
+    #[serde(rename = "issue.updated")]
+    IssueUpdated,
+    #[serde(rename = "issue.deleted")]
+    IssueDeleted,
+    #[serde(rename = "comment.created")]
+    CommentCreated,
+    #[serde(rename = "member.joined")]
+    MemberJoined,
+    // only sent by the test endpoint, it cannot be subscribed to
+    #[serde(rename = "webhook.test")]
+    WebhookTest,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::IssueCreated => "issue.created",
+            WebhookEvent::IssueUpdated => "issue.updated",
+            WebhookEvent::IssueDeleted => "issue.deleted",
+            WebhookEvent::CommentCreated => "comment.created",
+            WebhookEvent::MemberJoined => "member.joined",
+            WebhookEvent::WebhookTest => "webhook.test",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "webhook_delivery_status",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    // gave up after the last retry
+    Failed,
+}
+
+// the secret is only returned when it is generated
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub id: Uuid,
+    pub org_id: Uuid,
+    pub url: String,
+    pub events: Vec<String>,
+    pub is_active: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event: String,
+    pub payload: JsonValue,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    // when a pending delivery is tried next
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub response_status: Option<i32>,
+    pub response_body: Option<String>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+// --- request/response models ---
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateWebhookRequest {
+    #[validate(custom(function = "validate_webhook_url"))]
+    pub url: String,
+    #[validate(custom(function = "validate_events"))]
+    pub events: Vec<WebhookEvent>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateWebhookRequest {
+    #[validate(custom(function = "validate_webhook_url"))]
+    pub url: Option<String>,
+    #[validate(custom(function = "validate_events"))]
+    pub events: Option<Vec<WebhookEvent>>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookSecretResponse {
+    // the receiver verifies signatures with it, it cannot be shown again
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: Webhook,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct ListWebhookDeliveriesQuery {
+    pub status: Option<WebhookDeliveryStatus>,
+    #[validate(range(min = 1, message = "Page starts at 1"))]
+    pub page: Option<i64>,
+    #[validate(range(min = 1, max = 100, message = "Page size must be between 1 and 100"))]
+    pub per_page: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WebhookDeliveryPageResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+    pub page: i64,
+    pub per_page: i64,
+    pub total: i64,
+}
+
+// --- repository models ---
+
+#[derive(Debug)]
+pub struct CreateWebhookData {
+    pub org_id: Uuid,
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+    pub created_by: Uuid,
+}
+
+#[derive(Debug)]
+pub struct UpdateWebhookData {
+    pub url: Option<String>,
+    pub events: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+// a claimed delivery with what is needed to send it
+#[derive(Debug)]
+pub struct WebhookDeliveryJob {
+    pub id: Uuid,
+    pub event: String,
+    pub payload: JsonValue,
+    // including the one about to be made
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+}
+
+// --- validators funcs ---
+
+fn validate_webhook_url(url: &str) -> Result<(), ValidationError> {
+    match Url::parse(url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.host().is_some() => Ok(()),
+        _ => Err(ValidationError::new(
+            "url must be a valid http or https URL",
+        )),
+    }
+}
+
+fn validate_events(events: &[WebhookEvent]) -> Result<(), ValidationError> {
+    if events.is_empty() {
+        return Err(ValidationError::new("at least one event is required"));
+    }
+    if events.contains(&WebhookEvent::WebhookTest) {
+        return Err(ValidationError::new("webhook.test cannot be subscribed to"));
+    }
+    Ok(())
+}
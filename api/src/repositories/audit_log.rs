@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    audit_log::{AuditEntry, AuditLog, AuditLogFilter, CreateAuditLogData},
+    context::ClientInfo,
+};
+
+pub struct AuditLogRepository {
+    pool: PgPool,
+}
+
+impl AuditLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_log(&self, data: CreateAuditLogData) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO org_audit_logs (
+                org_id, actor_id, action, target_type, target_id,
+                ip_address, user_agent, before, after
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            data.org_id,
+            data.actor_id,
+            data.action,
+            data.target_type,
+            data.target_id,
+            data.ip_address,
+            data.user_agent,
+            data.before,
+            data.after
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // like the auth log, a failed write is reported but never fails the request
+    pub async fn log_event(
+        &self,
+        org_id: Uuid,
+        actor_id: Option<Uuid>,
+        client: &ClientInfo,
+        entry: AuditEntry,
+    ) {
+        let data = CreateAuditLogData {
+            org_id,
+            actor_id,
+            action: entry.event.as_str().to_string(),
+            target_type: entry.event.target_type().to_string(),
+            target_id: entry.target_id,
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            before: entry.before,
+            after: entry.after,
+        };
+
+        if let Err(e) = self.create_log(data).await {
+            log::error!("Failed to write audit log: {}", e);
+        }
+    }
+
+    // newest first, without a limit every matching entry is returned
+    pub async fn list_logs(
+        &self,
+        org_id: Uuid,
+        filter: &AuditLogFilter,
+        limit: Option<i64>,
+        offset: i64,
+    ) -> Result<Vec<AuditLog>, sqlx::Error> {
+        let logs = sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT
+                l.id,
+                l.org_id,
+                l.actor_id,
+                u.username as "actor_username?",
+                l.action,
+                l.target_type,
+                l.target_id,
+                l.ip_address,
+                l.user_agent,
+                l.before,
+                l.after,
+                l.created_at as "created_at!: DateTime<Utc>"
+            FROM org_audit_logs l
+            LEFT JOIN users u ON u.id = l.actor_id
+            WHERE l.org_id = $1
+            AND ($2::text IS NULL OR l.action = $2)
+            AND ($3::uuid IS NULL OR l.actor_id = $3)
+            AND ($4::uuid IS NULL OR l.target_id = $4)
+            AND ($5::timestamptz IS NULL OR l.created_at >= $5)
+            AND ($6::timestamptz IS NULL OR l.created_at < $6)
+            ORDER BY l.created_at DESC, l.id DESC
+            LIMIT $7
+            OFFSET $8
+            "#,
+            org_id,
+            filter.action,
+            filter.actor_id,
+            filter.target_id,
+            filter.from,
+            filter.to,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(logs)
+    }
+
+    pub async fn count_logs(
+        &self,
+        org_id: Uuid,
+        filter: &AuditLogFilter,
+    ) -> Result<i64, sqlx::Error> {
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "total!"
+            FROM org_audit_logs
+            WHERE org_id = $1
+            AND ($2::text IS NULL OR action = $2)
+            AND ($3::uuid IS NULL OR actor_id = $3)
+            AND ($4::uuid IS NULL OR target_id = $4)
+            AND ($5::timestamptz IS NULL OR created_at >= $5)
+            AND ($6::timestamptz IS NULL OR created_at < $6)
+            "#,
+            org_id,
+            filter.action,
+            filter.actor_id,
+            filter.target_id,
+            filter.from,
+            filter.to
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total)
+    }
+}
@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::auth::{AuthLockout, LockoutScope};
+
+pub struct AuthLockoutRepository {
+    pool: PgPool,
+}
+
+impl AuthLockoutRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_lockout(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+    ) -> Result<Option<AuthLockout>, sqlx::Error> {
+        let lockout = sqlx::query_as!(
+            AuthLockout,
+            r#"
+            SELECT
+                id,
+                scope,
+                identifier,
+                failed_attempts,
+                last_failed_at as "last_failed_at!: DateTime<Utc>",
+                locked_until
+            FROM auth_lockouts
+            WHERE scope = $1 AND identifier = $2
+            "#,
+            scope.as_str(),
+            identifier
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    // attempts older than the window do not count towards the next lockout
+    pub async fn record_failed_attempt(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+        window_secs: i64,
+    ) -> Result<AuthLockout, sqlx::Error> {
+        let lockout = sqlx::query_as!(
+            AuthLockout,
+            r#"
+            INSERT INTO auth_lockouts (scope, identifier, failed_attempts, last_failed_at)
+            VALUES ($1, $2, 1, now())
+            ON CONFLICT (scope, identifier) DO UPDATE
+            SET failed_attempts = CASE
+                    WHEN auth_lockouts.last_failed_at < now() - make_interval(secs => $3)
+                        AND (auth_lockouts.locked_until IS NULL OR auth_lockouts.locked_until < now())
+                    THEN 1
+                    ELSE auth_lockouts.failed_attempts + 1
+                END,
+                last_failed_at = now()
+            RETURNING
+                id,
+                scope,
+                identifier,
+                failed_attempts,
+                last_failed_at as "last_failed_at!: DateTime<Utc>",
+                locked_until
+            "#,
+            scope.as_str(),
+            identifier,
+            window_secs as f64
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(lockout)
+    }
+
+    pub async fn lock_until(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+        locked_until: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE auth_lockouts
+            SET locked_until = $3
+            WHERE scope = $1 AND identifier = $2
+            "#,
+            scope.as_str(),
+            identifier,
+            locked_until
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn clear_lockout(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            DELETE FROM auth_lockouts
+            WHERE scope = $1 AND identifier = $2
+            "#,
+            scope.as_str(),
+            identifier
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
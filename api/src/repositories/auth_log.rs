@@ -0,0 +1,66 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    auth::{AuthEvent, AuthLog, CreateAuthLogData},
+    context::ClientInfo,
+};
+
+pub struct AuthLogRepository {
+    pool: PgPool,
+}
+
+impl AuthLogRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_log(&self, data: CreateAuthLogData) -> Result<AuthLog, sqlx::Error> {
+        let log = sqlx::query_as!(
+            AuthLog,
+            r#"
+            INSERT INTO auth_logs (user_id, event_type, ip_address, user_agent, details)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING
+                id,
+                user_id,
+                event_type,
+                ip_address,
+                user_agent,
+                created_at as "created_at!: DateTime<Utc>",
+                details
+            "#,
+            data.user_id,
+            data.event_type,
+            data.ip_address,
+            data.user_agent,
+            data.details
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(log)
+    }
+
+    // auth logs are an audit trail, failing to write one should not fail the request itself
+    pub async fn log_event(
+        &self,
+        user_id: Option<Uuid>,
+        event: AuthEvent,
+        client: &ClientInfo,
+        details: Option<serde_json::Value>,
+    ) {
+        let data = CreateAuthLogData {
+            user_id,
+            event_type: event.as_str().to_string(),
+            ip_address: client.ip_address.clone(),
+            user_agent: client.user_agent.clone(),
+            details,
+        };
+
+        if let Err(e) = self.create_log(data).await {
+            log::error!("Failed to write auth log: {}", e);
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    auth::User,
+    identity::{CreateExternalUser, UnlinkIdentityResult, UserIdentity},
+};
+
+pub struct IdentityRepository {
+    pool: PgPool,
+}
+
+impl IdentityRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_identity(
+        &self,
+        provider: &str,
+        subject: &str,
+    ) -> Result<Option<UserIdentity>, sqlx::Error> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            SELECT
+                id,
+                user_id,
+                provider,
+                subject,
+                email,
+                linked_at as "linked_at!: DateTime<Utc>"
+            FROM user_identities
+            WHERE provider = $1 AND subject = $2
+            "#,
+            provider,
+            subject
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(identity)
+    }
+
+    pub async fn list_user_identities(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<UserIdentity>, sqlx::Error> {
+        let identities = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            SELECT
+                id,
+                user_id,
+                provider,
+                subject,
+                email,
+                linked_at as "linked_at!: DateTime<Utc>"
+            FROM user_identities
+            WHERE user_id = $1
+            ORDER BY linked_at
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(identities)
+    }
+
+    // relinking an identity the user already owns refreshes its email, an identity
+    // owned by someone else is left untouched and nothing is returned
+    pub async fn link_identity(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        subject: &str,
+        email: Option<String>,
+    ) -> Result<Option<UserIdentity>, sqlx::Error> {
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (provider, subject) DO UPDATE
+            SET email = EXCLUDED.email
+            WHERE user_identities.user_id = EXCLUDED.user_id
+            RETURNING
+                id,
+                user_id,
+                provider,
+                subject,
+                email,
+                linked_at as "linked_at!: DateTime<Utc>"
+            "#,
+            user_id,
+            provider,
+            subject,
+            email
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(identity)
+    }
+
+    pub async fn unlink_identity(
+        &self,
+        user_id: Uuid,
+        identity_id: Uuid,
+    ) -> Result<UnlinkIdentityResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        // the row lock serializes concurrent unlinks, otherwise two requests could
+        // each remove one of the last two identities
+        let user = sqlx::query!(
+            r#"
+            SELECT
+                password_hash IS NOT NULL as "has_password!",
+                (SELECT COUNT(*) FROM user_identities WHERE user_id = users.id) as "identities!"
+            FROM users
+            WHERE id = $1
+            FOR UPDATE
+            "#,
+            user_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        let identity = sqlx::query_as!(
+            UserIdentity,
+            r#"
+            SELECT
+                id,
+                user_id,
+                provider,
+                subject,
+                email,
+                linked_at as "linked_at!: DateTime<Utc>"
+            FROM user_identities
+            WHERE id = $1 AND user_id = $2
+            "#,
+            identity_id,
+            user_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let identity = match identity {
+            Some(identity) => identity,
+            None => return Ok(UnlinkIdentityResult::NotFound),
+        };
+
+        if !user.has_password && user.identities <= 1 {
+            return Ok(UnlinkIdentityResult::LastLoginMethod);
+        }
+
+        sqlx::query!("DELETE FROM user_identities WHERE id = $1", identity.id)
+            .execute(&mut *tx)
+            .await?;
+
+        // the github profile fields only describe a linked github account
+        if identity.provider == "github" {
+            sqlx::query!(
+                r#"
+                UPDATE users
+                SET github_id = NULL,
+                    github_url = NULL,
+                    updated_at = now()
+                WHERE id = $1 AND github_id = $2
+                "#,
+                user_id,
+                identity.subject
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(UnlinkIdentityResult::Unlinked(identity))
+    }
+
+    // the user and its first identity are created together so a failed insert
+    // cannot leave behind an account nobody can sign in to
+    pub async fn create_user_with_identity(
+        &self,
+        data: CreateExternalUser,
+    ) -> Result<User, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let user = sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (email, username, avatar_url, is_email_verified)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                id,
+                email,
+                username,
+                password_hash,
+                is_email_verified as "is_email_verified!: bool",
+                email_verification_token,
+                email_verification_expires_at,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                last_login_at,
+                avatar_url,
+                github_id,
+                github_url,
+                display_name
+            "#,
+            data.email,
+            data.username,
+            data.avatar_url,
+            data.is_email_verified
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_identities (user_id, provider, subject, email)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            user.id,
+            data.provider,
+            data.subject,
+            data.email
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(user)
+    }
+}
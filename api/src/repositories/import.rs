@@ -0,0 +1,619 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    comment::CommentType,
+    import::{
+        CreateImportData, ImportJob, ImportSource, ImportStatus, ImportedComment,
+        ImportedFileIssue, ImportedIssue, ImportedLabel, OrgImport,
+    },
+    issue::{IssuePriority, IssueStatus},
+};
+
+pub struct ImportRepository {
+    pool: PgPool,
+}
+
+impl ImportRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // returns none while the same repository is still being imported into the org
+    pub async fn create_import(
+        &self,
+        data: CreateImportData,
+    ) -> Result<Option<OrgImport>, sqlx::Error> {
+        let import = sqlx::query_as!(
+            OrgImport,
+            r#"
+            INSERT INTO org_imports (
+                org_id, requested_by, source, repository, team_id,
+                preserve_numbers, open_status, closed_status, access_token
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (org_id, repository) WHERE status IN ('PENDING', 'RUNNING') DO NOTHING
+            RETURNING
+                id,
+                org_id,
+                requested_by,
+                source as "source: ImportSource",
+                repository,
+                team_id,
+                preserve_numbers,
+                open_status as "open_status: IssueStatus",
+                closed_status as "closed_status: IssueStatus",
+                status as "status: ImportStatus",
+                total_issues,
+                imported_issues,
+                imported_comments,
+                renumbered_issues,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                completed_at
+            "#,
+            data.org_id,
+            data.requested_by,
+            data.source as ImportSource,
+            data.repository,
+            data.team_id,
+            data.preserve_numbers,
+            data.open_status as IssueStatus,
+            data.closed_status as IssueStatus,
+            data.access_token
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(import)
+    }
+
+    pub async fn find_import(
+        &self,
+        org_id: Uuid,
+        import_id: Uuid,
+    ) -> Result<Option<OrgImport>, sqlx::Error> {
+        let import = sqlx::query_as!(
+            OrgImport,
+            r#"
+            SELECT
+                id,
+                org_id,
+                requested_by,
+                source as "source: ImportSource",
+                repository,
+                team_id,
+                preserve_numbers,
+                open_status as "open_status: IssueStatus",
+                closed_status as "closed_status: IssueStatus",
+                status as "status: ImportStatus",
+                total_issues,
+                imported_issues,
+                imported_comments,
+                renumbered_issues,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                completed_at
+            FROM org_imports
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            import_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(import)
+    }
+
+    pub async fn list_imports(&self, org_id: Uuid) -> Result<Vec<OrgImport>, sqlx::Error> {
+        let imports = sqlx::query_as!(
+            OrgImport,
+            r#"
+            SELECT
+                id,
+                org_id,
+                requested_by,
+                source as "source: ImportSource",
+                repository,
+                team_id,
+                preserve_numbers,
+                open_status as "open_status: IssueStatus",
+                closed_status as "closed_status: IssueStatus",
+                status as "status: ImportStatus",
+                total_issues,
+                imported_issues,
+                imported_comments,
+                renumbered_issues,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                completed_at
+            FROM org_imports
+            WHERE org_id = $1
+            ORDER BY created_at DESC
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(imports)
+    }
+
+    // only a failed import that still has its access token can run again
+    pub async fn resume_import(
+        &self,
+        org_id: Uuid,
+        import_id: Uuid,
+    ) -> Result<Option<OrgImport>, sqlx::Error> {
+        let import = sqlx::query_as!(
+            OrgImport,
+            r#"
+            UPDATE org_imports
+            SET status = 'PENDING',
+                error = NULL,
+                completed_at = NULL,
+                updated_at = now()
+            WHERE org_id = $1
+            AND id = $2
+            AND status = 'FAILED'
+            AND access_token IS NOT NULL
+            RETURNING
+                id,
+                org_id,
+                requested_by,
+                source as "source: ImportSource",
+                repository,
+                team_id,
+                preserve_numbers,
+                open_status as "open_status: IssueStatus",
+                closed_status as "closed_status: IssueStatus",
+                status as "status: ImportStatus",
+                total_issues,
+                imported_issues,
+                imported_comments,
+                renumbered_issues,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                completed_at
+            "#,
+            org_id,
+            import_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(import)
+    }
+
+    // moves a pending import to running, none when another worker got to it first
+    pub async fn claim_import(&self, import_id: Uuid) -> Result<Option<ImportJob>, sqlx::Error> {
+        let job = sqlx::query_as!(
+            ImportJob,
+            r#"
+            UPDATE org_imports
+            SET status = 'RUNNING', updated_at = now()
+            WHERE id = $1 AND status = 'PENDING'
+            RETURNING
+                id,
+                org_id,
+                requested_by,
+                repository,
+                team_id,
+                preserve_numbers,
+                open_status as "open_status: IssueStatus",
+                closed_status as "closed_status: IssueStatus",
+                access_token,
+                next_page
+            "#,
+            import_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(job)
+    }
+
+    // jobs do not survive a restart, a running import without progress for this long
+    // is queued again and continues from its last page
+    pub async fn requeue_stale_imports(&self, stale_secs: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+        let ids = sqlx::query_scalar!(
+            r#"
+            UPDATE org_imports
+            SET status = 'PENDING', updated_at = now()
+            WHERE status IN ('PENDING', 'RUNNING')
+            AND updated_at < now() - make_interval(secs => $1)
+            RETURNING id
+            "#,
+            stale_secs as f64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(ids)
+    }
+
+    // a failed import nobody resumed gives up its access token, it can no longer be resumed
+    pub async fn forget_abandoned_imports(&self, abandoned_secs: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_imports
+            SET access_token = NULL
+            WHERE status = 'FAILED'
+            AND access_token IS NOT NULL
+            AND completed_at < now() - make_interval(secs => $1)
+            "#,
+            abandoned_secs as f64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn set_total_issues(&self, import_id: Uuid, total: i32) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE org_imports SET total_issues = $2, updated_at = now() WHERE id = $1",
+            import_id,
+            total
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn advance_page(&self, import_id: Uuid, next_page: i32) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE org_imports SET next_page = $2, updated_at = now() WHERE id = $1",
+            import_id,
+            next_page
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn complete_import(&self, import_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE org_imports
+            SET status = 'COMPLETED',
+                access_token = NULL,
+                updated_at = now(),
+                completed_at = now()
+            WHERE id = $1
+            "#,
+            import_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fail_import(&self, import_id: Uuid, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE org_imports
+            SET status = 'FAILED',
+                error = $2,
+                updated_at = now(),
+                completed_at = now()
+            WHERE id = $1
+            "#,
+            import_id,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn is_imported(
+        &self,
+        import_id: Uuid,
+        external_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let imported = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM org_import_items WHERE import_id = $1 AND external_id = $2
+            ) as "imported!"
+            "#,
+            import_id,
+            external_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(imported)
+    }
+
+    // active members whose github account is one of the given ids, either from their
+    // profile or from a linked identity
+    pub async fn find_members_by_github_ids(
+        &self,
+        org_id: Uuid,
+        github_ids: &[String],
+    ) -> Result<Vec<(String, Uuid)>, sqlx::Error> {
+        let members = sqlx::query!(
+            r#"
+            SELECT DISTINCT ON (github.github_id)
+                github.github_id as "github_id!",
+                github.user_id as "user_id!"
+            FROM (
+                SELECT u.github_id, u.id as user_id
+                FROM users u
+                WHERE u.github_id = ANY($2)
+                UNION
+                SELECT i.subject as github_id, i.user_id
+                FROM user_identities i
+                WHERE i.provider = 'github' AND i.subject = ANY($2)
+            ) github
+            INNER JOIN org_members om ON om.user_id = github.user_id
+            WHERE om.org_id = $1 AND om.status = 'ACTIVE'
+            ORDER BY github.github_id, github.user_id
+            "#,
+            org_id,
+            github_ids
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(members
+            .into_iter()
+            .map(|member| (member.github_id, member.user_id))
+            .collect())
+    }
+
+    // the issue, its labels, assignees and comments land together with the progress,
+    // so an interrupted import never leaves half an issue behind.
+    // returns whether the issue had to give up its original number
+    pub async fn import_issue(
+        &self,
+        import_id: Uuid,
+        org_id: Uuid,
+        team_id: Option<Uuid>,
+        issue: ImportedIssue,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let mut issue_id = None;
+        if let Some(number) = issue.number {
+            issue_id = sqlx::query_scalar!(
+                r#"
+                INSERT INTO issues (
+                    org_id, team_id, creator_id, number, title, description,
+                    priority, status, created_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                ON CONFLICT ON CONSTRAINT unique_issue_number_per_team DO NOTHING
+                RETURNING id
+                "#,
+                org_id,
+                team_id,
+                issue.creator_id,
+                number,
+                issue.title,
+                issue.description,
+                IssuePriority::Low as IssuePriority,
+                issue.status.clone() as IssueStatus,
+                issue.created_at
+            )
+            .fetch_optional(&mut *tx)
+            .await?;
+        }
+
+        let renumbered = issue.number.is_some() && issue_id.is_none();
+        let issue_id = match issue_id {
+            Some(issue_id) => issue_id,
+            None => {
+                sqlx::query_scalar!(
+                    r#"
+                    INSERT INTO issues (
+                        org_id, team_id, creator_id, number, title, description,
+                        priority, status, created_at
+                    )
+                    SELECT $1, $2, $3, COALESCE(MAX(number), 0) + 1, $4, $5, $6, $7, $8
+                    FROM issues
+                    WHERE org_id = $1 AND team_id IS NOT DISTINCT FROM $2
+                    RETURNING id
+                    "#,
+                    org_id,
+                    team_id,
+                    issue.creator_id,
+                    issue.title,
+                    issue.description,
+                    IssuePriority::Low as IssuePriority,
+                    issue.status as IssueStatus,
+                    issue.created_at
+                )
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        let comment_count = issue.comments.len() as i32;
+        Self::add_issue_details_tx(
+            &mut tx,
+            org_id,
+            issue_id,
+            &issue.labels,
+            &issue.assignee_ids,
+            &issue.comments,
+        )
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO org_import_items (import_id, external_id, issue_id)
+            VALUES ($1, $2, $3)
+            "#,
+            import_id,
+            issue.external_id,
+            issue_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE org_imports
+            SET imported_issues = imported_issues + 1,
+                imported_comments = imported_comments + $2,
+                renumbered_issues = renumbered_issues + $3,
+                updated_at = now()
+            WHERE id = $1
+            "#,
+            import_id,
+            comment_count,
+            renumbered as i32
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(renumbered)
+    }
+
+    // all issues are created in one transaction, parents within the file are linked
+    // once every issue exists
+    pub async fn import_file_issues(
+        &self,
+        org_id: Uuid,
+        team_id: Option<Uuid>,
+        issues: Vec<ImportedFileIssue>,
+    ) -> Result<Vec<(Uuid, i32)>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut created = Vec::with_capacity(issues.len());
+
+        for issue in &issues {
+            let row = sqlx::query!(
+                r#"
+                INSERT INTO issues (
+                    org_id, team_id, creator_id, number, title, description,
+                    priority, status, due_date, created_at
+                )
+                SELECT $1, $2, $3, COALESCE(MAX(number), 0) + 1, $4, $5, $6, $7, $8, $9
+                FROM issues
+                WHERE org_id = $1 AND team_id IS NOT DISTINCT FROM $2
+                RETURNING id, number
+                "#,
+                org_id,
+                team_id,
+                issue.creator_id,
+                issue.title,
+                issue.description,
+                issue.priority.clone() as IssuePriority,
+                issue.status.clone() as IssueStatus,
+                issue.due_date,
+                issue.created_at
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            Self::add_issue_details_tx(
+                &mut tx,
+                org_id,
+                row.id,
+                &issue.labels,
+                &issue.assignee_ids,
+                &issue.comments,
+            )
+            .await?;
+            created.push((row.id, row.number));
+        }
+
+        for (i, issue) in issues.iter().enumerate() {
+            if let Some(parent) = issue.parent {
+                sqlx::query!(
+                    "UPDATE issues SET parent_id = $2 WHERE id = $1",
+                    created[i].0,
+                    created[parent].0
+                )
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    async fn add_issue_details_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        issue_id: Uuid,
+        labels: &[ImportedLabel],
+        assignee_ids: &[Uuid],
+        comments: &[ImportedComment],
+    ) -> Result<(), sqlx::Error> {
+        for label in labels {
+            sqlx::query!(
+                r#"
+                WITH label AS (
+                    INSERT INTO labels (org_id, name, color, description)
+                    VALUES ($1, $2, $3, $4)
+                    ON CONFLICT (org_id, name) DO UPDATE SET name = EXCLUDED.name
+                    RETURNING id
+                )
+                INSERT INTO issue_labels (issue_id, label_id)
+                SELECT $5, id FROM label
+                ON CONFLICT DO NOTHING
+                "#,
+                org_id,
+                label.name,
+                label.color,
+                label.description,
+                issue_id
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            INSERT INTO issue_assignees (issue_id, org_id, user_id)
+            SELECT $1, $2, unnest($3::uuid[])
+            ON CONFLICT DO NOTHING
+            "#,
+            issue_id,
+            org_id,
+            assignee_ids
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        for comment in comments {
+            sqlx::query!(
+                r#"
+                INSERT INTO comments (
+                    org_id, creator_id, comment_type, comment_owner_id, content,
+                    created_at, updated_at
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $6)
+                "#,
+                org_id,
+                comment.creator_id,
+                CommentType::Issue as CommentType,
+                issue_id,
+                comment.content,
+                comment.created_at
+            )
+            .execute(&mut **tx)
+            .await?;
+        }
+
+        Ok(())
+    }
+}
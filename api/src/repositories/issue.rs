@@ -2,7 +2,10 @@ use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::issue::*;
+use crate::models::{
+    issue::*,
+    issue_csv::{CsvIssueRow, CsvParent, IssueCsvRecord},
+};
 use serde_json::Value as JsonValue;
 
 pub struct IssueRepository {
@@ -26,18 +29,18 @@ impl IssueRepository {
             WITH new_issue AS (
                 SELECT COALESCE(MAX(number), 0) + 1 as next_number
                 FROM issues
-                WHERE org_id = $1
+                WHERE org_id = $1 AND team_id IS NOT DISTINCT FROM $9
             )
             INSERT INTO issues (
                 org_id, creator_id, number, title, description,
-                priority, status, parent_id, due_date
+                priority, status, parent_id, due_date, team_id
             )
             SELECT
                 $1, $2, next_number, $3, $4,
-                $5::issue_priority, $6::issue_status, $7, $8
+                $5::issue_priority, $6::issue_status, $7, $8, $9
             FROM new_issue
             RETURNING
-                id, org_id, creator_id, number,
+                id, org_id, team_id, creator_id, number,
                 title, description as "description: JsonValue",
                 priority as "priority: _",
                 status as "status: _",
@@ -54,6 +57,7 @@ impl IssueRepository {
             new_issue.status as IssueStatus as _,
             new_issue.parent_id,
             new_issue.due_date,
+            new_issue.team_id,
         )
         .fetch_one(&self.pool)
         .await?;
@@ -66,7 +70,7 @@ impl IssueRepository {
             Issue,
             r#"
             SELECT
-                id, org_id, creator_id, number,
+                id, org_id, team_id, creator_id, number,
                 title, description as "description: JsonValue",
                 priority as "priority: _",
                 status as "status: _",
@@ -89,6 +93,56 @@ impl IssueRepository {
         Ok(issue)
     }
 
+    // the prefix is the current or a previous key of the team for team issues, otherwise
+    // the current or a previous org identifier
+    pub async fn find_issue_by_key(
+        &self,
+        org_id: Uuid,
+        prefix: &str,
+        number: i32,
+    ) -> Result<Option<Issue>, sqlx::Error> {
+        let issue = sqlx::query_as!(
+            Issue,
+            r#"
+            SELECT
+                i.id, i.org_id, i.team_id, i.creator_id, i.number,
+                i.title, i.description as "description: JsonValue",
+                i.priority as "priority: _",
+                i.status as "status: _",
+                i.parent_id,
+                i.due_date,
+                i.created_at as "created_at!: DateTime<Utc>",
+                i.updated_at as "updated_at!: DateTime<Utc>"
+            FROM issues i
+            WHERE i.org_id = $1
+            AND i.number = $3
+            AND (
+                i.team_id = (SELECT id FROM teams WHERE org_id = $1 AND key = $2)
+                OR i.team_id = (
+                    SELECT team_id FROM team_previous_keys WHERE org_id = $1 AND key = $2
+                )
+                OR (
+                    i.team_id IS NULL
+                    AND (
+                        EXISTS (SELECT 1 FROM org WHERE id = $1 AND custom_id = $2)
+                        OR EXISTS (
+                            SELECT 1 FROM org_previous_identifiers
+                            WHERE org_id = $1 AND custom_id = $2
+                        )
+                    )
+                )
+            )
+            "#,
+            org_id,
+            prefix,
+            number
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(issue)
+    }
+
     pub async fn update_issue(
         &self,
         issue_id: Uuid,
@@ -97,6 +151,15 @@ impl IssueRepository {
         let issue = sqlx::query_as!(
             Issue,
             r#"
+        WITH target AS (
+            SELECT CASE
+                WHEN $9 = true THEN NULL
+                WHEN $10::uuid IS NOT NULL THEN $10::uuid
+                ELSE team_id
+            END as new_team_id
+            FROM issues
+            WHERE id = $1
+        )
         UPDATE issues
         SET
             title = COALESCE($2, title),
@@ -108,10 +171,21 @@ impl IssueRepository {
                 WHEN $7 = true THEN NULL  -- When remove_due_date is true, set to NULL
                 WHEN $8::timestamptz IS NOT NULL THEN $8::timestamptz  -- When new date provided
                 ELSE due_date  -- Keep existing value
+            END,
+            team_id = target.new_team_id,
+            number = CASE
+                WHEN target.new_team_id IS NOT DISTINCT FROM team_id THEN number
+                ELSE (
+                    SELECT COALESCE(MAX(i.number), 0) + 1
+                    FROM issues i
+                    WHERE i.org_id = issues.org_id
+                    AND i.team_id IS NOT DISTINCT FROM target.new_team_id
+                )
             END
+        FROM target
         WHERE id = $1
         RETURNING
-            id, org_id, creator_id, number,
+            id, org_id, team_id, creator_id, number,
             title, description as "description: JsonValue",
             priority as "priority: _",
             status as "status: _",
@@ -128,6 +202,8 @@ impl IssueRepository {
             data.parent_id,
             data.remove_due_date.unwrap_or(false),
             data.due_date,
+            data.remove_team.unwrap_or(false),
+            data.team_id,
         )
         .fetch_one(&self.pool)
         .await?;
@@ -146,7 +222,7 @@ impl IssueRepository {
             DELETE FROM issues
             WHERE id = $1
             RETURNING
-                id, org_id, creator_id, number,
+                id, org_id, team_id, creator_id, number,
                 title, description as "description: JsonValue",
                 priority as "priority: _",
                 status as "status: _",
@@ -167,12 +243,16 @@ impl IssueRepository {
         Ok(())
     }
 
-    pub async fn get_all_issues_by_org_id(&self, org_id: Uuid) -> Result<Vec<Issue>, sqlx::Error> {
+    pub async fn get_all_issues_by_org_id(
+        &self,
+        org_id: Uuid,
+        filter: &IssueListQuery,
+    ) -> Result<Vec<Issue>, sqlx::Error> {
         let issues = sqlx::query_as!(
             Issue,
             r#"
             SELECT
-                id, org_id, creator_id, number,
+                id, org_id, team_id, creator_id, number,
                 title, description as "description: JsonValue",
                 priority as "priority: _",
                 status as "status: _",
@@ -180,10 +260,28 @@ impl IssueRepository {
                 due_date,
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>"
-            FROM issues
+            FROM issues i
             WHERE org_id = $1 AND parent_id IS NULL
+            AND ($2::uuid IS NULL OR team_id = $2)
+            AND ($3::issue_status IS NULL OR status = $3)
+            AND ($4::issue_priority IS NULL OR priority = $4)
+            AND ($5::uuid IS NULL OR creator_id = $5)
+            AND ($6::uuid IS NULL OR EXISTS (
+                SELECT 1 FROM issue_assignees a WHERE a.issue_id = i.id AND a.user_id = $6
+            ))
+            AND ($7::text IS NULL OR EXISTS (
+                SELECT 1 FROM issue_labels il
+                INNER JOIN labels l ON l.id = il.label_id
+                WHERE il.issue_id = i.id AND l.name = $7
+            ))
             "#,
             org_id,
+            filter.team_id,
+            filter.status.clone() as Option<IssueStatus>,
+            filter.priority.clone() as Option<IssuePriority>,
+            filter.creator_id,
+            filter.assignee_id,
+            filter.label,
         )
         .fetch_all(&self.pool)
         .await?;
@@ -191,12 +289,174 @@ impl IssueRepository {
         Ok(issues)
     }
 
+    // flat, sub-issues included, with everything resolved to what a spreadsheet shows
+    pub async fn export_issues(
+        &self,
+        org_id: Uuid,
+        filter: &IssueListQuery,
+    ) -> Result<Vec<IssueCsvRecord>, sqlx::Error> {
+        let records = sqlx::query_as!(
+            IssueCsvRecord,
+            r#"
+            SELECT
+                COALESCE(t.key, o.custom_id) || '-' || i.number as "key!",
+                i.number,
+                t.key as "team?",
+                i.title,
+                i.description as "description: JsonValue",
+                i.status as "status: _",
+                i.priority as "priority: _",
+                i.due_date,
+                p.number as "parent?",
+                u.email as "creator!",
+                ARRAY(
+                    SELECT au.email FROM issue_assignees a
+                    INNER JOIN users au ON au.id = a.user_id
+                    WHERE a.issue_id = i.id
+                    ORDER BY a.created_at
+                ) as "assignees!",
+                ARRAY(
+                    SELECT l.name FROM issue_labels il
+                    INNER JOIN labels l ON l.id = il.label_id
+                    WHERE il.issue_id = i.id
+                    ORDER BY l.name
+                ) as "labels!",
+                i.created_at as "created_at!: DateTime<Utc>",
+                i.updated_at as "updated_at!: DateTime<Utc>"
+            FROM issues i
+            INNER JOIN org o ON o.id = i.org_id
+            INNER JOIN users u ON u.id = i.creator_id
+            LEFT JOIN teams t ON t.id = i.team_id
+            LEFT JOIN issues p ON p.id = i.parent_id
+            WHERE i.org_id = $1
+            AND ($2::uuid IS NULL OR i.team_id = $2)
+            AND ($3::issue_status IS NULL OR i.status = $3)
+            AND ($4::issue_priority IS NULL OR i.priority = $4)
+            AND ($5::uuid IS NULL OR i.creator_id = $5)
+            AND ($6::uuid IS NULL OR EXISTS (
+                SELECT 1 FROM issue_assignees a WHERE a.issue_id = i.id AND a.user_id = $6
+            ))
+            AND ($7::text IS NULL OR EXISTS (
+                SELECT 1 FROM issue_labels il
+                INNER JOIN labels l ON l.id = il.label_id
+                WHERE il.issue_id = i.id AND l.name = $7
+            ))
+            ORDER BY t.key NULLS FIRST, i.number
+            "#,
+            org_id,
+            filter.team_id,
+            filter.status.clone() as Option<IssueStatus>,
+            filter.priority.clone() as Option<IssuePriority>,
+            filter.creator_id,
+            filter.assignee_id,
+            filter.label,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(records)
+    }
+
+    // issue numbers of the org or team, used to resolve parents given by number
+    pub async fn find_issue_ids_by_numbers(
+        &self,
+        org_id: Uuid,
+        team_id: Option<Uuid>,
+        numbers: &[i32],
+    ) -> Result<Vec<(i32, Uuid)>, sqlx::Error> {
+        let issues = sqlx::query!(
+            r#"
+            SELECT number, id
+            FROM issues
+            WHERE org_id = $1 AND team_id IS NOT DISTINCT FROM $2 AND number = ANY($3)
+            "#,
+            org_id,
+            team_id,
+            numbers
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(issues
+            .into_iter()
+            .map(|issue| (issue.number, issue.id))
+            .collect())
+    }
+
+    // all rows or none, parents within the file are linked once every row exists
+    pub async fn create_issues(
+        &self,
+        org_id: Uuid,
+        team_id: Option<Uuid>,
+        rows: Vec<CsvIssueRow>,
+    ) -> Result<Vec<Issue>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut issues = Vec::with_capacity(rows.len());
+
+        for row in &rows {
+            let issue = sqlx::query_as!(
+                Issue,
+                r#"
+                INSERT INTO issues (
+                    org_id, team_id, creator_id, number, title, description,
+                    priority, status, due_date, parent_id
+                )
+                SELECT $1, $2, $3, COALESCE(MAX(number), 0) + 1, $4, $5, $6, $7, $8, $9
+                FROM issues
+                WHERE org_id = $1 AND team_id IS NOT DISTINCT FROM $2
+                RETURNING
+                    id, org_id, team_id, creator_id, number,
+                    title, description as "description: JsonValue",
+                    priority as "priority: _",
+                    status as "status: _",
+                    parent_id,
+                    due_date,
+                    created_at as "created_at!: DateTime<Utc>",
+                    updated_at as "updated_at!: DateTime<Utc>"
+                "#,
+                org_id,
+                team_id,
+                row.creator_id,
+                row.title,
+                row.description,
+                row.priority.clone() as IssuePriority,
+                row.status.clone() as IssueStatus,
+                row.due_date,
+                match row.parent {
+                    Some(CsvParent::Issue(parent_id)) => Some(parent_id),
+                    _ => None,
+                }
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+            issues.push(issue);
+        }
+
+        for (i, row) in rows.iter().enumerate() {
+            if let Some(CsvParent::Row(parent)) = row.parent {
+                let parent_id = issues[parent].id;
+                sqlx::query!(
+                    "UPDATE issues SET parent_id = $2 WHERE id = $1",
+                    issues[i].id,
+                    parent_id
+                )
+                .execute(&mut *tx)
+                .await?;
+                issues[i].parent_id = Some(parent_id);
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok(issues)
+    }
+
     pub async fn get_sub_issues(&self, issue_id: Uuid) -> Result<Vec<Issue>, sqlx::Error> {
         let issues = sqlx::query_as!(
             Issue,
             r#"
             SELECT
-                id, org_id, creator_id, number,
+                id, org_id, team_id, creator_id, number,
                 title, description as "description: JsonValue",
                 priority as "priority: _",
                 status as "status: _",
@@ -214,4 +474,36 @@ impl IssueRepository {
 
         Ok(issues)
     }
+
+    pub async fn get_issue_labels(&self, issue_id: Uuid) -> Result<Vec<Label>, sqlx::Error> {
+        let labels = sqlx::query_as!(
+            Label,
+            r#"
+            SELECT
+                l.id, l.org_id, l.name, l.color, l.description,
+                l.created_at as "created_at!: DateTime<Utc>",
+                l.updated_at as "updated_at!: DateTime<Utc>"
+            FROM labels l
+            INNER JOIN issue_labels il ON il.label_id = l.id
+            WHERE il.issue_id = $1
+            ORDER BY l.name
+            "#,
+            issue_id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(labels)
+    }
+
+    pub async fn get_issue_assignee_ids(&self, issue_id: Uuid) -> Result<Vec<Uuid>, sqlx::Error> {
+        let assignee_ids = sqlx::query_scalar!(
+            "SELECT user_id FROM issue_assignees WHERE issue_id = $1 ORDER BY created_at",
+            issue_id,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(assignee_ids)
+    }
 }
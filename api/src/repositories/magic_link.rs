@@ -0,0 +1,120 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::magic_link::{CreateMagicLinkData, MagicLinkToken};
+
+pub struct MagicLinkRepository {
+    pool: PgPool,
+}
+
+impl MagicLinkRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_token(
+        &self,
+        data: CreateMagicLinkData,
+    ) -> Result<MagicLinkToken, sqlx::Error> {
+        let token = sqlx::query_as!(
+            MagicLinkToken,
+            r#"
+            INSERT INTO magic_link_tokens (user_id, token_hash, expires_at, ip_address)
+            VALUES ($1, $2, $3, $4)
+            RETURNING
+                id,
+                user_id,
+                token_hash,
+                expires_at,
+                used_at,
+                ip_address,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            data.user_id,
+            data.token_hash,
+            data.expires_at,
+            data.ip_address
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn count_recent_tokens(
+        &self,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM magic_link_tokens
+            WHERE user_id = $1 AND created_at > $2
+            "#,
+            user_id,
+            since
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    // marking the token used in the same statement that checks it keeps two
+    // requests racing with the same link from both getting a session
+    pub async fn use_token(&self, token_hash: &str) -> Result<Option<MagicLinkToken>, sqlx::Error> {
+        let token = sqlx::query_as!(
+            MagicLinkToken,
+            r#"
+            UPDATE magic_link_tokens
+            SET used_at = now()
+            WHERE token_hash = $1
+            AND used_at IS NULL
+            AND expires_at > now()
+            RETURNING
+                id,
+                user_id,
+                token_hash,
+                expires_at,
+                used_at,
+                ip_address,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    // a new link replaces any earlier one that has not been used yet
+    pub async fn invalidate_user_tokens(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE magic_link_tokens
+            SET expires_at = now()
+            WHERE user_id = $1 AND used_at IS NULL AND expires_at > now()
+            "#,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // links created since `keep_since` stay around so the rate limit can still count them
+    pub async fn delete_expired(&self, keep_since: DateTime<Utc>) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM magic_link_tokens WHERE expires_at < now() AND created_at <= $1",
+            keep_since
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,19 @@
+pub mod audit_log;
+pub mod auth_lockout;
+pub mod auth_log;
 pub mod auth_token;
 pub mod comment;
+pub mod identity;
+pub mod import;
 pub mod issue;
+pub mod magic_link;
+pub mod oauth_state;
 pub mod org;
+pub mod org_export;
+pub mod personal_access_token;
+pub mod role;
+pub mod team;
+pub mod two_factor;
 pub mod user;
 pub mod user_preferences;
+pub mod webhook;
@@ -0,0 +1,74 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::models::oauth::{CreateOauthStateData, OauthState};
+
+pub struct OauthStateRepository {
+    pool: PgPool,
+}
+
+impl OauthStateRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_state(&self, data: CreateOauthStateData) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_states
+                (provider, state_hash, pkce_verifier, nonce, redirect_to, link_user_id, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            data.provider,
+            data.state_hash,
+            data.pkce_verifier,
+            data.nonce,
+            data.redirect_to,
+            data.link_user_id,
+            data.expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // a state is single use, so it is deleted as it is read; expired rows are
+    // still returned so the caller can tell them apart from unknown ones
+    pub async fn consume_state(
+        &self,
+        provider: &str,
+        state_hash: &str,
+    ) -> Result<Option<OauthState>, sqlx::Error> {
+        let state = sqlx::query_as!(
+            OauthState,
+            r#"
+            DELETE FROM oauth_states
+            WHERE provider = $1 AND state_hash = $2
+            RETURNING
+                id,
+                provider,
+                pkce_verifier,
+                nonce,
+                redirect_to,
+                link_user_id,
+                expires_at,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            provider,
+            state_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(state)
+    }
+
+    pub async fn delete_expired(&self) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM oauth_states WHERE expires_at < now()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+}
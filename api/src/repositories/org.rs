@@ -5,8 +5,10 @@ use uuid::Uuid;
 use crate::models::{
     auth::User,
     org::{
-        CreateOrgRequest, MemberRole, MemberStatus, Org, OrgMember, OrgMemberInvite,
-        OrgMemberInviteResponse, OrgMemberResponse, UpdateOrgRequest,
+        CreateOrgMemberData, CreateOrgRequest, IdentifierChangeResult, JoinMethod,
+        JoinableOrgResponse, MemberChangeResult, MemberRole, MemberStatus, Org, OrgDomain,
+        OrgInviteLink, OrgMember, OrgMemberInvite, OrgMemberInviteResponse, OrgMemberResponse,
+        OrgMembership, TransferOwnershipResult, UpdateOrgRequest,
     },
 };
 
@@ -37,7 +39,9 @@ impl OrgRepository {
               custom_id,
               created_at as "created_at!: DateTime<Utc>",
               updated_at as "updated_at!: DateTime<Utc>",
-              logo_url
+              logo_url,
+              require_two_factor,
+              purge_at
           "#,
             data.name,
             data.logo_url,
@@ -61,7 +65,9 @@ impl OrgRepository {
                 o.custom_id,
                 o.created_at as "created_at!: DateTime<Utc>",
                 o.updated_at as "updated_at!: DateTime<Utc>",
-                o.logo_url
+                o.logo_url,
+                o.require_two_factor,
+                o.purge_at
             FROM org o
             INNER JOIN org_members om
                 ON o.id = om.org_id
@@ -83,33 +89,15 @@ impl OrgRepository {
         Ok(result)
     }
 
-    pub async fn public_find_by_slug(&self, slug: String) -> Result<Org, sqlx::Error> {
-        let result = sqlx::query_as!(
-            Org,
-            r#"
-            SELECT
-                id,
-                name,
-                slug,
-                custom_id,
-                created_at as "created_at!: DateTime<Utc>",
-                updated_at as "updated_at!: DateTime<Utc>",
-                logo_url
-            FROM org
-            WHERE slug = $1
-            "#,
-            slug
-        )
-        .fetch_optional(&self.pool)
-        .await?
-        .ok_or(sqlx::Error::RowNotFound)?;
-
-        Ok(result)
-    }
-
-    pub async fn find_by_slug(&self, slug: String, user_id: Uuid) -> Result<Org, sqlx::Error> {
-        let result = sqlx::query_as!(
-            Org,
+    // one lookup for the org guard, also finds suspended and invited members,
+    // the org is addressed by its id or by its current or a previous slug
+    pub async fn find_membership(
+        &self,
+        id: Option<Uuid>,
+        slug: Option<&str>,
+        user_id: Uuid,
+    ) -> Result<Option<OrgMembership>, sqlx::Error> {
+        let result = sqlx::query!(
             r#"
             SELECT
                 o.id,
@@ -118,24 +106,69 @@ impl OrgRepository {
                 o.custom_id,
                 o.created_at as "created_at!: DateTime<Utc>",
                 o.updated_at as "updated_at!: DateTime<Utc>",
-                o.logo_url
+                o.logo_url,
+                o.require_two_factor,
+                o.purge_at,
+                om.role as "role: MemberRole",
+                om.status as "status: MemberStatus",
+                r.permissions as "custom_permissions?"
             FROM org o
             INNER JOIN org_members om
                 ON o.id = om.org_id
                 AND om.user_id = $2
-                AND om.status = 'ACTIVE'
-            WHERE o.slug = $1
+            LEFT JOIN org_roles r
+                ON r.id = om.custom_role_id
+            WHERE o.id = $1
+            OR o.slug = $3
+            OR o.id = (SELECT org_id FROM org_previous_slugs WHERE slug = $3)
             "#,
-            slug,
-            user_id
+            id,
+            user_id,
+            slug
         )
         .fetch_optional(&self.pool)
-        .await?
-        .ok_or(sqlx::Error::RowNotFound)?;
+        .await?;
 
-        if result.slug != slug {
-            return Err(sqlx::Error::RowNotFound);
-        }
+        Ok(result.map(|row| OrgMembership {
+            org: Org {
+                id: row.id,
+                name: row.name,
+                slug: row.slug,
+                custom_id: row.custom_id,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                logo_url: row.logo_url,
+                require_two_factor: row.require_two_factor,
+                purge_at: row.purge_at,
+            },
+            role: row.role,
+            status: row.status,
+            custom_permissions: row.custom_permissions,
+        }))
+    }
+
+    // only for showing an org to someone who is about to join it
+    pub async fn public_find_by_id(&self, id: Uuid) -> Result<Org, sqlx::Error> {
+        let result = sqlx::query_as!(
+            Org,
+            r#"
+            SELECT
+                id,
+                name,
+                slug,
+                custom_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                logo_url,
+                require_two_factor,
+                purge_at
+            FROM org
+            WHERE id = $1
+            "#,
+            id
+        )
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok(result)
     }
@@ -151,7 +184,9 @@ impl OrgRepository {
                 o.custom_id,
                 o.created_at as "created_at!: DateTime<Utc>",
                 o.updated_at as "updated_at!: DateTime<Utc>",
-                o.logo_url
+                o.logo_url,
+                o.require_two_factor,
+                o.purge_at
             FROM org o
             INNER JOIN org_members om
                 ON o.id = om.org_id
@@ -171,9 +206,10 @@ impl OrgRepository {
             Org,
             r#"
             UPDATE org
-            SET name = $2,
+            SET name = COALESCE($2, name),
                 logo_url = $3,
-                updated_at = $4
+                updated_at = $4,
+                require_two_factor = COALESCE($5, require_two_factor)
             WHERE id = $1
             RETURNING
                 id,
@@ -182,12 +218,15 @@ impl OrgRepository {
                 custom_id,
                 created_at as "created_at!: DateTime<Utc>",
                 updated_at as "updated_at!: DateTime<Utc>",
-                logo_url
+                logo_url,
+                require_two_factor,
+                purge_at
             "#,
             id,
             data.name,
             data.logo_url,
-            Utc::now()
+            Utc::now(),
+            data.require_two_factor
         )
         .fetch_one(&self.pool)
         .await?;
@@ -199,37 +238,273 @@ impl OrgRepository {
         Ok(result)
     }
 
-    pub async fn delete_org(&self, id: Uuid) -> Result<(), sqlx::Error> {
-        sqlx::query!("DELETE FROM org WHERE id = $1", id)
-            .execute(&self.pool)
-            .await?;
+    // the old identifier is kept so issue keys using it keep resolving
+    pub async fn change_identifier(
+        &self,
+        id: Uuid,
+        custom_id: &str,
+    ) -> Result<IdentifierChangeResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
 
-        Ok(())
+        let current = sqlx::query!("SELECT custom_id FROM org WHERE id = $1 FOR UPDATE", id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        if current.custom_id == custom_id {
+            tx.rollback().await?;
+            return Ok(IdentifierChangeResult::Changed);
+        }
+
+        let used_by_team = sqlx::query!(
+            r#"
+            SELECT (
+                EXISTS (SELECT 1 FROM teams WHERE org_id = $1 AND key = $2)
+                OR EXISTS (SELECT 1 FROM team_previous_keys WHERE org_id = $1 AND key = $2)
+            ) as "exists!"
+            "#,
+            id,
+            custom_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        if used_by_team.exists {
+            tx.rollback().await?;
+            return Ok(IdentifierChangeResult::UsedByTeam);
+        }
+
+        if Self::is_identifier_taken_tx(&mut tx, custom_id, Some(id)).await? {
+            tx.rollback().await?;
+            return Ok(IdentifierChangeResult::Taken);
+        }
+
+        // switching back to a retired identifier takes it out of the history
+        sqlx::query!(
+            "DELETE FROM org_previous_identifiers WHERE custom_id = $1 AND org_id = $2",
+            custom_id,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO org_previous_identifiers (custom_id, org_id)
+            VALUES ($1, $2)
+            ON CONFLICT (custom_id) DO NOTHING
+            "#,
+            current.custom_id,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query!(
+            "UPDATE org SET custom_id = $2, updated_at = now() WHERE id = $1",
+            id,
+            custom_id
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match result {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                return Ok(IdentifierChangeResult::Taken);
+            }
+            Err(e) => return Err(e),
+        }
+
+        tx.commit().await?;
+
+        Ok(IdentifierChangeResult::Changed)
     }
 
-    pub async fn create_member(
-        &self,
-        org_id: Uuid,
-        user_id: Uuid,
-        role: MemberRole,
-        invited_by: Uuid,
-    ) -> Result<(), sqlx::Error> {
+    // the old slug is kept so links using it keep resolving
+    pub async fn change_slug(&self, id: Uuid, slug: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query!("SELECT slug FROM org WHERE id = $1 FOR UPDATE", id)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or(sqlx::Error::RowNotFound)?;
+
+        if current.slug == slug {
+            tx.rollback().await?;
+            return Ok(true);
+        }
+
+        if Self::is_slug_taken_tx(&mut tx, slug, Some(id)).await? {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        sqlx::query!(
+            "DELETE FROM org_previous_slugs WHERE slug = $1 AND org_id = $2",
+            slug,
+            id
+        )
+        .execute(&mut *tx)
+        .await?;
+
         sqlx::query!(
             r#"
-            INSERT INTO org_members (org_id, user_id, role, status, invited_by)
-            VALUES ($1, $2, $3::member_role, 'ACTIVE', $4)
+            INSERT INTO org_previous_slugs (slug, org_id)
+            VALUES ($1, $2)
+            ON CONFLICT (slug) DO NOTHING
             "#,
-            org_id,
-            user_id,
-            role as MemberRole,
-            invited_by
+            current.slug,
+            id
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
+        .await?;
+
+        let result = sqlx::query!(
+            "UPDATE org SET slug = $2, updated_at = now() WHERE id = $1",
+            id,
+            slug
+        )
+        .execute(&mut *tx)
+        .await;
+
+        match result {
+            Ok(_) => {}
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                return Ok(false);
+            }
+            Err(e) => return Err(e),
+        }
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn is_slug_taken(&self, slug: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let taken = Self::is_slug_taken_tx(&mut tx, slug, None).await?;
+        tx.commit().await?;
+
+        Ok(taken)
+    }
+
+    pub async fn is_identifier_taken(&self, custom_id: &str) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let taken = Self::is_identifier_taken_tx(&mut tx, custom_id, None).await?;
+        tx.commit().await?;
+
+        Ok(taken)
+    }
+
+    // returns none when the org is already scheduled for deletion
+    pub async fn schedule_deletion(
+        &self,
+        id: Uuid,
+        requested_by: Uuid,
+        purge_at: DateTime<Utc>,
+    ) -> Result<Option<Org>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            Org,
+            r#"
+            UPDATE org
+            SET deletion_requested_at = now(),
+                deletion_requested_by = $2,
+                purge_at = $3
+            WHERE id = $1 AND purge_at IS NULL
+            RETURNING
+                id,
+                name,
+                slug,
+                custom_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                logo_url,
+                require_two_factor,
+                purge_at
+            "#,
+            id,
+            requested_by,
+            purge_at
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    // returns none when the org is not scheduled for deletion
+    pub async fn cancel_deletion(&self, id: Uuid) -> Result<Option<Org>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            Org,
+            r#"
+            UPDATE org
+            SET deletion_requested_at = NULL,
+                deletion_requested_by = NULL,
+                purge_at = NULL
+            WHERE id = $1 AND purge_at IS NOT NULL
+            RETURNING
+                id,
+                name,
+                slug,
+                custom_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>",
+                logo_url,
+                require_two_factor,
+                purge_at
+            "#,
+            id
+        )
+        .fetch_optional(&self.pool)
         .await?;
 
+        Ok(result)
+    }
+
+    // cascades to everything the orgs own
+    pub async fn purge_due_orgs(&self) -> Result<Vec<Uuid>, sqlx::Error> {
+        let purged = sqlx::query_scalar!("DELETE FROM org WHERE purge_at <= now() RETURNING id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(purged)
+    }
+
+    // every way into an org ends here, a second membership for the same user
+    // fails on the org_members_org_id_user_id_key constraint
+    pub async fn create_member(&self, data: CreateOrgMemberData) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::create_member_tx(&mut tx, data).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    // the invite is only gone once the membership exists, a failed join leaves it usable
+    pub async fn accept_invite(
+        &self,
+        token: String,
+        data: CreateOrgMemberData,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!("DELETE FROM org_invites WHERE token = $1", token)
+            .execute(&mut *tx)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(sqlx::Error::RowNotFound);
+        }
+
+        Self::create_member_tx(&mut tx, data).await?;
+        tx.commit().await?;
+
         Ok(())
     }
 
+    // expired invites are included so they can be resent
     pub async fn list_org_invites(
         &self,
         org_id: Uuid,
@@ -241,13 +516,15 @@ impl OrgRepository {
             id,
             org_id,
             email,
+            user_id,
             role as "role: MemberRole",
             invited_by,
             invited_at as "invited_at!: DateTime<Utc>",
             expires_at as "expires_at!: DateTime<Utc>",
             token
         FROM org_invites
-        WHERE org_id = $1 AND expires_at > NOW()
+        WHERE org_id = $1
+        ORDER BY invited_at DESC
         "#,
             org_id
         )
@@ -262,53 +539,133 @@ impl OrgRepository {
         org_id: Uuid,
         user_id: Uuid,
         role: MemberRole,
-    ) -> Result<(), sqlx::Error> {
-        let role_str = match role {
-            MemberRole::Owner => "OWNER",
-            MemberRole::Admin => "ADMIN",
-            MemberRole::Member => "MEMBER",
+    ) -> Result<MemberChangeResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_org_tx(&mut tx, org_id).await?;
+
+        let current_role = match Self::find_member_role_tx(&mut tx, org_id, user_id).await? {
+            Some(current_role) => current_role,
+            None => {
+                tx.rollback().await?;
+                return Ok(MemberChangeResult::NotFound);
+            }
         };
 
-        let result = sqlx::query!(
+        if current_role == MemberRole::Owner
+            && role != MemberRole::Owner
+            && Self::count_other_owners_tx(&mut tx, org_id, user_id).await? == 0
+        {
+            tx.rollback().await?;
+            return Ok(MemberChangeResult::LastOwner);
+        }
+
+        sqlx::query!(
             r#"
-          UPDATE org_members
-          SET role = $3::member_role
-          WHERE org_id = $1 AND user_id = $2
-          "#,
+            UPDATE org_members
+            SET role = $3::member_role,
+                custom_role_id = CASE WHEN $3::member_role = 'OWNER' THEN NULL ELSE custom_role_id END,
+                updated_at = now()
+            WHERE org_id = $1 AND user_id = $2
+            "#,
             org_id,
             user_id,
-            role_str as &str
+            role as MemberRole
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(sqlx::Error::RowNotFound);
-        }
+        tx.commit().await?;
 
-        Ok(())
+        Ok(MemberChangeResult::Changed)
     }
 
-    pub async fn remove_member(&self, org_id: Uuid, user_id: Uuid) -> Result<(), sqlx::Error> {
-        let result = sqlx::query!(
+    pub async fn remove_member(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<MemberChangeResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_org_tx(&mut tx, org_id).await?;
+
+        let current_role = match Self::find_member_role_tx(&mut tx, org_id, user_id).await? {
+            Some(current_role) => current_role,
+            None => {
+                tx.rollback().await?;
+                return Ok(MemberChangeResult::NotFound);
+            }
+        };
+
+        if current_role == MemberRole::Owner
+            && Self::count_other_owners_tx(&mut tx, org_id, user_id).await? == 0
+        {
+            tx.rollback().await?;
+            return Ok(MemberChangeResult::LastOwner);
+        }
+
+        sqlx::query!(
             r#"
-          DELETE FROM org_members
-          WHERE org_id = $1 AND user_id = $2
-          "#,
+            DELETE FROM org_members
+            WHERE org_id = $1 AND user_id = $2
+            "#,
             org_id,
             user_id
         )
-        .execute(&self.pool)
+        .execute(&mut *tx)
         .await?;
 
-        if result.rows_affected() == 0 {
-            return Err(sqlx::Error::RowNotFound);
+        tx.commit().await?;
+
+        Ok(MemberChangeResult::Changed)
+    }
+
+    // the new owner is promoted and the previous one stays on as an admin
+    pub async fn transfer_ownership(
+        &self,
+        org_id: Uuid,
+        owner_id: Uuid,
+        new_owner_id: Uuid,
+    ) -> Result<TransferOwnershipResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::lock_org_tx(&mut tx, org_id).await?;
+
+        if Self::find_member_role_tx(&mut tx, org_id, owner_id).await? != Some(MemberRole::Owner) {
+            tx.rollback().await?;
+            return Ok(TransferOwnershipResult::NotOwner);
         }
 
-        Ok(())
+        if Self::find_member_role_tx(&mut tx, org_id, new_owner_id)
+            .await?
+            .is_none()
+        {
+            tx.rollback().await?;
+            return Ok(TransferOwnershipResult::MemberNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE org_members
+            SET role = CASE WHEN user_id = $2 THEN 'OWNER'::member_role ELSE 'ADMIN'::member_role END,
+                custom_role_id = CASE WHEN user_id = $2 THEN NULL ELSE custom_role_id END,
+                updated_at = now()
+            WHERE org_id = $1 AND user_id IN ($2, $3)
+            "#,
+            org_id,
+            new_owner_id,
+            owner_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(TransferOwnershipResult::Transferred)
     }
 
-    pub async fn list_members(&self, org_id: Uuid) -> Result<Vec<OrgMemberResponse>, sqlx::Error> {
+    pub async fn list_members(
+        &self,
+        org_id: Uuid,
+        status: MemberStatus,
+    ) -> Result<Vec<OrgMemberResponse>, sqlx::Error> {
         let results = sqlx::query!(
             r#"
             SELECT
@@ -325,6 +682,7 @@ impl OrgRepository {
                 u.avatar_url as "user_avatar_url",
                 u.github_id as "user_github_id",
                 u.github_url as "user_github_url",
+                u.display_name as "user_display_name",
                 om.id as "org_member_id",
                 om.org_id as "org_member_org_id",
                 om.user_id as "org_member_user_id",
@@ -332,14 +690,19 @@ impl OrgRepository {
                 om.status as "org_member_status: MemberStatus",
                 om.joined_at as "org_member_joined_at!: DateTime<Utc>",
                 om.invited_by as "org_member_invited_by",
+                om.join_method as "org_member_join_method: JoinMethod",
+                om.invite_link_id as "org_member_invite_link_id",
+                om.custom_role_id as "org_member_custom_role_id",
                 om.created_at as "org_member_created_at!: DateTime<Utc>",
                 om.updated_at as "org_member_updated_at!: DateTime<Utc>"
             FROM org_members om
             INNER JOIN users u
                 ON om.user_id = u.id
             WHERE om.org_id = $1
+            AND om.status = $2
             "#,
-            org_id
+            org_id,
+            status as MemberStatus
         )
         .fetch_all(&self.pool)
         .await?;
@@ -362,6 +725,7 @@ impl OrgRepository {
                     avatar_url: row.user_avatar_url,
                     github_id: row.user_github_id,
                     github_url: row.user_github_url,
+                    display_name: row.user_display_name,
                 },
                 org_member: OrgMember {
                     id: row.org_member_id,
@@ -371,6 +735,9 @@ impl OrgRepository {
                     status: row.org_member_status,
                     joined_at: row.org_member_joined_at,
                     invited_by: row.org_member_invited_by,
+                    join_method: row.org_member_join_method,
+                    invite_link_id: row.org_member_invite_link_id,
+                    custom_role_id: row.org_member_custom_role_id,
                     created_at: row.org_member_created_at,
                     updated_at: row.org_member_updated_at,
                 },
@@ -403,49 +770,131 @@ impl OrgRepository {
         Ok(result.role)
     }
 
+    // returns none when the email already has an invite for this org
     pub async fn create_invite(
         &self,
         org_id: Uuid,
         email: String,
+        user_id: Option<Uuid>,
         role: MemberRole,
         invited_by: Uuid,
-    ) -> Result<OrgMemberInvite, sqlx::Error> {
-        let expires_at = Utc::now() + chrono::Duration::days(7);
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<OrgMemberInvite>, sqlx::Error> {
         let token = uuid::Uuid::new_v4().to_string();
 
         let result = sqlx::query_as!(
             OrgMemberInvite,
             r#"
-          INSERT INTO org_invites (
-              org_id, email, role, invited_by, expires_at, token
-          )
-          VALUES ($1, $2, $3, $4, $5, $6)
-          RETURNING
-              id,
-              org_id,
-              email,
-              role as "role: MemberRole",
-              invited_by,
-              invited_at as "invited_at!: DateTime<Utc>",
-              expires_at as "expires_at!: DateTime<Utc>",
-              token
-          "#,
+            INSERT INTO org_invites (
+                org_id, email, user_id, role, invited_by, expires_at, token
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (org_id, email) DO NOTHING
+            RETURNING
+                id,
+                org_id,
+                email,
+                user_id,
+                role as "role: MemberRole",
+                invited_by,
+                invited_at as "invited_at!: DateTime<Utc>",
+                expires_at as "expires_at!: DateTime<Utc>",
+                token
+            "#,
             org_id,
             email,
+            user_id,
             role as MemberRole,
             invited_by,
             expires_at,
             token
         )
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
         Ok(result)
     }
 
+    pub async fn find_invite(
+        &self,
+        org_id: Uuid,
+        invite_id: Uuid,
+    ) -> Result<Option<OrgMemberInvite>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgMemberInvite,
+            r#"
+            SELECT
+                id,
+                org_id,
+                email,
+                user_id,
+                role as "role: MemberRole",
+                invited_by,
+                invited_at as "invited_at!: DateTime<Utc>",
+                expires_at as "expires_at!: DateTime<Utc>",
+                token
+            FROM org_invites
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            invite_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    // the token stays the same, so a link from an earlier email keeps working
+    pub async fn extend_invite(
+        &self,
+        org_id: Uuid,
+        invite_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<OrgMemberInvite>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgMemberInvite,
+            r#"
+            UPDATE org_invites
+            SET expires_at = $3,
+                invited_at = now()
+            WHERE org_id = $1 AND id = $2
+            RETURNING
+                id,
+                org_id,
+                email,
+                user_id,
+                role as "role: MemberRole",
+                invited_by,
+                invited_at as "invited_at!: DateTime<Utc>",
+                expires_at as "expires_at!: DateTime<Utc>",
+                token
+            "#,
+            org_id,
+            invite_id,
+            expires_at
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn delete_invite(&self, org_id: Uuid, invite_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM org_invites WHERE org_id = $1 AND id = $2",
+            org_id,
+            invite_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
     pub async fn list_user_invites(
         &self,
-        email: String,
+        user_id: Uuid,
     ) -> Result<Vec<OrgMemberInviteResponse>, sqlx::Error> {
         let results = sqlx::query!(
             r#"
@@ -453,6 +902,7 @@ impl OrgRepository {
             oi.id as "invite_id!",
             oi.org_id as "invite_org_id!",
             oi.email as "invite_email!",
+            oi.user_id as "invite_user_id",
             oi.role as "invite_role!: MemberRole",
             oi.invited_by as "invite_invited_by!",
             oi.invited_at as "invite_invited_at!: DateTime<Utc>",
@@ -463,10 +913,11 @@ impl OrgRepository {
             u.id as "user_id!",
             u.email as "user_email!",
             u.username as "user_username!",
-            u.password_hash as "user_password_hash!",
+            u.password_hash as "user_password_hash",
             u.avatar_url as "user_avatar_url",
             u.github_id as "user_github_id",
             u.github_url as "user_github_url",
+            u.display_name as "user_display_name",
             u.is_email_verified as "user_is_email_verified!: bool",
             u.email_verification_token as "user_email_verification_token",
             u.email_verification_expires_at as "user_email_verification_expires_at",
@@ -476,9 +927,9 @@ impl OrgRepository {
             FROM org_invites oi
             INNER JOIN org o ON oi.org_id = o.id
             INNER JOIN users u ON oi.invited_by = u.id
-            WHERE oi.email = $1 AND oi.expires_at > NOW()
+            WHERE oi.user_id = $1 AND oi.expires_at > NOW()
             "#,
-            email
+            user_id
         )
         .fetch_all(&self.pool)
         .await?;
@@ -491,6 +942,7 @@ impl OrgRepository {
                     id: row.invite_id,
                     org_id: row.invite_org_id,
                     email: row.invite_email,
+                    user_id: row.invite_user_id,
                     role: row.invite_role,
                     invited_by: row.invite_invited_by,
                     invited_at: row.invite_invited_at,
@@ -503,7 +955,7 @@ impl OrgRepository {
                     id: row.user_id,
                     email: row.user_email,
                     username: row.user_username,
-                    password_hash: Some(row.user_password_hash),
+                    password_hash: row.user_password_hash,
                     is_email_verified: row.user_is_email_verified,
                     email_verification_token: row.user_email_verification_token,
                     email_verification_expires_at: row.user_email_verification_expires_at,
@@ -513,6 +965,7 @@ impl OrgRepository {
                     avatar_url: row.user_avatar_url,
                     github_id: row.user_github_id,
                     github_url: row.user_github_url,
+                    display_name: row.user_display_name,
                 },
             });
         }
@@ -520,6 +973,24 @@ impl OrgRepository {
         Ok(invites)
     }
 
+    // called once an account has a verified address, invites sent to it before
+    // the account existed become visible to that account only
+    pub async fn attach_invites(&self, user_id: Uuid, email: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_invites
+            SET user_id = $1
+            WHERE lower(email) = lower($2) AND user_id IS NULL
+            "#,
+            user_id,
+            email
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     pub async fn remove_invite(&self, token: String) -> Result<(), sqlx::Error> {
         let result = sqlx::query!("DELETE FROM org_invites WHERE token = $1", token)
             .execute(&self.pool)
@@ -543,6 +1014,7 @@ impl OrgRepository {
               id,
               org_id,
               email,
+              user_id,
               role as "role: MemberRole",
               invited_by,
               invited_at as "invited_at!: DateTime<Utc>",
@@ -563,6 +1035,383 @@ impl OrgRepository {
         Ok(result)
     }
 
+    // unlike the other member lookups this also finds invited and suspended members
+    pub async fn find_member(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<OrgMember>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgMember,
+            r#"
+            SELECT
+                id,
+                org_id,
+                user_id,
+                role as "role: MemberRole",
+                status as "status: MemberStatus",
+                joined_at as "joined_at!: DateTime<Utc>",
+                invited_by,
+                join_method as "join_method: JoinMethod",
+                invite_link_id,
+                custom_role_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM org_members
+            WHERE org_id = $1 AND user_id = $2
+            "#,
+            org_id,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    // owners are never suspended, so the last-owner invariant does not depend on status,
+    // suspended members leave their teams since teams only hold active members
+    pub async fn update_member_status(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+        from: MemberStatus,
+        to: MemberStatus,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let suspended = to == MemberStatus::Disabled;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_members
+            SET status = $4::member_status,
+                updated_at = now()
+            WHERE org_id = $1
+            AND user_id = $2
+            AND status = $3::member_status
+            AND role <> 'OWNER'
+            "#,
+            org_id,
+            user_id,
+            from as MemberStatus,
+            to as MemberStatus
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let updated = result.rows_affected() == 1;
+
+        if updated && suspended {
+            sqlx::query!(
+                "DELETE FROM team_members WHERE org_id = $1 AND user_id = $2",
+                org_id,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(updated)
+    }
+
+    pub async fn create_invite_link(
+        &self,
+        org_id: Uuid,
+        role: MemberRole,
+        max_uses: Option<i32>,
+        expires_at: Option<DateTime<Utc>>,
+        created_by: Uuid,
+    ) -> Result<OrgInviteLink, sqlx::Error> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+
+        let result = sqlx::query_as!(
+            OrgInviteLink,
+            r#"
+            INSERT INTO org_invite_links (org_id, token, role, max_uses, expires_at, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING
+                id,
+                org_id,
+                token,
+                role as "role: MemberRole",
+                max_uses,
+                use_count,
+                expires_at,
+                created_by,
+                created_at,
+                revoked_at
+            "#,
+            org_id,
+            token,
+            role as MemberRole,
+            max_uses,
+            expires_at,
+            created_by
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    // revoked links stay listed so it remains visible who joined through them
+    pub async fn list_invite_links(&self, org_id: Uuid) -> Result<Vec<OrgInviteLink>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgInviteLink,
+            r#"
+            SELECT
+                id,
+                org_id,
+                token,
+                role as "role: MemberRole",
+                max_uses,
+                use_count,
+                expires_at,
+                created_by,
+                created_at,
+                revoked_at
+            FROM org_invite_links
+            WHERE org_id = $1
+            ORDER BY created_at DESC
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn find_invite_link(
+        &self,
+        org_id: Uuid,
+        link_id: Uuid,
+    ) -> Result<Option<OrgInviteLink>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgInviteLink,
+            r#"
+            SELECT
+                id,
+                org_id,
+                token,
+                role as "role: MemberRole",
+                max_uses,
+                use_count,
+                expires_at,
+                created_by,
+                created_at,
+                revoked_at
+            FROM org_invite_links
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            link_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn find_invite_link_by_token(
+        &self,
+        token: &str,
+    ) -> Result<Option<OrgInviteLink>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgInviteLink,
+            r#"
+            SELECT
+                id,
+                org_id,
+                token,
+                role as "role: MemberRole",
+                max_uses,
+                use_count,
+                expires_at,
+                created_by,
+                created_at,
+                revoked_at
+            FROM org_invite_links
+            WHERE token = $1
+            "#,
+            token
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn revoke_invite_link(
+        &self,
+        org_id: Uuid,
+        link_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_invite_links
+            SET revoked_at = now()
+            WHERE org_id = $1 AND id = $2 AND revoked_at IS NULL
+            "#,
+            org_id,
+            link_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // the checks live in the update so concurrent redemptions cannot go past max_uses
+    pub async fn claim_invite_link_use(&self, link_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_invite_links
+            SET use_count = use_count + 1
+            WHERE id = $1
+            AND revoked_at IS NULL
+            AND (expires_at IS NULL OR expires_at > now())
+            AND (max_uses IS NULL OR use_count < max_uses)
+            "#,
+            link_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn release_invite_link_use(&self, link_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE org_invite_links
+            SET use_count = use_count - 1
+            WHERE id = $1 AND use_count > 0
+            "#,
+            link_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // returns none when the org already has this domain
+    pub async fn add_domain(
+        &self,
+        org_id: Uuid,
+        domain: &str,
+        created_by: Uuid,
+    ) -> Result<Option<OrgDomain>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgDomain,
+            r#"
+            INSERT INTO org_domains (org_id, domain, created_by)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org_id, domain) DO NOTHING
+            RETURNING id, org_id, domain, created_by, created_at
+            "#,
+            org_id,
+            domain,
+            created_by
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn list_domains(&self, org_id: Uuid) -> Result<Vec<OrgDomain>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgDomain,
+            r#"
+            SELECT id, org_id, domain, created_by, created_at
+            FROM org_domains
+            WHERE org_id = $1
+            ORDER BY domain
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    pub async fn find_domain(
+        &self,
+        org_id: Uuid,
+        domain: &str,
+    ) -> Result<Option<OrgDomain>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgDomain,
+            r#"
+            SELECT id, org_id, domain, created_by, created_at
+            FROM org_domains
+            WHERE org_id = $1 AND domain = $2
+            "#,
+            org_id,
+            domain
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
+    // returns the removed domain, none when it was not registered for the org
+    pub async fn delete_domain(
+        &self,
+        org_id: Uuid,
+        domain_id: Uuid,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let domain = sqlx::query_scalar!(
+            "DELETE FROM org_domains WHERE org_id = $1 AND id = $2 RETURNING domain",
+            org_id,
+            domain_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(domain)
+    }
+
+    // orgs that registered the domain and the user has no membership row in, suspended included
+    pub async fn list_joinable_orgs(
+        &self,
+        user_id: Uuid,
+        domain: &str,
+    ) -> Result<Vec<JoinableOrgResponse>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            JoinableOrgResponse,
+            r#"
+            SELECT
+                o.id as org_id,
+                o.name as org_name,
+                o.logo_url as org_logo,
+                d.domain
+            FROM org_domains d
+            INNER JOIN org o
+                ON o.id = d.org_id
+            WHERE d.domain = $2
+            AND NOT EXISTS (
+                SELECT 1
+                FROM org_members om
+                WHERE om.org_id = o.id AND om.user_id = $1
+            )
+            ORDER BY o.name
+            "#,
+            user_id,
+            domain
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(result)
+    }
+
     pub async fn is_user_member_of_org(
         &self,
         org_id: Uuid,
@@ -584,4 +1433,205 @@ impl OrgRepository {
 
         Ok(result.exists.unwrap_or(false))
     }
+
+    // active members by their lowercased email
+    pub async fn find_member_ids_by_emails(
+        &self,
+        org_id: Uuid,
+        emails: &[String],
+    ) -> Result<Vec<(String, Uuid)>, sqlx::Error> {
+        let members = sqlx::query!(
+            r#"
+            SELECT lower(u.email) as "email!", u.id
+            FROM users u
+            INNER JOIN org_members om ON om.user_id = u.id
+            WHERE om.org_id = $1 AND om.status = 'ACTIVE' AND lower(u.email) = ANY($2)
+            "#,
+            org_id,
+            emails
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(members
+            .into_iter()
+            .map(|member| (member.email, member.id))
+            .collect())
+    }
+
+    // active members by lowercased email, username or display name, a name shared by
+    // several members matches none of them
+    pub async fn find_member_ids_by_names(
+        &self,
+        org_id: Uuid,
+        names: &[String],
+    ) -> Result<Vec<(String, Uuid)>, sqlx::Error> {
+        let members = sqlx::query!(
+            r#"
+            WITH members AS (
+                SELECT u.id, u.email, u.username, u.display_name
+                FROM users u
+                INNER JOIN org_members om ON om.user_id = u.id
+                WHERE om.org_id = $1 AND om.status = 'ACTIVE'
+            ),
+            names AS (
+                SELECT lower(email) as name, id FROM members
+                UNION
+                SELECT lower(username), id FROM members
+                UNION
+                SELECT lower(display_name), id FROM members WHERE display_name IS NOT NULL
+            )
+            SELECT name as "name!", (array_agg(id))[1] as "id!"
+            FROM names
+            WHERE name = ANY($2)
+            GROUP BY name
+            HAVING count(*) = 1
+            "#,
+            org_id,
+            names
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(members
+            .into_iter()
+            .map(|member| (member.name, member.id))
+            .collect())
+    }
+
+    // old slugs still resolve to the org that used them, so nobody else can take them
+    async fn is_slug_taken_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        slug: &str,
+        except_org_id: Option<Uuid>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT (
+                EXISTS (
+                    SELECT 1 FROM org
+                    WHERE slug = $1 AND id IS DISTINCT FROM $2
+                )
+                OR EXISTS (
+                    SELECT 1 FROM org_previous_slugs
+                    WHERE slug = $1 AND org_id IS DISTINCT FROM $2
+                )
+            ) as "taken!"
+            "#,
+            slug,
+            except_org_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(result.taken)
+    }
+
+    // retired identifiers stay reserved for the org that used them
+    async fn is_identifier_taken_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        custom_id: &str,
+        except_org_id: Option<Uuid>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT (
+                EXISTS (
+                    SELECT 1 FROM org
+                    WHERE custom_id = $1 AND id IS DISTINCT FROM $2
+                )
+                OR EXISTS (
+                    SELECT 1 FROM org_previous_identifiers
+                    WHERE custom_id = $1 AND org_id IS DISTINCT FROM $2
+                )
+            ) as "taken!"
+            "#,
+            custom_id,
+            except_org_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(result.taken)
+    }
+
+    // every change that can take away an owner locks the org row first, so two
+    // concurrent changes cannot each count the other owner and leave none behind
+    async fn lock_org_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!("SELECT id FROM org WHERE id = $1 FOR UPDATE", org_id)
+            .fetch_optional(&mut **tx)
+            .await?;
+
+        Ok(())
+    }
+
+    async fn find_member_role_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<MemberRole>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT role as "role: MemberRole"
+            FROM org_members
+            WHERE org_id = $1 AND user_id = $2 AND status = 'ACTIVE'
+            "#,
+            org_id,
+            user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(result.map(|row| row.role))
+    }
+
+    async fn count_other_owners_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM org_members
+            WHERE org_id = $1
+            AND user_id <> $2
+            AND role = 'OWNER'
+            AND status = 'ACTIVE'
+            "#,
+            org_id,
+            user_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    async fn create_member_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        data: CreateOrgMemberData,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO org_members (
+                org_id, user_id, role, status, invited_by, join_method, invite_link_id
+            )
+            VALUES ($1, $2, $3::member_role, 'ACTIVE', $4, $5::member_join_method, $6)
+            "#,
+            data.org_id,
+            data.user_id,
+            data.role as MemberRole,
+            data.invited_by,
+            data.join_method as JoinMethod,
+            data.invite_link_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,407 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value as JsonValue;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::org_export::{ExportEntity, ExportStatus, OrgExport};
+
+pub struct OrgExportRepository {
+    pool: PgPool,
+}
+
+impl OrgExportRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // returns none while another export of the org is still pending or running
+    pub async fn create_export(
+        &self,
+        org_id: Uuid,
+        requested_by: Uuid,
+    ) -> Result<Option<OrgExport>, sqlx::Error> {
+        let export = sqlx::query_as!(
+            OrgExport,
+            r#"
+            INSERT INTO org_exports (org_id, requested_by)
+            VALUES ($1, $2)
+            ON CONFLICT (org_id) WHERE status IN ('PENDING', 'RUNNING') DO NOTHING
+            RETURNING
+                id,
+                org_id,
+                requested_by,
+                status as "status: ExportStatus",
+                archive_size,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                completed_at,
+                expires_at
+            "#,
+            org_id,
+            requested_by
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(export)
+    }
+
+    pub async fn find_export(
+        &self,
+        org_id: Uuid,
+        export_id: Uuid,
+    ) -> Result<Option<OrgExport>, sqlx::Error> {
+        let export = sqlx::query_as!(
+            OrgExport,
+            r#"
+            SELECT
+                id,
+                org_id,
+                requested_by,
+                status as "status: ExportStatus",
+                archive_size,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                completed_at,
+                expires_at
+            FROM org_exports
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            export_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(export)
+    }
+
+    pub async fn list_exports(&self, org_id: Uuid) -> Result<Vec<OrgExport>, sqlx::Error> {
+        let exports = sqlx::query_as!(
+            OrgExport,
+            r#"
+            SELECT
+                id,
+                org_id,
+                requested_by,
+                status as "status: ExportStatus",
+                archive_size,
+                error,
+                created_at as "created_at!: DateTime<Utc>",
+                completed_at,
+                expires_at
+            FROM org_exports
+            WHERE org_id = $1
+            ORDER BY created_at DESC
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(exports)
+    }
+
+    // only completed exports that have not expired can be downloaded
+    pub async fn find_archive(
+        &self,
+        org_id: Uuid,
+        export_id: Uuid,
+    ) -> Result<Option<Vec<u8>>, sqlx::Error> {
+        let archive = sqlx::query_scalar!(
+            r#"
+            SELECT archive as "archive!"
+            FROM org_exports
+            WHERE org_id = $1
+            AND id = $2
+            AND status = 'COMPLETED'
+            AND archive IS NOT NULL
+            AND expires_at > now()
+            "#,
+            org_id,
+            export_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(archive)
+    }
+
+    pub async fn mark_running(&self, export_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "UPDATE org_exports SET status = 'RUNNING' WHERE id = $1 AND status = 'PENDING'",
+            export_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn complete_export(
+        &self,
+        export_id: Uuid,
+        archive: &[u8],
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE org_exports
+            SET status = 'COMPLETED',
+                archive = $2,
+                archive_size = $3,
+                completed_at = now(),
+                expires_at = $4
+            WHERE id = $1
+            "#,
+            export_id,
+            archive,
+            archive.len() as i64,
+            expires_at
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn fail_export(&self, export_id: Uuid, error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE org_exports
+            SET status = 'FAILED',
+                error = $2,
+                completed_at = now()
+            WHERE id = $1
+            "#,
+            export_id,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // jobs do not survive a restart, so anything unfinished for this long was interrupted
+    pub async fn fail_stale_exports(&self, stale_secs: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_exports
+            SET status = 'FAILED',
+                error = 'The export was interrupted, please request a new one',
+                completed_at = now()
+            WHERE status IN ('PENDING', 'RUNNING')
+            AND created_at < now() - make_interval(secs => $1)
+            "#,
+            stale_secs as f64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn delete_expired_exports(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!("DELETE FROM org_exports WHERE expires_at <= now()")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    // secrets such as invite tokens and password hashes never leave the database
+    pub async fn export_entity(
+        &self,
+        org_id: Uuid,
+        entity: ExportEntity,
+    ) -> Result<JsonValue, sqlx::Error> {
+        let data = match entity {
+            ExportEntity::Org => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT row_to_json(t) as "data!"
+                    FROM (
+                        SELECT
+                            id, name, slug, custom_id, logo_url, require_two_factor,
+                            created_at, updated_at
+                        FROM org
+                        WHERE id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Members => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.joined_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT
+                            om.user_id, u.username, u.email, om.role, om.status,
+                            r.name as custom_role, om.join_method, om.invited_by, om.joined_at
+                        FROM org_members om
+                        INNER JOIN users u ON u.id = om.user_id
+                        LEFT JOIN org_roles r ON r.id = om.custom_role_id
+                        WHERE om.org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Roles => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT id, name, permissions, created_at, updated_at
+                        FROM org_roles
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Teams => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT id, name, key, lead_id, created_at, updated_at
+                        FROM teams
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::TeamMembers => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT team_id, user_id, created_at
+                        FROM team_members
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Labels => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.name), '[]'::json) as "data!"
+                    FROM (
+                        SELECT id, name, color, description, created_at, updated_at
+                        FROM labels
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Issues => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT
+                            id, team_id, number, title, description, priority, status,
+                            parent_id, creator_id, due_date, created_at, updated_at,
+                            ARRAY(
+                                SELECT label_id FROM issue_labels WHERE issue_id = issues.id
+                            ) as label_ids,
+                            ARRAY(
+                                SELECT user_id FROM issue_assignees WHERE issue_id = issues.id
+                            ) as assignee_ids
+                        FROM issues
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Comments => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT
+                            id, comment_type, comment_owner_id, parent_id, creator_id,
+                            content, edited_at, created_at, updated_at
+                        FROM comments
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Invites => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.invited_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT id, email, role, invited_by, invited_at, expires_at
+                        FROM org_invites
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::Domains => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT id, domain, created_by, created_at
+                        FROM org_domains
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+            ExportEntity::AuditLog => {
+                sqlx::query_scalar!(
+                    r#"
+                    SELECT COALESCE(json_agg(t ORDER BY t.created_at), '[]'::json) as "data!"
+                    FROM (
+                        SELECT
+                            id, actor_id, action, target_type, target_id, ip_address,
+                            user_agent, before, after, created_at
+                        FROM org_audit_logs
+                        WHERE org_id = $1
+                    ) t
+                    "#,
+                    org_id
+                )
+                .fetch_one(&self.pool)
+                .await?
+            }
+        };
+
+        Ok(data)
+    }
+}
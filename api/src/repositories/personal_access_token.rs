@@ -0,0 +1,131 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::personal_access_token::{CreatePersonalAccessTokenData, PersonalAccessToken};
+
+pub struct PersonalAccessTokenRepository {
+    pool: PgPool,
+}
+
+impl PersonalAccessTokenRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_token(
+        &self,
+        data: CreatePersonalAccessTokenData,
+    ) -> Result<PersonalAccessToken, sqlx::Error> {
+        let token = sqlx::query_as!(
+            PersonalAccessToken,
+            r#"
+            INSERT INTO personal_access_tokens
+                (user_id, name, token_prefix, token_hash, scopes, org_ids, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING
+                id,
+                user_id,
+                name,
+                token_prefix,
+                scopes,
+                org_ids,
+                expires_at,
+                last_used_at,
+                revoked_at,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            data.user_id,
+            data.name,
+            data.token_prefix,
+            data.token_hash,
+            &data.scopes,
+            data.org_ids.as_deref(),
+            data.expires_at
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn list_user_tokens(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<PersonalAccessToken>, sqlx::Error> {
+        let tokens = sqlx::query_as!(
+            PersonalAccessToken,
+            r#"
+            SELECT
+                id,
+                user_id,
+                name,
+                token_prefix,
+                scopes,
+                org_ids,
+                expires_at,
+                last_used_at,
+                revoked_at,
+                created_at as "created_at!: DateTime<Utc>"
+            FROM personal_access_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    // looks the token up and records the use in one round trip, revoked and
+    // expired tokens simply do not match
+    pub async fn use_token(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<PersonalAccessToken>, sqlx::Error> {
+        let token = sqlx::query_as!(
+            PersonalAccessToken,
+            r#"
+            UPDATE personal_access_tokens
+            SET last_used_at = now()
+            WHERE token_hash = $1
+            AND revoked_at IS NULL
+            AND (expires_at IS NULL OR expires_at > now())
+            RETURNING
+                id,
+                user_id,
+                name,
+                token_prefix,
+                scopes,
+                org_ids,
+                expires_at,
+                last_used_at,
+                revoked_at,
+                created_at as "created_at!: DateTime<Utc>"
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn revoke_token(&self, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE personal_access_tokens
+            SET revoked_at = now()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            "#,
+            id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::role::{OrgRole, RoleWriteResult};
+
+pub struct RoleRepository {
+    pool: PgPool,
+}
+
+impl RoleRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_role(
+        &self,
+        org_id: Uuid,
+        name: &str,
+        permissions: &[String],
+    ) -> Result<RoleWriteResult, sqlx::Error> {
+        let role = sqlx::query_as!(
+            OrgRole,
+            r#"
+            INSERT INTO org_roles (org_id, name, permissions)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org_id, name) DO NOTHING
+            RETURNING
+                id,
+                org_id,
+                name,
+                permissions,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            org_id,
+            name,
+            permissions
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match role {
+            Some(role) => Ok(RoleWriteResult::Written(role)),
+            None => Ok(RoleWriteResult::NameTaken),
+        }
+    }
+
+    // returns none when the role does not exist in the org
+    pub async fn update_role(
+        &self,
+        org_id: Uuid,
+        role_id: Uuid,
+        name: Option<String>,
+        permissions: Option<Vec<String>>,
+    ) -> Result<Option<RoleWriteResult>, sqlx::Error> {
+        let result = sqlx::query_as!(
+            OrgRole,
+            r#"
+            UPDATE org_roles
+            SET
+                name = COALESCE($3, name),
+                permissions = COALESCE($4, permissions)
+            WHERE org_id = $1 AND id = $2
+            RETURNING
+                id,
+                org_id,
+                name,
+                permissions,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            org_id,
+            role_id,
+            name,
+            permissions.as_deref()
+        )
+        .fetch_optional(&self.pool)
+        .await;
+
+        match result {
+            Ok(role) => Ok(role.map(RoleWriteResult::Written)),
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                Ok(Some(RoleWriteResult::NameTaken))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    // fails with a foreign key violation while members still hold the role
+    pub async fn delete_role(&self, org_id: Uuid, role_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM org_roles WHERE org_id = $1 AND id = $2",
+            org_id,
+            role_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn list_roles(&self, org_id: Uuid) -> Result<Vec<OrgRole>, sqlx::Error> {
+        let roles = sqlx::query_as!(
+            OrgRole,
+            r#"
+            SELECT
+                id,
+                org_id,
+                name,
+                permissions,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM org_roles
+            WHERE org_id = $1
+            ORDER BY name
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(roles)
+    }
+
+    pub async fn find_role(
+        &self,
+        org_id: Uuid,
+        role_id: Uuid,
+    ) -> Result<Option<OrgRole>, sqlx::Error> {
+        let role = sqlx::query_as!(
+            OrgRole,
+            r#"
+            SELECT
+                id,
+                org_id,
+                name,
+                permissions,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM org_roles
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            role_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(role)
+    }
+
+    // owners are skipped, a custom role would never apply to them
+    pub async fn assign_role(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+        role_id: Option<Uuid>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE org_members
+            SET custom_role_id = $3,
+                updated_at = now()
+            WHERE org_id = $1
+            AND user_id = $2
+            AND role <> 'OWNER'
+            "#,
+            org_id,
+            user_id,
+            role_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,462 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::{
+    auth::User,
+    team::{
+        AddTeamMemberResult, CreateTeamRequest, Team, TeamMember, TeamMemberResponse,
+        TeamWriteResult, UpdateTeamRequest,
+    },
+};
+
+pub struct TeamRepository {
+    pool: PgPool,
+}
+
+impl TeamRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_team(
+        &self,
+        org_id: Uuid,
+        data: CreateTeamRequest,
+    ) -> Result<TeamWriteResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        if Self::is_org_identifier_tx(&mut tx, org_id, &data.key).await?
+            || Self::is_retired_key_tx(&mut tx, org_id, &data.key, None).await?
+        {
+            return Ok(TeamWriteResult::KeyTaken);
+        }
+
+        if let Some(lead_id) = data.lead_id {
+            if !Self::is_active_member_tx(&mut tx, org_id, lead_id).await? {
+                return Ok(TeamWriteResult::LeadNotMember);
+            }
+        }
+
+        let team = sqlx::query_as!(
+            Team,
+            r#"
+            INSERT INTO teams (org_id, name, key)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (org_id, key) DO NOTHING
+            RETURNING
+                id,
+                org_id,
+                name,
+                key,
+                lead_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            org_id,
+            data.name,
+            data.key
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let mut team = match team {
+            Some(team) => team,
+            None => return Ok(TeamWriteResult::KeyTaken),
+        };
+
+        // the lead has to be a team member before it can be set
+        if let Some(lead_id) = data.lead_id {
+            Self::insert_member_tx(&mut tx, org_id, team.id, lead_id).await?;
+
+            sqlx::query!(
+                "UPDATE teams SET lead_id = $2 WHERE id = $1",
+                team.id,
+                lead_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            team.lead_id = Some(lead_id);
+        }
+
+        tx.commit().await?;
+
+        Ok(TeamWriteResult::Written(team))
+    }
+
+    // returns none when the team does not exist in the org
+    pub async fn update_team(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+        data: UpdateTeamRequest,
+    ) -> Result<Option<TeamWriteResult>, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let current = sqlx::query!(
+            "SELECT key FROM teams WHERE org_id = $1 AND id = $2 FOR UPDATE",
+            org_id,
+            team_id
+        )
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let current_key = match current {
+            Some(current) => current.key,
+            None => return Ok(None),
+        };
+
+        if let Some(key) = data.key.as_deref().filter(|key| *key != current_key) {
+            let taken = sqlx::query!(
+                r#"
+                SELECT EXISTS (
+                    SELECT 1
+                    FROM teams
+                    WHERE org_id = $1 AND key = $2 AND id <> $3
+                ) as "exists!"
+                "#,
+                org_id,
+                key,
+                team_id
+            )
+            .fetch_one(&mut *tx)
+            .await?;
+
+            if taken.exists
+                || Self::is_org_identifier_tx(&mut tx, org_id, key).await?
+                || Self::is_retired_key_tx(&mut tx, org_id, key, Some(team_id)).await?
+            {
+                return Ok(Some(TeamWriteResult::KeyTaken));
+            }
+
+            // switching back to a retired key takes it out of the history
+            sqlx::query!(
+                "DELETE FROM team_previous_keys WHERE org_id = $1 AND key = $2 AND team_id = $3",
+                org_id,
+                key,
+                team_id
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query!(
+                r#"
+                INSERT INTO team_previous_keys (org_id, key, team_id)
+                VALUES ($1, $2, $3)
+                ON CONFLICT (org_id, key) DO NOTHING
+                "#,
+                org_id,
+                current_key,
+                team_id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        if let Some(lead_id) = data.lead_id {
+            if !Self::is_active_member_tx(&mut tx, org_id, lead_id).await? {
+                return Ok(Some(TeamWriteResult::LeadNotMember));
+            }
+
+            Self::insert_member_tx(&mut tx, org_id, team_id, lead_id).await?;
+        }
+
+        let team = sqlx::query_as!(
+            Team,
+            r#"
+            UPDATE teams
+            SET
+                name = COALESCE($2, name),
+                key = COALESCE($3, key),
+                lead_id = CASE
+                    WHEN $4 = true THEN NULL
+                    WHEN $5::uuid IS NOT NULL THEN $5::uuid
+                    ELSE lead_id
+                END
+            WHERE id = $1
+            RETURNING
+                id,
+                org_id,
+                name,
+                key,
+                lead_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            team_id,
+            data.name,
+            data.key,
+            data.remove_lead.unwrap_or(false),
+            data.lead_id
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(Some(TeamWriteResult::Written(team)))
+    }
+
+    // fails on the issues foreign key while the team still has issues
+    pub async fn delete_team(&self, org_id: Uuid, team_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM teams WHERE org_id = $1 AND id = $2",
+            org_id,
+            team_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn list_teams(&self, org_id: Uuid) -> Result<Vec<Team>, sqlx::Error> {
+        let teams = sqlx::query_as!(
+            Team,
+            r#"
+            SELECT
+                id,
+                org_id,
+                name,
+                key,
+                lead_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM teams
+            WHERE org_id = $1
+            ORDER BY name
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(teams)
+    }
+
+    pub async fn find_team(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+    ) -> Result<Option<Team>, sqlx::Error> {
+        let team = sqlx::query_as!(
+            Team,
+            r#"
+            SELECT
+                id,
+                org_id,
+                name,
+                key,
+                lead_id,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM teams
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            team_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(team)
+    }
+
+    pub async fn list_team_members(
+        &self,
+        team_id: Uuid,
+    ) -> Result<Vec<TeamMemberResponse>, sqlx::Error> {
+        let results = sqlx::query!(
+            r#"
+            SELECT
+                u.id as "user_id",
+                u.email as "user_email",
+                u.username as "user_username",
+                u.password_hash as "user_password_hash",
+                u.is_email_verified as "user_is_email_verified!: bool",
+                u.email_verification_token as "user_email_verification_token",
+                u.email_verification_expires_at as "user_email_verification_expires_at",
+                u.created_at as "user_created_at!: DateTime<Utc>",
+                u.updated_at as "user_updated_at!: DateTime<Utc>",
+                u.last_login_at as "user_last_login_at",
+                u.avatar_url as "user_avatar_url",
+                u.github_id as "user_github_id",
+                u.github_url as "user_github_url",
+                u.display_name as "user_display_name",
+                tm.team_id,
+                tm.created_at as "team_member_created_at!: DateTime<Utc>"
+            FROM team_members tm
+            INNER JOIN users u
+                ON tm.user_id = u.id
+            WHERE tm.team_id = $1
+            ORDER BY u.username
+            "#,
+            team_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let members = results
+            .into_iter()
+            .map(|row| TeamMemberResponse {
+                team_member: TeamMember {
+                    team_id: row.team_id,
+                    user_id: row.user_id,
+                    created_at: row.team_member_created_at,
+                },
+                user: User {
+                    id: row.user_id,
+                    email: row.user_email,
+                    username: row.user_username,
+                    password_hash: row.user_password_hash,
+                    is_email_verified: row.user_is_email_verified,
+                    email_verification_token: row.user_email_verification_token,
+                    email_verification_expires_at: row.user_email_verification_expires_at,
+                    created_at: row.user_created_at,
+                    updated_at: row.user_updated_at,
+                    last_login_at: row.user_last_login_at,
+                    avatar_url: row.user_avatar_url,
+                    github_id: row.user_github_id,
+                    github_url: row.user_github_url,
+                    display_name: row.user_display_name,
+                },
+            })
+            .collect();
+
+        Ok(members)
+    }
+
+    pub async fn add_team_member(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<AddTeamMemberResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        if !Self::is_active_member_tx(&mut tx, org_id, user_id).await? {
+            return Ok(AddTeamMemberResult::NotOrgMember);
+        }
+
+        let added = Self::insert_member_tx(&mut tx, org_id, team_id, user_id).await?;
+
+        tx.commit().await?;
+
+        if added {
+            Ok(AddTeamMemberResult::Added)
+        } else {
+            Ok(AddTeamMemberResult::AlreadyMember)
+        }
+    }
+
+    // removing the lead clears the lead of the team through teams_lead_fkey
+    pub async fn remove_team_member(
+        &self,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM team_members WHERE team_id = $1 AND user_id = $2",
+            team_id,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    // team keys and org identifiers share the prefixes of issue keys
+    async fn is_org_identifier_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        key: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT (
+                EXISTS (SELECT 1 FROM org WHERE id = $1 AND custom_id = $2)
+                OR EXISTS (
+                    SELECT 1 FROM org_previous_identifiers
+                    WHERE org_id = $1 AND custom_id = $2
+                )
+            ) as "exists!"
+            "#,
+            org_id,
+            key
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(result.exists)
+    }
+
+    // retired keys stay reserved for the team that used them
+    async fn is_retired_key_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        key: &str,
+        except_team_id: Option<Uuid>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM team_previous_keys
+                WHERE org_id = $1 AND key = $2 AND team_id IS DISTINCT FROM $3
+            ) as "exists!"
+            "#,
+            org_id,
+            key,
+            except_team_id
+        )
+        .fetch_one(&mut **tx)
+        .await?;
+
+        Ok(result.exists)
+    }
+
+    // locks the membership so a concurrent suspension waits for the team change
+    async fn is_active_member_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT id
+            FROM org_members
+            WHERE org_id = $1 AND user_id = $2 AND status = 'ACTIVE'
+            FOR SHARE
+            "#,
+            org_id,
+            user_id
+        )
+        .fetch_optional(&mut **tx)
+        .await?;
+
+        Ok(result.is_some())
+    }
+
+    async fn insert_member_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        org_id: Uuid,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO team_members (team_id, org_id, user_id)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (team_id, user_id) DO NOTHING
+            "#,
+            team_id,
+            org_id,
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+}
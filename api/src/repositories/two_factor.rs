@@ -0,0 +1,236 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::two_factor::UserTwoFactor;
+
+pub struct TwoFactorRepository {
+    pool: PgPool,
+}
+
+impl TwoFactorRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    // a pending enrolment is replaced, an enabled one is left untouched
+    pub async fn upsert_pending_secret(
+        &self,
+        user_id: Uuid,
+        secret: &str,
+    ) -> Result<Option<UserTwoFactor>, sqlx::Error> {
+        let two_factor = sqlx::query_as!(
+            UserTwoFactor,
+            r#"
+            INSERT INTO user_two_factor (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = EXCLUDED.secret,
+                last_used_step = NULL,
+                updated_at = now()
+            WHERE user_two_factor.enabled_at IS NULL
+            RETURNING
+                id,
+                user_id,
+                secret,
+                enabled_at,
+                last_used_step,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            "#,
+            user_id,
+            secret
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    pub async fn find_by_user_id(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Option<UserTwoFactor>, sqlx::Error> {
+        let two_factor = sqlx::query_as!(
+            UserTwoFactor,
+            r#"
+            SELECT
+                id,
+                user_id,
+                secret,
+                enabled_at,
+                last_used_step,
+                created_at as "created_at!: DateTime<Utc>",
+                updated_at as "updated_at!: DateTime<Utc>"
+            FROM user_two_factor
+            WHERE user_id = $1
+            "#,
+            user_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    pub async fn is_enabled(&self, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM user_two_factor
+                WHERE user_id = $1 AND enabled_at IS NOT NULL
+            ) as "exists!"
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.exists)
+    }
+
+    // only moves forward, so two requests racing with the same code cannot both succeed
+    pub async fn mark_step_used(&self, user_id: Uuid, step: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_two_factor
+            SET last_used_step = $2,
+                updated_at = now()
+            WHERE user_id = $1
+            AND (last_used_step IS NULL OR last_used_step < $2)
+            "#,
+            user_id,
+            step
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn enable(
+        &self,
+        user_id: Uuid,
+        step: i64,
+        recovery_code_hashes: Vec<String>,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_two_factor
+            SET enabled_at = now(),
+                last_used_step = $2,
+                updated_at = now()
+            WHERE user_id = $1 AND enabled_at IS NULL
+            "#,
+            user_id,
+            step
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Ok(false);
+        }
+
+        Self::replace_recovery_codes_tx(&mut tx, user_id, recovery_code_hashes).await?;
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn delete(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!(
+            "DELETE FROM user_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM user_two_factor WHERE user_id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn replace_recovery_codes(
+        &self,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        Self::replace_recovery_codes_tx(&mut tx, user_id, code_hashes).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn use_recovery_code(
+        &self,
+        user_id: Uuid,
+        code_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE user_recovery_codes
+            SET used_at = now()
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            code_hash
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn count_remaining_recovery_codes(&self, user_id: Uuid) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT COUNT(*) as "count!"
+            FROM user_recovery_codes
+            WHERE user_id = $1 AND used_at IS NULL
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.count)
+    }
+
+    async fn replace_recovery_codes_tx(
+        tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+        user_id: Uuid,
+        code_hashes: Vec<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "DELETE FROM user_recovery_codes WHERE user_id = $1",
+            user_id
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_recovery_codes (user_id, code_hash)
+            SELECT $1, UNNEST($2::text[])
+            "#,
+            user_id,
+            &code_hashes
+        )
+        .execute(&mut **tx)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -2,8 +2,9 @@ use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::models::auth::{
-    CreateGithubUser, CreateUserData, UpdateGithubUser, UpdateUserData, User,
+use crate::models::{
+    auth::{CreateUserData, UpdateGithubUser, User},
+    user::{DeleteAccountResult, PendingEmailChange, UpdateProfileData, DELETED_USER_ID},
 };
 
 pub struct UserRepository {
@@ -34,7 +35,8 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
+                github_url,
+                display_name
             "#,
             data.email,
             data.username,
@@ -63,7 +65,8 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
+                github_url,
+                display_name
             FROM users
             WHERE id = $1
             "#,
@@ -92,7 +95,8 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
+                github_url,
+                display_name
             FROM users
             WHERE email = $1
             "#,
@@ -163,86 +167,84 @@ impl UserRepository {
         Ok(())
     }
 
-    pub async fn delete_user(&self, user_id: Uuid) -> Result<(), sqlx::Error> {
-        sqlx::query!(
+    pub async fn user_exists(&self, email: String, username: String) -> Result<bool, sqlx::Error> {
+        let exists = sqlx::query!(
             r#"
-            DELETE FROM users
-            WHERE id = $1
+            SELECT EXISTS (
+                SELECT 1
+                FROM users
+                WHERE email = $1 OR username = $2
+            )
+            AS "exists!"
             "#,
-            user_id
+            email,
+            username
         )
-        .execute(&self.pool)
+        .fetch_one(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(exists.exists)
     }
 
-    pub async fn update_user(
+    pub async fn username_taken(
         &self,
-        user_data: UpdateUserData,
-        user_id: Uuid,
-    ) -> Result<User, sqlx::Error> {
-        let user = sqlx::query_as!(
-            User,
+        username: &str,
+        except_user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
             r#"
-            UPDATE users
-            SET email = $1,
-                username = $2,
-                password_hash = $3,
-                updated_at = $4
-            WHERE id = $5
-            RETURNING
-                id,
-                email,
-                username,
-                password_hash,
-                is_email_verified as "is_email_verified!: bool",
-                email_verification_token,
-                email_verification_expires_at,
-                created_at as "created_at!: DateTime<Utc>",
-                updated_at as "updated_at!: DateTime<Utc>",
-                last_login_at,
-                avatar_url,
-                github_id,
-                github_url
+            SELECT EXISTS (
+                SELECT 1
+                FROM users
+                WHERE username = $1 AND id <> $2
+            ) as "exists!"
             "#,
-            user_data.email,
-            user_data.username,
-            user_data.password,
-            Utc::now(),
-            user_id
+            username,
+            except_user_id
         )
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(user)
+        Ok(result.exists)
     }
 
-    pub async fn user_exists(&self, email: String, username: String) -> Result<bool, sqlx::Error> {
-        let exists = sqlx::query!(
+    pub async fn email_taken(
+        &self,
+        email: &str,
+        except_user_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
             r#"
             SELECT EXISTS (
                 SELECT 1
                 FROM users
-                WHERE email = $1 OR username = $2
-            )
-            AS "exists!"
+                WHERE email = $1 AND id <> $2
+            ) as "exists!"
             "#,
             email,
-            username
+            except_user_id
         )
         .fetch_one(&self.pool)
         .await?;
 
-        Ok(exists.exists)
+        Ok(result.exists)
     }
 
-    pub async fn insert_github_user(&self, data: CreateGithubUser) -> Result<User, sqlx::Error> {
+    // an empty display name or avatar url clears the column, a missing one keeps it
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        data: UpdateProfileData,
+    ) -> Result<User, sqlx::Error> {
         let user = sqlx::query_as!(
             User,
             r#"
-            INSERT INTO users (email, username, github_id, github_url, avatar_url)
-            VALUES ($1, $2, $3, $4, $5)
+            UPDATE users
+            SET username = COALESCE($1, username),
+                display_name = CASE WHEN $2::text IS NULL THEN display_name ELSE NULLIF($2, '') END,
+                avatar_url = CASE WHEN $3::text IS NULL THEN avatar_url ELSE NULLIF($3, '') END,
+                updated_at = now()
+            WHERE id = $4
             RETURNING
                 id,
                 email,
@@ -256,13 +258,13 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
+                github_url,
+                display_name
             "#,
-            data.email,
             data.username,
-            data.github_id,
-            data.github_url,
-            data.avatar_url
+            data.display_name,
+            data.avatar_url,
+            user_id
         )
         .fetch_one(&self.pool)
         .await?;
@@ -270,21 +272,88 @@ impl UserRepository {
         Ok(user)
     }
 
-    pub async fn update_github_user(
+    pub async fn get_pending_email(&self, user_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            SELECT pending_email
+            FROM users
+            WHERE id = $1
+            "#,
+            user_id
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.pending_email)
+    }
+
+    // a new request replaces the previous pending address and its token
+    pub async fn set_pending_email(
         &self,
         user_id: Uuid,
-        data: UpdateGithubUser,
-    ) -> Result<User, sqlx::Error> {
+        pending_email: &str,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE users
+            SET pending_email = $1,
+                email_verification_token = $2,
+                email_verification_expires_at = $3,
+                updated_at = now()
+            WHERE id = $4
+            "#,
+            pending_email,
+            token_hash,
+            expires_at,
+            user_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_pending_email_change(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<PendingEmailChange>, sqlx::Error> {
+        let pending = sqlx::query_as!(
+            PendingEmailChange,
+            r#"
+            SELECT id as user_id, email, pending_email as "pending_email!"
+            FROM users
+            WHERE email_verification_token = $1
+            AND pending_email IS NOT NULL
+            AND email_verification_expires_at > now()
+            "#,
+            token_hash
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(pending)
+    }
+
+    // the token is cleared in the same statement, so it can only be used once
+    pub async fn confirm_pending_email(
+        &self,
+        token_hash: &str,
+    ) -> Result<Option<User>, sqlx::Error> {
         let user = sqlx::query_as!(
             User,
             r#"
             UPDATE users
-            SET email = $1,
-                github_id = $2,
-                github_url = $3,
-                avatar_url = $4,
-                updated_at = $5
-            WHERE id = $6
+            SET email = pending_email,
+                is_email_verified = TRUE,
+                pending_email = NULL,
+                email_verification_token = NULL,
+                email_verification_expires_at = NULL,
+                updated_at = now()
+            WHERE email_verification_token = $1
+            AND pending_email IS NOT NULL
+            AND email_verification_expires_at > now()
             RETURNING
                 id,
                 email,
@@ -298,26 +367,130 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
+                github_url,
+                display_name
             "#,
-            data.email,
-            data.github_id,
-            data.github_url,
-            data.avatar_url,
-            Utc::now(),
-            user_id
+            token_hash
         )
-        .fetch_one(&self.pool)
+        .fetch_optional(&self.pool)
         .await?;
 
         Ok(user)
     }
 
-    pub async fn get_user_by_github_id(&self, github_id: String) -> Result<User, sqlx::Error> {
+    // issues, comments and invitations outlive the account and are handed to the
+    // placeholder user, everything else that belongs to the user cascades
+    pub async fn delete_account(&self, user_id: Uuid) -> Result<DeleteAccountResult, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query!("SELECT id FROM users WHERE id = $1 FOR UPDATE", user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+
+        // locking the orgs keeps another owner from leaving while we check
+        let sole_owner_of = sqlx::query!(
+            r#"
+            SELECT o.name
+            FROM org o
+            JOIN org_members om ON om.org_id = o.id
+            WHERE om.user_id = $1
+            AND om.role = 'OWNER'
+            AND om.status = 'ACTIVE'
+            AND NOT EXISTS (
+                SELECT 1
+                FROM org_members other
+                WHERE other.org_id = o.id
+                AND other.user_id <> $1
+                AND other.role = 'OWNER'
+                AND other.status = 'ACTIVE'
+            )
+            ORDER BY o.name
+            FOR UPDATE OF o
+            "#,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        if !sole_owner_of.is_empty() {
+            tx.rollback().await?;
+            return Ok(DeleteAccountResult::SoleOwner(
+                sole_owner_of.into_iter().map(|row| row.name).collect(),
+            ));
+        }
+
+        sqlx::query!(
+            "UPDATE issues SET creator_id = $2 WHERE creator_id = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE comments SET creator_id = $2 WHERE creator_id = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE org_members SET invited_by = $2 WHERE invited_by = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE org_invites SET invited_by = $2 WHERE invited_by = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE org_invite_links SET created_by = $2 WHERE created_by = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!(
+            "UPDATE org_domains SET created_by = $2 WHERE created_by = $1",
+            user_id,
+            DELETED_USER_ID
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query!("DELETE FROM users WHERE id = $1", user_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(DeleteAccountResult::Deleted)
+    }
+
+    pub async fn update_github_user(
+        &self,
+        user_id: Uuid,
+        data: UpdateGithubUser,
+    ) -> Result<User, sqlx::Error> {
         let user = sqlx::query_as!(
             User,
             r#"
-            SELECT
+            UPDATE users
+            SET github_id = $1,
+                github_url = COALESCE($2, github_url),
+                avatar_url = COALESCE($3, avatar_url),
+                updated_at = $4
+            WHERE id = $5
+            RETURNING
                 id,
                 email,
                 username,
@@ -330,11 +503,14 @@ impl UserRepository {
                 last_login_at,
                 avatar_url,
                 github_id,
-                github_url
-            FROM users
-            WHERE github_id = $1
+                github_url,
+                display_name
             "#,
-            github_id
+            data.github_id,
+            data.github_url,
+            data.avatar_url,
+            Utc::now(),
+            user_id
         )
         .fetch_one(&self.pool)
         .await?;
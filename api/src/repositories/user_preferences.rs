@@ -119,4 +119,22 @@ impl UserPreferencesRepository {
 
         Ok(result)
     }
+
+    // keeps a user who left or was removed from landing on an org they can no longer open
+    pub async fn clear_default_org(&self, user_id: Uuid, org_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE user_preferences
+            SET default_org_id = NULL,
+                updated_at = now()
+            WHERE user_id = $1 AND default_org_id = $2
+            "#,
+            user_id,
+            org_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
 }
@@ -0,0 +1,382 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::webhook::{
+    CreateWebhookData, UpdateWebhookData, Webhook, WebhookDelivery, WebhookDeliveryJob,
+    WebhookDeliveryStatus, WebhookEvent,
+};
+
+pub struct WebhookRepository {
+    pool: PgPool,
+}
+
+impl WebhookRepository {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_webhook(&self, data: CreateWebhookData) -> Result<Webhook, sqlx::Error> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            INSERT INTO webhooks (org_id, url, secret, events, created_by)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, org_id, url, events, is_active, created_by, created_at, updated_at
+            "#,
+            data.org_id,
+            data.url,
+            data.secret,
+            &data.events,
+            data.created_by
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn list_webhooks(&self, org_id: Uuid) -> Result<Vec<Webhook>, sqlx::Error> {
+        let webhooks = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, org_id, url, events, is_active, created_by, created_at, updated_at
+            FROM webhooks
+            WHERE org_id = $1
+            ORDER BY created_at
+            "#,
+            org_id
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(webhooks)
+    }
+
+    pub async fn find_webhook(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Option<Webhook>, sqlx::Error> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            SELECT id, org_id, url, events, is_active, created_by, created_at, updated_at
+            FROM webhooks
+            WHERE org_id = $1 AND id = $2
+            "#,
+            org_id,
+            webhook_id
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn update_webhook(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        data: UpdateWebhookData,
+    ) -> Result<Option<Webhook>, sqlx::Error> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET url = COALESCE($3, url),
+                events = COALESCE($4, events),
+                is_active = COALESCE($5, is_active)
+            WHERE org_id = $1 AND id = $2
+            RETURNING id, org_id, url, events, is_active, created_by, created_at, updated_at
+            "#,
+            org_id,
+            webhook_id,
+            data.url,
+            data.events.as_deref(),
+            data.is_active
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn rotate_secret(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        secret: &str,
+    ) -> Result<Option<Webhook>, sqlx::Error> {
+        let webhook = sqlx::query_as!(
+            Webhook,
+            r#"
+            UPDATE webhooks
+            SET secret = $3
+            WHERE org_id = $1 AND id = $2
+            RETURNING id, org_id, url, events, is_active, created_by, created_at, updated_at
+            "#,
+            org_id,
+            webhook_id,
+            secret
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(webhook)
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM webhooks WHERE org_id = $1 AND id = $2",
+            org_id,
+            webhook_id
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    // one delivery for every active webhook subscribed to the event, like the audit
+    // log a failed write is reported but never fails the request
+    pub async fn queue_event(&self, org_id: Uuid, event: WebhookEvent, data: JsonValue) {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, next_attempt_at)
+            SELECT id, $2, $3, now()
+            FROM webhooks
+            WHERE org_id = $1 AND is_active AND $2 = ANY(events)
+            "#,
+            org_id,
+            event.as_str(),
+            webhook_payload(org_id, event, data)
+        )
+        .execute(&self.pool)
+        .await;
+
+        if let Err(e) = result {
+            log::error!("Failed to queue webhook event {}: {}", event.as_str(), e);
+        }
+    }
+
+    // sent even when the webhook is disabled, so it can be checked before turning it on
+    pub async fn queue_test_event(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        data: JsonValue,
+    ) -> Result<WebhookDelivery, sqlx::Error> {
+        let event = WebhookEvent::WebhookTest;
+        let delivery = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            INSERT INTO webhook_deliveries (webhook_id, event, payload, next_attempt_at)
+            VALUES ($1, $2, $3, now())
+            RETURNING
+                id, webhook_id, event, payload,
+                status as "status: WebhookDeliveryStatus",
+                attempts, next_attempt_at, response_status, response_body, error,
+                created_at, delivered_at
+            "#,
+            webhook_id,
+            event.as_str(),
+            webhook_payload(org_id, event, data)
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(delivery)
+    }
+
+    // due deliveries are locked for a while, one whose worker died is picked up again
+    // once the lock runs out
+    pub async fn claim_deliveries(
+        &self,
+        limit: i64,
+        lock_secs: i64,
+    ) -> Result<Vec<WebhookDeliveryJob>, sqlx::Error> {
+        let jobs = sqlx::query_as!(
+            WebhookDeliveryJob,
+            r#"
+            WITH claimed AS (
+                UPDATE webhook_deliveries
+                SET attempts = attempts + 1,
+                    locked_until = now() + make_interval(secs => $2)
+                WHERE id IN (
+                    SELECT d.id
+                    FROM webhook_deliveries d
+                    INNER JOIN webhooks w ON w.id = d.webhook_id
+                    WHERE d.status = 'PENDING'
+                    AND d.next_attempt_at <= now()
+                    AND (d.locked_until IS NULL OR d.locked_until < now())
+                    AND (w.is_active OR d.event = 'webhook.test')
+                    ORDER BY d.next_attempt_at
+                    LIMIT $1
+                    FOR UPDATE OF d SKIP LOCKED
+                )
+                RETURNING id, webhook_id, event, payload, attempts
+            )
+            SELECT c.id, c.event, c.payload, c.attempts, w.url, w.secret
+            FROM claimed c
+            INNER JOIN webhooks w ON w.id = c.webhook_id
+            "#,
+            limit,
+            lock_secs as f64
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(jobs)
+    }
+
+    pub async fn complete_delivery(
+        &self,
+        delivery_id: Uuid,
+        response_status: i32,
+        response_body: Option<String>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = 'SUCCEEDED',
+                next_attempt_at = NULL,
+                locked_until = NULL,
+                response_status = $2,
+                response_body = $3,
+                error = NULL,
+                delivered_at = now()
+            WHERE id = $1
+            "#,
+            delivery_id,
+            response_status,
+            response_body
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // without a retry delay the delivery is given up on
+    pub async fn fail_delivery_attempt(
+        &self,
+        delivery_id: Uuid,
+        retry_in_secs: Option<i64>,
+        response_status: Option<i32>,
+        response_body: Option<String>,
+        error: String,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = CASE
+                    WHEN $2::float8 IS NULL THEN 'FAILED'::webhook_delivery_status
+                    ELSE 'PENDING'::webhook_delivery_status
+                END,
+                next_attempt_at = now() + make_interval(secs => $2),
+                locked_until = NULL,
+                response_status = $3,
+                response_body = $4,
+                error = $5
+            WHERE id = $1
+            "#,
+            delivery_id,
+            retry_in_secs.map(|secs| secs as f64),
+            response_status,
+            response_body,
+            error
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    // newest first
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Uuid,
+        status: Option<WebhookDeliveryStatus>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<WebhookDelivery>, sqlx::Error> {
+        let deliveries = sqlx::query_as!(
+            WebhookDelivery,
+            r#"
+            SELECT
+                id, webhook_id, event, payload,
+                status as "status: WebhookDeliveryStatus",
+                attempts, next_attempt_at, response_status, response_body, error,
+                created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            AND ($2::webhook_delivery_status IS NULL OR status = $2)
+            ORDER BY created_at DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            webhook_id,
+            status as Option<WebhookDeliveryStatus>,
+            limit,
+            offset
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(deliveries)
+    }
+
+    pub async fn count_deliveries(
+        &self,
+        webhook_id: Uuid,
+        status: Option<WebhookDeliveryStatus>,
+    ) -> Result<i64, sqlx::Error> {
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) as "total!"
+            FROM webhook_deliveries
+            WHERE webhook_id = $1
+            AND ($2::webhook_delivery_status IS NULL OR status = $2)
+            "#,
+            webhook_id,
+            status as Option<WebhookDeliveryStatus>
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(total)
+    }
+
+    // finished deliveries only, pending ones are still part of the queue
+    pub async fn delete_old_deliveries(&self, older_than_secs: i64) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM webhook_deliveries
+            WHERE status <> 'PENDING'
+            AND created_at < now() - make_interval(secs => $1)
+            "#,
+            older_than_secs as f64
+        )
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+// the id stays the same across retries, so receivers can drop duplicates
+fn webhook_payload(org_id: Uuid, event: WebhookEvent, data: JsonValue) -> JsonValue {
+    let created_at: DateTime<Utc> = Utc::now();
+    json!({
+        "id": Uuid::new_v4(),
+        "event": event.as_str(),
+        "org_id": org_id,
+        "created_at": created_at,
+        "data": data,
+    })
+}
@@ -0,0 +1,88 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    errors::CustomError,
+    models::audit_log::{AuditLogFilter, AuditLogPageResponse, ListAuditLogQuery},
+    repositories::audit_log::AuditLogRepository,
+};
+
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+pub struct AuditLogService {
+    audit_log_repo: AuditLogRepository,
+}
+
+impl AuditLogService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            audit_log_repo: AuditLogRepository::new(pool),
+        }
+    }
+
+    pub async fn list_logs(
+        &self,
+        org_id: Uuid,
+        query: ListAuditLogQuery,
+    ) -> Result<AuditLogPageResponse, CustomError> {
+        if let Err(validation_errors) = query.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let page = query.page.unwrap_or(1);
+        let per_page = query.per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+        let filter = audit_log_filter(query);
+
+        let total = self
+            .audit_log_repo
+            .count_logs(org_id, &filter)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let entries = self
+            .audit_log_repo
+            .list_logs(org_id, &filter, Some(per_page), (page - 1) * per_page)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(AuditLogPageResponse {
+            entries,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    // one json object per line, pagination is ignored so the export is complete
+    pub async fn export_logs(
+        &self,
+        org_id: Uuid,
+        query: ListAuditLogQuery,
+    ) -> Result<String, CustomError> {
+        let logs = self
+            .audit_log_repo
+            .list_logs(org_id, &audit_log_filter(query), None, 0)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let mut export = String::new();
+        for log in logs {
+            let line = serde_json::to_string(&log).map_err(|_| CustomError::InternalServerError)?;
+            export.push_str(&line);
+            export.push('\n');
+        }
+
+        Ok(export)
+    }
+}
+
+fn audit_log_filter(query: ListAuditLogQuery) -> AuditLogFilter {
+    AuditLogFilter {
+        action: query.action.map(|action| action.as_str().to_string()),
+        actor_id: query.actor_id,
+        target_id: query.target_id,
+        from: query.from,
+        to: query.to,
+    }
+}
@@ -1,37 +1,94 @@
+use chrono::{Duration, Utc};
 use futures::TryFutureExt;
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use std::sync::Arc;
 use uuid::Uuid;
+use validator::Validate;
 
 use crate::{
+    config::{Config, PasswordPolicy},
     errors::CustomError,
     models::{
         auth::{
-            CreateUserData, LoginRequest, LoginResponse, RegisterRequest, RegisterResponse, User,
+            AuthEvent, CreateUserData, LockoutScope, LoginRequest, LoginResponse, LoginResult,
+            RegisterRequest, RegisterResponse, User,
         },
+        context::ClientInfo,
+        magic_link::{
+            CreateMagicLinkData, MagicLinkLoginRequest, MagicLinkRequest, MagicLinkResponse,
+        },
+        two_factor::{TwoFactorChallengeResponse, TwoFactorLoginRequest},
+        user::DELETED_USER_ID,
         user_preferences::UserPreferenceRequest,
     },
-    repositories::{user::UserRepository, user_preferences::UserPreferencesRepository},
+    repositories::{
+        auth_log::AuthLogRepository, magic_link::MagicLinkRepository, org::OrgRepository,
+        user::UserRepository, user_preferences::UserPreferencesRepository,
+    },
+    utils::password::{check_password_strength, verify_password},
 };
 use argon2::{
     password_hash::{PasswordHasher, SaltString},
-    Argon2, PasswordHash, PasswordVerifier,
+    Argon2,
+};
+
+use super::{
+    lockout::LockoutService,
+    mailer::{EmailMessage, Mailer},
+    token::TokenService,
+    two_factor::{TwoFactorService, VerifiedCode},
+    user::UserService,
 };
 
-use super::token::TokenService;
+const MAGIC_LINK_TOKEN_LENGTH: usize = 48;
+const MAGIC_LINK_WINDOW_MINUTES: i64 = 15;
+const MAX_MAGIC_LINKS_PER_WINDOW: i64 = 3;
 
 pub struct AuthService {
     user_repo: UserRepository,
     token_service: Arc<TokenService>,
+    two_factor_service: Arc<TwoFactorService>,
+    user_service: Arc<UserService>,
+    lockout_service: Arc<LockoutService>,
     user_preferences_repo: UserPreferencesRepository,
+    auth_log_repo: AuthLogRepository,
+    magic_link_repo: MagicLinkRepository,
+    org_repo: OrgRepository,
+    mailer: Arc<dyn Mailer>,
+    password_policy: PasswordPolicy,
+    instance_admins: Vec<Uuid>,
+    app_url: String,
+    magic_link_expiration: i64,
 }
 
 impl AuthService {
-    pub fn new(pool: PgPool, token_service: Arc<TokenService>) -> Self {
+    pub fn new(
+        pool: PgPool,
+        config: &Config,
+        token_service: Arc<TokenService>,
+        two_factor_service: Arc<TwoFactorService>,
+        user_service: Arc<UserService>,
+        lockout_service: Arc<LockoutService>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
         Self {
             user_repo: UserRepository::new(pool.clone()),
             token_service,
+            two_factor_service,
+            user_service,
+            lockout_service,
             user_preferences_repo: UserPreferencesRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool.clone()),
+            magic_link_repo: MagicLinkRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool),
+            mailer,
+            password_policy: config.password_policy.clone(),
+            instance_admins: config.instance_admins.clone(),
+            app_url: config.app_url.clone(),
+            magic_link_expiration: config.token_config.magic_link_expiration,
         }
     }
 
@@ -39,6 +96,17 @@ impl AuthService {
         &self,
         request: RegisterRequest,
     ) -> Result<RegisterResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        check_password_strength(
+            &request.password,
+            &request.username,
+            &request.email,
+            &self.password_policy,
+        )?;
+
         let user_exist = self
             .user_repo
             .user_exists(request.email.clone(), request.username.clone())
@@ -76,6 +144,11 @@ impl AuthService {
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
             .await?;
 
+        // the account is usable right away, pending org invites wait for the confirmation
+        if let Err(e) = self.user_service.send_email_verification(&user).await {
+            log::warn!("Failed to send verification email to {}: {}", user.email, e);
+        }
+
         Ok(RegisterResponse {
             message: "User registered successfully".to_string(),
             user_id: user.id.to_string(),
@@ -83,53 +156,359 @@ impl AuthService {
         })
     }
 
-    pub async fn login_user(&self, request: LoginRequest) -> Result<LoginResponse, CustomError> {
+    pub async fn login_user(
+        &self,
+        request: LoginRequest,
+        client: ClientInfo,
+    ) -> Result<LoginResult, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            self.lockout_service
+                .ensure_not_locked(LockoutScope::Ip, ip_address, None, &client)
+                .await?;
+        }
+
+        let user = match self
+            .user_repo
+            .get_user_by_email(request.email.clone())
+            .await
+        {
+            Ok(user) => user,
+            Err(_) => {
+                self.lockout_service
+                    .record_failed_login(None, &request.email, "unknown_email", &client)
+                    .await?;
+                return Err(CustomError::InvalidCredentials);
+            }
+        };
+
+        // a locked account answers like an unknown email, so the lockout doesn't
+        // tell which emails have an account
+        match self
+            .lockout_service
+            .ensure_not_locked(
+                LockoutScope::Account,
+                &user.id.to_string(),
+                Some(user.id),
+                &client,
+            )
+            .await
+        {
+            Err(CustomError::TooManyAttempts(_)) => {
+                self.lockout_service
+                    .record_failed_login(None, &request.email, "account_locked", &client)
+                    .await?;
+                return Err(CustomError::InvalidCredentials);
+            }
+            result => result?,
+        }
+
+        if !verify_password(&request.password, user.password_hash.as_deref()) {
+            self.lockout_service
+                .record_failed_login(Some(user.id), &request.email, "invalid_password", &client)
+                .await?;
+            return Err(CustomError::InvalidCredentials);
+        }
+
+        if self.two_factor_service.is_enabled(user.id).await? {
+            let challenge = self.create_two_factor_challenge(user.id, &client).await?;
+            return Ok(LoginResult::TwoFactorRequired(challenge));
+        }
+
+        let login_res = self
+            .complete_login(user, "User logged in successfully", &client, None)
+            .await?;
+
+        Ok(LoginResult::Tokens(login_res))
+    }
+
+    pub async fn login_with_two_factor(
+        &self,
+        request: TwoFactorLoginRequest,
+        client: ClientInfo,
+    ) -> Result<LoginResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let user_id = self
+            .token_service
+            .verify_two_factor_challenge(&request.challenge_token)
+            .await?;
+
+        self.lockout_service
+            .ensure_can_attempt(user_id, &client)
+            .await?;
+
         let user = self
             .user_repo
-            .get_user_by_email(request.email)
-            .map_err(|_| CustomError::InvalidCredentials)
+            .get_user_by_id(user_id)
+            .map_err(|_| CustomError::InvalidToken("User not found".to_string()))
             .await?;
 
-        let argon2 = Argon2::default();
-        let parsed_hash = PasswordHash::new(
-            user.password_hash
-                .as_deref()
-                .ok_or(CustomError::InvalidCredentials)?,
+        let verified = match self
+            .two_factor_service
+            .verify_code(user.id, &request.code)
+            .await
+        {
+            Ok(verified) => verified,
+            Err(CustomError::InvalidConfirmationCode) => {
+                self.lockout_service
+                    .record_failed_login(
+                        Some(user.id),
+                        &user.email,
+                        "invalid_two_factor_code",
+                        &client,
+                    )
+                    .await?;
+                return Err(CustomError::InvalidConfirmationCode);
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let VerifiedCode::RecoveryCode = verified {
+            self.auth_log_repo
+                .log_event(Some(user.id), AuthEvent::RecoveryCodeUsed, &client, None)
+                .await;
+        }
+
+        self.complete_login(
+            user,
+            "User logged in successfully",
+            &client,
+            Some(json!({ "two_factor": true })),
         )
-        .map_err(|_| CustomError::InvalidCredentials)?;
+        .await
+    }
+
+    // the response is the same whether or not the email belongs to an account
+    pub async fn request_magic_link(
+        &self,
+        request: MagicLinkRequest,
+        client: ClientInfo,
+    ) -> Result<MagicLinkResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            self.lockout_service
+                .ensure_not_locked(LockoutScope::Ip, ip_address, None, &client)
+                .await?;
+        }
+
+        let response = MagicLinkResponse {
+            message: "If an account exists for this email, a sign-in link has been sent"
+                .to_string(),
+        };
+
+        let user = match self.user_repo.get_user_by_email(request.email).await {
+            Ok(user) if user.id != DELETED_USER_ID => user,
+            _ => return Ok(response),
+        };
+
+        let window_start = Utc::now() - Duration::minutes(MAGIC_LINK_WINDOW_MINUTES);
+        let recent_links = self
+            .magic_link_repo
+            .count_recent_tokens(user.id, window_start)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
-        if let Err(_) = argon2
-            .verify_password(request.password.as_bytes(), &parsed_hash)
-            .map_err(|_| CustomError::InvalidCredentials)
+        // keeps the endpoint from being used to flood someone's inbox
+        if recent_links >= MAX_MAGIC_LINKS_PER_WINDOW {
+            log::warn!("Magic link limit reached for user {}", user.id);
+            return Ok(response);
+        }
+
+        if let Err(e) = self.magic_link_repo.delete_expired(window_start).await {
+            log::warn!("Failed to delete expired magic links: {}", e);
+        }
+
+        self.magic_link_repo
+            .invalidate_user_tokens(user.id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(MAGIC_LINK_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        self.magic_link_repo
+            .create_token(CreateMagicLinkData {
+                user_id: user.id,
+                token_hash: hash_magic_link_token(&token),
+                expires_at: Utc::now() + Duration::minutes(self.magic_link_expiration),
+                ip_address: client.ip_address.clone(),
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.mailer
+            .send(EmailMessage {
+                to: user.email,
+                subject: "Your sign-in link".to_string(),
+                body: format!(
+                    "Use the link below to sign in. It expires in {} minutes and can only be used once.\n\n{}/auth/magic-link?token={}\n\nIf you did not request this email you can ignore it.",
+                    self.magic_link_expiration, self.app_url, token
+                ),
+            })
+            .await?;
+
+        self.auth_log_repo
+            .log_event(Some(user.id), AuthEvent::MagicLinkRequested, &client, None)
+            .await;
+
+        Ok(response)
+    }
+
+    pub async fn login_with_magic_link(
+        &self,
+        request: MagicLinkLoginRequest,
+        client: ClientInfo,
+    ) -> Result<LoginResult, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            self.lockout_service
+                .ensure_not_locked(LockoutScope::Ip, ip_address, None, &client)
+                .await?;
+        }
+
+        let magic_link = match self
+            .magic_link_repo
+            .use_token(&hash_magic_link_token(&request.token))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
         {
-            return Err(CustomError::InvalidCredentials);
+            Some(magic_link) => magic_link,
+            None => {
+                self.auth_log_repo
+                    .log_event(
+                        None,
+                        AuthEvent::LoginFailed,
+                        &client,
+                        Some(json!({ "method": "magic_link", "reason": "invalid_magic_link" })),
+                    )
+                    .await;
+
+                if let Some(ip_address) = client.ip_address.as_deref() {
+                    self.lockout_service
+                        .register_failed_attempt(LockoutScope::Ip, ip_address)
+                        .await?;
+                }
+
+                return Err(CustomError::InvalidToken(
+                    "Invalid or expired sign-in link".to_string(),
+                ));
+            }
+        };
+
+        let mut user = self
+            .user_repo
+            .get_user_by_id(magic_link.user_id)
+            .map_err(|_| CustomError::InvalidToken("User not found".to_string()))
+            .await?;
+
+        // following the link proves the user controls the address
+        if !user.is_email_verified {
+            self.user_repo
+                .update_user_email_verification_status(user.id, true)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+            user.is_email_verified = true;
+
+            self.org_repo
+                .attach_invites(user.id, &user.email)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
         }
 
-        let (access_token, refresh_token, access_token_exp, refresh_token_exp) = self
-            .token_service
-            .generate_token_pair(user.id.clone())
+        if self.two_factor_service.is_enabled(user.id).await? {
+            let challenge = self.create_two_factor_challenge(user.id, &client).await?;
+            return Ok(LoginResult::TwoFactorRequired(challenge));
+        }
+
+        let login_res = self
+            .complete_login(
+                user,
+                "User logged in successfully",
+                &client,
+                Some(json!({ "method": "magic_link" })),
+            )
             .await?;
 
+        Ok(LoginResult::Tokens(login_res))
+    }
+
+    // shared by every login flow once all factors have been verified
+    pub async fn complete_login(
+        &self,
+        user: User,
+        message: &str,
+        client: &ClientInfo,
+        details: Option<serde_json::Value>,
+    ) -> Result<LoginResponse, CustomError> {
+        self.lockout_service.clear_account_lockout(user.id).await?;
+
+        self.auth_log_repo
+            .log_event(Some(user.id), AuthEvent::LoginSuccess, client, details)
+            .await;
+
+        let (access_token, refresh_token, access_token_exp, refresh_token_exp) =
+            self.token_service.generate_token_pair(user.id).await?;
+
         self.user_repo
             .update_user_last_login(user.id)
             .map_err(|_| CustomError::DatabaseError("Failed to update last login".to_string()))
             .await?;
 
         Ok(LoginResponse {
-            message: "User logged in successfully".to_string(),
+            message: message.to_string(),
             user_id: user.id.to_string(),
             email: user.email,
             access_token,
             refresh_token,
             expires_in_access: access_token_exp * 60,
             expires_in_refresh: refresh_token_exp * 60,
+            redirect_to: None,
+        })
+    }
+
+    pub async fn create_two_factor_challenge(
+        &self,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<TwoFactorChallengeResponse, CustomError> {
+        let (challenge_token, expires_in) = self
+            .token_service
+            .generate_two_factor_challenge(user_id)
+            .await?;
+
+        self.auth_log_repo
+            .log_event(Some(user_id), AuthEvent::TwoFactorChallenge, client, None)
+            .await;
+
+        Ok(TwoFactorChallengeResponse {
+            message: "Two-factor authentication required".to_string(),
+            user_id: user_id.to_string(),
+            two_factor_required: true,
+            challenge_token,
+            expires_in: expires_in * 60,
+            redirect_to: None,
         })
     }
 
     pub async fn refresh_token(&self, refresh_token: &str) -> Result<LoginResponse, CustomError> {
         let user_id_str = self
             .token_service
-            .verify_refresh_token(&refresh_token)
+            .verify_refresh_token(refresh_token)
             .await?;
 
         let user_id = Uuid::parse_str(&user_id_str)
@@ -152,6 +531,7 @@ impl AuthService {
             refresh_token,
             expires_in_access: access_token_exp * 60,
             expires_in_refresh: refresh_token_exp * 60,
+            redirect_to: None,
         })
     }
 
@@ -164,4 +544,42 @@ impl AuthService {
 
         Ok(user)
     }
+
+    // the lockout covers every org the account is in, so only instance admins can lift it
+    pub async fn unlock_account(
+        &self,
+        user_id: Uuid,
+        unlocked_by: Uuid,
+        client: ClientInfo,
+    ) -> Result<(), CustomError> {
+        if !self.instance_admins.contains(&unlocked_by) {
+            return Err(CustomError::Forbidden);
+        }
+
+        self.user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => CustomError::NotFound("User".to_string()),
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        self.lockout_service.clear_account_lockout(user_id).await?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::AccountUnlocked,
+                &client,
+                Some(json!({ "unlocked_by": unlocked_by })),
+            )
+            .await;
+
+        Ok(())
+    }
+}
+
+// like refresh tokens only a hash is stored, the token itself is only ever in the email
+fn hash_magic_link_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
 }
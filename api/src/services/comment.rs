@@ -1,23 +1,29 @@
 use futures::TryFutureExt;
+use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
 
 use crate::{
     errors::CustomError,
-    models::comment::{CommentRequest, CommentResponse, UpdateCommentRequest},
-    repositories::{comment::CommentRepository, user::UserRepository},
+    models::{
+        comment::{Comment, CommentRequest, CommentResponse, UpdateCommentRequest},
+        webhook::WebhookEvent,
+    },
+    repositories::{comment::CommentRepository, user::UserRepository, webhook::WebhookRepository},
 };
 
 pub struct CommentService {
     comment_repo: CommentRepository,
     user_repo: UserRepository,
+    webhook_repo: WebhookRepository,
 }
 
 impl CommentService {
     pub fn new(pool: PgPool) -> Self {
         Self {
             comment_repo: CommentRepository::new(pool.clone()),
-            user_repo: UserRepository::new(pool),
+            user_repo: UserRepository::new(pool.clone()),
+            webhook_repo: WebhookRepository::new(pool),
         }
     }
 
@@ -39,18 +45,29 @@ impl CommentService {
             .map_err(|_| CustomError::InvalidToken("User not found".to_string()))
             .await?;
 
+        self.webhook_repo
+            .queue_event(
+                org_id,
+                WebhookEvent::CommentCreated,
+                json!({ "comment": comment }),
+            )
+            .await;
+
         Ok(CommentResponse { comment, creator })
     }
 
-    pub async fn delete_comment(&self, comment_id: Uuid, user_id: Uuid) -> Result<(), CustomError> {
-        let comment = self
-            .comment_repo
-            .get_comment_by_id(comment_id)
-            .map_err(|e| CustomError::DatabaseError(e.to_string()))
-            .await?;
+    // moderators may delete comments of other members
+    pub async fn delete_comment(
+        &self,
+        org_id: Uuid,
+        comment_id: Uuid,
+        user_id: Uuid,
+        can_moderate: bool,
+    ) -> Result<(), CustomError> {
+        let comment = self.find_comment(org_id, comment_id).await?;
 
-        if comment.creator_id != user_id {
-            return Err(CustomError::Unauthorized);
+        if comment.creator_id != user_id && !can_moderate {
+            return Err(CustomError::Forbidden);
         }
 
         self.comment_repo
@@ -61,15 +78,12 @@ impl CommentService {
 
     pub async fn update_comment(
         &self,
+        org_id: Uuid,
         comment_id: Uuid,
         user_id: Uuid,
         data: UpdateCommentRequest,
     ) -> Result<CommentResponse, CustomError> {
-        let comment = self
-            .comment_repo
-            .get_comment_by_id(comment_id)
-            .map_err(|e| CustomError::DatabaseError(e.to_string()))
-            .await?;
+        let comment = self.find_comment(org_id, comment_id).await?;
 
         if comment.creator_id != user_id {
             return Err(CustomError::Unauthorized);
@@ -132,4 +146,22 @@ impl CommentService {
 
         Ok(comment_responses)
     }
+
+    // comments of other orgs are treated as missing
+    async fn find_comment(&self, org_id: Uuid, comment_id: Uuid) -> Result<Comment, CustomError> {
+        let comment = self
+            .comment_repo
+            .get_comment_by_id(comment_id)
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => CustomError::NotFound("Comment not found".to_string()),
+                _ => CustomError::DatabaseError(e.to_string()),
+            })
+            .await?;
+
+        if comment.org_id != org_id {
+            return Err(CustomError::NotFound("Comment not found".to_string()));
+        }
+
+        Ok(comment)
+    }
 }
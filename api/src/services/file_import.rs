@@ -0,0 +1,1088 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use csv::StringRecord;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
+        import::{
+            FileImportRequest, FileImportResponse, FileImportedIssue, ImportFileFormat,
+            ImportedComment, ImportedFileIssue, ImportedLabel, UnmappedKind, UnmappedValue,
+        },
+        issue::{normalized_name, IssuePriority, IssueStatus},
+    },
+    repositories::{
+        audit_log::AuditLogRepository, import::ImportRepository, org::OrgRepository,
+        team::TeamRepository,
+    },
+    utils::editor::markdown_document,
+};
+
+// every issue of the file is held in memory and created in one transaction
+const MAX_FILE_ISSUES: usize = 5000;
+
+pub struct FileImportService {
+    import_repo: ImportRepository,
+    org_repo: OrgRepository,
+    team_repo: TeamRepository,
+    audit_log_repo: AuditLogRepository,
+}
+
+// an issue as the export describes it, nothing resolved yet
+#[derive(Debug, Default)]
+struct ExternalIssue {
+    key: String,
+    // jira csv links sub-tasks to the numeric id of their parent instead of its key
+    id: Option<String>,
+    parent: Option<String>,
+    title: String,
+    // markdown
+    description: String,
+    status: Option<String>,
+    // the category of a custom status, used when the status itself is unknown
+    status_category: Option<String>,
+    resolution: Option<String>,
+    priority: Option<String>,
+    creator: Option<String>,
+    assignees: Vec<String>,
+    labels: Vec<String>,
+    due_date: Option<String>,
+    created_at: Option<String>,
+    comments: Vec<ExternalComment>,
+}
+
+#[derive(Debug)]
+struct ExternalComment {
+    author: Option<String>,
+    body: String,
+    created_at: Option<String>,
+}
+
+// values that had no counterpart, counted per value
+#[derive(Default)]
+struct ImportReport(BTreeMap<(UnmappedKind, String), (usize, String)>);
+
+impl ImportReport {
+    fn add(&mut self, kind: UnmappedKind, value: &str, replaced_with: &str) {
+        let entry = self
+            .0
+            .entry((kind, value.to_string()))
+            .or_insert_with(|| (0, replaced_with.to_string()));
+        entry.0 += 1;
+    }
+
+    fn into_values(self) -> Vec<UnmappedValue> {
+        self.0
+            .into_iter()
+            .map(
+                |((kind, value), (occurrences, replaced_with))| UnmappedValue {
+                    kind,
+                    value,
+                    occurrences,
+                    replaced_with,
+                },
+            )
+            .collect()
+    }
+}
+
+impl FileImportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            import_repo: ImportRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            team_repo: TeamRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool),
+        }
+    }
+
+    // values with no counterpart fall back to a default and are listed in the
+    // response, so a dry run shows what to fix in the mapping before importing
+    pub async fn import_file(
+        &self,
+        org_id: Uuid,
+        importer_id: Uuid,
+        data: FileImportRequest,
+        client: &ClientInfo,
+    ) -> Result<FileImportResponse, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(team_id) = data.team_id {
+            self.team_repo
+                .find_team(org_id, team_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .ok_or(CustomError::NotFound("Team not found".to_string()))?;
+        }
+
+        let (source, external) = match data.format {
+            ImportFileFormat::JiraXml => ("Jira", jira_xml_issues(&data.file)),
+            ImportFileFormat::JiraCsv => ("Jira", jira_csv_issues(&data.file)),
+            ImportFileFormat::LinearCsv => ("Linear", linear_csv_issues(&data.file)),
+        };
+        let external = external.map_err(file_error)?;
+
+        if external.is_empty() {
+            return Err(file_error("The file contains no issues".to_string()));
+        }
+        if external.len() > MAX_FILE_ISSUES {
+            return Err(file_error(format!(
+                "The file has more than {} issues",
+                MAX_FILE_ISSUES
+            )));
+        }
+
+        let people = self
+            .match_people(org_id, &external, data.user_mapping.unwrap_or_default())
+            .await?;
+
+        let mut report = ImportReport::default();
+        let keys: Vec<String> = external.iter().map(|issue| issue.key.clone()).collect();
+        let issues = convert_issues(external, &people, importer_id, source, &mut report);
+
+        let issue_count = issues.len();
+        let sub_issues = issues.iter().filter(|issue| issue.parent.is_some()).count();
+        let comments = issues.iter().map(|issue| issue.comments.len()).sum();
+
+        if data.dry_run.unwrap_or(false) {
+            return Ok(FileImportResponse {
+                dry_run: true,
+                issues: issue_count,
+                sub_issues,
+                comments,
+                unmapped: report.into_values(),
+                imported: Vec::new(),
+            });
+        }
+
+        let created = self
+            .import_repo
+            .import_file_issues(org_id, data.team_id, issues)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(importer_id),
+                client,
+                AuditEntry::new(AuditEvent::OrgFileImported, None).after(json!({
+                    "format": data.format,
+                    "team_id": data.team_id,
+                    "issues": issue_count,
+                    "comments": comments,
+                })),
+            )
+            .await;
+
+        Ok(FileImportResponse {
+            dry_run: false,
+            issues: issue_count,
+            sub_issues,
+            comments,
+            unmapped: report.into_values(),
+            imported: keys
+                .into_iter()
+                .zip(created)
+                .map(|(external_key, (issue_id, number))| FileImportedIssue {
+                    external_key,
+                    issue_id,
+                    number,
+                })
+                .collect(),
+        })
+    }
+
+    // people of the export by lowercased name, the explicit mapping wins over a match
+    async fn match_people(
+        &self,
+        org_id: Uuid,
+        issues: &[ExternalIssue],
+        user_mapping: HashMap<String, Uuid>,
+    ) -> Result<HashMap<String, Uuid>, CustomError> {
+        for user_id in user_mapping.values().collect::<HashSet<_>>() {
+            let is_member = self
+                .org_repo
+                .is_user_member_of_org(org_id, *user_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+            if !is_member {
+                let mut errors = ValidationErrors::new();
+                errors.add(
+                    "user_mapping",
+                    ValidationError::new("not_a_member").with_message(Cow::Owned(format!(
+                        "{} is not an active member of the org",
+                        user_id
+                    ))),
+                );
+                return Err(CustomError::ValidationError(errors));
+            }
+        }
+
+        let names: Vec<String> = issues
+            .iter()
+            .flat_map(|issue| {
+                issue
+                    .creator
+                    .iter()
+                    .chain(issue.assignees.iter())
+                    .chain(issue.comments.iter().filter_map(|c| c.author.as_ref()))
+            })
+            .map(|name| name.to_lowercase())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+
+        let mut people: HashMap<String, Uuid> = match names.is_empty() {
+            true => HashMap::new(),
+            false => self
+                .org_repo
+                .find_member_ids_by_names(org_id, &names)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .collect(),
+        };
+        people.extend(
+            user_mapping
+                .into_iter()
+                .map(|(name, user_id)| (name.to_lowercase(), user_id)),
+        );
+
+        Ok(people)
+    }
+}
+
+fn convert_issues(
+    external: Vec<ExternalIssue>,
+    people: &HashMap<String, Uuid>,
+    importer_id: Uuid,
+    source: &str,
+    report: &mut ImportReport,
+) -> Vec<ImportedFileIssue> {
+    let mut indexes = HashMap::new();
+    for (i, issue) in external.iter().enumerate() {
+        indexes.insert(issue.key.clone(), i);
+        if let Some(id) = &issue.id {
+            indexes.insert(id.clone(), i);
+        }
+    }
+
+    let mut parents: Vec<Option<usize>> = external
+        .iter()
+        .map(|issue| {
+            let parent = issue.parent.as_ref()?;
+            let index = indexes.get(parent).copied();
+            if index.is_none() {
+                report.add(UnmappedKind::Parent, parent, "no parent");
+            }
+            index
+        })
+        .collect();
+    break_parent_loops(&mut parents, &external, report);
+
+    let now = Utc::now();
+    let member = |name: &Option<String>| {
+        name.as_ref()
+            .and_then(|name| people.get(&name.to_lowercase()).copied())
+    };
+
+    external
+        .into_iter()
+        .zip(parents)
+        .map(|(issue, parent)| {
+            let status = issue_status(&issue, report);
+            let priority = match issue.priority.as_deref() {
+                None => IssuePriority::Low,
+                Some(name) => external_priority(name).unwrap_or_else(|| {
+                    report.add(UnmappedKind::Priority, name, "Low");
+                    IssuePriority::Low
+                }),
+            };
+
+            let creator_id = member(&issue.creator);
+            let mut description = issue.description;
+            if let (Some(creator), None) = (&issue.creator, creator_id) {
+                report.add(UnmappedKind::User, creator, "importer");
+                description = format!(
+                    "Originally created by {} in {}\n\n{}",
+                    creator, source, description
+                );
+            }
+
+            let assignee_ids = issue
+                .assignees
+                .iter()
+                .filter_map(|assignee| {
+                    let assignee_id = people.get(&assignee.to_lowercase()).copied();
+                    if assignee_id.is_none() {
+                        report.add(UnmappedKind::Assignee, assignee, "unassigned");
+                    }
+                    assignee_id
+                })
+                .collect();
+
+            let mut labels: Vec<ImportedLabel> = Vec::new();
+            for name in issue.labels {
+                if !labels.iter().any(|label| label.name == name) {
+                    labels.push(ImportedLabel {
+                        name,
+                        color: None,
+                        description: None,
+                    });
+                }
+            }
+
+            let due_date = issue.due_date.as_deref().and_then(|value| {
+                let due_date = external_date(value);
+                if due_date.is_none() {
+                    report.add(UnmappedKind::Date, value, "no due date");
+                }
+                due_date
+            });
+            let issue_created_at = created_at(&issue.created_at, now, report);
+
+            let comments = issue
+                .comments
+                .into_iter()
+                .map(|comment| {
+                    let author_id = member(&comment.author);
+                    let body = match (&comment.author, author_id) {
+                        (Some(author), None) => {
+                            report.add(UnmappedKind::User, author, "importer");
+                            format!(
+                                "Originally posted by {} in {}\n\n{}",
+                                author, source, comment.body
+                            )
+                        }
+                        _ => comment.body,
+                    };
+                    ImportedComment {
+                        creator_id: author_id.unwrap_or(importer_id),
+                        content: markdown_document(&body),
+                        created_at: created_at(&comment.created_at, now, report),
+                    }
+                })
+                .collect();
+
+            ImportedFileIssue {
+                parent,
+                title: match issue.title.trim().is_empty() {
+                    true => issue.key,
+                    false => issue.title,
+                },
+                description: (!description.trim().is_empty())
+                    .then(|| markdown_document(&description)),
+                status,
+                priority,
+                due_date,
+                creator_id: creator_id.unwrap_or(importer_id),
+                created_at: issue_created_at,
+                labels,
+                assignee_ids,
+                comments,
+            }
+        })
+        .collect()
+}
+
+fn created_at(
+    value: &Option<String>,
+    now: DateTime<Utc>,
+    report: &mut ImportReport,
+) -> DateTime<Utc> {
+    match value.as_deref() {
+        None => now,
+        Some(value) => external_date(value).unwrap_or_else(|| {
+            report.add(UnmappedKind::Date, value, "time of import");
+            now
+        }),
+    }
+}
+
+fn issue_status(issue: &ExternalIssue, report: &mut ImportReport) -> IssueStatus {
+    let name = match issue.status.as_deref() {
+        Some(name) => name,
+        None => return IssueStatus::Backlog,
+    };
+
+    let status = external_status(name)
+        .or_else(|| issue.status_category.as_deref().and_then(category_status));
+    match status {
+        // jira closes issues that will not be done with a resolution instead of a status
+        Some(IssueStatus::Done)
+            if issue.resolution.as_deref().is_some_and(|resolution| {
+                matches!(
+                    normalized_name(resolution).as_str(),
+                    "wontdo"
+                        | "wontfix"
+                        | "duplicate"
+                        | "cannotreproduce"
+                        | "incomplete"
+                        | "declined"
+                )
+            }) =>
+        {
+            IssueStatus::Canceled
+        }
+        Some(status) => status,
+        None => {
+            report.add(UnmappedKind::Status, name, "Backlog");
+            IssueStatus::Backlog
+        }
+    }
+}
+
+// a parent chain that loops back could never be shown, the link closing it is dropped
+fn break_parent_loops(
+    parents: &mut [Option<usize>],
+    issues: &[ExternalIssue],
+    report: &mut ImportReport,
+) {
+    for i in 0..parents.len() {
+        let mut current = parents[i];
+        for _ in 0..parents.len() {
+            match current {
+                Some(parent) if parent == i => {
+                    if let Some(key) = &issues[i].parent {
+                        report.add(UnmappedKind::Parent, key, "no parent");
+                    }
+                    parents[i] = None;
+                    break;
+                }
+                Some(parent) => current = parents[parent],
+                None => break,
+            }
+        }
+    }
+}
+
+// workflow names of jira and linear on top of the ones of this app
+fn external_status(name: &str) -> Option<IssueStatus> {
+    IssueStatus::from_name(name).or_else(|| match normalized_name(name).as_str() {
+        "triage" | "icebox" => Some(IssueStatus::Backlog),
+        "open" | "new" | "reopened" | "unstarted" | "selectedfordevelopment" => {
+            Some(IssueStatus::Todo)
+        }
+        "started" | "doing" | "indevelopment" => Some(IssueStatus::InProgress),
+        "review" | "codereview" | "readyforreview" | "qa" | "inqa" | "testing" | "intesting" => {
+            Some(IssueStatus::InReview)
+        }
+        "closed" | "resolved" | "complete" | "completed" | "released" | "shipped" => {
+            Some(IssueStatus::Done)
+        }
+        "wontdo" | "wontfix" | "duplicate" | "rejected" | "declined" | "obsolete" => {
+            Some(IssueStatus::Canceled)
+        }
+        "onhold" | "waiting" | "impeded" => Some(IssueStatus::Blocked),
+        _ => None,
+    })
+}
+
+// jira's status categories, and what linear's timestamps say about a custom status
+fn category_status(category: &str) -> Option<IssueStatus> {
+    match normalized_name(category).as_str() {
+        "new" | "todo" => Some(IssueStatus::Todo),
+        "indeterminate" | "inprogress" | "started" => Some(IssueStatus::InProgress),
+        "done" | "completed" => Some(IssueStatus::Done),
+        "canceled" | "cancelled" => Some(IssueStatus::Canceled),
+        _ => None,
+    }
+}
+
+fn external_priority(name: &str) -> Option<IssuePriority> {
+    IssuePriority::from_name(name).or_else(|| match normalized_name(name).as_str() {
+        "highest" | "blocker" | "critical" => Some(IssuePriority::Urgent),
+        "major" => Some(IssuePriority::High),
+        "normal" => Some(IssuePriority::Medium),
+        "minor" | "lowest" | "trivial" | "nopriority" | "none" => Some(IssuePriority::Low),
+        _ => None,
+    })
+}
+
+// rfc 3339, rfc 2822 in jira xml, javascript's Date string in linear and the
+// day/month/year of jira csv
+fn external_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc2822(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    let without_zone_name = value.split(" (").next().unwrap_or(value);
+    if let Ok(date) = DateTime::parse_from_str(without_zone_name, "%a %b %d %Y %H:%M:%S GMT%z") {
+        return Some(date.with_timezone(&Utc));
+    }
+
+    for format in [
+        "%d/%b/%y %I:%M %p",
+        "%d/%b/%Y %I:%M %p",
+        "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(value, format) {
+            return Some(date.and_utc());
+        }
+    }
+    for format in ["%Y-%m-%d", "%d/%b/%y", "%d/%b/%Y"] {
+        if let Ok(date) = NaiveDate::parse_from_str(value, format) {
+            return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+        }
+    }
+
+    None
+}
+
+// the rss of a jira issue search, descriptions and comments are html
+fn jira_xml_issues(file: &str) -> Result<Vec<ExternalIssue>, String> {
+    let document = roxmltree::Document::parse(file).map_err(|e| e.to_string())?;
+
+    let issues = document
+        .descendants()
+        .filter(|node| node.has_tag_name("item"))
+        .map(|item| {
+            let element = |name: &str| item.children().find(|node| node.has_tag_name(name));
+            let text = |name: &str| element(name).and_then(|node| non_empty(node.text()));
+            let person = |name: &str| element(name).and_then(jira_xml_person);
+
+            let comments = element("comments")
+                .map(|comments| {
+                    comments
+                        .children()
+                        .filter(|node| node.has_tag_name("comment"))
+                        .map(|comment| ExternalComment {
+                            author: non_empty(comment.attribute("author")),
+                            body: html_markdown(comment.text().unwrap_or("")),
+                            created_at: non_empty(comment.attribute("created")),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+            let labels = element("labels")
+                .map(|labels| {
+                    labels
+                        .children()
+                        .filter(|node| node.has_tag_name("label"))
+                        .filter_map(|label| non_empty(label.text()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            ExternalIssue {
+                key: text("key").unwrap_or_default(),
+                id: element("key").and_then(|key| non_empty(key.attribute("id"))),
+                parent: text("parent"),
+                title: text("summary").unwrap_or_default(),
+                description: html_markdown(&text("description").unwrap_or_default()),
+                status: text("status"),
+                status_category: element("statusCategory")
+                    .and_then(|category| non_empty(category.attribute("key"))),
+                resolution: text("resolution"),
+                priority: text("priority"),
+                creator: person("reporter"),
+                assignees: person("assignee").into_iter().collect(),
+                labels,
+                due_date: text("due"),
+                created_at: text("created"),
+                comments,
+            }
+        })
+        .collect();
+
+    Ok(issues)
+}
+
+// server exports name people by username, cloud exports only by display name
+fn jira_xml_person(node: roxmltree::Node) -> Option<String> {
+    match node.attribute("username") {
+        Some("-1") => None,
+        Some(username) if !username.trim().is_empty() => Some(username.trim().to_string()),
+        _ => non_empty(node.text()),
+    }
+}
+
+fn jira_csv_issues(file: &str) -> Result<Vec<ExternalIssue>, String> {
+    let csv = ExportCsv::parse(file)?;
+
+    let issues = csv
+        .records
+        .iter()
+        .map(|record| ExternalIssue {
+            key: csv.value(record, &["Issue key"]).unwrap_or_default(),
+            id: csv.value(record, &["Issue id"]),
+            parent: csv.value(record, &["Parent id", "Parent", "Parent key"]),
+            title: csv.value(record, &["Summary"]).unwrap_or_default(),
+            description: jira_wiki_markdown(
+                &csv.value(record, &["Description"]).unwrap_or_default(),
+            ),
+            status: csv.value(record, &["Status"]),
+            status_category: csv.value(record, &["Status Category"]),
+            resolution: csv.value(record, &["Resolution"]),
+            priority: csv.value(record, &["Priority"]),
+            creator: csv.value(record, &["Reporter", "Creator"]),
+            assignees: csv.values(record, "Assignee"),
+            labels: csv.values(record, "Labels"),
+            due_date: csv.value(record, &["Due Date", "Due"]),
+            created_at: csv.value(record, &["Created"]),
+            comments: csv
+                .values(record, "Comment")
+                .into_iter()
+                .map(|comment| jira_csv_comment(&comment))
+                .collect(),
+        })
+        .collect();
+
+    Ok(issues)
+}
+
+// written as "date;author;body"
+fn jira_csv_comment(comment: &str) -> ExternalComment {
+    let parts: Vec<&str> = comment.splitn(3, ';').collect();
+    match parts.as_slice() {
+        [created_at, author, body] if external_date(created_at.trim()).is_some() => {
+            ExternalComment {
+                author: non_empty(Some(author)),
+                body: jira_wiki_markdown(body),
+                created_at: non_empty(Some(created_at)),
+            }
+        }
+        _ => ExternalComment {
+            author: None,
+            body: jira_wiki_markdown(comment),
+            created_at: None,
+        },
+    }
+}
+
+// linear exports no comments, its descriptions are markdown already
+fn linear_csv_issues(file: &str) -> Result<Vec<ExternalIssue>, String> {
+    let csv = ExportCsv::parse(file)?;
+
+    let issues = csv
+        .records
+        .iter()
+        .map(|record| {
+            let status_category = if csv.value(record, &["Canceled"]).is_some() {
+                Some("canceled".to_string())
+            } else if csv.value(record, &["Completed"]).is_some() {
+                Some("completed".to_string())
+            } else if csv.value(record, &["Started"]).is_some() {
+                Some("started".to_string())
+            } else {
+                None
+            };
+
+            ExternalIssue {
+                key: csv.value(record, &["ID"]).unwrap_or_default(),
+                id: None,
+                parent: csv.value(record, &["Parent issue", "Parent"]),
+                title: csv.value(record, &["Title"]).unwrap_or_default(),
+                description: csv.value(record, &["Description"]).unwrap_or_default(),
+                status: csv.value(record, &["Status"]),
+                status_category,
+                resolution: None,
+                priority: csv.value(record, &["Priority"]),
+                creator: csv.value(record, &["Creator"]),
+                assignees: csv.value(record, &["Assignee"]).into_iter().collect(),
+                labels: csv
+                    .value(record, &["Labels"])
+                    .map(|labels| {
+                        labels
+                            .split(',')
+                            .filter_map(|label| non_empty(Some(label)))
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+                due_date: csv.value(record, &["Due Date"]),
+                created_at: csv.value(record, &["Created"]),
+                comments: Vec::new(),
+            }
+        })
+        .collect();
+
+    Ok(issues)
+}
+
+// jira repeats a column for every label, assignee or comment of an issue
+struct ExportCsv {
+    headers: Vec<String>,
+    records: Vec<StringRecord>,
+}
+
+impl ExportCsv {
+    fn parse(file: &str) -> Result<Self, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(file.as_bytes());
+
+        let headers = reader
+            .headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .map(normalized_name)
+            .collect();
+        let records = reader
+            .records()
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+
+        Ok(Self { headers, records })
+    }
+
+    // the first non-empty column of the given names
+    fn value(&self, record: &StringRecord, names: &[&str]) -> Option<String> {
+        names
+            .iter()
+            .find_map(|name| self.values(record, name).into_iter().next())
+    }
+
+    fn values(&self, record: &StringRecord, name: &str) -> Vec<String> {
+        let name = normalized_name(name);
+        self.headers
+            .iter()
+            .enumerate()
+            .filter(|(_, header)| **header == name)
+            .filter_map(|(i, _)| non_empty(record.get(i)))
+            .collect()
+    }
+}
+
+fn non_empty(value: Option<&str>) -> Option<String> {
+    value
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+// only the block structure survives, like markdown_document keeps it
+fn jira_wiki_markdown(wiki: &str) -> String {
+    wiki.replace("\r\n", "\n")
+        .lines()
+        .map(|line| {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("{code") || trimmed.starts_with("{noformat") {
+                return "```".to_string();
+            }
+            for level in 1..=6 {
+                if let Some(text) = trimmed.strip_prefix(&format!("h{}. ", level)) {
+                    return format!("{} {}", "#".repeat(level), text);
+                }
+            }
+            if let Some(text) = trimmed.strip_prefix("# ") {
+                return format!("1. {}", text);
+            }
+            line.to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn html_markdown(html: &str) -> String {
+    let mut markdown = String::new();
+    // numbered lists count their items
+    let mut lists: Vec<Option<usize>> = Vec::new();
+    let mut rest = html;
+
+    while let Some(start) = rest.find('<') {
+        markdown.push_str(&decode_entities(&rest[..start]));
+        let end = match rest[start..].find('>') {
+            Some(end) => start + end,
+            None => break,
+        };
+        let tag = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        let closing = tag.starts_with('/');
+        let name: String = tag
+            .trim_start_matches('/')
+            .chars()
+            .take_while(char::is_ascii_alphanumeric)
+            .collect::<String>()
+            .to_lowercase();
+        let heading = name
+            .strip_prefix('h')
+            .and_then(|level| level.parse::<usize>().ok())
+            .filter(|level| (1..=6).contains(level));
+
+        match (name.as_str(), closing) {
+            ("br", _) => markdown.push('\n'),
+            ("p" | "div" | "table" | "tr" | "blockquote", _) => markdown.push_str("\n\n"),
+            (_, false) if heading.is_some() => {
+                markdown.push_str("\n\n");
+                markdown.push_str(&"#".repeat(heading.unwrap_or(1)));
+                markdown.push(' ');
+            }
+            (_, true) if heading.is_some() => markdown.push_str("\n\n"),
+            ("ul", false) => lists.push(None),
+            ("ol", false) => lists.push(Some(0)),
+            ("ul" | "ol", true) => {
+                lists.pop();
+                markdown.push_str("\n\n");
+            }
+            ("li", false) => {
+                markdown.push('\n');
+                match lists.last_mut() {
+                    Some(Some(count)) => {
+                        *count += 1;
+                        markdown.push_str(&format!("{}. ", count));
+                    }
+                    _ => markdown.push_str("- "),
+                }
+            }
+            ("pre", false) => markdown.push_str("\n\n```\n"),
+            ("pre", true) => markdown.push_str("\n```\n\n"),
+            ("td" | "th", true) => markdown.push(' '),
+            _ => {}
+        }
+    }
+    markdown.push_str(&decode_entities(rest));
+
+    let mut lines: Vec<&str> = Vec::new();
+    for line in markdown.lines().map(str::trim_end) {
+        if line.is_empty() && matches!(lines.last(), None | Some(&"")) {
+            continue;
+        }
+        lines.push(line);
+    }
+    lines.join("\n").trim().to_string()
+}
+
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let entity = rest
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| (&rest[1..end], end));
+        let character = entity.and_then(|(name, _)| match name {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => name
+                .strip_prefix('#')
+                .and_then(|code| match code.strip_prefix('x') {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                })
+                .and_then(char::from_u32),
+        });
+
+        match (character, entity) {
+            (Some(character), Some((_, end))) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+
+    decoded
+}
+
+fn file_error(message: String) -> CustomError {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "file",
+        ValidationError::new("invalid_file").with_message(Cow::Owned(message)),
+    );
+    CustomError::ValidationError(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JIRA_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<rss version="0.92">
+  <channel>
+    <item>
+      <key id="10001">PROJ-1</key>
+      <summary>Crash on save</summary>
+      <description>&lt;p&gt;Steps &amp;amp; logs&lt;/p&gt;</description>
+      <status>In Progress</status>
+      <priority>Highest</priority>
+      <reporter username="alice">Alice</reporter>
+      <assignee username="-1">Unassigned</assignee>
+      <labels><label>bug</label><label>bug</label></labels>
+      <created>Mon, 6 Jan 2025 10:00:00 +0000</created>
+    </item>
+    <item>
+      <key id="10002">PROJ-2</key>
+      <parent id="10001">PROJ-1</parent>
+      <summary>Write a regression test</summary>
+      <status>Done</status>
+      <resolution>Won't Fix</resolution>
+      <priority>Sometimes</priority>
+      <reporter username="carol">Carol</reporter>
+      <comments>
+        <comment author="alice" created="Tue, 7 Jan 2025 09:30:00 +0000">&lt;p&gt;Not needed&lt;/p&gt;</comment>
+      </comments>
+    </item>
+    <item>
+      <key id="10003">PROJ-3</key>
+      <summary>Custom workflow</summary>
+      <status>Waiting for Design</status>
+      <statusCategory id="4" key="indeterminate"/>
+    </item>
+  </channel>
+</rss>"#;
+
+    const JIRA_CSV: &str = "\
+Issue key,Issue id,Parent id,Summary,Status,Priority,Reporter,Assignee,Labels,Labels,Comment,Due Date
+PROJ-1,10001,,Parent task,Selected for Development,Major,alice,alice,api,ui,06/Jan/25 10:00 AM;bob;h2. Looks good,not a date
+PROJ-2,10002,10001,Sub-task,Somewhere Else,Minor,alice,dave,,,,
+";
+
+    const LINEAR_CSV: &str = "\
+ID,Title,Status,Priority,Creator,Assignee,Labels,Parent issue,Created,Started,Completed,Canceled
+ENG-1,Ship it,Ready to Ship,Urgent,alice,,\"backend, infra\",,2025-01-06 10:00:00,2025-01-07 10:00:00,2025-01-08 10:00:00,
+ENG-2,Follow up,Triage,No priority,alice,,,ENG-9,,,,
+ENG-3,Loop a,Todo,High,alice,,,ENG-4,,,,
+ENG-4,Loop b,Todo,High,alice,,,ENG-3,,,,
+";
+
+    fn convert(external: Vec<ExternalIssue>) -> (Vec<ImportedFileIssue>, Vec<UnmappedValue>) {
+        let people = HashMap::from([("alice".to_string(), Uuid::new_v4())]);
+        let mut report = ImportReport::default();
+        let issues = convert_issues(external, &people, Uuid::new_v4(), "Test", &mut report);
+        (issues, report.into_values())
+    }
+
+    fn unmapped(report: &[UnmappedValue], kind: UnmappedKind, value: &str) -> Option<usize> {
+        report
+            .iter()
+            .find(|unmapped| unmapped.kind == kind && unmapped.value == value)
+            .map(|unmapped| unmapped.occurrences)
+    }
+
+    #[test]
+    fn jira_xml_maps_statuses_and_priorities() {
+        let (issues, report) = convert(jira_xml_issues(JIRA_XML).unwrap());
+
+        assert_eq!(issues.len(), 3);
+        assert!(matches!(issues[0].status, IssueStatus::InProgress));
+        assert!(matches!(issues[0].priority, IssuePriority::Urgent));
+        assert_eq!(issues[0].labels.len(), 1);
+        assert!(issues[0].assignee_ids.is_empty());
+        // done with a won't fix resolution
+        assert!(matches!(issues[1].status, IssueStatus::Canceled));
+        assert!(matches!(issues[1].priority, IssuePriority::Low));
+        // an unknown status falls back to its category
+        assert!(matches!(issues[2].status, IssueStatus::InProgress));
+
+        assert_eq!(
+            unmapped(&report, UnmappedKind::Priority, "Sometimes"),
+            Some(1)
+        );
+        assert_eq!(unmapped(&report, UnmappedKind::User, "carol"), Some(1));
+        assert_eq!(
+            unmapped(&report, UnmappedKind::Status, "Waiting for Design"),
+            None
+        );
+    }
+
+    #[test]
+    fn jira_xml_links_sub_issues_by_key() {
+        let (issues, _) = convert(jira_xml_issues(JIRA_XML).unwrap());
+
+        assert_eq!(issues[0].parent, None);
+        assert_eq!(issues[1].parent, Some(0));
+        assert_eq!(issues[1].comments.len(), 1);
+    }
+
+    #[test]
+    fn jira_csv_links_sub_tasks_by_id_and_reports_unmapped_values() {
+        let (issues, report) = convert(jira_csv_issues(JIRA_CSV).unwrap());
+
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[1].parent, Some(0));
+        assert!(matches!(issues[0].status, IssueStatus::Todo));
+        assert!(matches!(issues[0].priority, IssuePriority::High));
+        assert_eq!(issues[0].labels.len(), 2);
+        assert_eq!(issues[0].assignee_ids.len(), 1);
+        assert_eq!(issues[0].due_date, None);
+        assert!(matches!(issues[1].status, IssueStatus::Backlog));
+        assert!(matches!(issues[1].priority, IssuePriority::Low));
+
+        assert_eq!(
+            unmapped(&report, UnmappedKind::Status, "Somewhere Else"),
+            Some(1)
+        );
+        assert_eq!(unmapped(&report, UnmappedKind::Assignee, "dave"), Some(1));
+        assert_eq!(unmapped(&report, UnmappedKind::User, "bob"), Some(1));
+        assert_eq!(unmapped(&report, UnmappedKind::Date, "not a date"), Some(1));
+    }
+
+    #[test]
+    fn linear_csv_uses_timestamps_for_custom_statuses() {
+        let (issues, report) = convert(linear_csv_issues(LINEAR_CSV).unwrap());
+
+        assert!(matches!(issues[0].status, IssueStatus::Done));
+        assert!(matches!(issues[0].priority, IssuePriority::Urgent));
+        assert_eq!(issues[0].labels.len(), 2);
+        assert!(matches!(issues[1].status, IssueStatus::Backlog));
+        assert!(matches!(issues[1].priority, IssuePriority::Low));
+        assert_eq!(
+            unmapped(&report, UnmappedKind::Status, "Ready to Ship"),
+            None
+        );
+    }
+
+    #[test]
+    fn linear_csv_drops_missing_and_looping_parents() {
+        let (issues, report) = convert(linear_csv_issues(LINEAR_CSV).unwrap());
+
+        assert_eq!(issues[1].parent, None);
+        assert_eq!(unmapped(&report, UnmappedKind::Parent, "ENG-9"), Some(1));
+        // only the link closing the loop is dropped
+        assert_eq!(issues[2].parent, None);
+        assert_eq!(issues[3].parent, Some(2));
+        assert_eq!(unmapped(&report, UnmappedKind::Parent, "ENG-4"), Some(1));
+    }
+
+    #[test]
+    fn malformed_files_are_rejected() {
+        assert!(jira_xml_issues("<rss><channel><item><key>PROJ-1</key>").is_err());
+        assert!(jira_xml_issues("not xml at all").is_err());
+        // the import refuses files without issues
+        assert!(linear_csv_issues("").unwrap().is_empty());
+        assert!(jira_xml_issues("<rss><channel></channel></rss>")
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn unclosed_csv_quotes_run_to_the_end_of_the_file() {
+        let issues =
+            jira_csv_issues("Issue key,Summary\n\"PROJ-1,Unclosed\nPROJ-2,Next\n").unwrap();
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].key, "PROJ-1,Unclosed\nPROJ-2,Next");
+        assert_eq!(issues[0].title, "");
+    }
+}
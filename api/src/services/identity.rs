@@ -0,0 +1,237 @@
+use rand::Rng;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    errors::CustomError,
+    models::{
+        auth::{AuthEvent, User},
+        context::ClientInfo,
+        identity::{CreateExternalUser, ExternalIdentity, UnlinkIdentityResult, UserIdentity},
+        user_preferences::UserPreferenceRequest,
+    },
+    repositories::{
+        auth_log::AuthLogRepository, identity::IdentityRepository, org::OrgRepository,
+        user::UserRepository, user_preferences::UserPreferencesRepository,
+    },
+};
+
+const MIN_USERNAME_LENGTH: usize = 3;
+const MAX_USERNAME_LENGTH: usize = 40;
+const USERNAME_ATTEMPTS: usize = 5;
+
+// maps accounts at external providers (github, oidc) onto local users
+pub struct IdentityService {
+    identity_repo: IdentityRepository,
+    user_repo: UserRepository,
+    user_preferences_repo: UserPreferencesRepository,
+    org_repo: OrgRepository,
+    auth_log_repo: AuthLogRepository,
+}
+
+impl IdentityService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            identity_repo: IdentityRepository::new(pool.clone()),
+            user_repo: UserRepository::new(pool.clone()),
+            user_preferences_repo: UserPreferencesRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool),
+        }
+    }
+
+    pub async fn list_identities(&self, user_id: Uuid) -> Result<Vec<UserIdentity>, CustomError> {
+        self.identity_repo
+            .list_user_identities(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn resolve_login_user(
+        &self,
+        external: ExternalIdentity,
+        client: &ClientInfo,
+    ) -> Result<User, CustomError> {
+        if let Some(identity) = self
+            .identity_repo
+            .find_identity(&external.provider, &external.subject)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+        {
+            return self
+                .user_repo
+                .get_user_by_id(identity.user_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()));
+        }
+
+        let email = external
+            .email
+            .clone()
+            .ok_or(CustomError::ExternalServiceError(
+                "Provider did not return an email address".to_string(),
+            ))?;
+
+        if let Ok(user) = self.user_repo.get_user_by_email(email.clone()).await {
+            // only when both sides proved ownership of the address, otherwise whoever
+            // registers an address first could take over the other account
+            if !(external.email_verified && user.is_email_verified) {
+                return Err(CustomError::Conflict(
+                    "An account with this email already exists, sign in and link this provider from your account settings".to_string(),
+                    "IDN_001".to_string(),
+                ));
+            }
+
+            self.link_identity(user.id, external, client).await?;
+            return Ok(user);
+        }
+
+        self.create_user(external, email).await
+    }
+
+    pub async fn link_identity(
+        &self,
+        user_id: Uuid,
+        external: ExternalIdentity,
+        client: &ClientInfo,
+    ) -> Result<UserIdentity, CustomError> {
+        let identity = self
+            .identity_repo
+            .link_identity(
+                user_id,
+                &external.provider,
+                &external.subject,
+                external.email,
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "This account is already linked to another user".to_string(),
+                "IDN_002".to_string(),
+            ))?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::IdentityLinked,
+                client,
+                Some(json!({ "provider": identity.provider })),
+            )
+            .await;
+
+        Ok(identity)
+    }
+
+    pub async fn unlink_identity(
+        &self,
+        user_id: Uuid,
+        identity_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let identity = match self
+            .identity_repo
+            .unlink_identity(user_id, identity_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+        {
+            UnlinkIdentityResult::Unlinked(identity) => identity,
+            UnlinkIdentityResult::NotFound => {
+                return Err(CustomError::NotFound("Identity".to_string()))
+            }
+            UnlinkIdentityResult::LastLoginMethod => {
+                return Err(CustomError::Conflict(
+                    "Cannot remove the last sign-in method of an account".to_string(),
+                    "IDN_003".to_string(),
+                ))
+            }
+        };
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::IdentityUnlinked,
+                client,
+                Some(json!({ "provider": identity.provider })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn create_user(
+        &self,
+        external: ExternalIdentity,
+        email: String,
+    ) -> Result<User, CustomError> {
+        let username = self.available_username(&external, &email).await?;
+
+        let user = self
+            .identity_repo
+            .create_user_with_identity(CreateExternalUser {
+                email,
+                username,
+                avatar_url: external.avatar_url,
+                is_email_verified: external.email_verified,
+                provider: external.provider,
+                subject: external.subject,
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.user_preferences_repo
+            .create_user_preference(UserPreferenceRequest { user_id: user.id })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        // the provider vouches for the address, so invites sent to it can be shown right away
+        if user.is_email_verified {
+            self.org_repo
+                .attach_invites(user.id, &user.email)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+        }
+
+        Ok(user)
+    }
+
+    async fn available_username(
+        &self,
+        external: &ExternalIdentity,
+        email: &str,
+    ) -> Result<String, CustomError> {
+        let source = external
+            .username
+            .as_deref()
+            .unwrap_or_else(|| email.split('@').next().unwrap_or_default());
+
+        let mut base: String = source
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+            .take(MAX_USERNAME_LENGTH)
+            .collect();
+        if base.len() < MIN_USERNAME_LENGTH {
+            base = format!("user{}", base);
+        }
+
+        let mut candidate = base.clone();
+        for _ in 0..USERNAME_ATTEMPTS {
+            let taken = self
+                .user_repo
+                .user_exists(email.to_string(), candidate.clone())
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+            if !taken {
+                return Ok(candidate);
+            }
+
+            candidate = format!("{}-{}", base, rand::thread_rng().gen_range(1000..10000));
+        }
+
+        Err(CustomError::Conflict(
+            "Could not generate a unique username".to_string(),
+            "IDN_004".to_string(),
+        ))
+    }
+}
@@ -0,0 +1,764 @@
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::Config,
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
+        github::{GithubComment, GithubIssue, GithubSearchResult, GithubUser},
+        import::{
+            CreateImportData, GithubImportRequest, ImportJob, ImportSource, ImportedComment,
+            ImportedIssue, ImportedLabel, OrgImport,
+        },
+        issue::IssueStatus,
+    },
+    repositories::{audit_log::AuditLogRepository, import::ImportRepository, team::TeamRepository},
+    utils::editor::markdown_document,
+};
+
+use super::oauth::OauthService;
+
+const GITHUB_PAGE_SIZE: usize = 100;
+const GITHUB_TIMEOUT_SECS: u64 = 30;
+// a running import records progress after every issue, one that stopped for this
+// long was cut off by a restart and is picked up again
+const STALE_IMPORT_SECS: i64 = 10 * 60;
+const ABANDONED_IMPORT_SECS: i64 = 7 * 24 * 60 * 60;
+
+pub struct ImportService {
+    pool: PgPool,
+    github_api_url: String,
+    import_repo: ImportRepository,
+    team_repo: TeamRepository,
+    audit_log_repo: AuditLogRepository,
+    oauth_service: Arc<OauthService>,
+}
+
+impl ImportService {
+    pub fn new(pool: PgPool, config: &Config, oauth_service: Arc<OauthService>) -> Self {
+        Self {
+            github_api_url: config.github_api_url.clone(),
+            import_repo: ImportRepository::new(pool.clone()),
+            team_repo: TeamRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool.clone()),
+            pool,
+            oauth_service,
+        }
+    }
+
+    // the callback of this authorization comes back to the import with its code and state
+    pub async fn authorize_github_import(
+        &self,
+        user_id: Uuid,
+        redirect_to: Option<String>,
+    ) -> Result<reqwest::Url, CustomError> {
+        self.oauth_service
+            .init_github_oauth(user_id, redirect_to)
+            .await
+    }
+
+    // the issues are copied in the background, clients poll the import for its progress
+    pub async fn start_github_import(
+        &self,
+        org_id: Uuid,
+        requested_by: Uuid,
+        data: GithubImportRequest,
+        client: &ClientInfo,
+    ) -> Result<OrgImport, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(team_id) = data.team_id {
+            self.team_repo
+                .find_team(org_id, team_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .ok_or(CustomError::NotFound("Team not found".to_string()))?;
+        }
+
+        let token = self
+            .oauth_service
+            .get_github_access_token(requested_by, data.code, &data.state)
+            .await?;
+
+        // checked up front so a typo fails the request instead of the job
+        let github = GithubClient::new(&self.github_api_url, &token.access_token)?;
+        github
+            .get::<JsonValue>(&format!("/repos/{}", data.repository), &[])
+            .await
+            .map_err(|e| match e {
+                GithubError::NotFound => CustomError::NotFound(
+                    "Repository not found or not accessible with this GitHub account".to_string(),
+                ),
+                e => CustomError::ExternalServiceError(e.message()),
+            })?;
+
+        let import = self
+            .import_repo
+            .create_import(CreateImportData {
+                org_id,
+                requested_by,
+                source: ImportSource::Github,
+                repository: data.repository,
+                team_id: data.team_id,
+                preserve_numbers: data.preserve_numbers.unwrap_or(false),
+                open_status: data.open_status.unwrap_or(IssueStatus::Todo),
+                closed_status: data.closed_status.unwrap_or(IssueStatus::Done),
+                access_token: token.access_token,
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(import_in_progress())?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(requested_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgImportStarted, Some(import.id)).after(json!({
+                    "source": import.source,
+                    "repository": import.repository,
+                    "team_id": import.team_id,
+                    "preserve_numbers": import.preserve_numbers,
+                })),
+            )
+            .await;
+
+        self.spawn_import(import.id);
+
+        Ok(import)
+    }
+
+    pub async fn list_imports(&self, org_id: Uuid) -> Result<Vec<OrgImport>, CustomError> {
+        self.import_repo
+            .list_imports(org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_import(
+        &self,
+        org_id: Uuid,
+        import_id: Uuid,
+    ) -> Result<OrgImport, CustomError> {
+        self.import_repo
+            .find_import(org_id, import_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Import not found".to_string()))
+    }
+
+    // continues after the last imported page, issues already imported are skipped
+    pub async fn resume_import(
+        &self,
+        org_id: Uuid,
+        import_id: Uuid,
+        resumed_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<OrgImport, CustomError> {
+        self.get_import(org_id, import_id).await?;
+
+        let import = match self.import_repo.resume_import(org_id, import_id).await {
+            Ok(import) => import,
+            Err(sqlx::Error::Database(db_err)) if db_err.is_unique_violation() => {
+                return Err(import_in_progress())
+            }
+            Err(e) => return Err(CustomError::DatabaseError(e.to_string())),
+        }
+        .ok_or(CustomError::Conflict(
+            "Only a failed import that has not expired can be resumed".to_string(),
+            "ORG_021".to_string(),
+        ))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(resumed_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgImportResumed, Some(import.id)).after(json!({
+                    "imported_issues": import.imported_issues,
+                })),
+            )
+            .await;
+
+        self.spawn_import(import.id);
+
+        Ok(import)
+    }
+
+    pub async fn resume_stale_imports(&self) -> Result<(), CustomError> {
+        let stale = self
+            .import_repo
+            .requeue_stale_imports(STALE_IMPORT_SECS)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        for import_id in stale {
+            log::info!("Resuming interrupted import {}", import_id);
+            self.spawn_import(import_id);
+        }
+
+        let forgotten = self
+            .import_repo
+            .forget_abandoned_imports(ABANDONED_IMPORT_SECS)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if forgotten > 0 {
+            log::info!(
+                "Dropped the access tokens of {} abandoned imports",
+                forgotten
+            );
+        }
+
+        Ok(())
+    }
+
+    fn spawn_import(&self, import_id: Uuid) {
+        actix_web::rt::spawn(run_import(
+            ImportRepository::new(self.pool.clone()),
+            self.github_api_url.clone(),
+            import_id,
+        ));
+    }
+}
+
+fn import_in_progress() -> CustomError {
+    CustomError::Conflict(
+        "An import of this repository is already in progress".to_string(),
+        "ORG_020".to_string(),
+    )
+}
+
+async fn run_import(import_repo: ImportRepository, github_api_url: String, import_id: Uuid) {
+    let job = match import_repo.claim_import(import_id).await {
+        Ok(Some(job)) => job,
+        Ok(None) => return,
+        Err(e) => {
+            log::error!("Failed to start import {}: {}", import_id, e);
+            return;
+        }
+    };
+
+    let result = match import_github_issues(&import_repo, &github_api_url, &job).await {
+        Ok(()) => import_repo.complete_import(import_id).await,
+        Err(e) => {
+            log::error!("Import {} failed: {}", import_id, e);
+            import_repo.fail_import(import_id, &e).await
+        }
+    };
+
+    if let Err(e) = result {
+        log::error!("Failed to store the result of import {}: {}", import_id, e);
+    }
+}
+
+// pages are walked oldest first so issues opened during the import only add pages
+async fn import_github_issues(
+    import_repo: &ImportRepository,
+    github_api_url: &str,
+    job: &ImportJob,
+) -> Result<(), String> {
+    let access_token = job
+        .access_token
+        .as_deref()
+        .ok_or("The GitHub authorization of this import expired, start a new import")?;
+    let requested_by = job
+        .requested_by
+        .ok_or("The member who started this import no longer exists")?;
+    let github = GithubClient::new(github_api_url, access_token).map_err(|e| e.to_string())?;
+
+    let count = github
+        .get::<GithubSearchResult>(
+            "/search/issues",
+            &[
+                ("q", format!("repo:{} is:issue", job.repository)),
+                ("per_page", "1".to_string()),
+            ],
+        )
+        .await;
+    match count {
+        Ok(count) => import_repo
+            .set_total_issues(job.id, count.total_count as i32)
+            .await
+            .map_err(|e| e.to_string())?,
+        Err(e) => log::warn!(
+            "Could not count the issues of {}: {}",
+            job.repository,
+            e.message()
+        ),
+    }
+
+    let mut page = job.next_page;
+    loop {
+        let issues = github
+            .get::<Vec<GithubIssue>>(
+                &format!("/repos/{}/issues", job.repository),
+                &[
+                    ("state", "all".to_string()),
+                    ("sort", "created".to_string()),
+                    ("direction", "asc".to_string()),
+                    ("per_page", GITHUB_PAGE_SIZE.to_string()),
+                    ("page", page.to_string()),
+                ],
+            )
+            .await
+            .map_err(|e| e.message())?;
+        let last_page = issues.len() < GITHUB_PAGE_SIZE;
+
+        for issue in issues {
+            if issue.pull_request.is_some() {
+                continue;
+            }
+
+            let imported = import_repo
+                .is_imported(job.id, issue.id)
+                .await
+                .map_err(|e| e.to_string())?;
+            if imported {
+                continue;
+            }
+
+            let comments = if issue.comments > 0 {
+                fetch_comments(&github, &job.repository, issue.number).await?
+            } else {
+                Vec::new()
+            };
+
+            let issue = github_issue(import_repo, job, requested_by, issue, comments).await?;
+            import_repo
+                .import_issue(job.id, job.org_id, job.team_id, issue)
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+
+        page += 1;
+        import_repo
+            .advance_page(job.id, page)
+            .await
+            .map_err(|e| e.to_string())?;
+
+        if last_page {
+            return Ok(());
+        }
+    }
+}
+
+async fn fetch_comments(
+    github: &GithubClient,
+    repository: &str,
+    number: i32,
+) -> Result<Vec<GithubComment>, String> {
+    let mut comments = Vec::new();
+    let mut page = 1;
+
+    loop {
+        let batch = github
+            .get::<Vec<GithubComment>>(
+                &format!("/repos/{}/issues/{}/comments", repository, number),
+                &[
+                    ("per_page", GITHUB_PAGE_SIZE.to_string()),
+                    ("page", page.to_string()),
+                ],
+            )
+            .await
+            .map_err(|e| e.message())?;
+        let last_page = batch.len() < GITHUB_PAGE_SIZE;
+        comments.extend(batch);
+
+        if last_page {
+            return Ok(comments);
+        }
+        page += 1;
+    }
+}
+
+// authors and assignees are matched to org members by their github account, content
+// of anyone without a match is attributed to them in the text and owned by the importer
+async fn github_issue(
+    import_repo: &ImportRepository,
+    job: &ImportJob,
+    requested_by: Uuid,
+    issue: GithubIssue,
+    comments: Vec<GithubComment>,
+) -> Result<ImportedIssue, String> {
+    let github_ids: Vec<String> = issue
+        .user
+        .iter()
+        .chain(issue.assignees.iter())
+        .chain(comments.iter().filter_map(|comment| comment.user.as_ref()))
+        .map(|user| user.id.to_string())
+        .collect();
+    let members: HashMap<String, Uuid> = import_repo
+        .find_members_by_github_ids(job.org_id, &github_ids)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .collect();
+    let member = |user: Option<&GithubUser>| {
+        user.and_then(|user| members.get(&user.id.to_string()).copied())
+    };
+
+    let status = match (issue.state.as_str(), issue.state_reason.as_deref()) {
+        ("open", _) => job.open_status.clone(),
+        (_, Some("not_planned")) => IssueStatus::Canceled,
+        _ => job.closed_status.clone(),
+    };
+
+    let creator = member(issue.user.as_ref());
+    let description = attributed_text(issue.body, issue.user.as_ref(), creator.is_some());
+
+    Ok(ImportedIssue {
+        external_id: issue.id,
+        number: job.preserve_numbers.then_some(issue.number),
+        title: issue.title,
+        description: (!description.trim().is_empty()).then(|| markdown_document(&description)),
+        status,
+        creator_id: creator.unwrap_or(requested_by),
+        created_at: issue.created_at,
+        labels: issue
+            .labels
+            .into_iter()
+            .map(|label| ImportedLabel {
+                name: label.name,
+                color: label.color.map(|color| format!("#{}", color)),
+                description: label
+                    .description
+                    .filter(|description| !description.is_empty()),
+            })
+            .collect(),
+        assignee_ids: issue
+            .assignees
+            .iter()
+            .filter_map(|assignee| member(Some(assignee)))
+            .collect(),
+        comments: comments
+            .into_iter()
+            .map(|comment| {
+                let creator = member(comment.user.as_ref());
+                let content =
+                    attributed_text(comment.body, comment.user.as_ref(), creator.is_some());
+                ImportedComment {
+                    creator_id: creator.unwrap_or(requested_by),
+                    content: markdown_document(&content),
+                    created_at: comment.created_at,
+                }
+            })
+            .collect(),
+    })
+}
+
+fn attributed_text(body: Option<String>, author: Option<&GithubUser>, matched: bool) -> String {
+    let body = body.unwrap_or_default();
+    match author {
+        Some(author) if !matched => {
+            format!(
+                "Originally posted by @{} on GitHub\n\n{}",
+                author.login, body
+            )
+        }
+        _ => body,
+    }
+}
+
+enum GithubError {
+    NotFound,
+    RateLimited,
+    Failed(String),
+}
+
+impl GithubError {
+    fn message(self) -> String {
+        match self {
+            GithubError::NotFound => "The repository could not be found on GitHub".to_string(),
+            GithubError::RateLimited => {
+                "GitHub rate limit reached, resume the import once it resets".to_string()
+            }
+            GithubError::Failed(message) => message,
+        }
+    }
+}
+
+struct GithubClient {
+    http: reqwest::Client,
+    api_url: String,
+    access_token: String,
+}
+
+impl GithubClient {
+    fn new(api_url: &str, access_token: &str) -> Result<Self, CustomError> {
+        let http = reqwest::Client::builder()
+            .timeout(Duration::from_secs(GITHUB_TIMEOUT_SECS))
+            .build()
+            .map_err(|e| CustomError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            http,
+            api_url: api_url.to_string(),
+            access_token: access_token.to_string(),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+    ) -> Result<T, GithubError> {
+        let res = self
+            .http
+            .get(format!("{}{}", self.api_url, path))
+            .header("User-Agent", "rust-github-oauth")
+            .header("Accept", "application/vnd.github+json")
+            .bearer_auth(&self.access_token)
+            .query(query)
+            .send()
+            .await
+            .map_err(|e| GithubError::Failed(format!("GitHub could not be reached: {}", e)))?;
+
+        let status = res.status();
+        let rate_limited = res
+            .headers()
+            .get("x-ratelimit-remaining")
+            .is_some_and(|remaining| remaining == "0");
+
+        match status {
+            StatusCode::NOT_FOUND => return Err(GithubError::NotFound),
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS if rate_limited => {
+                return Err(GithubError::RateLimited)
+            }
+            status if !status.is_success() => {
+                return Err(GithubError::Failed(format!(
+                    "GitHub responded with {} for {}",
+                    status, path
+                )))
+            }
+            _ => {}
+        }
+
+        res.json::<T>().await.map_err(|e| {
+            GithubError::Failed(format!(
+                "Unexpected response from GitHub for {}: {}",
+                path, e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::{
+        matchers::{header, method, path, query_param},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+
+    const REPOSITORY: &str = "acme/widgets";
+    const ACCESS_TOKEN: &str = "gho_test";
+
+    async fn claimed_import(repo: &ImportRepository, pool: &PgPool) -> ImportJob {
+        let user_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO users (email, username) VALUES ('importer@example.com', 'importer') RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+        let org_id: Uuid = sqlx::query_scalar(
+            "INSERT INTO org (name, slug, custom_id) VALUES ('Acme', 'acme', 'ACM') RETURNING id",
+        )
+        .fetch_one(pool)
+        .await
+        .unwrap();
+
+        let import = repo
+            .create_import(CreateImportData {
+                org_id,
+                requested_by: user_id,
+                source: ImportSource::Github,
+                repository: REPOSITORY.to_string(),
+                team_id: None,
+                preserve_numbers: false,
+                open_status: IssueStatus::Todo,
+                closed_status: IssueStatus::Done,
+                access_token: ACCESS_TOKEN.to_string(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
+
+        repo.claim_import(import.id).await.unwrap().unwrap()
+    }
+
+    fn github_issue(number: i32, comments: i32) -> JsonValue {
+        json!({
+            "id": 1000 + number,
+            "number": number,
+            "title": format!("Issue {}", number),
+            "body": "Imported from GitHub",
+            "state": "open",
+            "state_reason": null,
+            "user": { "id": 1, "login": "octocat" },
+            "labels": [],
+            "assignees": [],
+            "comments": comments,
+            "pull_request": null,
+            "created_at": "2025-01-06T10:00:00Z",
+        })
+    }
+
+    async fn mount_search(server: &MockServer, total_count: i64) {
+        Mock::given(method("GET"))
+            .and(path("/search/issues"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "total_count": total_count })),
+            )
+            .mount(server)
+            .await;
+    }
+
+    async fn mount_page(server: &MockServer, page: i32, issues: Vec<JsonValue>, requests: u64) {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{}/issues", REPOSITORY)))
+            .and(query_param("page", page.to_string()))
+            .and(header("authorization", format!("Bearer {}", ACCESS_TOKEN)))
+            .respond_with(ResponseTemplate::new(200).set_body_json(issues))
+            .expect(requests)
+            .mount(server)
+            .await;
+    }
+
+    // imported issues and comments and the page the import continues from
+    async fn progress(pool: &PgPool, import_id: Uuid) -> (i32, i32, i32) {
+        sqlx::query_as(
+            "SELECT imported_issues, imported_comments, next_page FROM org_imports WHERE id = $1",
+        )
+        .bind(import_id)
+        .fetch_one(pool)
+        .await
+        .unwrap()
+    }
+
+    async fn issue_count(pool: &PgPool, org_id: Uuid) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM issues WHERE org_id = $1")
+            .bind(org_id)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn walks_pages_until_a_short_one(pool: PgPool) {
+        let repo = ImportRepository::new(pool.clone());
+        let job = claimed_import(&repo, &pool).await;
+        let server = MockServer::start().await;
+
+        let mut first_page: Vec<JsonValue> = (1..=100).map(|n| github_issue(n, 0)).collect();
+        first_page[4]["pull_request"] = json!({ "url": "https://github.com/acme/widgets/pull/5" });
+        mount_search(&server, 101).await;
+        mount_page(&server, 1, first_page, 1).await;
+        mount_page(&server, 2, vec![github_issue(101, 0)], 1).await;
+        mount_page(&server, 3, Vec::new(), 0).await;
+
+        import_github_issues(&repo, &server.uri(), &job)
+            .await
+            .unwrap();
+
+        // the pull request is skipped
+        assert_eq!(progress(&pool, job.id).await, (100, 0, 3));
+        assert_eq!(issue_count(&pool, job.org_id).await, 100);
+    }
+
+    #[sqlx::test]
+    async fn resumes_from_the_page_it_stopped_at(pool: PgPool) {
+        let repo = ImportRepository::new(pool.clone());
+        let job = claimed_import(&repo, &pool).await;
+        let server = MockServer::start().await;
+
+        mount_search(&server, 102).await;
+        mount_page(
+            &server,
+            1,
+            (1..=100).map(|n| github_issue(n, 0)).collect(),
+            1,
+        )
+        .await;
+        mount_page(
+            &server,
+            2,
+            vec![github_issue(101, 0), github_issue(102, 1)],
+            2,
+        )
+        .await;
+
+        // the comments of the last issue fail once, after the one before it was imported
+        let comments_path = format!("/repos/{}/issues/102/comments", REPOSITORY);
+        Mock::given(method("GET"))
+            .and(path(comments_path.clone()))
+            .respond_with(ResponseTemplate::new(502))
+            .up_to_n_times(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path(comments_path))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([{
+                "id": 1,
+                "body": "A comment",
+                "user": { "id": 1, "login": "octocat" },
+                "created_at": "2025-01-07T10:00:00Z",
+            }])))
+            .mount(&server)
+            .await;
+
+        let error = import_github_issues(&repo, &server.uri(), &job)
+            .await
+            .unwrap_err();
+        assert!(error.contains("502"), "{}", error);
+        assert_eq!(progress(&pool, job.id).await, (101, 0, 2));
+
+        repo.fail_import(job.id, &error).await.unwrap();
+        repo.resume_import(job.org_id, job.id)
+            .await
+            .unwrap()
+            .unwrap();
+        let job = repo.claim_import(job.id).await.unwrap().unwrap();
+        assert_eq!(job.next_page, 2);
+
+        import_github_issues(&repo, &server.uri(), &job)
+            .await
+            .unwrap();
+
+        // issue 101 was imported before the failure and is not imported again
+        assert_eq!(progress(&pool, job.id).await, (102, 1, 3));
+        assert_eq!(issue_count(&pool, job.org_id).await, 102);
+    }
+
+    #[sqlx::test]
+    async fn rate_limit_keeps_the_page(pool: PgPool) {
+        let repo = ImportRepository::new(pool.clone());
+        let job = claimed_import(&repo, &pool).await;
+        let server = MockServer::start().await;
+
+        mount_search(&server, 1).await;
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{}/issues", REPOSITORY)))
+            .respond_with(ResponseTemplate::new(403).insert_header("x-ratelimit-remaining", "0"))
+            .mount(&server)
+            .await;
+
+        let error = import_github_issues(&repo, &server.uri(), &job)
+            .await
+            .unwrap_err();
+
+        assert_eq!(error, GithubError::RateLimited.message());
+        assert_eq!(progress(&pool, job.id).await, (0, 0, 1));
+    }
+}
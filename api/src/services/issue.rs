@@ -1,3 +1,4 @@
+use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
@@ -5,16 +6,25 @@ use validator::Validate;
 use crate::{
     errors::CustomError,
     models::{
+        audit_log::{AuditEntry, AuditEvent},
         comment::CommentResponse,
-        issue::{IssueRequest, IssueResponse, UpdateIssueRequest},
+        context::ClientInfo,
+        issue::{Issue, IssueListQuery, IssueRequest, IssueResponse, Label, UpdateIssueRequest},
+        webhook::WebhookEvent,
+    },
+    repositories::{
+        audit_log::AuditLogRepository, comment::CommentRepository, issue::IssueRepository,
+        team::TeamRepository, user::UserRepository, webhook::WebhookRepository,
     },
-    repositories::{comment::CommentRepository, issue::IssueRepository, user::UserRepository},
 };
 
 pub struct IssueService {
     issue_repo: IssueRepository,
     comment_repo: CommentRepository,
     user_repo: UserRepository,
+    team_repo: TeamRepository,
+    audit_log_repo: AuditLogRepository,
+    webhook_repo: WebhookRepository,
 }
 
 impl IssueService {
@@ -22,6 +32,9 @@ impl IssueService {
         Self {
             issue_repo: IssueRepository::new(pool.clone()),
             user_repo: UserRepository::new(pool.clone()),
+            team_repo: TeamRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool.clone()),
+            webhook_repo: WebhookRepository::new(pool.clone()),
             comment_repo: CommentRepository::new(pool),
         }
     }
@@ -36,18 +49,32 @@ impl IssueService {
             return Err(CustomError::ValidationError(validation_errors));
         }
 
+        if let Some(team_id) = data.team_id {
+            self.ensure_team(org_id, team_id).await?;
+        }
+
         let issue = self
             .issue_repo
             .create_issue(data, org_id, creator_id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
-        return Ok(IssueResponse {
-            issue_id: issue.id.clone(),
+        self.webhook_repo
+            .queue_event(
+                org_id,
+                WebhookEvent::IssueCreated,
+                json!({ "issue": issue }),
+            )
+            .await;
+
+        Ok(IssueResponse {
+            issue_id: issue.id,
             issue,
             sub_issues: None,
             comments: None,
-        });
+            labels: None,
+            assignee_ids: None,
+        })
     }
 
     pub async fn get_issue(&self, id: Uuid) -> Result<IssueResponse, CustomError> {
@@ -70,7 +97,7 @@ impl IssueService {
 
         let comments = self
             .comment_repo
-            .get_comments_by_owner_id(issue.id.clone())
+            .get_comments_by_owner_id(issue.id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
@@ -89,25 +116,103 @@ impl IssueService {
             });
         }
 
+        let (labels, assignee_ids) = self.get_labels_and_assignees(id).await?;
+
         Ok(IssueResponse {
             issue_id: id,
             issue,
             sub_issues: Some(sub_issues),
             comments: Some(comments_response),
+            labels: Some(labels),
+            assignee_ids: Some(assignee_ids),
         })
     }
 
-    pub async fn delete_issue(&self, id: Uuid) -> Result<(), CustomError> {
-        self.issue_repo.delete_issue(id).await.map_err(|e| match e {
-            sqlx::Error::RowNotFound => {
-                CustomError::NotFound(format!("Issue with id: {} not found", id))
-            }
-            _ => CustomError::DatabaseError(e.to_string()),
-        })
+    // keys look like ENG-123, the prefix is matched case-insensitively
+    pub async fn get_issue_by_key(
+        &self,
+        org_id: Uuid,
+        key: &str,
+    ) -> Result<IssueResponse, CustomError> {
+        let (prefix, number) = key.rsplit_once('-').ok_or(CustomError::BadRequest)?;
+        let number = number.parse::<i32>().map_err(|_| CustomError::BadRequest)?;
+
+        let issue = self
+            .issue_repo
+            .find_issue_by_key(org_id, &prefix.to_uppercase(), number)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound(format!(
+                "Issue with key: {} not found",
+                key
+            )))?;
+
+        self.get_issue(issue.id).await
+    }
+
+    // authors may always delete their own issues
+    pub async fn delete_issue(
+        &self,
+        org_id: Uuid,
+        id: Uuid,
+        user_id: Uuid,
+        can_delete_any: bool,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let issue = self.find_issue(org_id, id).await?;
+
+        if issue.creator_id != user_id && !can_delete_any {
+            return Err(CustomError::Forbidden);
+        }
+
+        self.issue_repo
+            .delete_issue(id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => {
+                    CustomError::NotFound(format!("Issue with id: {} not found", id))
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(user_id),
+                client,
+                AuditEntry::new(AuditEvent::IssueDeleted, Some(issue.id)).before(json!({
+                    "number": issue.number,
+                    "team_id": issue.team_id,
+                    "title": issue.title,
+                    "creator_id": issue.creator_id,
+                    "status": issue.status,
+                    "priority": issue.priority,
+                })),
+            )
+            .await;
+
+        self.webhook_repo
+            .queue_event(
+                org_id,
+                WebhookEvent::IssueDeleted,
+                json!({
+                    "issue": {
+                        "id": issue.id,
+                        "number": issue.number,
+                        "team_id": issue.team_id,
+                        "title": issue.title,
+                    },
+                    "deleted_by": user_id,
+                }),
+            )
+            .await;
+
+        Ok(())
     }
 
     pub async fn update_issue(
         &self,
+        org_id: Uuid,
         id: Uuid,
         update_data: UpdateIssueRequest,
     ) -> Result<IssueResponse, CustomError> {
@@ -115,6 +220,12 @@ impl IssueService {
             return Err(CustomError::ValidationError(validation_errors));
         }
 
+        self.find_issue(org_id, id).await?;
+
+        if let Some(team_id) = update_data.team_id {
+            self.ensure_team(org_id, team_id).await?;
+        }
+
         let issue = self
             .issue_repo
             .update_issue(id, update_data)
@@ -134,7 +245,7 @@ impl IssueService {
 
         let comments = self
             .comment_repo
-            .get_comments_by_owner_id(issue.id.clone())
+            .get_comments_by_owner_id(issue.id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
@@ -153,18 +264,38 @@ impl IssueService {
             });
         }
 
+        let (labels, assignee_ids) = self.get_labels_and_assignees(id).await?;
+
+        self.webhook_repo
+            .queue_event(
+                org_id,
+                WebhookEvent::IssueUpdated,
+                json!({
+                    "issue": issue,
+                    "labels": labels,
+                    "assignee_ids": assignee_ids,
+                }),
+            )
+            .await;
+
         Ok(IssueResponse {
             issue_id: id,
             issue,
             sub_issues: Some(sub_issues),
             comments: Some(comments_response),
+            labels: Some(labels),
+            assignee_ids: Some(assignee_ids),
         })
     }
 
-    pub async fn get_all_by_org_id(&self, org_id: Uuid) -> Result<Vec<IssueResponse>, CustomError> {
+    pub async fn get_all_by_org_id(
+        &self,
+        org_id: Uuid,
+        filter: IssueListQuery,
+    ) -> Result<Vec<IssueResponse>, CustomError> {
         let issues = self
             .issue_repo
-            .get_all_issues_by_org_id(org_id)
+            .get_all_issues_by_org_id(org_id, &filter)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()));
 
@@ -173,13 +304,13 @@ impl IssueService {
         for issue in issues? {
             let sub_issues = self
                 .issue_repo
-                .get_sub_issues(issue.id.clone())
+                .get_sub_issues(issue.id)
                 .await
                 .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
             let comments = self
                 .comment_repo
-                .get_comments_by_owner_id(issue.id.clone())
+                .get_comments_by_owner_id(issue.id)
                 .await
                 .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
@@ -198,14 +329,71 @@ impl IssueService {
                 });
             }
 
+            let (labels, assignee_ids) = self.get_labels_and_assignees(issue.id).await?;
+
             issue_responses.push(IssueResponse {
-                issue_id: issue.id.clone(),
+                issue_id: issue.id,
                 issue,
                 comments: Some(comments_response),
                 sub_issues: Some(sub_issues),
+                labels: Some(labels),
+                assignee_ids: Some(assignee_ids),
             });
         }
 
         Ok(issue_responses)
     }
+
+    // issues of other orgs are treated as missing
+    async fn find_issue(&self, org_id: Uuid, id: Uuid) -> Result<Issue, CustomError> {
+        let issue = self
+            .issue_repo
+            .get_issue_by_id(id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => {
+                    CustomError::NotFound(format!("Issue with id: {} not found", id))
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        if issue.org_id != org_id {
+            return Err(CustomError::NotFound(format!(
+                "Issue with id: {} not found",
+                id
+            )));
+        }
+
+        Ok(issue)
+    }
+
+    // teams of other orgs are treated as missing
+    async fn get_labels_and_assignees(
+        &self,
+        issue_id: Uuid,
+    ) -> Result<(Vec<Label>, Vec<Uuid>), CustomError> {
+        let labels = self
+            .issue_repo
+            .get_issue_labels(issue_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let assignee_ids = self
+            .issue_repo
+            .get_issue_assignee_ids(issue_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok((labels, assignee_ids))
+    }
+
+    async fn ensure_team(&self, org_id: Uuid, team_id: Uuid) -> Result<(), CustomError> {
+        self.team_repo
+            .find_team(org_id, team_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Team not found".to_string()))?;
+
+        Ok(())
+    }
 }
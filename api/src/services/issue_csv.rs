@@ -0,0 +1,663 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use csv::StringRecord;
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::{
+    errors::CustomError,
+    models::{
+        issue::{normalized_name, IssueListQuery, IssuePriority, IssueStatus},
+        issue_csv::{
+            CsvColumnMapping, CsvColumnsRequest, CsvColumnsResponse, CsvImportRequest,
+            CsvImportResponse, CsvIssueRow, CsvParent, IssueCsvRecord,
+        },
+    },
+    repositories::{issue::IssueRepository, org::OrgRepository, team::TeamRepository},
+    utils::editor::{document_markdown, markdown_document},
+};
+
+// every row is validated before anything is written, so the whole file is held in memory
+const MAX_IMPORT_ROWS: usize = 5000;
+
+pub struct IssueCsvService {
+    issue_repo: IssueRepository,
+    org_repo: OrgRepository,
+    team_repo: TeamRepository,
+}
+
+// column indexes of the mapped fields
+struct CsvColumns {
+    title: usize,
+    description: Option<usize>,
+    status: Option<usize>,
+    priority: Option<usize>,
+    due_date: Option<usize>,
+    parent: Option<usize>,
+    number: Option<usize>,
+    creator: Option<usize>,
+}
+
+// what the rows of a file are resolved against
+struct CsvLookups {
+    importer_id: Uuid,
+    // number column of the file to row index
+    file_numbers: HashMap<i32, usize>,
+    duplicate_numbers: HashSet<i32>,
+    existing_numbers: HashMap<i32, Uuid>,
+    members: HashMap<String, Uuid>,
+}
+
+impl IssueCsvService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            issue_repo: IssueRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            team_repo: TeamRepository::new(pool),
+        }
+    }
+
+    pub async fn export_issues(
+        &self,
+        org_id: Uuid,
+        filter: IssueListQuery,
+    ) -> Result<String, CustomError> {
+        let records = self
+            .issue_repo
+            .export_issues(org_id, &filter)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer
+            .write_record(IssueCsvRecord::HEADERS)
+            .map_err(|_| CustomError::InternalServerError)?;
+
+        for record in records {
+            writer
+                .write_record([
+                    record.key,
+                    record.number.to_string(),
+                    escape_formula(record.team.unwrap_or_default()),
+                    escape_formula(record.title),
+                    escape_formula(
+                        record
+                            .description
+                            .as_ref()
+                            .map(document_markdown)
+                            .unwrap_or_default(),
+                    ),
+                    variant_name(&record.status),
+                    variant_name(&record.priority),
+                    record
+                        .due_date
+                        .map(|due_date| due_date.to_rfc3339())
+                        .unwrap_or_default(),
+                    record
+                        .parent
+                        .map(|parent| parent.to_string())
+                        .unwrap_or_default(),
+                    escape_formula(record.creator),
+                    escape_formula(record.assignees.join(", ")),
+                    escape_formula(record.labels.join(", ")),
+                    record.created_at.to_rfc3339(),
+                    record.updated_at.to_rfc3339(),
+                ])
+                .map_err(|_| CustomError::InternalServerError)?;
+        }
+
+        let export = writer
+            .into_inner()
+            .map_err(|_| CustomError::InternalServerError)?;
+        String::from_utf8(export).map_err(|_| CustomError::InternalServerError)
+    }
+
+    // first step of an import, the client shows the columns to map with a guess for each field
+    pub fn csv_columns(&self, data: CsvColumnsRequest) -> Result<CsvColumnsResponse, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let (columns, records) = read_csv(&data.csv)?;
+        let suggest = |names: &[&str]| {
+            columns
+                .iter()
+                .find(|column| names.contains(&normalized_name(column).as_str()))
+                .cloned()
+        };
+
+        let suggested_mapping = CsvColumnMapping {
+            title: suggest(&["title", "summary", "name"]),
+            description: suggest(&["description", "body", "details"]),
+            status: suggest(&["status", "state"]),
+            priority: suggest(&["priority"]),
+            due_date: suggest(&["duedate", "due"]),
+            parent: suggest(&["parent", "parentnumber"]),
+            number: suggest(&["number", "issuenumber", "id"]),
+            creator: suggest(&["creator", "reporter", "author", "createdby"]),
+        };
+
+        Ok(CsvColumnsResponse {
+            columns,
+            suggested_mapping,
+            rows: records.len(),
+        })
+    }
+
+    // all rows are checked first, a single invalid row fails the import with the
+    // errors of every row keyed by their line in the file
+    pub async fn import_issues(
+        &self,
+        org_id: Uuid,
+        importer_id: Uuid,
+        data: CsvImportRequest,
+    ) -> Result<CsvImportResponse, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(team_id) = data.team_id {
+            self.team_repo
+                .find_team(org_id, team_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .ok_or(CustomError::NotFound("Team not found".to_string()))?;
+        }
+
+        let (headers, records) = read_csv(&data.csv)?;
+        let columns = map_columns(&headers, &data.mapping)?;
+
+        if records.len() > MAX_IMPORT_ROWS {
+            return Err(csv_error(format!(
+                "The file has more than {} rows",
+                MAX_IMPORT_ROWS
+            )));
+        }
+
+        let lookups = self
+            .csv_lookups(org_id, data.team_id, importer_id, &columns, &records)
+            .await?;
+
+        let mut parsed: Vec<Result<CsvIssueRow, ValidationErrors>> = records
+            .iter()
+            .map(|record| parse_row(record, &columns, &lookups))
+            .collect();
+        flag_parent_loops(&mut parsed);
+
+        let mut rows = Vec::with_capacity(parsed.len());
+        let mut row_errors = BTreeMap::new();
+        for (i, row) in parsed.into_iter().enumerate() {
+            match row {
+                Ok(row) => rows.push(row),
+                Err(errors) => {
+                    row_errors.insert(line_number(&records[i], i), Box::new(errors));
+                }
+            }
+        }
+
+        if !row_errors.is_empty() {
+            let mut errors = ValidationErrors::new();
+            errors
+                .errors_mut()
+                .insert("rows", ValidationErrorsKind::List(row_errors));
+            return Err(CustomError::ValidationError(errors));
+        }
+
+        let row_count = rows.len();
+        if data.dry_run.unwrap_or(false) {
+            return Ok(CsvImportResponse {
+                dry_run: true,
+                rows: row_count,
+                issues: Vec::new(),
+            });
+        }
+
+        let issues = self
+            .issue_repo
+            .create_issues(org_id, data.team_id, rows)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(CsvImportResponse {
+            dry_run: false,
+            rows: row_count,
+            issues,
+        })
+    }
+
+    async fn csv_lookups(
+        &self,
+        org_id: Uuid,
+        team_id: Option<Uuid>,
+        importer_id: Uuid,
+        columns: &CsvColumns,
+        records: &[StringRecord],
+    ) -> Result<CsvLookups, CustomError> {
+        let mut file_numbers = HashMap::new();
+        let mut duplicate_numbers = HashSet::new();
+        for (i, record) in records.iter().enumerate() {
+            if let Ok(number) = field(record, columns.number).parse::<i32>() {
+                if file_numbers.insert(number, i).is_some() {
+                    duplicate_numbers.insert(number);
+                }
+            }
+        }
+
+        let parent_numbers: Vec<i32> = records
+            .iter()
+            .filter_map(|record| field(record, columns.parent).parse::<i32>().ok())
+            .filter(|number| !file_numbers.contains_key(number))
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let existing_numbers = match parent_numbers.is_empty() {
+            true => HashMap::new(),
+            false => self
+                .issue_repo
+                .find_issue_ids_by_numbers(org_id, team_id, &parent_numbers)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .collect(),
+        };
+
+        let emails: Vec<String> = records
+            .iter()
+            .map(|record| field(record, columns.creator).to_lowercase())
+            .filter(|email| !email.is_empty())
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect();
+        let members = match emails.is_empty() {
+            true => HashMap::new(),
+            false => self
+                .org_repo
+                .find_member_ids_by_emails(org_id, &emails)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .collect(),
+        };
+
+        Ok(CsvLookups {
+            importer_id,
+            file_numbers,
+            duplicate_numbers,
+            existing_numbers,
+            members,
+        })
+    }
+}
+
+fn read_csv(csv: &str) -> Result<(Vec<String>, Vec<StringRecord>), CustomError> {
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(csv.as_bytes());
+
+    let headers = reader
+        .headers()
+        .map_err(|e| csv_error(e.to_string()))?
+        .iter()
+        .map(|header| header.trim().to_string())
+        .collect();
+    let records = reader
+        .records()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| csv_error(e.to_string()))?;
+
+    Ok((headers, records))
+}
+
+fn map_columns(headers: &[String], mapping: &CsvColumnMapping) -> Result<CsvColumns, CustomError> {
+    let mut errors = ValidationErrors::new();
+    let mut column = |field: &'static str, name: &Option<String>| {
+        let name = name.as_deref()?;
+        let index = headers.iter().position(|header| header == name);
+        if index.is_none() {
+            errors.add(
+                field,
+                ValidationError::new("unknown_column")
+                    .with_message(Cow::Owned(format!("The file has no column named {}", name))),
+            );
+        }
+        index
+    };
+
+    let title = column("title", &mapping.title);
+    let columns = CsvColumns {
+        title: title.unwrap_or_default(),
+        description: column("description", &mapping.description),
+        status: column("status", &mapping.status),
+        priority: column("priority", &mapping.priority),
+        due_date: column("due_date", &mapping.due_date),
+        parent: column("parent", &mapping.parent),
+        number: column("number", &mapping.number),
+        creator: column("creator", &mapping.creator),
+    };
+
+    if mapping.title.is_none() {
+        errors.add(
+            "title",
+            ValidationError::new("title_required")
+                .with_message(Cow::Borrowed("A column must be mapped to the title")),
+        );
+    }
+
+    if !errors.is_empty() {
+        let mut mapping_errors = ValidationErrors::new();
+        mapping_errors
+            .errors_mut()
+            .insert("mapping", ValidationErrorsKind::Struct(Box::new(errors)));
+        return Err(CustomError::ValidationError(mapping_errors));
+    }
+
+    Ok(columns)
+}
+
+fn parse_row(
+    record: &StringRecord,
+    columns: &CsvColumns,
+    lookups: &CsvLookups,
+) -> Result<CsvIssueRow, ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    let mut fail = |field: &'static str, code: &'static str, message: String| {
+        errors.add(
+            field,
+            ValidationError::new(code).with_message(Cow::Owned(message)),
+        );
+    };
+
+    let title = field(record, Some(columns.title));
+    if title.is_empty() {
+        fail("title", "required", "Title cannot be empty".to_string());
+    }
+
+    let status = match field(record, columns.status) {
+        "" => Some(IssueStatus::Backlog),
+        name => IssueStatus::from_name(name).or_else(|| {
+            fail(
+                "status",
+                "unknown_status",
+                format!("Unknown status {}", name),
+            );
+            None
+        }),
+    };
+
+    let priority = match field(record, columns.priority) {
+        "" => Some(IssuePriority::Low),
+        name => IssuePriority::from_name(name).or_else(|| {
+            fail(
+                "priority",
+                "unknown_priority",
+                format!("Unknown priority {}", name),
+            );
+            None
+        }),
+    };
+
+    // imported issues may well be overdue, unlike the ones created in the app
+    let due_date = match field(record, columns.due_date) {
+        "" => None,
+        value => parse_date(value).or_else(|| {
+            fail(
+                "due_date",
+                "invalid_date",
+                format!("{} is not a date, use YYYY-MM-DD or RFC 3339", value),
+            );
+            None
+        }),
+    };
+
+    match field(record, columns.number) {
+        "" => {}
+        value => match value.parse::<i32>() {
+            Ok(number) if lookups.duplicate_numbers.contains(&number) => fail(
+                "number",
+                "duplicate_number",
+                format!("Number {} appears on more than one row", number),
+            ),
+            Ok(_) => {}
+            Err(_) => fail(
+                "number",
+                "invalid_number",
+                format!("{} is not a number", value),
+            ),
+        },
+    }
+
+    let parent = match field(record, columns.parent) {
+        "" => None,
+        value => match value.parse::<i32>() {
+            Ok(number) => {
+                let parent = match lookups.file_numbers.get(&number) {
+                    Some(row) => Some(CsvParent::Row(*row)),
+                    None => lookups
+                        .existing_numbers
+                        .get(&number)
+                        .map(|issue_id| CsvParent::Issue(*issue_id)),
+                };
+                if parent.is_none() {
+                    fail(
+                        "parent",
+                        "unknown_parent",
+                        format!("No issue numbered {} in the file or the team", number),
+                    );
+                }
+                parent
+            }
+            Err(_) => {
+                fail(
+                    "parent",
+                    "invalid_number",
+                    format!("{} is not a number", value),
+                );
+                None
+            }
+        },
+    };
+
+    let creator_id = match field(record, columns.creator) {
+        "" => Some(lookups.importer_id),
+        email => lookups
+            .members
+            .get(&email.to_lowercase())
+            .copied()
+            .or_else(|| {
+                fail(
+                    "creator",
+                    "unknown_member",
+                    format!("No active member with the email {}", email),
+                );
+                None
+            }),
+    };
+
+    let description = match field(record, columns.description) {
+        "" => None,
+        markdown => Some(markdown_document(markdown)),
+    };
+
+    match (status, priority, creator_id) {
+        (Some(status), Some(priority), Some(creator_id)) if errors.is_empty() => Ok(CsvIssueRow {
+            title: title.to_string(),
+            description,
+            status,
+            priority,
+            due_date,
+            parent,
+            creator_id,
+        }),
+        _ => Err(errors),
+    }
+}
+
+// a row that is its own ancestor within the file could never be linked
+fn flag_parent_loops(rows: &mut [Result<CsvIssueRow, ValidationErrors>]) {
+    let parent_of = |rows: &[Result<CsvIssueRow, ValidationErrors>], row: usize| match rows.get(row)
+    {
+        Some(Ok(CsvIssueRow {
+            parent: Some(CsvParent::Row(parent)),
+            ..
+        })) => Some(*parent),
+        _ => None,
+    };
+
+    let looping: Vec<usize> = (0..rows.len())
+        .filter(|row| {
+            let mut current = parent_of(rows, *row);
+            for _ in 0..rows.len() {
+                match current {
+                    Some(parent) if parent == *row => return true,
+                    Some(parent) => current = parent_of(rows, parent),
+                    None => return false,
+                }
+            }
+            false
+        })
+        .collect();
+
+    for row in looping {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "parent",
+            ValidationError::new("parent_loop")
+                .with_message(Cow::Borrowed("The row is its own ancestor")),
+        );
+        rows[row] = Err(errors);
+    }
+}
+
+fn field(record: &StringRecord, column: Option<usize>) -> &str {
+    let value = column
+        .and_then(|column| record.get(column))
+        .map(str::trim)
+        .unwrap_or("");
+
+    match value.strip_prefix('\'') {
+        Some(unescaped) if starts_formula(unescaped) => unescaped,
+        _ => value,
+    }
+}
+
+// spreadsheets run cells starting like a formula, a leading quote makes them plain text,
+// values that already start with quotes get one more so the import can strip it again
+fn escape_formula(value: String) -> String {
+    match starts_formula(&value) {
+        true => format!("'{}", value),
+        false => value,
+    }
+}
+
+fn starts_formula(value: &str) -> bool {
+    value
+        .trim_start_matches('\'')
+        .starts_with(['=', '+', '-', '@', '\t', '\r'])
+}
+
+// the header is line 1, quoted values may span several lines
+fn line_number(record: &StringRecord, index: usize) -> usize {
+    record
+        .position()
+        .map(|position| position.line() as usize)
+        .unwrap_or(index + 2)
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+// the names the api uses, so exported values can be imported again
+fn variant_name<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(name)) => name,
+        _ => String::new(),
+    }
+}
+
+fn csv_error(message: String) -> CustomError {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        "csv",
+        ValidationError::new("invalid_csv").with_message(Cow::Owned(message)),
+    );
+    CustomError::ValidationError(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // writes the values as one exported row and reads them back like an import does
+    fn round_trip(values: &[&str]) -> (String, Vec<String>) {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["value"]).unwrap();
+        for value in values {
+            writer
+                .write_record([escape_formula(value.to_string())])
+                .unwrap();
+        }
+        let export = String::from_utf8(writer.into_inner().unwrap()).unwrap();
+
+        let (_, records) = read_csv(&export).unwrap();
+        let imported = records
+            .iter()
+            .map(|record| field(record, Some(0)).to_string())
+            .collect();
+
+        (export, imported)
+    }
+
+    #[test]
+    fn formula_prefixes_are_neutralized_on_export() {
+        for value in ["=SUM(A1:A2)", "+1", "-1", "@cmd", "\tindented", "\rreturn"] {
+            let escaped = escape_formula(value.to_string());
+            assert_eq!(escaped, format!("'{}", value));
+        }
+
+        assert_eq!(escape_formula("plain".to_string()), "plain");
+        assert_eq!(escape_formula("a=b".to_string()), "a=b");
+        assert_eq!(escape_formula("'quoted".to_string()), "'quoted");
+    }
+
+    #[test]
+    fn escaped_values_import_unchanged() {
+        let values = [
+            "=SUM(A1:A2)",
+            "+1",
+            "-1",
+            "@cmd",
+            "\tindented",
+            "\rreturn",
+            "plain",
+            "a=b",
+            "'quoted",
+            "'=already quoted",
+            "''=quoted twice",
+        ];
+
+        let (export, imported) = round_trip(&values);
+
+        for line in export.lines().skip(1) {
+            let cell = line.trim_start_matches('"');
+            assert!(
+                !cell.starts_with(['=', '+', '-', '@', '\t', '\r']),
+                "{:?}",
+                line
+            );
+        }
+        assert_eq!(imported, values);
+    }
+}
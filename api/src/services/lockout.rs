@@ -0,0 +1,153 @@
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::{Config, LockoutPolicy},
+    errors::CustomError,
+    models::{
+        auth::{AuthEvent, LockoutScope},
+        context::ClientInfo,
+    },
+    repositories::{auth_lockout::AuthLockoutRepository, auth_log::AuthLogRepository},
+};
+
+// failed attempts are counted per account and per ip, whether they come from a login
+// or from confirming a sensitive change in an existing session
+pub struct LockoutService {
+    auth_lockout_repo: AuthLockoutRepository,
+    auth_log_repo: AuthLogRepository,
+    lockout_policy: LockoutPolicy,
+}
+
+impl LockoutService {
+    pub fn new(pool: PgPool, config: &Config) -> Self {
+        Self {
+            auth_lockout_repo: AuthLockoutRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool),
+            lockout_policy: config.lockout_policy.clone(),
+        }
+    }
+
+    // for checks of a known account, both the client address and the account itself
+    pub async fn ensure_can_attempt(
+        &self,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            self.ensure_not_locked(LockoutScope::Ip, ip_address, Some(user_id), client)
+                .await?;
+        }
+
+        self.ensure_not_locked(
+            LockoutScope::Account,
+            &user_id.to_string(),
+            Some(user_id),
+            client,
+        )
+        .await
+    }
+
+    pub async fn ensure_not_locked(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+        user_id: Option<Uuid>,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let lockout = self
+            .auth_lockout_repo
+            .find_lockout(scope, identifier)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let locked_until = match lockout.and_then(|lockout| lockout.locked_until) {
+            Some(locked_until) if locked_until > Utc::now() => locked_until,
+            _ => return Ok(()),
+        };
+
+        self.auth_log_repo
+            .log_event(
+                user_id,
+                AuthEvent::LoginBlocked,
+                client,
+                Some(json!({ "scope": scope.as_str(), "locked_until": locked_until })),
+            )
+            .await;
+
+        let retry_after = (locked_until - Utc::now()).num_seconds().max(1);
+        Err(CustomError::TooManyAttempts(retry_after))
+    }
+
+    pub async fn record_failed_login(
+        &self,
+        user_id: Option<Uuid>,
+        email: &str,
+        reason: &str,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        self.auth_log_repo
+            .log_event(
+                user_id,
+                AuthEvent::LoginFailed,
+                client,
+                Some(json!({ "email": email, "reason": reason })),
+            )
+            .await;
+
+        if let Some(ip_address) = client.ip_address.as_deref() {
+            self.register_failed_attempt(LockoutScope::Ip, ip_address)
+                .await?;
+        }
+
+        if let Some(user_id) = user_id {
+            self.register_failed_attempt(LockoutScope::Account, &user_id.to_string())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn register_failed_attempt(
+        &self,
+        scope: LockoutScope,
+        identifier: &str,
+    ) -> Result<(), CustomError> {
+        let max_attempts = match scope {
+            LockoutScope::Account => self.lockout_policy.max_account_attempts,
+            LockoutScope::Ip => self.lockout_policy.max_ip_attempts,
+        };
+
+        let lockout = self
+            .auth_lockout_repo
+            .record_failed_attempt(scope, identifier, self.lockout_policy.attempt_window_secs)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if lockout.failed_attempts < max_attempts {
+            return Ok(());
+        }
+
+        let lockout_secs = self
+            .lockout_policy
+            .lockout_secs(lockout.failed_attempts, max_attempts);
+
+        self.auth_lockout_repo
+            .lock_until(
+                scope,
+                identifier,
+                Utc::now() + Duration::seconds(lockout_secs),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn clear_account_lockout(&self, user_id: Uuid) -> Result<(), CustomError> {
+        self.auth_lockout_repo
+            .clear_lockout(LockoutScope::Account, &user_id.to_string())
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+
+use futures::future::BoxFuture;
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport,
+    AsyncTransport, Message, Tokio1Executor,
+};
+
+use crate::{config::MailerConfig, errors::CustomError};
+
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+pub trait Mailer: Send + Sync {
+    fn send(&self, message: EmailMessage) -> BoxFuture<'_, Result<(), CustomError>>;
+}
+
+pub fn mailer_from_config(config: &MailerConfig) -> Result<Arc<dyn Mailer>, CustomError> {
+    match &config.smtp {
+        Some(_) => Ok(Arc::new(SmtpMailer::new(config)?)),
+        None => {
+            log::warn!("SMTP_HOST is not set, emails will only be logged");
+            Ok(Arc::new(LogMailer))
+        }
+    }
+}
+
+// development fallback, the message (including any links in it) only ends up in the log
+pub struct LogMailer;
+
+impl Mailer for LogMailer {
+    fn send(&self, message: EmailMessage) -> BoxFuture<'_, Result<(), CustomError>> {
+        Box::pin(async move {
+            log::info!(
+                "Email to {}: {}\n{}",
+                message.to,
+                message.subject,
+                message.body
+            );
+            Ok(())
+        })
+    }
+}
+
+pub struct SmtpMailer {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &MailerConfig) -> Result<Self, CustomError> {
+        let smtp = config.smtp.as_ref().ok_or(CustomError::ConfigError(
+            "SMTP_HOST environment variable not set".to_string(),
+        ))?;
+
+        let from = config
+            .from
+            .parse()
+            .map_err(|_| CustomError::ConfigError("Failed to parse MAIL_FROM".to_string()))?;
+
+        let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)
+            .map_err(|e| CustomError::ConfigError(format!("Invalid SMTP_HOST: {}", e)))?
+            .port(smtp.port);
+
+        if let (Some(username), Some(password)) = (&smtp.username, &smtp.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, message: EmailMessage) -> BoxFuture<'_, Result<(), CustomError>> {
+        Box::pin(async move {
+            let to: Mailbox = message
+                .to
+                .parse()
+                .map_err(|_| CustomError::ExternalServiceError("Invalid recipient".to_string()))?;
+
+            let email = Message::builder()
+                .from(self.from.clone())
+                .to(to)
+                .subject(message.subject)
+                .body(message.body)
+                .map_err(|e| CustomError::ExternalServiceError(e.to_string()))?;
+
+            self.transport
+                .send(email)
+                .await
+                .map_err(|e| CustomError::ExternalServiceError(e.to_string()))?;
+
+            Ok(())
+        })
+    }
+}
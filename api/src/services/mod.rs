@@ -1,7 +1,23 @@
+pub mod audit_log;
 pub mod auth;
 pub mod comment;
+pub mod file_import;
+pub mod identity;
+pub mod import;
 pub mod issue;
+pub mod issue_csv;
+pub mod lockout;
+pub mod mailer;
 pub mod oauth;
+pub mod oauth_state;
+pub mod oidc;
 pub mod org;
+pub mod org_export;
+pub mod personal_access_token;
+pub mod role;
+pub mod team;
 pub mod token;
+pub mod two_factor;
+pub mod user;
 pub mod user_preferences;
+pub mod webhook;
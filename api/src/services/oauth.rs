@@ -1,35 +1,50 @@
 use std::sync::Arc;
 
-use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, CsrfToken, Scope, TokenUrl};
+use oauth2::{basic::BasicClient, AuthUrl, ClientId, ClientSecret, Scope, TokenUrl};
+use serde_json::json;
 use sqlx::PgPool;
+use uuid::Uuid;
 
 use crate::{
     config::Config,
     errors::CustomError,
     models::{
-        auth::{CreateGithubUser, UpdateGithubUser, User, UserGithubResponse},
+        auth::{AuthEvent, GithubLoginResult, UpdateGithubUser, User, UserGithubResponse},
+        context::ClientInfo,
         github::{AccessTokenResponse, UserEmail, UserInfo},
-        user_preferences::UserPreferenceRequest,
+        identity::{ExternalIdentity, UserIdentity},
     },
-    repositories::{user::UserRepository, user_preferences::UserPreferencesRepository},
+    repositories::{auth_log::AuthLogRepository, user::UserRepository},
 };
 
-use super::token::TokenService;
+use super::{
+    auth::AuthService, identity::IdentityService, oauth_state::OauthStateService,
+    two_factor::TwoFactorService,
+};
+
+const LOGIN_SCOPES: &[&str] = &["user:email", "read:user"];
+
+const GITHUB_PROVIDER: &str = "github";
 
 pub struct OauthService {
     oauth_client: BasicClient,
     client_secret: String,
     client_id: String,
     user_repo: UserRepository,
-    user_preferences_repo: UserPreferencesRepository,
-    token_service: Arc<TokenService>,
+    auth_log_repo: AuthLogRepository,
+    oauth_state_service: OauthStateService,
+    auth_service: Arc<AuthService>,
+    two_factor_service: Arc<TwoFactorService>,
+    identity_service: Arc<IdentityService>,
 }
 
 impl OauthService {
     pub fn new(
         pool: PgPool,
         config: &Config,
-        token_service: Arc<TokenService>,
+        auth_service: Arc<AuthService>,
+        two_factor_service: Arc<TwoFactorService>,
+        identity_service: Arc<IdentityService>,
     ) -> Result<Self, CustomError> {
         let oauth_client = BasicClient::new(
             ClientId::new(config.github_client_id.clone()),
@@ -46,124 +61,230 @@ impl OauthService {
             oauth_client,
             client_id: config.github_client_id.clone(),
             client_secret: config.github_client_secret.clone(),
-            user_preferences_repo: UserPreferencesRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool.clone()),
+            oauth_state_service: OauthStateService::new(pool.clone(), config),
             user_repo: UserRepository::new(pool),
-            token_service,
+            auth_service,
+            two_factor_service,
+            identity_service,
         })
     }
 
-    pub async fn init_github_link(&self) -> reqwest::Url {
-        let (authorize_url, _) = self
-            .oauth_client
-            .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("user:email".to_string()))
-            .add_scope(Scope::new("read:user".to_string()))
-            .url();
+    pub async fn init_github_link(
+        &self,
+        redirect_to: Option<String>,
+    ) -> Result<reqwest::Url, CustomError> {
+        self.authorize_url(LOGIN_SCOPES, redirect_to, None).await
+    }
 
-        authorize_url
+    pub async fn init_github_identity_link(
+        &self,
+        user_id: Uuid,
+        redirect_to: Option<String>,
+    ) -> Result<reqwest::Url, CustomError> {
+        self.authorize_url(LOGIN_SCOPES, redirect_to, Some(user_id))
+            .await
     }
 
-    pub async fn init_github_oauth(&self) -> reqwest::Url {
-        let (authorize_url, _) = self
-            .oauth_client
-            .authorize_url(CsrfToken::new_random)
-            .add_scope(Scope::new("user:email".to_string()))
-            .add_scope(Scope::new("read:user".to_string()))
-            .add_scope(Scope::new("repo".to_string()))
-            .add_scope(Scope::new("read:org".to_string()))
-            .url();
-
-        authorize_url
+    pub async fn link_github_identity(
+        &self,
+        user_id: Uuid,
+        code: String,
+        state: String,
+        client: ClientInfo,
+    ) -> Result<UserIdentity, CustomError> {
+        let oauth_state = self
+            .oauth_state_service
+            .consume(GITHUB_PROVIDER, &state, Some(user_id))
+            .await?;
+        let (user_info, external) = self
+            .fetch_github_identity(code, &oauth_state.pkce_verifier)
+            .await?;
+
+        let identity = self
+            .identity_service
+            .link_identity(user_id, external, &client)
+            .await?;
+
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+        self.update_user_if_needed(&user, &user_info).await?;
+
+        Ok(identity)
+    }
+
+    // repository access for a signed-in user, the token is only handed to that user
+    pub async fn init_github_oauth(
+        &self,
+        user_id: Uuid,
+        redirect_to: Option<String>,
+    ) -> Result<reqwest::Url, CustomError> {
+        self.authorize_url(
+            &["user:email", "read:user", "repo", "read:org"],
+            redirect_to,
+            Some(user_id),
+        )
+        .await
     }
 
     pub async fn get_github_access_token(
         &self,
+        user_id: Uuid,
         code: String,
+        state: &str,
     ) -> Result<AccessTokenResponse, CustomError> {
-        self.exchange_code_for_token(code).await
+        let oauth_state = self
+            .oauth_state_service
+            .consume(GITHUB_PROVIDER, state, Some(user_id))
+            .await?;
+        self.exchange_code_for_token(code, &oauth_state.pkce_verifier)
+            .await
     }
 
     pub async fn handle_github_callback(
         &self,
         code: String,
-    ) -> Result<UserGithubResponse, CustomError> {
-        let token_res = self.exchange_code_for_token(code).await?;
-        let (user_info, user_email) = self.get_user_info(&token_res.access_token).await?;
+        state: String,
+        client: ClientInfo,
+    ) -> Result<GithubLoginResult, CustomError> {
+        let resolved = match self
+            .oauth_state_service
+            .consume(GITHUB_PROVIDER, &state, None)
+            .await
+        {
+            Ok(oauth_state) => self
+                .resolve_github_user(code, &oauth_state.pkce_verifier, &client)
+                .await
+                .map(|user| (user, oauth_state.redirect_to)),
+            Err(e) => Err(e),
+        };
 
-        let mut user = self.get_or_create_user(&user_info, &user_email).await?;
+        let (user, redirect_to) = match resolved {
+            Ok(user) => user,
+            Err(e) => {
+                self.auth_log_repo
+                    .log_event(
+                        None,
+                        AuthEvent::LoginFailed,
+                        &client,
+                        Some(json!({ "provider": "github", "reason": e.to_string() })),
+                    )
+                    .await;
+                return Err(e);
+            }
+        };
 
-        if let Some(updated_user) = self
-            .update_user_if_needed(&user, &user_info, &user_email)
-            .await?
-        {
-            user = updated_user;
+        if self.two_factor_service.is_enabled(user.id).await? {
+            let mut challenge = self
+                .auth_service
+                .create_two_factor_challenge(user.id, &client)
+                .await?;
+            challenge.redirect_to = redirect_to;
+            return Ok(GithubLoginResult::TwoFactorRequired(challenge));
         }
 
-        let (access_token, refresh_token, access_token_expiration, refresh_token_expiration) = self
-            .token_service
-            .generate_token_pair(user.id)
-            .await
-            .map_err(|_| CustomError::InternalServerError)?;
-
-        Ok(UserGithubResponse {
+        // the lockout, last login and auth log are handled like every other login
+        let login_res = self
+            .auth_service
+            .complete_login(
+                user.clone(),
+                "User logged in successfully",
+                &client,
+                Some(json!({ "provider": "github" })),
+            )
+            .await?;
+
+        // github logins keep their response shape, which counts lifetimes in minutes
+        Ok(GithubLoginResult::Tokens(Box::new(UserGithubResponse {
             user,
-            access_token,
-            refresh_token,
-            access_token_expiration,
-            refresh_token_expiration,
-        })
+            access_token: login_res.access_token,
+            refresh_token: login_res.refresh_token,
+            access_token_expiration: login_res.expires_in_access / 60,
+            refresh_token_expiration: login_res.expires_in_refresh / 60,
+            redirect_to,
+        })))
     }
 
-    async fn get_or_create_user(
+    async fn authorize_url(
         &self,
-        user_info: &UserInfo,
-        user_email: &str,
+        scopes: &[&str],
+        redirect_to: Option<String>,
+        link_user_id: Option<Uuid>,
+    ) -> Result<reqwest::Url, CustomError> {
+        let pending = self
+            .oauth_state_service
+            .begin(GITHUB_PROVIDER, redirect_to, None, link_user_id)
+            .await?;
+
+        let mut request = self
+            .oauth_client
+            .authorize_url(|| pending.state)
+            .set_pkce_challenge(pending.pkce_challenge);
+        for scope in scopes {
+            request = request.add_scope(Scope::new(scope.to_string()));
+        }
+        let (authorize_url, _) = request.url();
+
+        Ok(authorize_url)
+    }
+
+    async fn resolve_github_user(
+        &self,
+        code: String,
+        pkce_verifier: &str,
+        client: &ClientInfo,
     ) -> Result<User, CustomError> {
-        match self
-            .user_repo
-            .get_user_by_github_id(user_info.id.to_string())
-            .await
-        {
-            Ok(user) => Ok(user),
-            Err(_) => {
-                let create_user_data = CreateGithubUser {
-                    email: user_email.to_string(),
-                    username: user_info.login.clone(),
-                    github_id: user_info.id.to_string(),
-                    github_url: user_info.html_url.clone(),
-                    avatar_url: Some(user_info.avatar_url.clone()),
-                };
-
-                let user = self
-                    .user_repo
-                    .insert_github_user(create_user_data)
-                    .await
-                    .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
-
-                self.user_preferences_repo
-                    .create_user_preference(UserPreferenceRequest { user_id: user.id })
-                    .await
-                    .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
-
-                Ok(user)
-            }
+        let (user_info, external) = self.fetch_github_identity(code, pkce_verifier).await?;
+
+        let mut user = self
+            .identity_service
+            .resolve_login_user(external, client)
+            .await?;
+
+        if let Some(updated_user) = self.update_user_if_needed(&user, &user_info).await? {
+            user = updated_user;
         }
+
+        Ok(user)
     }
 
+    async fn fetch_github_identity(
+        &self,
+        code: String,
+        pkce_verifier: &str,
+    ) -> Result<(UserInfo, ExternalIdentity), CustomError> {
+        let token_res = self.exchange_code_for_token(code, pkce_verifier).await?;
+        let (user_info, primary_email) = self.get_user_info(&token_res.access_token).await?;
+
+        let external = ExternalIdentity {
+            provider: GITHUB_PROVIDER.to_string(),
+            subject: user_info.id.to_string(),
+            email: Some(primary_email.email),
+            email_verified: primary_email.verified,
+            username: Some(user_info.login.clone()),
+            avatar_url: Some(user_info.avatar_url.clone()),
+        };
+
+        Ok((user_info, external))
+    }
+
+    // keeps the github profile fields in sync, the account email is never taken
+    // from github since the account may have been created with another address
     async fn update_user_if_needed(
         &self,
         user: &User,
         user_info: &UserInfo,
-        user_email: &str,
     ) -> Result<Option<User>, CustomError> {
         let mut update_user = UpdateGithubUser {
-            email: None,
             avatar_url: None,
-            github_id: user.github_id.clone(),
+            github_id: user_info.id.to_string(),
             github_url: None,
         };
 
-        let needs_update = self.collect_user_updates(&mut update_user, user, user_info, user_email);
+        let needs_update = self.collect_user_updates(&mut update_user, user, user_info);
 
         if needs_update {
             self.user_repo
@@ -181,10 +302,13 @@ impl OauthService {
         update_user: &mut UpdateGithubUser,
         user: &User,
         user_info: &UserInfo,
-        user_email: &str,
     ) -> bool {
         let mut needs_update = false;
 
+        if user.github_id.as_ref() != Some(&update_user.github_id) {
+            needs_update = true;
+        }
+
         if user.github_url.as_ref() != Some(&user_info.html_url) {
             update_user.github_url = Some(user_info.html_url.clone());
             needs_update = true;
@@ -195,17 +319,13 @@ impl OauthService {
             needs_update = true;
         }
 
-        if user.email != user_email {
-            update_user.email = Some(user_email.to_string());
-            needs_update = true;
-        }
-
         needs_update
     }
 
     async fn exchange_code_for_token(
         &self,
         code: String,
+        pkce_verifier: &str,
     ) -> Result<AccessTokenResponse, CustomError> {
         let token_url = self
             .oauth_client
@@ -216,6 +336,7 @@ impl OauthService {
             ("client_id", self.client_id.as_str()),
             ("client_secret", self.client_secret.as_str()),
             ("code", code.as_str()),
+            ("code_verifier", pkce_verifier),
             ("grant_type", "authorization_code"),
         ];
 
@@ -283,7 +404,10 @@ impl OauthService {
         Ok(emails)
     }
 
-    async fn get_user_info(&self, access_token: &str) -> Result<(UserInfo, String), CustomError> {
+    async fn get_user_info(
+        &self,
+        access_token: &str,
+    ) -> Result<(UserInfo, UserEmail), CustomError> {
         let client = reqwest::Client::new();
         let res = client
             .get("https://api.github.com/user")
@@ -325,18 +449,17 @@ impl OauthService {
 
         let emails = self.get_user_emails(access_token).await?;
 
-        let primary_email = emails.into_iter().find(|e| e.primary).map(|e| e.email);
+        let primary_email =
+            emails
+                .into_iter()
+                .find(|e| e.primary)
+                .ok_or(CustomError::ExternalServiceError(
+                    "Primary email not found".to_string(),
+                ))?;
 
         let mut user_info = user_info?;
-        user_info.email = primary_email;
-
-        let email = user_info
-            .email
-            .clone()
-            .ok_or(CustomError::ExternalServiceError(
-                "Primary email not found".to_string(),
-            ))?;
+        user_info.email = Some(primary_email.email.clone());
 
-        Ok((user_info, email))
+        Ok((user_info, primary_email))
     }
 }
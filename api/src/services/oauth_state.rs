@@ -0,0 +1,106 @@
+use chrono::{Duration, Utc};
+use oauth2::{CsrfToken, PkceCodeChallenge};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    config::Config,
+    errors::CustomError,
+    models::oauth::{CreateOauthStateData, OauthState},
+    repositories::oauth_state::OauthStateRepository,
+    utils::urls::is_allowed_redirect,
+};
+
+const OAUTH_STATE_EXPIRATION: i64 = 10; // 10 minutes
+
+pub struct PendingAuthorization {
+    pub state: CsrfToken,
+    pub pkce_challenge: PkceCodeChallenge,
+}
+
+// shared by every oauth/oidc provider, ties a callback to the attempt that started it
+pub struct OauthStateService {
+    oauth_state_repo: OauthStateRepository,
+    redirect_allowlist: Vec<String>,
+}
+
+impl OauthStateService {
+    pub fn new(pool: PgPool, config: &Config) -> Self {
+        Self {
+            oauth_state_repo: OauthStateRepository::new(pool),
+            redirect_allowlist: config.oauth_redirect_allowlist.clone(),
+        }
+    }
+
+    pub async fn begin(
+        &self,
+        provider: &str,
+        redirect_to: Option<String>,
+        nonce: Option<String>,
+        link_user_id: Option<Uuid>,
+    ) -> Result<PendingAuthorization, CustomError> {
+        if let Some(redirect_to) = redirect_to.as_deref() {
+            if !is_allowed_redirect(redirect_to, &self.redirect_allowlist) {
+                return Err(CustomError::RedirectNotAllowed);
+            }
+        }
+
+        let state = CsrfToken::new_random();
+        let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+
+        // abandoned attempts are never consumed, so clean them up as new ones come in
+        if let Err(e) = self.oauth_state_repo.delete_expired().await {
+            log::warn!("Failed to delete expired oauth states: {}", e);
+        }
+
+        self.oauth_state_repo
+            .create_state(CreateOauthStateData {
+                provider: provider.to_string(),
+                state_hash: hash_state(state.secret()),
+                pkce_verifier: pkce_verifier.secret().to_string(),
+                nonce,
+                redirect_to,
+                link_user_id,
+                expires_at: Utc::now() + Duration::minutes(OAUTH_STATE_EXPIRATION),
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(PendingAuthorization {
+            state,
+            pkce_challenge,
+        })
+    }
+
+    // a state started for linking only completes a link for that same user, and a
+    // login state never completes a link
+    pub async fn consume(
+        &self,
+        provider: &str,
+        state: &str,
+        link_user_id: Option<Uuid>,
+    ) -> Result<OauthState, CustomError> {
+        let oauth_state = self
+            .oauth_state_repo
+            .consume_state(provider, &hash_state(state))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::OauthStateMismatch)?;
+
+        if oauth_state.link_user_id != link_user_id {
+            return Err(CustomError::OauthStateMismatch);
+        }
+
+        if oauth_state.expires_at < Utc::now() {
+            return Err(CustomError::OauthStateExpired);
+        }
+
+        Ok(oauth_state)
+    }
+}
+
+// the state travels through the browser, only its hash is stored
+fn hash_state(state: &str) -> String {
+    format!("{:x}", Sha256::digest(state.as_bytes()))
+}
@@ -1,35 +1,82 @@
+use std::{borrow::Cow, sync::Arc};
+
+use chrono::{Duration, Utc};
 use futures::TryFutureExt;
 use rand::Rng;
+use serde_json::{json, Value as JsonValue};
 use sqlx::PgPool;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError, ValidationErrors};
 
 use crate::{
+    config::Config,
     errors::CustomError,
     models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
         org::{
-            CreateOrgRequest, InviteOrgMemberRequest, MemberRole, Org, OrgMemberInvite,
-            OrgMemberInviteResponse, OrgMemberResponse, UpdateOrgRequest,
+            AddOrgDomainRequest, CreateInviteLinkRequest, CreateOrgMemberData, CreateOrgRequest,
+            IdentifierChangeResult, InviteLinkPreview, InviteOrgMemberRequest, JoinMethod,
+            JoinableOrgResponse, MemberChangeResult, MemberRole, MemberStatus, Org, OrgDomain,
+            OrgInviteLink, OrgMemberInvite, OrgMemberInviteResponse, OrgMemberResponse,
+            OrgMembership, TransferOwnershipRequest, TransferOwnershipResult, UpdateOrgRequest,
         },
         user_preferences::UserPreferenceUpdateRequest,
+        webhook::WebhookEvent,
     },
     repositories::{
-        org::OrgRepository, user::UserRepository, user_preferences::UserPreferencesRepository,
+        audit_log::AuditLogRepository, org::OrgRepository, two_factor::TwoFactorRepository,
+        user::UserRepository, user_preferences::UserPreferencesRepository,
+        webhook::WebhookRepository,
     },
 };
 
+use super::mailer::{EmailMessage, Mailer};
+
+const INVITE_EXPIRATION_DAYS: i64 = 7;
+
+// registering one of these would let anyone with a free mailbox join
+const PUBLIC_EMAIL_DOMAINS: &[&str] = &[
+    "gmail.com",
+    "googlemail.com",
+    "outlook.com",
+    "hotmail.com",
+    "live.com",
+    "yahoo.com",
+    "icloud.com",
+    "me.com",
+    "aol.com",
+    "proton.me",
+    "protonmail.com",
+    "gmx.com",
+    "mail.com",
+    "yandex.com",
+];
+
 pub struct OrgService {
     user_repo: UserRepository,
     org_repo: OrgRepository,
     user_preferences_repo: UserPreferencesRepository,
+    two_factor_repo: TwoFactorRepository,
+    audit_log_repo: AuditLogRepository,
+    webhook_repo: WebhookRepository,
+    mailer: Arc<dyn Mailer>,
+    app_url: String,
+    deletion_grace_days: i64,
 }
 
 impl OrgService {
-    pub fn new(pool: PgPool) -> Self {
+    pub fn new(pool: PgPool, config: &Config, mailer: Arc<dyn Mailer>) -> Self {
         Self {
             user_repo: UserRepository::new(pool.clone()),
             org_repo: OrgRepository::new(pool.clone()),
             user_preferences_repo: UserPreferencesRepository::new(pool.clone()),
+            two_factor_repo: TwoFactorRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool.clone()),
+            webhook_repo: WebhookRepository::new(pool.clone()),
+            mailer,
+            app_url: config.app_url.clone(),
+            deletion_grace_days: config.org_deletion_grace_days,
         }
     }
 
@@ -42,8 +89,16 @@ impl OrgService {
             return Err(CustomError::ValidationError(validation_errors));
         }
 
-        let slug = self.generate_slug(&data.name).await;
-        let custom_id = self.generate_custom_id(&data.name).await;
+        let slug = match data.slug.clone() {
+            Some(slug) => {
+                if self.is_slug_taken(&slug).await? {
+                    return Err(slug_taken());
+                }
+                slug
+            }
+            None => self.generate_slug(&data.name).await?,
+        };
+        let custom_id = self.generate_custom_id(&data.name).await?;
         let org = self
             .org_repo
             .create_org(data, slug, custom_id)
@@ -51,7 +106,14 @@ impl OrgService {
             .await?;
 
         self.org_repo
-            .create_member(org.id, user_id, MemberRole::Owner, user_id)
+            .create_member(CreateOrgMemberData {
+                org_id: org.id,
+                user_id,
+                role: MemberRole::Owner,
+                invited_by: user_id,
+                join_method: JoinMethod::Created,
+                invite_link_id: None,
+            })
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
@@ -90,6 +152,23 @@ impl OrgService {
             })
     }
 
+    // the org is addressed by its id or by one of its slugs
+    pub async fn get_membership(
+        &self,
+        org_ref: &str,
+        user_id: Uuid,
+    ) -> Result<Option<OrgMembership>, CustomError> {
+        let (id, slug) = match Uuid::parse_str(org_ref) {
+            Ok(id) => (Some(id), None),
+            Err(_) => (None, Some(org_ref)),
+        };
+
+        self.org_repo
+            .find_membership(id, slug, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
     pub async fn list_user_orgs(&self, user_id: Uuid) -> Result<Vec<Org>, CustomError> {
         self.org_repo
             .list_user_orgs(user_id)
@@ -97,12 +176,53 @@ impl OrgService {
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
 
-    pub async fn update_org(&self, id: Uuid, data: UpdateOrgRequest) -> Result<Org, CustomError> {
+    pub async fn update_org(
+        &self,
+        id: Uuid,
+        data: UpdateOrgRequest,
+        updated_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Org, CustomError> {
         if let Err(validation_errors) = data.validate() {
             return Err(CustomError::ValidationError(validation_errors));
         }
 
-        self.org_repo
+        let before = self.get_org(id, updated_by).await?;
+
+        // otherwise the admin enabling the requirement would lock themselves out
+        if data.require_two_factor == Some(true)
+            && !self
+                .two_factor_repo
+                .is_enabled(updated_by)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+        {
+            return Err(CustomError::TwoFactorRequired);
+        }
+
+        if let Some(custom_id) = data.custom_id.as_deref() {
+            self.change_identifier(id, custom_id).await?;
+        }
+
+        if let Some(slug) = data.slug.as_deref() {
+            let changed = self
+                .org_repo
+                .change_slug(id, slug)
+                .await
+                .map_err(|e| match e {
+                    sqlx::Error::RowNotFound => {
+                        CustomError::NotFound(format!("Org with id: {} not found", id))
+                    }
+                    _ => CustomError::DatabaseError(e.to_string()),
+                })?;
+
+            if !changed {
+                return Err(slug_taken());
+            }
+        }
+
+        let org = self
+            .org_repo
             .update_org(id, data)
             .await
             .map_err(|e| match e {
@@ -110,42 +230,218 @@ impl OrgService {
                     CustomError::NotFound(format!("Org with id: {} not found", id))
                 }
                 _ => CustomError::DatabaseError(e.to_string()),
-            })
+            })?;
+
+        let (before, after) = (org_settings(&before), org_settings(&org));
+        if before != after {
+            self.audit_log_repo
+                .log_event(
+                    id,
+                    Some(updated_by),
+                    client,
+                    AuditEntry::new(AuditEvent::OrgUpdated, Some(id)).changes(before, after),
+                )
+                .await;
+        }
+
+        Ok(org)
     }
 
-    pub async fn delete_org(&self, id: Uuid) -> Result<(), CustomError> {
-        self.org_repo.delete_org(id).await.map_err(|e| match e {
-            sqlx::Error::RowNotFound => {
-                CustomError::NotFound(format!("Org with id: {} not found", id))
-            }
-            _ => CustomError::DatabaseError(e.to_string()),
-        })
+    // nothing is removed until the grace period is over, an owner can restore the org until then
+    pub async fn delete_org(
+        &self,
+        id: Uuid,
+        requested_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Org, CustomError> {
+        let org = self
+            .org_repo
+            .schedule_deletion(
+                id,
+                requested_by,
+                Utc::now() + Duration::days(self.deletion_grace_days),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "This organization is scheduled for deletion".to_string(),
+                "ORG_017".to_string(),
+            ))?;
+
+        self.audit_log_repo
+            .log_event(
+                id,
+                Some(requested_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgDeletionScheduled, Some(id))
+                    .after(json!({ "purge_at": org.purge_at })),
+            )
+            .await;
+
+        Ok(org)
+    }
+
+    pub async fn restore_org(
+        &self,
+        id: Uuid,
+        restored_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Org, CustomError> {
+        let org = self
+            .org_repo
+            .cancel_deletion(id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "This organization is not scheduled for deletion".to_string(),
+                "ORG_018".to_string(),
+            ))?;
+
+        self.audit_log_repo
+            .log_event(
+                id,
+                Some(restored_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgRestored, Some(id)),
+            )
+            .await;
+
+        Ok(org)
+    }
+
+    pub async fn purge_due_orgs(&self) -> Result<Vec<Uuid>, CustomError> {
+        self.org_repo
+            .purge_due_orgs()
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
 
     pub async fn invite_user_to_org(
         &self,
-        org_id: Uuid,
+        org: &Org,
         data: InviteOrgMemberRequest,
-        role: MemberRole,
         invited_by: Uuid,
+        client: &ClientInfo,
     ) -> Result<OrgMemberInvite, CustomError> {
         if let Err(validation_errors) = data.validate() {
             return Err(CustomError::ValidationError(validation_errors));
         }
 
-        if self
-            .user_repo
-            .get_user_by_email(data.email.clone())
-            .await
-            .is_err()
-        {
-            return Err(CustomError::NotFound("User not found".to_string()));
+        let role = data.role.unwrap_or(MemberRole::Member);
+        self.ensure_can_grant(org.id, invited_by, &role).await?;
+
+        // people without an account, or who haven't verified the address yet, get
+        // the invite attached to their account once they verify it
+        let user = match self.user_repo.get_user_by_email(data.email.clone()).await {
+            Ok(user) => Some(user),
+            Err(sqlx::Error::RowNotFound) => None,
+            Err(e) => return Err(CustomError::DatabaseError(e.to_string())),
+        };
+
+        if let Some(user) = &user {
+            if self.get_member_status(org.id, user.id).await?.is_some() {
+                return Err(CustomError::Conflict(
+                    "This user is already a member of the organization".to_string(),
+                    "ORG_007".to_string(),
+                ));
+            }
         }
 
-        self.org_repo
-            .create_invite(org_id, data.email, role, invited_by)
+        let user_id = user
+            .filter(|user| user.is_email_verified)
+            .map(|user| user.id);
+
+        let invite = self
+            .org_repo
+            .create_invite(
+                org.id,
+                data.email,
+                user_id,
+                role,
+                invited_by,
+                Utc::now() + Duration::days(INVITE_EXPIRATION_DAYS),
+            )
             .await
-            .map_err(|e: sqlx::Error| CustomError::DatabaseError(e.to_string()))
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "This email has already been invited, resend the invite instead".to_string(),
+                "ORG_008".to_string(),
+            ))?;
+
+        self.send_invite_email(org, &invite).await;
+
+        self.audit_log_repo
+            .log_event(
+                org.id,
+                Some(invited_by),
+                client,
+                AuditEntry::new(AuditEvent::MemberInvited, Some(invite.id))
+                    .after(json!({ "email": invite.email, "role": invite.role })),
+            )
+            .await;
+
+        Ok(invite)
+    }
+
+    // pushes the expiry out again and repeats the email, expired invites included
+    pub async fn resend_invite(
+        &self,
+        org: &Org,
+        invite_id: Uuid,
+        resent_by: Uuid,
+    ) -> Result<OrgMemberInvite, CustomError> {
+        let invite = self.find_invite(org.id, invite_id).await?;
+        self.ensure_can_grant(org.id, resent_by, &invite.role)
+            .await?;
+
+        let invite = self
+            .org_repo
+            .extend_invite(
+                org.id,
+                invite.id,
+                Utc::now() + Duration::days(INVITE_EXPIRATION_DAYS),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Invite not found".to_string()))?;
+
+        self.send_invite_email(org, &invite).await;
+
+        Ok(invite)
+    }
+
+    pub async fn revoke_invite(
+        &self,
+        org_id: Uuid,
+        invite_id: Uuid,
+        revoked_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let invite = self.find_invite(org_id, invite_id).await?;
+        self.ensure_can_grant(org_id, revoked_by, &invite.role)
+            .await?;
+
+        let deleted = self
+            .org_repo
+            .delete_invite(org_id, invite.id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !deleted {
+            return Err(CustomError::NotFound("Invite not found".to_string()));
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(revoked_by),
+                client,
+                AuditEntry::new(AuditEvent::MemberInviteRevoked, Some(invite.id))
+                    .before(json!({ "email": invite.email, "role": invite.role })),
+            )
+            .await;
+
+        Ok(())
     }
 
     pub async fn accept_org_invite(
@@ -153,43 +449,414 @@ impl OrgService {
         org_id: Uuid,
         user_id: Uuid,
         token: String,
+        client: &ClientInfo,
     ) -> Result<(), CustomError> {
-        let invite = self
-            .org_repo
-            .find_invite_by_token(token.clone())
+        let invite = self.find_user_invite(user_id, &token).await?;
+
+        // the org in the path has to be the one that issued the invite
+        if invite.org_id != org_id {
+            return Err(CustomError::NotFound("Invite not found".to_string()));
+        }
+
+        if invite.expires_at <= Utc::now() {
+            return Err(CustomError::Conflict(
+                "This invite has expired, ask for a new one".to_string(),
+                "ORG_009".to_string(),
+            ));
+        }
+
+        self.ensure_can_join(org_id, user_id).await?;
+
+        let member = CreateOrgMemberData {
+            org_id,
+            user_id,
+            role: invite.role,
+            invited_by: invite.invited_by,
+            join_method: JoinMethod::Invite,
+            invite_link_id: None,
+        };
+        let joined = member_joined(&member);
+        let joined_event = member_joined_event(&member);
+
+        // removing the invite in the same transaction means it can only be used once
+        self.org_repo
+            .accept_invite(token, member)
             .await
             .map_err(|e| match e {
                 sqlx::Error::RowNotFound => CustomError::NotFound("Invite not found".to_string()),
-                _ => CustomError::DatabaseError(e.to_string()),
+                _ => create_member_error(e),
             })?;
 
+        self.audit_log_repo
+            .log_event(org_id, Some(user_id), client, joined)
+            .await;
+
+        self.webhook_repo
+            .queue_event(org_id, WebhookEvent::MemberJoined, joined_event)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn decline_org_invite(
+        &self,
+        user_id: Uuid,
+        token: String,
+    ) -> Result<(), CustomError> {
+        self.find_user_invite(user_id, &token).await?;
+
         self.org_repo
-            .create_member(org_id, user_id, MemberRole::Member, invite.invited_by)
+            .remove_invite(token)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => CustomError::NotFound("Invite not found".to_string()),
+                _ => CustomError::DatabaseError(e.to_string()),
+            })
+    }
+
+    pub async fn create_invite_link(
+        &self,
+        org_id: Uuid,
+        created_by: Uuid,
+        data: CreateInviteLinkRequest,
+        client: &ClientInfo,
+    ) -> Result<OrgInviteLink, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let role = data.role.unwrap_or(MemberRole::Member);
+        if role == MemberRole::Owner {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "role",
+                ValidationError::new("owner_role")
+                    .with_message(Cow::Borrowed("Invite links cannot grant the owner role")),
+            );
+            return Err(CustomError::ValidationError(errors));
+        }
+        self.ensure_can_grant(org_id, created_by, &role).await?;
+
+        let link = self
+            .org_repo
+            .create_invite_link(
+                org_id,
+                role,
+                data.max_uses,
+                data.expires_in_days
+                    .map(|days| Utc::now() + Duration::days(days)),
+                created_by,
+            )
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
 
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(created_by),
+                client,
+                AuditEntry::new(AuditEvent::InviteLinkCreated, Some(link.id)).after(json!({
+                    "role": link.role,
+                    "max_uses": link.max_uses,
+                    "expires_at": link.expires_at,
+                })),
+            )
+            .await;
+
+        Ok(link)
+    }
+
+    pub async fn list_invite_links(&self, org_id: Uuid) -> Result<Vec<OrgInviteLink>, CustomError> {
         self.org_repo
-            .remove_invite(token)
+            .list_invite_links(org_id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
 
-    pub async fn decline_org_invite(&self, token: String) -> Result<(), CustomError> {
+    pub async fn revoke_invite_link(
+        &self,
+        org_id: Uuid,
+        link_id: Uuid,
+        revoked_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let link = self
+            .org_repo
+            .find_invite_link(org_id, link_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Invite link not found".to_string()))?;
+        self.ensure_can_grant(org_id, revoked_by, &link.role)
+            .await?;
+
+        let revoked = self
+            .org_repo
+            .revoke_invite_link(org_id, link.id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !revoked {
+            return Err(CustomError::NotFound("Invite link not found".to_string()));
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(revoked_by),
+                client,
+                AuditEntry::new(AuditEvent::InviteLinkRevoked, Some(link.id))
+                    .before(json!({ "role": link.role, "use_count": link.use_count })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn preview_invite_link(&self, token: &str) -> Result<InviteLinkPreview, CustomError> {
+        let link = self.find_usable_invite_link(token).await?;
+
+        let org = self
+            .org_repo
+            .public_find_by_id(link.org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(InviteLinkPreview {
+            org_id: org.id,
+            org_name: org.name,
+            org_logo: org.logo_url,
+            role: link.role,
+            expires_at: link.expires_at,
+        })
+    }
+
+    pub async fn redeem_invite_link(
+        &self,
+        user_id: Uuid,
+        token: &str,
+        client: &ClientInfo,
+    ) -> Result<Org, CustomError> {
+        let link = self.find_usable_invite_link(token).await?;
+        self.ensure_can_join(link.org_id, user_id).await?;
+
+        let claimed = self
+            .org_repo
+            .claim_invite_link_use(link.id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        // another redemption took the last use or the link was revoked meanwhile
+        if !claimed {
+            return Err(invite_link_unusable());
+        }
+
+        let member = CreateOrgMemberData {
+            org_id: link.org_id,
+            user_id,
+            role: link.role,
+            invited_by: link.created_by,
+            join_method: JoinMethod::InviteLink,
+            invite_link_id: Some(link.id),
+        };
+        let joined = member_joined(&member);
+        let joined_event = member_joined_event(&member);
+
+        if let Err(e) = self.org_repo.create_member(member).await {
+            if let Err(release_err) = self.org_repo.release_invite_link_use(link.id).await {
+                log::warn!(
+                    "Failed to release use of invite link {}: {}",
+                    link.id,
+                    release_err
+                );
+            }
+            return Err(create_member_error(e));
+        }
+
+        self.audit_log_repo
+            .log_event(link.org_id, Some(user_id), client, joined)
+            .await;
+
+        self.webhook_repo
+            .queue_event(link.org_id, WebhookEvent::MemberJoined, joined_event)
+            .await;
+
+        self.get_org(link.org_id, user_id).await
+    }
+
+    // the owner has to hold a verified address on the domain themselves
+    pub async fn add_domain(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+        data: AddOrgDomainRequest,
+        client: &ClientInfo,
+    ) -> Result<OrgDomain, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let domain = data.domain.to_lowercase();
+
+        if PUBLIC_EMAIL_DOMAINS.contains(&domain.as_str()) {
+            return Err(CustomError::Conflict(
+                "Public email providers cannot be registered as an organization domain".to_string(),
+                "ORG_011".to_string(),
+            ));
+        }
+
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !user.is_email_verified || email_domain(&user.email) != domain {
+            return Err(CustomError::Conflict(
+                "You need a verified email address on this domain to register it".to_string(),
+                "ORG_012".to_string(),
+            ));
+        }
+
+        let domain = self
+            .org_repo
+            .add_domain(org_id, &domain, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "This domain is already registered for the organization".to_string(),
+                "ORG_013".to_string(),
+            ))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(user_id),
+                client,
+                AuditEntry::new(AuditEvent::DomainAdded, Some(domain.id))
+                    .after(json!({ "domain": domain.domain })),
+            )
+            .await;
+
+        Ok(domain)
+    }
+
+    pub async fn list_domains(&self, org_id: Uuid) -> Result<Vec<OrgDomain>, CustomError> {
         self.org_repo
-            .remove_invite(token)
+            .list_domains(org_id)
             .await
-            .map_err(|e| match e {
-                sqlx::Error::RowNotFound => CustomError::NotFound("Invite not found".to_string()),
-                _ => CustomError::DatabaseError(e.to_string()),
-            })
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    // members who joined through the domain stay members
+    pub async fn remove_domain(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        domain_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let domain = self
+            .org_repo
+            .delete_domain(org_id, domain_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Domain not found".to_string()))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::DomainRemoved, Some(domain_id))
+                    .before(json!({ "domain": domain })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn list_joinable_orgs(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<JoinableOrgResponse>, CustomError> {
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !user.is_email_verified {
+            return Ok(Vec::new());
+        }
+
+        self.org_repo
+            .list_joinable_orgs(user_id, &email_domain(&user.email))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn join_by_domain(
+        &self,
+        user_id: Uuid,
+        org_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Org, CustomError> {
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        // orgs are not revealed to users who could not join them
+        let not_found = || CustomError::NotFound(format!("Org with id: {} not found", org_id));
+
+        if !user.is_email_verified {
+            return Err(not_found());
+        }
+
+        let domain = self
+            .org_repo
+            .find_domain(org_id, &email_domain(&user.email))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or_else(not_found)?;
+
+        self.ensure_can_join(org_id, user_id).await?;
+
+        let member = CreateOrgMemberData {
+            org_id,
+            user_id,
+            role: MemberRole::Member,
+            invited_by: domain.created_by,
+            join_method: JoinMethod::Domain,
+            invite_link_id: None,
+        };
+        let joined = member_joined(&member);
+        let joined_event = member_joined_event(&member);
+
+        self.org_repo
+            .create_member(member)
+            .await
+            .map_err(create_member_error)?;
+
+        self.audit_log_repo
+            .log_event(org_id, Some(user_id), client, joined)
+            .await;
+
+        self.webhook_repo
+            .queue_event(org_id, WebhookEvent::MemberJoined, joined_event)
+            .await;
+
+        self.get_org(org_id, user_id).await
     }
 
     pub async fn list_org_members(
         &self,
         org_id: Uuid,
+        status: MemberStatus,
     ) -> Result<Vec<OrgMemberResponse>, CustomError> {
         self.org_repo
-            .list_members(org_id)
+            .list_members(org_id, status)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
@@ -207,53 +874,235 @@ impl OrgService {
     pub async fn change_member_role(
         &self,
         org_id: Uuid,
+        actor_id: Uuid,
         member_id: Uuid,
         role: MemberRole,
+        client: &ClientInfo,
     ) -> Result<(), CustomError> {
-        if !self
+        self.ensure_can_grant(org_id, actor_id, &role).await?;
+
+        let previous_role = self
             .org_repo
-            .is_user_member_of_org(org_id, member_id)
+            .find_member(org_id, member_id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?
-        {
-            return Err(CustomError::NotFound("Member not found".to_string()));
-        }
+            .map(|member| member.role);
 
-        self.org_repo
-            .update_member_role(org_id, member_id, role)
+        let result = self
+            .org_repo
+            .update_member_role(org_id, member_id, role.clone())
             .await
-            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        member_change_result(result)?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::MemberRoleChanged, Some(member_id))
+                    .changes(json!({ "role": previous_role }), json!({ "role": role })),
+            )
+            .await;
+
+        Ok(())
     }
 
-    pub async fn remove_member(&self, org_id: Uuid, member_id: Uuid) -> Result<(), CustomError> {
-        if !self
+    // member management can be handed out through custom roles, so nobody may
+    // touch a member that ranks above them
+    pub async fn ensure_can_manage(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+    ) -> Result<(), CustomError> {
+        let member = self
             .org_repo
-            .is_user_member_of_org(org_id, member_id)
+            .find_member(org_id, member_id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))?
-        {
-            return Err(CustomError::NotFound("Member not found".to_string()));
-        }
+            .ok_or(CustomError::NotFound("Member not found".to_string()))?;
 
-        self.org_repo
+        self.ensure_can_grant(org_id, actor_id, &member.role).await
+    }
+
+    // recorded as leaving when members remove themselves
+    pub async fn remove_member(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let previous_role = self
+            .org_repo
+            .find_member(org_id, member_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .map(|member| member.role);
+
+        let result = self
+            .org_repo
             .remove_member(org_id, member_id)
             .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        member_change_result(result)?;
+
+        let event = match actor_id == member_id {
+            true => AuditEvent::MemberLeft,
+            false => AuditEvent::MemberRemoved,
+        };
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(event, Some(member_id)).before(json!({ "role": previous_role })),
+            )
+            .await;
+
+        self.user_preferences_repo
+            .clear_default_org(member_id, org_id)
+            .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
 
-    // i guess saving user id in the invite table is better solution
+    pub async fn transfer_ownership(
+        &self,
+        org: &Org,
+        owner_id: Uuid,
+        new_owner_id: Uuid,
+        data: TransferOwnershipRequest,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if data.confirmation != org.name {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "confirmation",
+                ValidationError::new("confirmation_mismatch")
+                    .with_message(Cow::Borrowed("Type the organization name to confirm")),
+            );
+            return Err(CustomError::ValidationError(errors));
+        }
+
+        if owner_id == new_owner_id {
+            return Err(CustomError::Conflict(
+                "You already own this organization".to_string(),
+                "ORG_003".to_string(),
+            ));
+        }
+
+        let result = self
+            .org_repo
+            .transfer_ownership(org.id, owner_id, new_owner_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        match result {
+            TransferOwnershipResult::Transferred => {}
+            TransferOwnershipResult::NotOwner => return Err(CustomError::Forbidden),
+            TransferOwnershipResult::MemberNotFound => {
+                return Err(CustomError::NotFound("Member not found".to_string()))
+            }
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org.id,
+                Some(owner_id),
+                client,
+                AuditEntry::new(AuditEvent::OwnershipTransferred, Some(new_owner_id)).changes(
+                    json!({ "owner_id": owner_id }),
+                    json!({ "owner_id": new_owner_id }),
+                ),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    // admins may suspend members, only owners may suspend admins
+    pub async fn suspend_member(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        self.change_member_status(
+            org_id,
+            actor_id,
+            member_id,
+            MemberStatus::Active,
+            MemberStatus::Disabled,
+            client,
+        )
+        .await
+    }
+
+    pub async fn reactivate_member(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        self.change_member_status(
+            org_id,
+            actor_id,
+            member_id,
+            MemberStatus::Disabled,
+            MemberStatus::Active,
+            client,
+        )
+        .await
+    }
+
+    pub async fn get_member_status(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<MemberStatus>, CustomError> {
+        let member = self
+            .org_repo
+            .find_member(org_id, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(member.map(|member| member.status))
+    }
+
+    // owners hand the org over first, so leaving can never orphan it
+    pub async fn leave_org(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let role = self.check_user_role(org_id, user_id).await?;
+
+        if role == MemberRole::Owner {
+            return Err(CustomError::Conflict(
+                "Transfer ownership before leaving the organization".to_string(),
+                "ORG_002".to_string(),
+            ));
+        }
+
+        self.remove_member(org_id, user_id, user_id, client).await
+    }
+
     pub async fn list_user_invites(
         &self,
         user_id: Uuid,
     ) -> Result<Vec<OrgMemberInviteResponse>, CustomError> {
-        let user = self
-            .user_repo
-            .get_user_by_id(user_id)
-            .map_err(|e| CustomError::DatabaseError(e.to_string()))
-            .await?;
-
         self.org_repo
-            .list_user_invites(user.email)
+            .list_user_invites(user_id)
             .await
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
@@ -269,42 +1118,376 @@ impl OrgService {
             .map_err(|e| CustomError::DatabaseError(e.to_string()))
     }
 
-    // temp solution
-    async fn generate_slug(&self, name: &str) -> String {
-        let sanitized = name
+    // the bare name first, then with a random suffix
+    async fn generate_slug(&self, name: &str) -> Result<String, CustomError> {
+        let base_slug = name
             .to_lowercase()
-            .chars()
-            .map(|c| match c {
-                'a'..='z' | '0'..='9' => c,
-                ' ' | '-' | '_' => '-',
-                _ => '-',
-            })
-            .collect::<String>();
-
-        let base_slug = sanitized
-            .split('-')
+            .split(|c: char| !c.is_ascii_alphanumeric())
             .filter(|s| !s.is_empty())
             .collect::<Vec<_>>()
             .join("-");
 
+        let base_slug = if base_slug.len() < 3 || Uuid::parse_str(&base_slug).is_ok() {
+            format!("org-{}", base_slug)
+                .trim_end_matches('-')
+                .to_string()
+        } else {
+            base_slug
+                .chars()
+                .take(50)
+                .collect::<String>()
+                .trim_end_matches('-')
+                .to_string()
+        };
+
+        if !self.is_slug_taken(&base_slug).await? {
+            return Ok(base_slug);
+        }
+
         for _ in 0..10 {
             let random_numbers: u32 = rand::thread_rng().gen_range(100..1000);
             let new_slug = format!("{}-{}", base_slug, random_numbers);
 
-            if self
+            if !self.is_slug_taken(&new_slug).await? {
+                return Ok(new_slug);
+            }
+        }
+
+        Ok(format!("{}-{}", base_slug, uuid::Uuid::new_v4()))
+    }
+
+    async fn is_slug_taken(&self, slug: &str) -> Result<bool, CustomError> {
+        self.org_repo
+            .is_slug_taken(slug)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    async fn change_identifier(&self, id: Uuid, custom_id: &str) -> Result<(), CustomError> {
+        let result = self
+            .org_repo
+            .change_identifier(id, custom_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => {
+                    CustomError::NotFound(format!("Org with id: {} not found", id))
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        match result {
+            IdentifierChangeResult::Changed => Ok(()),
+            IdentifierChangeResult::Taken => Err(CustomError::Conflict(
+                "This identifier is already in use".to_string(),
+                "ORG_014".to_string(),
+            )),
+            IdentifierChangeResult::UsedByTeam => Err(CustomError::Conflict(
+                "A team of this organization already uses this identifier as its key".to_string(),
+                "ORG_015".to_string(),
+            )),
+        }
+    }
+
+    // up to three letters or digits of the name, numbered on collisions
+    async fn generate_custom_id(&self, name: &str) -> Result<String, CustomError> {
+        let alphanumeric = name
+            .to_uppercase()
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .skip_while(|c| !c.is_ascii_alphabetic())
+            .take(3)
+            .collect::<String>();
+
+        let base = if alphanumeric.len() < 2 {
+            "ORG".to_string()
+        } else {
+            alphanumeric
+        };
+
+        for n in 1..1000 {
+            let custom_id = match n {
+                1 => base.clone(),
+                _ => format!("{}{}", base, n),
+            };
+
+            if !self
                 .org_repo
-                .public_find_by_slug(new_slug.clone())
+                .is_identifier_taken(&custom_id)
                 .await
-                .is_err()
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
             {
-                return new_slug;
+                return Ok(custom_id);
+            }
+        }
+
+        Err(CustomError::InternalServerError)
+    }
+
+    async fn ensure_can_grant(
+        &self,
+        org_id: Uuid,
+        user_id: Uuid,
+        role: &MemberRole,
+    ) -> Result<(), CustomError> {
+        if !self.check_user_role(org_id, user_id).await?.can_grant(role) {
+            return Err(CustomError::Forbidden);
+        }
+
+        Ok(())
+    }
+
+    // a suspended member must not be able to rejoin through a fresh invite or link
+    async fn ensure_can_join(&self, org_id: Uuid, user_id: Uuid) -> Result<(), CustomError> {
+        match self.get_member_status(org_id, user_id).await? {
+            Some(MemberStatus::Disabled) => Err(CustomError::MembershipSuspended),
+            Some(_) => Err(already_member()),
+            None => Ok(()),
+        }
+    }
+
+    async fn find_usable_invite_link(&self, token: &str) -> Result<OrgInviteLink, CustomError> {
+        let link = self
+            .org_repo
+            .find_invite_link_by_token(token)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .filter(|link| link.revoked_at.is_none())
+            .ok_or(CustomError::NotFound("Invite link not found".to_string()))?;
+
+        let expired = link
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now());
+        let used_up = link
+            .max_uses
+            .is_some_and(|max_uses| link.use_count >= max_uses);
+
+        if expired || used_up {
+            return Err(invite_link_unusable());
+        }
+
+        Ok(link)
+    }
+
+    async fn find_invite(
+        &self,
+        org_id: Uuid,
+        invite_id: Uuid,
+    ) -> Result<OrgMemberInvite, CustomError> {
+        self.org_repo
+            .find_invite(org_id, invite_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Invite not found".to_string()))
+    }
+
+    // an invite is only usable by the account it was attached to
+    async fn find_user_invite(
+        &self,
+        user_id: Uuid,
+        token: &str,
+    ) -> Result<OrgMemberInvite, CustomError> {
+        let invite = self
+            .org_repo
+            .find_invite_by_token(token.to_string())
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => CustomError::NotFound("Invite not found".to_string()),
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        if invite.user_id != Some(user_id) {
+            return Err(CustomError::NotFound("Invite not found".to_string()));
+        }
+
+        Ok(invite)
+    }
+
+    // the invite is listed in the app either way, so a mail failure is only logged
+    async fn send_invite_email(&self, org: &Org, invite: &OrgMemberInvite) {
+        let role = format!("{:?}", invite.role).to_lowercase();
+        // an account that still has to verify the address sees the invite once it does
+        let has_account = invite.user_id.is_some()
+            || self
+                .user_repo
+                .get_user_by_email(invite.email.clone())
+                .await
+                .is_ok();
+        let link = match has_account {
+            true => format!("{}/invites", self.app_url),
+            false => match reqwest::Url::parse_with_params(
+                &format!("{}/auth/register", self.app_url),
+                &[("email", invite.email.as_str())],
+            ) {
+                Ok(url) => url.to_string(),
+                Err(e) => {
+                    log::warn!(
+                        "Failed to build sign-up link for invite {}: {}",
+                        invite.id,
+                        e
+                    );
+                    return;
+                }
+            },
+        };
+
+        let message = EmailMessage {
+            to: invite.email.clone(),
+            subject: format!("You have been invited to join {}", org.name),
+            body: format!(
+                "You have been invited to join {} as {}. The invite expires on {}.\n\n{}",
+                org.name,
+                role,
+                invite.expires_at.format("%Y-%m-%d"),
+                link
+            ),
+        };
+
+        if let Err(e) = self.mailer.send(message).await {
+            log::warn!("Failed to send invite {}: {}", invite.id, e);
+        }
+    }
+
+    async fn change_member_status(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        member_id: Uuid,
+        from: MemberStatus,
+        to: MemberStatus,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let member = self
+            .org_repo
+            .find_member(org_id, member_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .filter(|member| member.status != MemberStatus::Invited)
+            .ok_or(CustomError::NotFound("Member not found".to_string()))?;
+
+        match member.role {
+            MemberRole::Owner => {
+                return Err(CustomError::Conflict(
+                    "Owners cannot be suspended".to_string(),
+                    "ORG_005".to_string(),
+                ))
             }
+            MemberRole::Admin => {
+                if self.check_user_role(org_id, actor_id).await? != MemberRole::Owner {
+                    return Err(CustomError::Forbidden);
+                }
+            }
+            MemberRole::Member => {}
+        }
+
+        if member.status != from {
+            return Err(CustomError::Conflict(
+                match to {
+                    MemberStatus::Disabled => "Member is already suspended".to_string(),
+                    _ => "Member is not suspended".to_string(),
+                },
+                "ORG_006".to_string(),
+            ));
+        }
+
+        let event = match to {
+            MemberStatus::Disabled => AuditEvent::MemberSuspended,
+            _ => AuditEvent::MemberReactivated,
+        };
+        let entry = AuditEntry::new(event, Some(member_id))
+            .changes(json!({ "status": from }), json!({ "status": to }));
+
+        let updated = self
+            .org_repo
+            .update_member_status(org_id, member_id, from, to)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        // the role or status changed between the lookup and the update
+        if !updated {
+            return Err(CustomError::Conflict(
+                "The member was changed concurrently, please try again".to_string(),
+                "ORG_006".to_string(),
+            ));
         }
 
-        format!("{}-{}", base_slug, uuid::Uuid::new_v4())
+        self.audit_log_repo
+            .log_event(org_id, Some(actor_id), client, entry)
+            .await;
+
+        Ok(())
     }
+}
+
+// the settings an admin can change, timestamps would show up in every diff
+fn org_settings(org: &Org) -> JsonValue {
+    json!({
+        "name": org.name,
+        "slug": org.slug,
+        "custom_id": org.custom_id,
+        "logo_url": org.logo_url,
+        "require_two_factor": org.require_two_factor,
+    })
+}
 
-    async fn generate_custom_id(&self, name: &str) -> String {
-        name.to_uppercase().chars().take(3).collect::<String>()
+fn member_joined(member: &CreateOrgMemberData) -> AuditEntry {
+    AuditEntry::new(AuditEvent::MemberJoined, Some(member.user_id)).after(json!({
+        "role": member.role,
+        "join_method": member.join_method,
+        "invite_link_id": member.invite_link_id,
+    }))
+}
+
+fn member_joined_event(member: &CreateOrgMemberData) -> JsonValue {
+    json!({
+        "user_id": member.user_id,
+        "role": member.role,
+        "join_method": member.join_method,
+    })
+}
+
+fn already_member() -> CustomError {
+    CustomError::Conflict(
+        "You are already a member of this organization".to_string(),
+        "ORG_007".to_string(),
+    )
+}
+
+fn invite_link_unusable() -> CustomError {
+    CustomError::Conflict(
+        "This invite link has expired or reached its maximum number of uses".to_string(),
+        "ORG_010".to_string(),
+    )
+}
+
+// a concurrent join of the same user loses on the unique membership constraint
+fn create_member_error(e: sqlx::Error) -> CustomError {
+    match e {
+        sqlx::Error::Database(db_err) if db_err.is_unique_violation() => already_member(),
+        _ => CustomError::DatabaseError(e.to_string()),
     }
 }
+
+fn email_domain(email: &str) -> String {
+    email
+        .rsplit_once('@')
+        .map(|(_, domain)| domain.to_lowercase())
+        .unwrap_or_default()
+}
+
+fn member_change_result(result: MemberChangeResult) -> Result<(), CustomError> {
+    match result {
+        MemberChangeResult::Changed => Ok(()),
+        MemberChangeResult::NotFound => Err(CustomError::NotFound("Member not found".to_string())),
+        MemberChangeResult::LastOwner => Err(CustomError::Conflict(
+            "An organization needs at least one owner, transfer ownership first".to_string(),
+            "ORG_001".to_string(),
+        )),
+    }
+}
+
+fn slug_taken() -> CustomError {
+    CustomError::Conflict(
+        "This slug is already in use".to_string(),
+        "ORG_016".to_string(),
+    )
+}
@@ -0,0 +1,198 @@
+use std::io::{Cursor, Write};
+
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+use crate::{
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
+        org_export::{ExportEntity, OrgExport},
+    },
+    repositories::{audit_log::AuditLogRepository, org_export::OrgExportRepository},
+};
+
+const EXPORT_RETENTION_DAYS: i64 = 7;
+// an export that has not finished by then was cut off by a restart
+const STALE_EXPORT_SECS: i64 = 60 * 60;
+
+pub struct OrgExportService {
+    pool: PgPool,
+    export_repo: OrgExportRepository,
+    audit_log_repo: AuditLogRepository,
+}
+
+impl OrgExportService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            export_repo: OrgExportRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool.clone()),
+            pool,
+        }
+    }
+
+    // the archive is built in the background, clients poll the export until it completes
+    pub async fn request_export(
+        &self,
+        org_id: Uuid,
+        requested_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<OrgExport, CustomError> {
+        let export = self
+            .export_repo
+            .create_export(org_id, requested_by)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "An export of this organization is already in progress".to_string(),
+                "ORG_019".to_string(),
+            ))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(requested_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgExportRequested, Some(export.id)),
+            )
+            .await;
+
+        actix_web::rt::spawn(run_export(
+            OrgExportRepository::new(self.pool.clone()),
+            export.id,
+            org_id,
+        ));
+
+        Ok(export)
+    }
+
+    pub async fn list_exports(&self, org_id: Uuid) -> Result<Vec<OrgExport>, CustomError> {
+        self.export_repo
+            .list_exports(org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_export(
+        &self,
+        org_id: Uuid,
+        export_id: Uuid,
+    ) -> Result<OrgExport, CustomError> {
+        self.export_repo
+            .find_export(org_id, export_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Export not found".to_string()))
+    }
+
+    pub async fn download_export(
+        &self,
+        org_id: Uuid,
+        export_id: Uuid,
+        downloaded_by: Uuid,
+        client: &ClientInfo,
+    ) -> Result<Vec<u8>, CustomError> {
+        let archive = self
+            .export_repo
+            .find_archive(org_id, export_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound(
+                "Export not found, not finished yet or expired".to_string(),
+            ))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(downloaded_by),
+                client,
+                AuditEntry::new(AuditEvent::OrgExportDownloaded, Some(export_id))
+                    .after(json!({ "archive_size": archive.len() })),
+            )
+            .await;
+
+        Ok(archive)
+    }
+
+    pub async fn clean_up_exports(&self) -> Result<(), CustomError> {
+        let failed = self
+            .export_repo
+            .fail_stale_exports(STALE_EXPORT_SECS)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let deleted = self
+            .export_repo
+            .delete_expired_exports()
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if failed > 0 || deleted > 0 {
+            log::info!(
+                "Marked {} stale exports as failed, deleted {} expired exports",
+                failed,
+                deleted
+            );
+        }
+
+        Ok(())
+    }
+}
+
+async fn run_export(export_repo: OrgExportRepository, export_id: Uuid, org_id: Uuid) {
+    match export_repo.mark_running(export_id).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            log::error!("Failed to start export {}: {}", export_id, e);
+            return;
+        }
+    }
+
+    let result = match build_archive(&export_repo, org_id).await {
+        Ok(archive) => {
+            export_repo
+                .complete_export(
+                    export_id,
+                    &archive,
+                    Utc::now() + Duration::days(EXPORT_RETENTION_DAYS),
+                )
+                .await
+        }
+        Err(e) => {
+            log::error!("Export {} failed: {}", export_id, e);
+            export_repo
+                .fail_export(export_id, "The export could not be generated")
+                .await
+        }
+    };
+
+    if let Err(e) = result {
+        log::error!("Failed to store the result of export {}: {}", export_id, e);
+    }
+}
+
+async fn build_archive(export_repo: &OrgExportRepository, org_id: Uuid) -> Result<Vec<u8>, String> {
+    let mut archive = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    for entity in ExportEntity::ALL {
+        let data = export_repo
+            .export_entity(org_id, entity)
+            .await
+            .map_err(|e| e.to_string())?;
+        let content = serde_json::to_vec_pretty(&data).map_err(|e| e.to_string())?;
+
+        archive
+            .start_file(entity.file_name(), options)
+            .map_err(|e| e.to_string())?;
+        archive.write_all(&content).map_err(|e| e.to_string())?;
+    }
+
+    let archive = archive.finish().map_err(|e| e.to_string())?;
+    Ok(archive.into_inner())
+}
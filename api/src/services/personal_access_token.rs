@@ -0,0 +1,235 @@
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        auth::AuthEvent,
+        context::{AccessTokenContext, ClientInfo},
+        personal_access_token::{
+            CreatePersonalAccessTokenData, CreatePersonalAccessTokenRequest,
+            CreatePersonalAccessTokenResponse, PersonalAccessToken,
+        },
+    },
+    repositories::{
+        audit_log::AuditLogRepository, auth_log::AuthLogRepository, org::OrgRepository,
+        personal_access_token::PersonalAccessTokenRepository,
+    },
+};
+
+// lets the token extractor tell personal access tokens apart from paseto tokens
+pub const TOKEN_PREFIX: &str = "iap_";
+const TOKEN_SECRET_LENGTH: usize = 40;
+const DISPLAY_PREFIX_LENGTH: usize = 12;
+
+pub struct PersonalAccessTokenService {
+    token_repo: PersonalAccessTokenRepository,
+    org_repo: OrgRepository,
+    auth_log_repo: AuthLogRepository,
+    audit_log_repo: AuditLogRepository,
+}
+
+impl PersonalAccessTokenService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            token_repo: PersonalAccessTokenRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool),
+        }
+    }
+
+    pub async fn create_token(
+        &self,
+        user_id: Uuid,
+        request: CreatePersonalAccessTokenRequest,
+        client: &ClientInfo,
+    ) -> Result<CreatePersonalAccessTokenResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(org_ids) = &request.org_ids {
+            for org_id in org_ids {
+                let is_member = self
+                    .org_repo
+                    .is_user_member_of_org(*org_id, user_id)
+                    .await
+                    .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+                if !is_member {
+                    return Err(CustomError::NotFound(format!("Org with id: {}", org_id)));
+                }
+            }
+        }
+
+        let mut scopes: Vec<String> = request
+            .scopes
+            .iter()
+            .map(|scope| scope.as_str().to_string())
+            .collect();
+        scopes.sort();
+        scopes.dedup();
+
+        let token = generate_token();
+
+        let personal_access_token = self
+            .token_repo
+            .create_token(CreatePersonalAccessTokenData {
+                user_id,
+                name: request.name,
+                token_prefix: token[..DISPLAY_PREFIX_LENGTH].to_string(),
+                token_hash: hash_token(&token),
+                scopes,
+                org_ids: request.org_ids,
+                expires_at: request
+                    .expires_in_days
+                    .map(|days| Utc::now() + Duration::days(days)),
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::AccessTokenCreated,
+                client,
+                Some(json!({ "token_id": personal_access_token.id })),
+            )
+            .await;
+
+        self.log_token_created(&personal_access_token, client)
+            .await?;
+
+        Ok(CreatePersonalAccessTokenResponse {
+            token,
+            personal_access_token,
+        })
+    }
+
+    pub async fn list_tokens(
+        &self,
+        user_id: Uuid,
+    ) -> Result<Vec<PersonalAccessToken>, CustomError> {
+        self.token_repo
+            .list_user_tokens(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn revoke_token(
+        &self,
+        user_id: Uuid,
+        token_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let revoked = self
+            .token_repo
+            .revoke_token(token_id, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !revoked {
+            return Err(CustomError::NotFound(format!(
+                "Access token with id: {}",
+                token_id
+            )));
+        }
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::AccessTokenRevoked,
+                client,
+                Some(json!({ "token_id": token_id })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn authenticate(
+        &self,
+        token: &str,
+    ) -> Result<Option<(Uuid, AccessTokenContext)>, CustomError> {
+        let personal_access_token = self
+            .token_repo
+            .use_token(&hash_token(token))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(personal_access_token.map(|personal_access_token| {
+            (
+                personal_access_token.user_id,
+                AccessTokenContext {
+                    token_id: personal_access_token.id,
+                    scopes: personal_access_token
+                        .scopes
+                        .iter()
+                        .filter_map(|scope| scope.parse().ok())
+                        .collect(),
+                    org_ids: personal_access_token.org_ids,
+                },
+            )
+        }))
+    }
+
+    // every org the token can reach sees it in its audit log, unrestricted
+    // tokens reach all orgs the user is an active member of
+    async fn log_token_created(
+        &self,
+        token: &PersonalAccessToken,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let org_ids = match &token.org_ids {
+            Some(org_ids) => org_ids.clone(),
+            None => self
+                .org_repo
+                .list_user_orgs(token.user_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+                .into_iter()
+                .map(|org| org.id)
+                .collect(),
+        };
+
+        for org_id in org_ids {
+            self.audit_log_repo
+                .log_event(
+                    org_id,
+                    Some(token.user_id),
+                    client,
+                    AuditEntry::new(AuditEvent::AccessTokenCreated, Some(token.id)).after(json!({
+                        "name": token.name,
+                        "scopes": token.scopes,
+                        "org_ids": token.org_ids,
+                        "expires_at": token.expires_at,
+                    })),
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+}
+
+fn generate_token() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+
+    format!("{}{}", TOKEN_PREFIX, secret)
+}
+
+// tokens are long and random so a fast hash is enough, and lets us look them up by hash
+fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
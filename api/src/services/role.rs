@@ -0,0 +1,289 @@
+use serde_json::{json, Value as JsonValue};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
+        org::{MemberRole, MemberStatus},
+        role::{
+            BuiltInRoleResponse, CreateOrgRoleRequest, OrgRole, OrgRolesResponse, Permission,
+            RoleWriteResult, UpdateOrgRoleRequest,
+        },
+    },
+    repositories::{audit_log::AuditLogRepository, org::OrgRepository, role::RoleRepository},
+};
+
+pub struct RoleService {
+    role_repo: RoleRepository,
+    org_repo: OrgRepository,
+    audit_log_repo: AuditLogRepository,
+}
+
+impl RoleService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            role_repo: RoleRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool),
+        }
+    }
+
+    pub async fn list_roles(&self, org_id: Uuid) -> Result<OrgRolesResponse, CustomError> {
+        let custom = self
+            .role_repo
+            .list_roles(org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let built_in = [MemberRole::Owner, MemberRole::Admin, MemberRole::Member]
+            .into_iter()
+            .map(|role| BuiltInRoleResponse {
+                permissions: role.default_permissions(),
+                role,
+            })
+            .collect();
+
+        Ok(OrgRolesResponse { built_in, custom })
+    }
+
+    pub async fn create_role(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        actor_permissions: &[Permission],
+        data: CreateOrgRoleRequest,
+        client: &ClientInfo,
+    ) -> Result<OrgRole, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        ensure_can_grant(actor_permissions, &data.permissions)?;
+
+        let result = self
+            .role_repo
+            .create_role(
+                org_id,
+                data.name.trim(),
+                &permission_names(&data.permissions),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+        let role = role_write_result(result)?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::RoleCreated, Some(role.id)).after(role_settings(&role)),
+            )
+            .await;
+
+        Ok(role)
+    }
+
+    pub async fn update_role(
+        &self,
+        org_id: Uuid,
+        role_id: Uuid,
+        actor_id: Uuid,
+        actor_permissions: &[Permission],
+        data: UpdateOrgRoleRequest,
+        client: &ClientInfo,
+    ) -> Result<OrgRole, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let before = self.find_role(org_id, role_id).await?;
+
+        if let Some(permissions) = &data.permissions {
+            ensure_can_grant(actor_permissions, permissions)?;
+        }
+
+        let result = self
+            .role_repo
+            .update_role(
+                org_id,
+                role_id,
+                data.name.map(|name| name.trim().to_string()),
+                data.permissions.as_deref().map(permission_names),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Role not found".to_string()))?;
+        let role = role_write_result(result)?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::RoleUpdated, Some(role.id))
+                    .changes(role_settings(&before), role_settings(&role)),
+            )
+            .await;
+
+        Ok(role)
+    }
+
+    pub async fn delete_role(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        role_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let role = self.find_role(org_id, role_id).await?;
+
+        let deleted = self
+            .role_repo
+            .delete_role(org_id, role_id)
+            .await
+            .map_err(|e| match e {
+                // members would silently fall back to their built-in defaults
+                sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                    CustomError::Conflict(
+                        "This role is still assigned to members, assign them another role first"
+                            .to_string(),
+                        "ROLE_003".to_string(),
+                    )
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        if !deleted {
+            return Err(CustomError::NotFound("Role not found".to_string()));
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::RoleDeleted, Some(role.id))
+                    .before(role_settings(&role)),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    // handing out a custom role is limited to what the actor holds as well
+    pub async fn assign_role(
+        &self,
+        org_id: Uuid,
+        member_id: Uuid,
+        role_id: Option<Uuid>,
+        actor_id: Uuid,
+        actor_permissions: &[Permission],
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let member = self
+            .org_repo
+            .find_member(org_id, member_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .filter(|member| member.status != MemberStatus::Invited)
+            .ok_or(CustomError::NotFound("Member not found".to_string()))?;
+
+        if member.role == MemberRole::Owner {
+            return Err(CustomError::Conflict(
+                "Owners always hold every permission".to_string(),
+                "ROLE_002".to_string(),
+            ));
+        }
+
+        // clearing the role hands back the built-in defaults, which may be more than the actor holds
+        let permissions = match role_id {
+            Some(role_id) => {
+                let role = self.find_role(org_id, role_id).await?;
+                Permission::resolve(&member.role, Some(role.permissions.as_slice()))
+            }
+            None => member.role.default_permissions(),
+        };
+        ensure_can_grant(actor_permissions, &permissions)?;
+
+        let assigned = self
+            .role_repo
+            .assign_role(org_id, member_id, role_id)
+            .await
+            .map_err(|e| match e {
+                // the role was deleted in the meantime
+                sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                    CustomError::NotFound("Role not found".to_string())
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        // the member became an owner or left between the lookup and the update
+        if !assigned {
+            return Err(CustomError::NotFound("Member not found".to_string()));
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::MemberRoleChanged, Some(member_id)).changes(
+                    json!({ "custom_role_id": member.custom_role_id }),
+                    json!({ "custom_role_id": role_id }),
+                ),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn find_role(&self, org_id: Uuid, role_id: Uuid) -> Result<OrgRole, CustomError> {
+        self.role_repo
+            .find_role(org_id, role_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Role not found".to_string()))
+    }
+}
+
+fn ensure_can_grant(
+    actor_permissions: &[Permission],
+    permissions: &[Permission],
+) -> Result<(), CustomError> {
+    if !permissions
+        .iter()
+        .all(|permission| actor_permissions.contains(permission))
+    {
+        return Err(CustomError::Forbidden);
+    }
+
+    Ok(())
+}
+
+fn role_settings(role: &OrgRole) -> JsonValue {
+    json!({ "name": role.name, "permissions": role.permissions })
+}
+
+fn permission_names(permissions: &[Permission]) -> Vec<String> {
+    let mut names = permissions
+        .iter()
+        .map(|permission| permission.as_str().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn role_write_result(result: RoleWriteResult) -> Result<OrgRole, CustomError> {
+    match result {
+        RoleWriteResult::Written(role) => Ok(role),
+        RoleWriteResult::NameTaken => Err(CustomError::Conflict(
+            "Another role in this organization already uses this name".to_string(),
+            "ROLE_001".to_string(),
+        )),
+    }
+}
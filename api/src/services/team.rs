@@ -0,0 +1,175 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    errors::CustomError,
+    models::team::{
+        AddTeamMemberResult, CreateTeamRequest, Team, TeamResponse, TeamWriteResult,
+        UpdateTeamRequest,
+    },
+    repositories::team::TeamRepository,
+};
+
+pub struct TeamService {
+    team_repo: TeamRepository,
+}
+
+impl TeamService {
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            team_repo: TeamRepository::new(pool),
+        }
+    }
+
+    pub async fn create_team(
+        &self,
+        org_id: Uuid,
+        data: CreateTeamRequest,
+    ) -> Result<Team, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let result = self
+            .team_repo
+            .create_team(org_id, data)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        team_write_result(result)
+    }
+
+    pub async fn list_teams(&self, org_id: Uuid) -> Result<Vec<Team>, CustomError> {
+        self.team_repo
+            .list_teams(org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_team(&self, org_id: Uuid, team_id: Uuid) -> Result<TeamResponse, CustomError> {
+        let team = self.find_team(org_id, team_id).await?;
+
+        let members = self
+            .team_repo
+            .list_team_members(team.id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(TeamResponse { team, members })
+    }
+
+    pub async fn update_team(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+        data: UpdateTeamRequest,
+    ) -> Result<Team, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let result = self
+            .team_repo
+            .update_team(org_id, team_id, data)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Team not found".to_string()))?;
+
+        team_write_result(result)
+    }
+
+    // issue numbers belong to the team key, so a team with issues cannot go away
+    pub async fn delete_team(&self, org_id: Uuid, team_id: Uuid) -> Result<(), CustomError> {
+        let deleted = self
+            .team_repo
+            .delete_team(org_id, team_id)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::Database(db_err) if db_err.is_foreign_key_violation() => {
+                    CustomError::Conflict(
+                        "Move or delete the issues of this team first".to_string(),
+                        "TEAM_002".to_string(),
+                    )
+                }
+                _ => CustomError::DatabaseError(e.to_string()),
+            })?;
+
+        if !deleted {
+            return Err(CustomError::NotFound("Team not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    pub async fn add_team_member(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), CustomError> {
+        let team = self.find_team(org_id, team_id).await?;
+
+        let result = self
+            .team_repo
+            .add_team_member(org_id, team.id, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        match result {
+            AddTeamMemberResult::Added => Ok(()),
+            AddTeamMemberResult::AlreadyMember => Err(CustomError::Conflict(
+                "This user is already a member of the team".to_string(),
+                "TEAM_004".to_string(),
+            )),
+            AddTeamMemberResult::NotOrgMember => Err(not_active_member()),
+        }
+    }
+
+    pub async fn remove_team_member(
+        &self,
+        org_id: Uuid,
+        team_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<(), CustomError> {
+        let team = self.find_team(org_id, team_id).await?;
+
+        let removed = self
+            .team_repo
+            .remove_team_member(team.id, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !removed {
+            return Err(CustomError::NotFound("Team member not found".to_string()));
+        }
+
+        Ok(())
+    }
+
+    async fn find_team(&self, org_id: Uuid, team_id: Uuid) -> Result<Team, CustomError> {
+        self.team_repo
+            .find_team(org_id, team_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Team not found".to_string()))
+    }
+}
+
+fn team_write_result(result: TeamWriteResult) -> Result<Team, CustomError> {
+    match result {
+        TeamWriteResult::Written(team) => Ok(team),
+        TeamWriteResult::KeyTaken => Err(CustomError::Conflict(
+            "This key is already used by another team or by the organization".to_string(),
+            "TEAM_001".to_string(),
+        )),
+        TeamWriteResult::LeadNotMember => Err(not_active_member()),
+    }
+}
+
+fn not_active_member() -> CustomError {
+    CustomError::Conflict(
+        "Only active members of the organization can be added to a team".to_string(),
+        "TEAM_003".to_string(),
+    )
+}
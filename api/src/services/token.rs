@@ -1,23 +1,50 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use rusty_paseto::prelude::*;
-use serde_json::Value;
-use std::convert::TryFrom;
+use serde_json::{json, Value};
+use std::{collections::HashMap, convert::TryFrom};
 use time::{Duration, OffsetDateTime};
 use uuid::Uuid;
 
-use crate::errors::CustomError;
+use crate::{config::TokenConfig, errors::CustomError};
 
-const ACCESS_TOKEN_EXPIRATION: i64 = 15; // 15 minutes
-const REFRESH_TOKEN_EXPIRATION: i64 = 7 * 24 * 60; // 7 days in minutes
+const TWO_FACTOR_CHALLENGE_EXPIRATION: i64 = 5; // 5 minutes
 
 pub struct TokenService {
-    key: PasetoSymmetricKey<V4, Local>,
+    keys: HashMap<String, PasetoSymmetricKey<V4, Local>>,
+    active_key_id: String,
+    // carries the key id so verification knows which key to use after a rotation
+    active_footer: String,
+    access_token_expiration: i64,
+    refresh_token_expiration: i64,
 }
 
 impl TokenService {
-    pub fn new() -> Result<Self, CustomError> {
-        let key =
-            PasetoSymmetricKey::<V4, Local>::from(Key::from(b"wubbalubbadubdubwubbalubbadubdub")); // -> 32 bytes -> no production use
-        Ok(Self { key })
+    pub fn new(config: &TokenConfig) -> Result<Self, CustomError> {
+        let keys = config
+            .keys
+            .iter()
+            .map(|token_key| {
+                (
+                    token_key.id.clone(),
+                    PasetoSymmetricKey::<V4, Local>::from(Key::from(token_key.key)),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        if !keys.contains_key(&config.active_key_id) {
+            return Err(CustomError::ConfigError(format!(
+                "No PASETO key with id {}",
+                config.active_key_id
+            )));
+        }
+
+        Ok(Self {
+            keys,
+            active_key_id: config.active_key_id.clone(),
+            active_footer: json!({ "kid": config.active_key_id }).to_string(),
+            access_token_expiration: config.access_token_expiration,
+            refresh_token_expiration: config.refresh_token_expiration,
+        })
     }
 
     pub async fn generate_token_pair(
@@ -26,7 +53,7 @@ impl TokenService {
     ) -> Result<(String, String, i64, i64), CustomError> {
         let now = OffsetDateTime::now_utc();
 
-        let access_exp = (now + Duration::minutes(ACCESS_TOKEN_EXPIRATION))
+        let access_exp = (now + Duration::minutes(self.access_token_expiration))
             .format(&time::format_description::well_known::Rfc3339)
             .map_err(|_| CustomError::InternalServerError)?;
 
@@ -44,10 +71,11 @@ impl TokenService {
                 CustomClaim::try_from(("type", "access"))
                     .map_err(|_| CustomError::InternalServerError)?,
             )
-            .build(&self.key)
+            .set_footer(Footer::from(self.active_footer.as_str()))
+            .build(self.active_key())
             .map_err(|_| CustomError::InternalServerError)?;
 
-        let refresh_exp = (now + Duration::minutes(REFRESH_TOKEN_EXPIRATION))
+        let refresh_exp = (now + Duration::minutes(self.refresh_token_expiration))
             .format(&time::format_description::well_known::Rfc3339)
             .map_err(|_| CustomError::InternalServerError)?;
 
@@ -64,36 +92,66 @@ impl TokenService {
                 CustomClaim::try_from(("type", "refresh"))
                     .map_err(|_| CustomError::InternalServerError)?,
             )
-            .build(&self.key)
+            .set_footer(Footer::from(self.active_footer.as_str()))
+            .build(self.active_key())
             .map_err(|_| CustomError::InternalServerError)?;
 
         Ok((
             access_token,
             refresh_token,
-            ACCESS_TOKEN_EXPIRATION,
-            REFRESH_TOKEN_EXPIRATION,
+            self.access_token_expiration,
+            self.refresh_token_expiration,
         ))
     }
 
-    pub async fn verify_access_token(&self, token: &str) -> Result<Value, CustomError> {
-        let claims = PasetoParser::<V4, Local>::default()
-            .parse(token, &self.key)
-            .map_err(|_| CustomError::InvalidToken("Invalid token".to_string()))?;
+    pub async fn generate_two_factor_challenge(
+        &self,
+        user_id: Uuid,
+    ) -> Result<(String, i64), CustomError> {
+        let exp = (OffsetDateTime::now_utc() + Duration::minutes(TWO_FACTOR_CHALLENGE_EXPIRATION))
+            .format(&time::format_description::well_known::Rfc3339)
+            .map_err(|_| CustomError::InternalServerError)?;
 
-        if claims["type"].as_str() != Some("access") {
-            return Err(CustomError::InvalidToken("Not an access token".to_string()));
-        }
+        let user_id_str = user_id.to_string();
+        let token_id = Uuid::new_v4().to_string();
+        let token = PasetoBuilder::<V4, Local>::default()
+            .set_claim(SubjectClaim::from(user_id_str.as_str()))
+            .set_claim(TokenIdentifierClaim::from(token_id.as_str()))
+            .set_claim(
+                ExpirationClaim::try_from(exp.as_str())
+                    .map_err(|_| CustomError::InternalServerError)?,
+            )
+            .set_claim(
+                CustomClaim::try_from(("type", "two_factor_challenge"))
+                    .map_err(|_| CustomError::InternalServerError)?,
+            )
+            .set_footer(Footer::from(self.active_footer.as_str()))
+            .build(self.active_key())
+            .map_err(|_| CustomError::InternalServerError)?;
 
-        Ok(claims)
+        Ok((token, TWO_FACTOR_CHALLENGE_EXPIRATION))
+    }
+
+    pub async fn verify_two_factor_challenge(&self, token: &str) -> Result<Uuid, CustomError> {
+        let claims = self
+            .parse_token(token, "two_factor_challenge")
+            .map_err(|_| CustomError::InvalidToken("Invalid challenge token".to_string()))?;
+
+        let user_id_str = claims["sub"]
+            .as_str()
+            .ok_or(CustomError::InvalidToken("Missing sub claim".to_string()))?;
+
+        Uuid::parse_str(user_id_str)
+            .map_err(|_| CustomError::InvalidToken("Invalid user ID".to_string()))
+    }
+
+    pub async fn verify_access_token(&self, token: &str) -> Result<Value, CustomError> {
+        self.parse_token(token, "access")
     }
 
     pub async fn verify_refresh_token(&self, token: &str) -> Result<String, CustomError> {
-        let claims = PasetoParser::<V4, Local>::default()
-            .check_claim(
-                CustomClaim::try_from(("type", "refresh"))
-                    .map_err(|_| CustomError::InternalServerError)?,
-            )
-            .parse(token, &self.key)
+        let claims = self
+            .parse_token(token, "refresh")
             .map_err(|_| CustomError::InvalidToken("Invalid refresh token".to_string()))?;
 
         claims["sub"]
@@ -112,4 +170,125 @@ impl TokenService {
         Uuid::parse_str(user_id_str)
             .map_err(|_| CustomError::InvalidToken("Invalid user ID".to_string()))
     }
+
+    fn active_key(&self) -> &PasetoSymmetricKey<V4, Local> {
+        &self.keys[&self.active_key_id]
+    }
+
+    // picks the key named in the footer, tokens without one are tried against every key
+    fn parse_token(&self, token: &str, token_type: &str) -> Result<Value, CustomError> {
+        let footer = extract_footer(token);
+        let key_id = footer
+            .as_deref()
+            .and_then(|footer| serde_json::from_str::<Value>(footer).ok())
+            .and_then(|footer| footer["kid"].as_str().map(str::to_string));
+
+        let candidates: Vec<&PasetoSymmetricKey<V4, Local>> = match &key_id {
+            Some(key_id) => self.keys.get(key_id).into_iter().collect(),
+            None => self.keys.values().collect(),
+        };
+
+        let claims = candidates
+            .into_iter()
+            .find_map(|key| {
+                let mut parser = PasetoParser::<V4, Local>::default();
+                if let Some(footer) = footer.as_deref() {
+                    parser.set_footer(Footer::from(footer));
+                }
+                parser.parse(token, key).ok()
+            })
+            .ok_or(CustomError::InvalidToken("Invalid token".to_string()))?;
+
+        if claims["type"].as_str() != Some(token_type) {
+            return Err(CustomError::InvalidToken(
+                "Unexpected token type".to_string(),
+            ));
+        }
+
+        Ok(claims)
+    }
+}
+
+fn extract_footer(token: &str) -> Option<String> {
+    let encoded = token.splitn(4, '.').nth(3)?;
+    let decoded = URL_SAFE_NO_PAD.decode(encoded).ok()?;
+    String::from_utf8(decoded).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TokenKey;
+
+    fn service(keys: &[(&str, u8)], active_key_id: &str) -> TokenService {
+        TokenService::new(&TokenConfig {
+            keys: keys
+                .iter()
+                .map(|(id, byte)| TokenKey {
+                    id: id.to_string(),
+                    key: [*byte; 32],
+                })
+                .collect(),
+            active_key_id: active_key_id.to_string(),
+            access_token_expiration: 15,
+            refresh_token_expiration: 60,
+            magic_link_expiration: 15,
+        })
+        .unwrap()
+    }
+
+    fn key_id(token: &str) -> Option<String> {
+        let footer = extract_footer(token)?;
+        let footer = serde_json::from_str::<Value>(&footer).ok()?;
+        footer["kid"].as_str().map(str::to_string)
+    }
+
+    #[actix_web::test]
+    async fn tokens_of_the_previous_key_are_accepted_during_rotation() {
+        let user_id = Uuid::new_v4();
+        let before = service(&[("old", 1)], "old");
+        let (access_token, refresh_token, _, _) =
+            before.generate_token_pair(user_id).await.unwrap();
+        assert_eq!(key_id(&access_token).as_deref(), Some("old"));
+
+        let rotated = service(&[("new", 2), ("old", 1)], "new");
+        assert_eq!(
+            rotated.extract_user_id(&access_token).await.unwrap(),
+            user_id
+        );
+        assert_eq!(
+            rotated.verify_refresh_token(&refresh_token).await.unwrap(),
+            user_id.to_string()
+        );
+
+        let (access_token, _, _, _) = rotated.generate_token_pair(user_id).await.unwrap();
+        assert_eq!(key_id(&access_token).as_deref(), Some("new"));
+        assert_eq!(
+            rotated.extract_user_id(&access_token).await.unwrap(),
+            user_id
+        );
+    }
+
+    #[actix_web::test]
+    async fn tokens_of_a_removed_key_are_rejected() {
+        let before = service(&[("old", 1)], "old");
+        let (access_token, _, _, _) = before.generate_token_pair(Uuid::new_v4()).await.unwrap();
+
+        let after = service(&[("new", 2)], "new");
+        assert!(after.extract_user_id(&access_token).await.is_err());
+
+        // a different key under the same id doesn't verify either
+        let reused_id = service(&[("old", 3)], "old");
+        assert!(reused_id.extract_user_id(&access_token).await.is_err());
+    }
+
+    #[actix_web::test]
+    async fn token_types_are_not_interchangeable() {
+        let tokens = service(&[("k", 1)], "k");
+        let (access_token, refresh_token, _, _) =
+            tokens.generate_token_pair(Uuid::new_v4()).await.unwrap();
+
+        assert!(tokens.verify_refresh_token(&access_token).await.is_err());
+        assert!(tokens.verify_access_token(&refresh_token).await.is_err());
+    }
 }
@@ -0,0 +1,297 @@
+use chrono::Utc;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::Config,
+    errors::CustomError,
+    models::{
+        auth::AuthEvent,
+        context::ClientInfo,
+        two_factor::{
+            RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorEnrollResponse,
+            TwoFactorStatusResponse,
+        },
+    },
+    repositories::{
+        auth_log::AuthLogRepository, two_factor::TwoFactorRepository, user::UserRepository,
+    },
+    utils::totp,
+};
+
+use super::lockout::LockoutService;
+
+pub enum VerifiedCode {
+    Totp,
+    RecoveryCode,
+}
+
+pub struct TwoFactorService {
+    two_factor_repo: TwoFactorRepository,
+    user_repo: UserRepository,
+    auth_log_repo: AuthLogRepository,
+    lockout_service: Arc<LockoutService>,
+    issuer: String,
+}
+
+impl TwoFactorService {
+    pub fn new(pool: PgPool, config: &Config, lockout_service: Arc<LockoutService>) -> Self {
+        Self {
+            two_factor_repo: TwoFactorRepository::new(pool.clone()),
+            user_repo: UserRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool),
+            lockout_service,
+            issuer: config.totp_issuer.clone(),
+        }
+    }
+
+    pub async fn is_enabled(&self, user_id: Uuid) -> Result<bool, CustomError> {
+        self.two_factor_repo
+            .is_enabled(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_status(&self, user_id: Uuid) -> Result<TwoFactorStatusResponse, CustomError> {
+        let two_factor = self
+            .two_factor_repo
+            .find_by_user_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let enabled_at = two_factor.and_then(|two_factor| two_factor.enabled_at);
+
+        let remaining_recovery_codes = match enabled_at {
+            Some(_) => self
+                .two_factor_repo
+                .count_remaining_recovery_codes(user_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?,
+            None => 0,
+        };
+
+        Ok(TwoFactorStatusResponse {
+            enabled: enabled_at.is_some(),
+            enabled_at,
+            remaining_recovery_codes,
+        })
+    }
+
+    pub async fn enroll(&self, user_id: Uuid) -> Result<TwoFactorEnrollResponse, CustomError> {
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let secret = totp::generate_secret();
+
+        self.two_factor_repo
+            .upsert_pending_secret(user_id, &secret)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+                "TFA_001".to_string(),
+            ))?;
+
+        Ok(TwoFactorEnrollResponse {
+            otpauth_uri: totp::otpauth_uri(&secret, &user.email, &self.issuer),
+            secret,
+        })
+    }
+
+    pub async fn confirm(
+        &self,
+        user_id: Uuid,
+        request: TwoFactorCodeRequest,
+        client: &ClientInfo,
+    ) -> Result<RecoveryCodesResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let two_factor = self
+            .two_factor_repo
+            .find_by_user_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Two-factor enrolment".to_string()))?;
+
+        if two_factor.enabled_at.is_some() {
+            return Err(CustomError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+                "TFA_001".to_string(),
+            ));
+        }
+
+        let step = totp::verify_code(&two_factor.secret, &request.code, Utc::now(), None)
+            .ok_or(CustomError::InvalidConfirmationCode)?;
+
+        let recovery_codes = totp::generate_recovery_codes();
+        let hashes = recovery_codes
+            .iter()
+            .map(|code| totp::hash_recovery_code(code))
+            .collect();
+
+        let enabled = self
+            .two_factor_repo
+            .enable(user_id, step, hashes)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !enabled {
+            return Err(CustomError::Conflict(
+                "Two-factor authentication is already enabled".to_string(),
+                "TFA_001".to_string(),
+            ));
+        }
+
+        self.auth_log_repo
+            .log_event(Some(user_id), AuthEvent::TwoFactorEnabled, client, None)
+            .await;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    pub async fn disable(
+        &self,
+        user_id: Uuid,
+        request: TwoFactorCodeRequest,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        self.verify_code_with_lockout(user_id, &request.code, client)
+            .await?;
+
+        self.two_factor_repo
+            .delete(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.auth_log_repo
+            .log_event(Some(user_id), AuthEvent::TwoFactorDisabled, client, None)
+            .await;
+
+        Ok(())
+    }
+
+    pub async fn regenerate_recovery_codes(
+        &self,
+        user_id: Uuid,
+        request: TwoFactorCodeRequest,
+        client: &ClientInfo,
+    ) -> Result<RecoveryCodesResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        self.verify_code_with_lockout(user_id, &request.code, client)
+            .await?;
+
+        let recovery_codes = totp::generate_recovery_codes();
+        let hashes = recovery_codes
+            .iter()
+            .map(|code| totp::hash_recovery_code(code))
+            .collect();
+
+        self.two_factor_repo
+            .replace_recovery_codes(user_id, hashes)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::RecoveryCodesRegenerated,
+                client,
+                None,
+            )
+            .await;
+
+        Ok(RecoveryCodesResponse { recovery_codes })
+    }
+
+    // disabling and regenerating take nothing but a code on top of the session,
+    // so wrong codes are throttled like they are at login
+    async fn verify_code_with_lockout(
+        &self,
+        user_id: Uuid,
+        code: &str,
+        client: &ClientInfo,
+    ) -> Result<VerifiedCode, CustomError> {
+        self.lockout_service
+            .ensure_can_attempt(user_id, client)
+            .await?;
+
+        match self.verify_code(user_id, code).await {
+            Err(CustomError::InvalidConfirmationCode) => {
+                let user = self
+                    .user_repo
+                    .get_user_by_id(user_id)
+                    .await
+                    .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+                self.lockout_service
+                    .record_failed_login(
+                        Some(user_id),
+                        &user.email,
+                        "invalid_two_factor_code",
+                        client,
+                    )
+                    .await?;
+                Err(CustomError::InvalidConfirmationCode)
+            }
+            result => result,
+        }
+    }
+
+    // accepts either a current totp code or an unused recovery code, both single use
+    pub async fn verify_code(
+        &self,
+        user_id: Uuid,
+        code: &str,
+    ) -> Result<VerifiedCode, CustomError> {
+        let two_factor = self
+            .two_factor_repo
+            .find_by_user_id(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .filter(|two_factor| two_factor.enabled_at.is_some())
+            .ok_or(CustomError::BadRequest)?;
+
+        if let Some(step) = totp::verify_code(
+            &two_factor.secret,
+            code,
+            Utc::now(),
+            two_factor.last_used_step,
+        ) {
+            let accepted = self
+                .two_factor_repo
+                .mark_step_used(user_id, step)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+            if accepted {
+                return Ok(VerifiedCode::Totp);
+            }
+        }
+
+        let used = self
+            .two_factor_repo
+            .use_recovery_code(user_id, &totp::hash_recovery_code(code))
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if used {
+            return Ok(VerifiedCode::RecoveryCode);
+        }
+
+        Err(CustomError::InvalidConfirmationCode)
+    }
+}
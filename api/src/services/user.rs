@@ -0,0 +1,475 @@
+use std::{borrow::Cow, sync::Arc};
+
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{
+    config::Config,
+    errors::CustomError,
+    models::{
+        auth::{AuthEvent, User},
+        context::ClientInfo,
+        user::{
+            ChangeEmailRequest, ChangeEmailResponse, DeleteAccountRequest, DeleteAccountResult,
+            UpdateProfileData, UpdateProfileRequest, UserProfileResponse, VerifyEmailRequest,
+        },
+    },
+    repositories::{
+        auth_log::AuthLogRepository, magic_link::MagicLinkRepository, org::OrgRepository,
+        user::UserRepository,
+    },
+    utils::password::verify_password,
+};
+
+use super::{
+    lockout::LockoutService,
+    mailer::{EmailMessage, Mailer},
+    two_factor::TwoFactorService,
+};
+
+const EMAIL_TOKEN_LENGTH: usize = 48;
+const EMAIL_TOKEN_EXPIRATION_HOURS: i64 = 24;
+// accounts without a password must have signed in this recently for sensitive changes
+const REAUTHENTICATION_WINDOW_MINUTES: i64 = 10;
+
+pub struct UserService {
+    user_repo: UserRepository,
+    magic_link_repo: MagicLinkRepository,
+    org_repo: OrgRepository,
+    auth_log_repo: AuthLogRepository,
+    two_factor_service: Arc<TwoFactorService>,
+    lockout_service: Arc<LockoutService>,
+    mailer: Arc<dyn Mailer>,
+    app_url: String,
+}
+
+impl UserService {
+    pub fn new(
+        pool: PgPool,
+        config: &Config,
+        two_factor_service: Arc<TwoFactorService>,
+        lockout_service: Arc<LockoutService>,
+        mailer: Arc<dyn Mailer>,
+    ) -> Self {
+        Self {
+            user_repo: UserRepository::new(pool.clone()),
+            magic_link_repo: MagicLinkRepository::new(pool.clone()),
+            org_repo: OrgRepository::new(pool.clone()),
+            auth_log_repo: AuthLogRepository::new(pool),
+            two_factor_service,
+            lockout_service,
+            mailer,
+            app_url: config.app_url.clone(),
+        }
+    }
+
+    pub async fn get_profile(&self, user_id: Uuid) -> Result<UserProfileResponse, CustomError> {
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| CustomError::NotFound("User".to_string()))?;
+
+        let pending_email = self
+            .user_repo
+            .get_pending_email(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(UserProfileResponse::new(user, pending_email))
+    }
+
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        request: UpdateProfileRequest,
+    ) -> Result<UserProfileResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        if let Some(username) = request.username.as_deref() {
+            let taken = self
+                .user_repo
+                .username_taken(username, user_id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+            if taken {
+                return Err(CustomError::Conflict(
+                    "This username is already taken".to_string(),
+                    "USR_001".to_string(),
+                ));
+            }
+        }
+
+        let user = self
+            .user_repo
+            .update_profile(
+                user_id,
+                UpdateProfileData {
+                    username: request.username,
+                    display_name: request.display_name.map(|name| name.trim().to_string()),
+                    avatar_url: request.avatar_url,
+                },
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let pending_email = self
+            .user_repo
+            .get_pending_email(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(UserProfileResponse::new(user, pending_email))
+    }
+
+    // the address is only switched once the link sent to it has been followed
+    pub async fn request_email_change(
+        &self,
+        user_id: Uuid,
+        request: ChangeEmailRequest,
+        client: &ClientInfo,
+    ) -> Result<ChangeEmailResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| CustomError::NotFound("User".to_string()))?;
+
+        self.ensure_reauthenticated(
+            &user,
+            request.password.as_deref(),
+            request.code.as_deref(),
+            client,
+        )
+        .await?;
+
+        if request.email == user.email {
+            return Err(CustomError::Conflict(
+                "This is already the email address of your account".to_string(),
+                "USR_004".to_string(),
+            ));
+        }
+
+        let email_taken = self
+            .user_repo
+            .email_taken(&request.email, user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if email_taken {
+            return Err(CustomError::Conflict(
+                "This email is already in use".to_string(),
+                "USR_002".to_string(),
+            ));
+        }
+
+        let token = self.create_email_token(user_id, &request.email).await?;
+
+        self.mailer
+            .send(EmailMessage {
+                to: request.email.clone(),
+                subject: "Confirm your new email address".to_string(),
+                body: format!(
+                    "Use the link below to confirm {} as the email address of your account. It expires in {} hours.\n\n{}/auth/verify-email?token={}\n\nIf you did not request this change you can ignore this email.",
+                    request.email, EMAIL_TOKEN_EXPIRATION_HOURS, self.app_url, token
+                ),
+            })
+            .await?;
+
+        self.mailer
+            .send(EmailMessage {
+                to: user.email,
+                subject: "Your email address is being changed".to_string(),
+                body: format!(
+                    "A change of your account email address to {} was requested. It takes effect once the new address is confirmed.\n\nIf this was not you, change your password and review your account at {}.",
+                    request.email, self.app_url
+                ),
+            })
+            .await?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user_id),
+                AuthEvent::EmailChangeRequested,
+                client,
+                Some(json!({ "pending_email": request.email })),
+            )
+            .await;
+
+        Ok(ChangeEmailResponse {
+            message: "A confirmation link has been sent to the new email address".to_string(),
+            pending_email: request.email,
+        })
+    }
+
+    // new accounts confirm their address through the same link as an email change
+    pub async fn send_email_verification(&self, user: &User) -> Result<(), CustomError> {
+        if user.is_email_verified {
+            return Err(CustomError::AccountAlreadyConfirmed);
+        }
+
+        let token = self.create_email_token(user.id, &user.email).await?;
+
+        self.mailer
+            .send(EmailMessage {
+                to: user.email.clone(),
+                subject: "Confirm your email address".to_string(),
+                body: format!(
+                    "Use the link below to confirm your email address. It expires in {} hours.\n\n{}/auth/verify-email?token={}\n\nIf you did not create an account you can ignore this email.",
+                    EMAIL_TOKEN_EXPIRATION_HOURS, self.app_url, token
+                ),
+            })
+            .await
+    }
+
+    pub async fn resend_email_verification(&self, user_id: Uuid) -> Result<(), CustomError> {
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| CustomError::NotFound("User".to_string()))?;
+
+        self.send_email_verification(&user).await
+    }
+
+    pub async fn verify_email(
+        &self,
+        request: VerifyEmailRequest,
+        client: &ClientInfo,
+    ) -> Result<UserProfileResponse, CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let token_hash = hash_email_token(&request.token);
+        let invalid_token =
+            || CustomError::InvalidToken("Invalid or expired verification link".to_string());
+
+        let pending = self
+            .user_repo
+            .find_pending_email_change(&token_hash)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or_else(invalid_token)?;
+
+        // someone else may have registered the address since the change was requested
+        let email_taken = self
+            .user_repo
+            .email_taken(&pending.pending_email, pending.user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if email_taken {
+            return Err(CustomError::Conflict(
+                "This email is already in use".to_string(),
+                "USR_002".to_string(),
+            ));
+        }
+
+        let user = self
+            .user_repo
+            .confirm_pending_email(&token_hash)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or_else(invalid_token)?;
+
+        let email_changed = pending.email != pending.pending_email;
+
+        // sign-in links still in flight were sent to the old address
+        if email_changed {
+            self.magic_link_repo
+                .invalidate_user_tokens(user.id)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+        }
+
+        self.org_repo
+            .attach_invites(user.id, &user.email)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.auth_log_repo
+            .log_event(
+                Some(user.id),
+                if email_changed {
+                    AuthEvent::EmailChanged
+                } else {
+                    AuthEvent::EmailVerified
+                },
+                client,
+                Some(json!({ "email": user.email })),
+            )
+            .await;
+
+        Ok(UserProfileResponse::new(user, None))
+    }
+
+    // refresh tokens stop working once the user row is gone and personal access tokens
+    // are deleted with it, access tokens already issued run out on their own shortly after
+    pub async fn delete_account(
+        &self,
+        user_id: Uuid,
+        request: DeleteAccountRequest,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        if let Err(validation_errors) = request.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let user = self
+            .user_repo
+            .get_user_by_id(user_id)
+            .await
+            .map_err(|_| CustomError::NotFound("User".to_string()))?;
+
+        if request.confirmation != user.username {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "confirmation",
+                ValidationError::new("confirmation_mismatch")
+                    .with_message(Cow::Borrowed("Type your username to confirm")),
+            );
+            return Err(CustomError::ValidationError(errors));
+        }
+
+        self.ensure_reauthenticated(
+            &user,
+            request.password.as_deref(),
+            request.code.as_deref(),
+            client,
+        )
+        .await?;
+
+        let result = self
+            .user_repo
+            .delete_account(user_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if let DeleteAccountResult::SoleOwner(orgs) = result {
+            return Err(CustomError::Conflict(
+                format!(
+                    "Transfer ownership of or delete these organizations first: {}",
+                    orgs.join(", ")
+                ),
+                "USR_003".to_string(),
+            ));
+        }
+
+        // the user row is gone, so the event is recorded without it
+        self.auth_log_repo
+            .log_event(
+                None,
+                AuthEvent::AccountDeleted,
+                client,
+                Some(json!({ "user_id": user_id, "username": user.username })),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    async fn create_email_token(&self, user_id: Uuid, email: &str) -> Result<String, CustomError> {
+        let token: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(EMAIL_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+
+        self.user_repo
+            .set_pending_email(
+                user_id,
+                email,
+                &hash_email_token(&token),
+                Utc::now() + Duration::hours(EMAIL_TOKEN_EXPIRATION_HOURS),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(token)
+    }
+
+    // a stolen session alone must not be enough, the password is asked for again, or
+    // the two-factor code, and accounts with neither must have just signed in. wrong
+    // answers count towards the same lockout as failed logins
+    async fn ensure_reauthenticated(
+        &self,
+        user: &User,
+        password: Option<&str>,
+        code: Option<&str>,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        self.lockout_service
+            .ensure_can_attempt(user.id, client)
+            .await?;
+
+        // the password goes first so a wrong one doesn't use up a valid totp step
+        if user.password_hash.is_some() {
+            let password = password.ok_or(CustomError::InvalidCredentials)?;
+
+            if !verify_password(password, user.password_hash.as_deref()) {
+                self.lockout_service
+                    .record_failed_login(
+                        Some(user.id),
+                        &user.email,
+                        "reauthentication_invalid_password",
+                        client,
+                    )
+                    .await?;
+                return Err(CustomError::InvalidCredentials);
+            }
+        }
+
+        let two_factor_enabled = self.two_factor_service.is_enabled(user.id).await?;
+
+        if two_factor_enabled {
+            let code = code.ok_or(CustomError::InvalidConfirmationCode)?;
+
+            match self.two_factor_service.verify_code(user.id, code).await {
+                Ok(_) => {}
+                Err(CustomError::InvalidConfirmationCode) => {
+                    self.lockout_service
+                        .record_failed_login(
+                            Some(user.id),
+                            &user.email,
+                            "reauthentication_invalid_two_factor_code",
+                            client,
+                        )
+                        .await?;
+                    return Err(CustomError::InvalidConfirmationCode);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if user.password_hash.is_some() {
+            return Ok(());
+        }
+
+        let signed_in_recently = user.last_login_at.is_some_and(|last_login_at| {
+            last_login_at > Utc::now() - Duration::minutes(REAUTHENTICATION_WINDOW_MINUTES)
+        });
+
+        if !two_factor_enabled && !signed_in_recently {
+            return Err(CustomError::ReauthenticationRequired);
+        }
+
+        Ok(())
+    }
+}
+
+fn hash_email_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,634 @@
+use std::{
+    borrow::Cow,
+    error::Error,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use chrono::Utc;
+use futures::future::join_all;
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::{
+    dns::{Addrs, Name, Resolve, Resolving},
+    header, redirect, Url,
+};
+use serde_json::{json, Value as JsonValue};
+use sha2::Sha256;
+use sqlx::PgPool;
+use tokio::net::lookup_host;
+use uuid::Uuid;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+use crate::{
+    config::Config,
+    errors::CustomError,
+    models::{
+        audit_log::{AuditEntry, AuditEvent},
+        context::ClientInfo,
+        webhook::{
+            CreateWebhookData, CreateWebhookRequest, ListWebhookDeliveriesQuery, UpdateWebhookData,
+            UpdateWebhookRequest, Webhook, WebhookDelivery, WebhookDeliveryJob,
+            WebhookDeliveryPageResponse, WebhookEvent, WebhookSecretResponse,
+        },
+    },
+    repositories::{audit_log::AuditLogRepository, webhook::WebhookRepository},
+};
+
+const SECRET_PREFIX: &str = "whsec_";
+const SECRET_LENGTH: usize = 40;
+const DELIVERY_BATCH_SIZE: i64 = 20;
+const DELIVERY_TIMEOUT_SECS: u64 = 10;
+// well over the timeout, so a delivery is never sent twice at the same time
+const DELIVERY_LOCK_SECS: i64 = 60;
+// retried after 30 seconds, a minute, two minutes and so on, about four hours in all
+const MAX_DELIVERY_ATTEMPTS: i32 = 10;
+const RETRY_BASE_SECS: i64 = 30;
+const DELIVERY_RETENTION_SECS: i64 = 30 * 24 * 60 * 60;
+const MAX_RESPONSE_BODY_LENGTH: usize = 1024;
+const DEFAULT_PAGE_SIZE: i64 = 50;
+
+pub struct WebhookService {
+    webhook_repo: WebhookRepository,
+    audit_log_repo: AuditLogRepository,
+    http: reqwest::Client,
+    allow_private_targets: bool,
+}
+
+impl WebhookService {
+    pub fn new(pool: PgPool, config: &Config) -> Result<Self, CustomError> {
+        // a redirect could point the signed payload anywhere
+        let mut builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(DELIVERY_TIMEOUT_SECS))
+            .redirect(redirect::Policy::none());
+
+        // connections only go to the addresses the resolver checked, so a second dns
+        // answer can't swap in an internal one, and a proxy would resolve on its own
+        if !config.webhook_allow_private_targets {
+            builder = builder
+                .dns_resolver(Arc::new(PublicAddressResolver))
+                .no_proxy();
+        }
+
+        let http = builder
+            .build()
+            .map_err(|e| CustomError::ExternalServiceError(e.to_string()))?;
+
+        Ok(Self {
+            webhook_repo: WebhookRepository::new(pool.clone()),
+            audit_log_repo: AuditLogRepository::new(pool),
+            http,
+            allow_private_targets: config.webhook_allow_private_targets,
+        })
+    }
+
+    pub async fn create_webhook(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        data: CreateWebhookRequest,
+        client: &ClientInfo,
+    ) -> Result<WebhookSecretResponse, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+        self.ensure_allowed_target(&data.url).await?;
+
+        let secret = generate_secret();
+        let webhook = self
+            .webhook_repo
+            .create_webhook(CreateWebhookData {
+                org_id,
+                url: data.url,
+                secret: secret.clone(),
+                events: event_names(&data.events),
+                created_by: actor_id,
+            })
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::WebhookCreated, Some(webhook.id))
+                    .after(webhook_settings(&webhook)),
+            )
+            .await;
+
+        Ok(WebhookSecretResponse { secret, webhook })
+    }
+
+    pub async fn list_webhooks(&self, org_id: Uuid) -> Result<Vec<Webhook>, CustomError> {
+        self.webhook_repo
+            .list_webhooks(org_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn get_webhook(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<Webhook, CustomError> {
+        self.webhook_repo
+            .find_webhook(org_id, webhook_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Webhook not found".to_string()))
+    }
+
+    pub async fn update_webhook(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        webhook_id: Uuid,
+        data: UpdateWebhookRequest,
+        client: &ClientInfo,
+    ) -> Result<Webhook, CustomError> {
+        if let Err(validation_errors) = data.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+        if let Some(url) = &data.url {
+            self.ensure_allowed_target(url).await?;
+        }
+
+        let before = self.get_webhook(org_id, webhook_id).await?;
+
+        let webhook = self
+            .webhook_repo
+            .update_webhook(
+                org_id,
+                webhook_id,
+                UpdateWebhookData {
+                    url: data.url,
+                    events: data.events.as_deref().map(event_names),
+                    is_active: data.is_active,
+                },
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Webhook not found".to_string()))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::WebhookUpdated, Some(webhook.id))
+                    .changes(webhook_settings(&before), webhook_settings(&webhook)),
+            )
+            .await;
+
+        Ok(webhook)
+    }
+
+    // the old secret stops working right away, receivers have to switch at once
+    pub async fn rotate_secret(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        webhook_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<WebhookSecretResponse, CustomError> {
+        let secret = generate_secret();
+        let webhook = self
+            .webhook_repo
+            .rotate_secret(org_id, webhook_id, &secret)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?
+            .ok_or(CustomError::NotFound("Webhook not found".to_string()))?;
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::WebhookUpdated, Some(webhook.id))
+                    .after(json!({ "secret_rotated": true })),
+            )
+            .await;
+
+        Ok(WebhookSecretResponse { secret, webhook })
+    }
+
+    pub async fn delete_webhook(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        webhook_id: Uuid,
+        client: &ClientInfo,
+    ) -> Result<(), CustomError> {
+        let webhook = self.get_webhook(org_id, webhook_id).await?;
+
+        let deleted = self
+            .webhook_repo
+            .delete_webhook(org_id, webhook_id)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if !deleted {
+            return Err(CustomError::NotFound("Webhook not found".to_string()));
+        }
+
+        self.audit_log_repo
+            .log_event(
+                org_id,
+                Some(actor_id),
+                client,
+                AuditEntry::new(AuditEvent::WebhookDeleted, Some(webhook.id))
+                    .before(webhook_settings(&webhook)),
+            )
+            .await;
+
+        Ok(())
+    }
+
+    // queued like any other event, the delivery log shows how the receiver answered
+    pub async fn send_test_event(
+        &self,
+        org_id: Uuid,
+        actor_id: Uuid,
+        webhook_id: Uuid,
+    ) -> Result<WebhookDelivery, CustomError> {
+        let webhook = self.get_webhook(org_id, webhook_id).await?;
+
+        self.webhook_repo
+            .queue_test_event(
+                org_id,
+                webhook.id,
+                json!({
+                    "webhook_id": webhook.id,
+                    "sent_by": actor_id,
+                }),
+            )
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))
+    }
+
+    pub async fn list_deliveries(
+        &self,
+        org_id: Uuid,
+        webhook_id: Uuid,
+        query: ListWebhookDeliveriesQuery,
+    ) -> Result<WebhookDeliveryPageResponse, CustomError> {
+        if let Err(validation_errors) = query.validate() {
+            return Err(CustomError::ValidationError(validation_errors));
+        }
+
+        let webhook = self.get_webhook(org_id, webhook_id).await?;
+        let page = query.page.unwrap_or(1);
+        let per_page = query.per_page.unwrap_or(DEFAULT_PAGE_SIZE);
+
+        let total = self
+            .webhook_repo
+            .count_deliveries(webhook.id, query.status)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        let deliveries = self
+            .webhook_repo
+            .list_deliveries(webhook.id, query.status, per_page, (page - 1) * per_page)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        Ok(WebhookDeliveryPageResponse {
+            deliveries,
+            page,
+            per_page,
+            total,
+        })
+    }
+
+    // sends everything that is due, batch by batch, and returns how many were attempted
+    pub async fn deliver_due(&self) -> Result<usize, CustomError> {
+        let mut attempted = 0;
+
+        loop {
+            let jobs = self
+                .webhook_repo
+                .claim_deliveries(DELIVERY_BATCH_SIZE, DELIVERY_LOCK_SECS)
+                .await
+                .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+            let claimed = jobs.len();
+
+            join_all(jobs.into_iter().map(|job| self.deliver(job))).await;
+            attempted += claimed;
+
+            if (claimed as i64) < DELIVERY_BATCH_SIZE {
+                return Ok(attempted);
+            }
+        }
+    }
+
+    pub async fn clean_up_deliveries(&self) -> Result<(), CustomError> {
+        let deleted = self
+            .webhook_repo
+            .delete_old_deliveries(DELIVERY_RETENTION_SECS)
+            .await
+            .map_err(|e| CustomError::DatabaseError(e.to_string()))?;
+
+        if deleted > 0 {
+            log::info!("Deleted {} old webhook deliveries", deleted);
+        }
+
+        Ok(())
+    }
+
+    // receivers check the signature over "{timestamp}.{body}" and reject old timestamps,
+    // so a captured delivery cannot be replayed
+    async fn deliver(&self, job: WebhookDeliveryJob) {
+        // host names are checked by the resolver, addresses in the url never reach it
+        if !self.allow_private_targets {
+            if let Err(message) = check_address_literal(&job.url) {
+                let saved = self
+                    .webhook_repo
+                    .fail_delivery_attempt(job.id, retry_delay(job.attempts), None, None, message)
+                    .await;
+                if let Err(e) = saved {
+                    log::error!("Failed to record webhook delivery {}: {}", job.id, e);
+                }
+                return;
+            }
+        }
+
+        let body = job.payload.to_string();
+        let timestamp = Utc::now().timestamp();
+        let signature = sign_payload(&job.secret, timestamp, &body);
+
+        let result = self
+            .http
+            .post(&job.url)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::USER_AGENT, "IssueApp-Webhooks")
+            .header("X-IssueApp-Event", &job.event)
+            .header("X-IssueApp-Delivery", job.id.to_string())
+            .header("X-IssueApp-Timestamp", timestamp.to_string())
+            .header("X-IssueApp-Signature", format!("sha256={}", signature))
+            .body(body)
+            .send()
+            .await;
+
+        let saved = match result {
+            Ok(res) => {
+                let status = res.status();
+                let response_body = res
+                    .text()
+                    .await
+                    .ok()
+                    .filter(|text| !text.is_empty())
+                    .map(|text| text.chars().take(MAX_RESPONSE_BODY_LENGTH).collect());
+
+                if status.is_success() {
+                    self.webhook_repo
+                        .complete_delivery(job.id, status.as_u16() as i32, response_body)
+                        .await
+                } else {
+                    self.webhook_repo
+                        .fail_delivery_attempt(
+                            job.id,
+                            retry_delay(job.attempts),
+                            Some(status.as_u16() as i32),
+                            response_body,
+                            format!("Receiver responded with {}", status),
+                        )
+                        .await
+                }
+            }
+            Err(e) => {
+                self.webhook_repo
+                    .fail_delivery_attempt(
+                        job.id,
+                        retry_delay(job.attempts),
+                        None,
+                        None,
+                        error_chain(&e),
+                    )
+                    .await
+            }
+        };
+
+        if let Err(e) = saved {
+            log::error!("Failed to record webhook delivery {}: {}", job.id, e);
+        }
+    }
+
+    // checked again on every delivery, the host may resolve differently by then
+    async fn ensure_allowed_target(&self, url: &str) -> Result<(), CustomError> {
+        if self.allow_private_targets {
+            return Ok(());
+        }
+
+        let checked = match check_address_literal(url) {
+            Ok(Some(host)) => resolve_public_addrs(&host).await.map(|_| ()),
+            Ok(None) => Ok(()),
+            Err(message) => Err(message),
+        };
+
+        if let Err(message) = checked {
+            let mut errors = ValidationErrors::new();
+            errors.add(
+                "url",
+                ValidationError::new("disallowed_target").with_message(Cow::Owned(message)),
+            );
+            return Err(CustomError::ValidationError(errors));
+        }
+
+        Ok(())
+    }
+}
+
+struct PublicAddressResolver;
+
+impl Resolve for PublicAddressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addrs = resolve_public_addrs(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+// the port is filled in by the client, every address has to be public
+async fn resolve_public_addrs(host: &str) -> Result<Vec<SocketAddr>, String> {
+    let addrs: Vec<SocketAddr> = lookup_host((host, 0))
+        .await
+        .map_err(|e| format!("Could not resolve {}: {}", host, e))?
+        .collect();
+
+    if addrs.is_empty() {
+        return Err(format!("Could not resolve {}", host));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| !is_public_address(addr.ip())) {
+        return Err(format!(
+            "{} resolves to the internal address {}",
+            host,
+            addr.ip()
+        ));
+    }
+
+    Ok(addrs)
+}
+
+// returns the host name when there is one left to resolve
+fn check_address_literal(url: &str) -> Result<Option<String>, String> {
+    let url = Url::parse(url).map_err(|e| e.to_string())?;
+    let host = url.host_str().ok_or("The url has no host")?;
+    // ipv6 hosts keep their brackets
+    let ip: IpAddr = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => ip,
+        Err(_) => return Ok(Some(host.to_string())),
+    };
+
+    if !is_public_address(ip) {
+        return Err(format!("{} is an internal address", ip));
+    }
+    Ok(None)
+}
+
+// loopback, private, link-local, unique-local and the like, including ipv4 inside ipv6
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                || a == 0
+                // carrier-grade nat
+                || (a == 100 && (b & 0xc0) == 64))
+        }
+        IpAddr::V6(ip) => {
+            if let Some(mapped) = ip.to_ipv4_mapped() {
+                return is_public_address(mapped.into());
+            }
+            let segments = ip.segments();
+            // nat64 carries the ipv4 address in the last 32 bits
+            if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+                let [.., a, b, c, d] = ip.octets();
+                return is_public_address(Ipv4Addr::new(a, b, c, d).into());
+            }
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                || (segments[0] & 0xfe00) == 0xfc00
+                || (segments[0] & 0xffc0) == 0xfe80)
+        }
+    }
+}
+
+// reqwest keeps the reason a connection was refused in the error source
+fn error_chain(error: &reqwest::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        // some layers already include their cause in their own message
+        let cause_message = cause.to_string();
+        if !message.contains(&cause_message) {
+            message.push_str(": ");
+            message.push_str(&cause_message);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+// none once the last attempt was made
+fn retry_delay(attempts: i32) -> Option<i64> {
+    if attempts >= MAX_DELIVERY_ATTEMPTS {
+        return None;
+    }
+    Some(RETRY_BASE_SECS << (attempts - 1).max(0))
+}
+
+fn sign_payload(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+fn generate_secret() -> String {
+    let secret: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(SECRET_LENGTH)
+        .map(char::from)
+        .collect();
+
+    format!("{}{}", SECRET_PREFIX, secret)
+}
+
+fn event_names(events: &[WebhookEvent]) -> Vec<String> {
+    let mut names: Vec<String> = events
+        .iter()
+        .map(|event| event.as_str().to_string())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+fn webhook_settings(webhook: &Webhook) -> JsonValue {
+    json!({
+        "url": webhook.url,
+        "events": webhook.events,
+        "is_active": webhook.is_active,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_addresses_are_not_public() {
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "::",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+            "64:ff9b::a9fe:a9fe",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        for ip in [
+            "8.8.8.8",
+            "1.1.1.1",
+            "2606:4700:4700::1111",
+            "::ffff:8.8.8.8",
+        ] {
+            assert!(is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn address_literals_are_checked_without_resolving() {
+        assert!(check_address_literal("http://127.0.0.1:8080/hook").is_err());
+        assert!(check_address_literal("http://[::1]/hook").is_err());
+        // the url parser normalizes numeric hosts
+        assert!(check_address_literal("http://2130706433/hook").is_err());
+        assert_eq!(check_address_literal("https://8.8.8.8/hook"), Ok(None));
+        assert_eq!(
+            check_address_literal("https://hooks.example.com/hook"),
+            Ok(Some("hooks.example.com".to_string()))
+        );
+    }
+}
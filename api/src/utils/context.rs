@@ -1,11 +1,15 @@
-use actix_web::{HttpMessage, HttpRequest};
+use std::net::IpAddr;
+
+use actix_web::{web, HttpMessage, HttpRequest};
 use uuid::Uuid;
 
 use crate::{
+    app_state::AppState,
     errors::CustomError,
     models::{
-        context::{OrgContext, UserContext},
-        org::Org,
+        context::{ClientInfo, OrgContext, UserContext},
+        org::{MemberRole, Org},
+        role::Permission,
     },
 };
 
@@ -30,3 +34,123 @@ pub async fn get_context_org(req: HttpRequest) -> Result<Org, CustomError> {
 
     Ok(org)
 }
+
+pub async fn get_context_role(req: HttpRequest) -> Result<MemberRole, CustomError> {
+    let extensions = req.extensions();
+    let org_context = extensions
+        .get::<OrgContext>()
+        .ok_or(CustomError::Unauthorized)?;
+
+    let role = org_context.role.clone().ok_or(CustomError::Unauthorized)?;
+
+    Ok(role)
+}
+
+pub async fn get_context_permissions(req: HttpRequest) -> Result<Vec<Permission>, CustomError> {
+    let extensions = req.extensions();
+    let org_context = extensions
+        .get::<OrgContext>()
+        .ok_or(CustomError::Unauthorized)?;
+
+    Ok(org_context.permissions.clone())
+}
+
+pub fn has_permission(req: &HttpRequest, permission: Permission) -> bool {
+    req.extensions()
+        .get::<OrgContext>()
+        .is_some_and(|org_context| org_context.has_permission(permission))
+}
+
+// for handlers whose route mixes permissions, the rest use the permission guard
+pub fn ensure_permission(req: &HttpRequest, permission: Permission) -> Result<(), CustomError> {
+    if !has_permission(req, permission) {
+        return Err(CustomError::Forbidden);
+    }
+
+    Ok(())
+}
+
+// true for sessions and unrestricted tokens, org routes check this in the org guard
+pub fn allows_org(req: &HttpRequest, org_id: Uuid) -> bool {
+    req.extensions()
+        .get::<UserContext>()
+        .and_then(|user_context| user_context.access_token.as_ref())
+        .is_none_or(|access_token| access_token.allows_org(org_id))
+}
+
+// a token limited to some orgs can't reach orgs created after it either
+pub fn ensure_unrestricted(req: &HttpRequest) -> Result<(), CustomError> {
+    let restricted = req
+        .extensions()
+        .get::<UserContext>()
+        .and_then(|user_context| user_context.access_token.as_ref())
+        .is_some_and(|access_token| access_token.org_ids.is_some());
+
+    if restricted {
+        return Err(CustomError::Forbidden);
+    }
+
+    Ok(())
+}
+
+// restoring and exporting an org stay with its owners, no permission hands them out
+pub fn ensure_owner(req: &HttpRequest) -> Result<(), CustomError> {
+    let is_owner = req
+        .extensions()
+        .get::<OrgContext>()
+        .is_some_and(|org_context| org_context.role == Some(MemberRole::Owner));
+
+    if !is_owner {
+        return Err(CustomError::Forbidden);
+    }
+
+    Ok(())
+}
+
+pub fn get_client_info(req: &HttpRequest) -> ClientInfo {
+    let ip_address = client_ip(req).map(|ip| ip.to_string());
+
+    let user_agent = req
+        .headers()
+        .get("User-Agent")
+        .and_then(|header| header.to_str().ok())
+        .map(|ua| ua.to_string());
+
+    ClientInfo {
+        ip_address,
+        user_agent,
+    }
+}
+
+// forwarded headers are written by whoever sends the request, so they only count when the
+// connection comes from a trusted proxy, and then the rightmost untrusted hop is the client
+fn client_ip(req: &HttpRequest) -> Option<IpAddr> {
+    let peer = req.peer_addr()?.ip();
+    let trusted_proxies = match req.app_data::<web::Data<AppState>>() {
+        Some(state) => &state.config.trusted_proxies,
+        None => return Some(peer),
+    };
+    let is_trusted = |ip: &IpAddr| trusted_proxies.iter().any(|proxy| proxy.contains(ip));
+
+    if !is_trusted(&peer) {
+        return Some(peer);
+    }
+
+    let forwarded = req
+        .headers()
+        .get_all("X-Forwarded-For")
+        .filter_map(|header| header.to_str().ok())
+        .flat_map(|header| header.split(','))
+        .map(|hop| hop.trim().parse::<IpAddr>())
+        .collect::<Vec<_>>();
+
+    let mut client = peer;
+    for hop in forwarded.into_iter().rev() {
+        match hop {
+            Ok(ip) if is_trusted(&client) => client = ip,
+            _ => break,
+        }
+    }
+
+    Some(client)
+}
@@ -0,0 +1,210 @@
+use serde_json::{json, Value as JsonValue};
+
+// the editor stores its document as a json string, only the block structure of
+// markdown is kept, inline formatting stays as written
+pub fn markdown_document(markdown: &str) -> JsonValue {
+    let markdown = markdown.replace("\r\n", "\n");
+    let mut blocks = Vec::new();
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut lines = markdown.lines().peekable();
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("```") {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut code = Vec::new();
+            for line in lines.by_ref() {
+                if line.trim().starts_with("```") {
+                    break;
+                }
+                code.push(line);
+            }
+            blocks.push(code_block(&code.join("\n")));
+        } else if trimmed.is_empty() {
+            flush_paragraph(&mut blocks, &mut paragraph);
+        } else if let Some((level, text)) = heading(trimmed) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            blocks.push(json!({
+                "type": "heading",
+                "attrs": { "level": level },
+                "content": text_nodes(text),
+            }));
+        } else if let Some((ordered, text)) = list_item(trimmed) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            let mut items = vec![list_item_node(text)];
+            while let Some((_, text)) = lines
+                .peek()
+                .copied()
+                .and_then(|next| list_item(next.trim()))
+                .filter(|(next_ordered, _)| *next_ordered == ordered)
+            {
+                items.push(list_item_node(text));
+                lines.next();
+            }
+            let list_type = if ordered { "orderedList" } else { "bulletList" };
+            blocks.push(json!({ "type": list_type, "content": items }));
+        } else {
+            paragraph.push(trimmed);
+        }
+    }
+    flush_paragraph(&mut blocks, &mut paragraph);
+
+    JsonValue::String(json!({ "type": "doc", "content": blocks }).to_string())
+}
+
+// the reverse of markdown_document, descriptions written before the editor existed
+// are plain strings and come back as they are
+pub fn document_markdown(document: &JsonValue) -> String {
+    let document = match document {
+        JsonValue::String(text) => match serde_json::from_str::<JsonValue>(text) {
+            Ok(document) if document.is_object() => document,
+            _ => return text.clone(),
+        },
+        JsonValue::Null => return String::new(),
+        document => document.clone(),
+    };
+
+    children(&document)
+        .iter()
+        .map(block_markdown)
+        .filter(|block| !block.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+fn flush_paragraph(blocks: &mut Vec<JsonValue>, paragraph: &mut Vec<&str>) {
+    if paragraph.is_empty() {
+        return;
+    }
+
+    let mut content = Vec::new();
+    for (i, line) in paragraph.drain(..).enumerate() {
+        if i > 0 {
+            content.push(json!({ "type": "hardBreak" }));
+        }
+        content.extend(text_nodes(line));
+    }
+    blocks.push(json!({ "type": "paragraph", "content": content }));
+}
+
+fn list_item_node(text: &str) -> JsonValue {
+    json!({
+        "type": "listItem",
+        "content": [{ "type": "paragraph", "content": text_nodes(text) }],
+    })
+}
+
+fn code_block(code: &str) -> JsonValue {
+    json!({ "type": "codeBlock", "content": text_nodes(code) })
+}
+
+// text nodes may not be empty
+fn text_nodes(text: &str) -> Vec<JsonValue> {
+    if text.is_empty() {
+        return Vec::new();
+    }
+    vec![json!({ "type": "text", "text": text })]
+}
+
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    let text = line[level..].strip_prefix(' ')?;
+    (1..=6).contains(&level).then_some((level, text.trim()))
+}
+
+// whether the item is numbered and its text
+fn list_item(line: &str) -> Option<(bool, &str)> {
+    if let Some(text) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+        return Some((false, text.trim()));
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if digits == 0 {
+        return None;
+    }
+    line[digits..]
+        .strip_prefix(". ")
+        .map(|text| (true, text.trim()))
+}
+
+fn children(node: &JsonValue) -> &[JsonValue] {
+    node.get("content")
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn block_markdown(node: &JsonValue) -> String {
+    match node.get("type").and_then(JsonValue::as_str) {
+        Some("heading") => {
+            let level = node
+                .pointer("/attrs/level")
+                .and_then(JsonValue::as_u64)
+                .unwrap_or(1)
+                .clamp(1, 6);
+            format!("{} {}", "#".repeat(level as usize), inline_markdown(node))
+        }
+        Some("bulletList") | Some("orderedList") => {
+            let ordered = node.get("type").and_then(JsonValue::as_str) == Some("orderedList");
+            children(node)
+                .iter()
+                .enumerate()
+                .map(|(i, item)| {
+                    let text = children(item)
+                        .iter()
+                        .map(block_markdown)
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    if ordered {
+                        format!("{}. {}", i + 1, text)
+                    } else {
+                        format!("- {}", text)
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        }
+        Some("codeBlock") => format!("```\n{}\n```", inline_markdown(node)),
+        Some("blockquote") => children(node)
+            .iter()
+            .map(|block| format!("> {}", block_markdown(block)))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => inline_markdown(node),
+    }
+}
+
+fn inline_markdown(node: &JsonValue) -> String {
+    children(node)
+        .iter()
+        .map(
+            |child| match child.get("type").and_then(JsonValue::as_str) {
+                Some("text") => {
+                    let text = child.get("text").and_then(JsonValue::as_str).unwrap_or("");
+                    marked_text(text, child.get("marks"))
+                }
+                Some("hardBreak") => "\n".to_string(),
+                _ => inline_markdown(child),
+            },
+        )
+        .collect()
+}
+
+fn marked_text(text: &str, marks: Option<&JsonValue>) -> String {
+    let marks = marks
+        .and_then(JsonValue::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+
+    marks
+        .iter()
+        .filter_map(|mark| mark.get("type").and_then(JsonValue::as_str))
+        .fold(text.to_string(), |text, mark| match mark {
+            "bold" => format!("**{}**", text),
+            "italic" => format!("*{}*", text),
+            "code" => format!("`{}`", text),
+            "strike" => format!("~~{}~~", text),
+            _ => text,
+        })
+}
@@ -1,4 +1,8 @@
 pub mod bearer_token;
 pub mod context;
+pub mod editor;
 pub mod logger;
+pub mod password;
+pub mod totp;
 pub mod urls;
+pub mod validation;
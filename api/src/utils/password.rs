@@ -0,0 +1,149 @@
+use std::borrow::Cow;
+
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use validator::{ValidationError, ValidationErrors};
+
+use crate::{config::PasswordPolicy, errors::CustomError};
+
+const COMMON_PASSWORDS: &str = include_str!("../../assets/common-passwords.txt");
+
+// user info shorter than this is too likely to appear by chance to be rejected
+const MIN_USER_INFO_LENGTH: usize = 3;
+
+pub fn check_password_strength(
+    password: &str,
+    username: &str,
+    email: &str,
+    policy: &PasswordPolicy,
+) -> Result<(), CustomError> {
+    let mut errors = ValidationErrors::new();
+    let length = password.chars().count();
+
+    if length < policy.min_length {
+        errors.add(
+            "password",
+            policy_error(
+                "password_too_short",
+                format!(
+                    "Password must be at least {} characters long",
+                    policy.min_length
+                ),
+            ),
+        );
+    }
+
+    if length > policy.max_length {
+        errors.add(
+            "password",
+            policy_error(
+                "password_too_long",
+                format!(
+                    "Password must be at most {} characters long",
+                    policy.max_length
+                ),
+            ),
+        );
+    }
+
+    if policy.require_uppercase && !password.chars().any(|c| c.is_uppercase()) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_missing_uppercase",
+                "Password must contain at least one uppercase letter".to_string(),
+            ),
+        );
+    }
+
+    if policy.require_lowercase && !password.chars().any(|c| c.is_lowercase()) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_missing_lowercase",
+                "Password must contain at least one lowercase letter".to_string(),
+            ),
+        );
+    }
+
+    if policy.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_missing_digit",
+                "Password must contain at least one number".to_string(),
+            ),
+        );
+    }
+
+    if policy.require_special && password.chars().all(|c| c.is_alphanumeric()) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_missing_special",
+                "Password must contain at least one special character".to_string(),
+            ),
+        );
+    }
+
+    let lowered = password.to_lowercase();
+
+    if policy.reject_user_info && contains_user_info(&lowered, username, email) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_contains_user_info",
+                "Password must not contain your username or email".to_string(),
+            ),
+        );
+    }
+
+    if policy.reject_common && is_common_password(&lowered) {
+        errors.add(
+            "password",
+            policy_error(
+                "password_too_common",
+                "Password is too common, please choose a different one".to_string(),
+            ),
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(CustomError::NotStrongPassword(errors))
+    }
+}
+
+// accounts created through an external provider have no password and never match
+pub fn verify_password(password: &str, password_hash: Option<&str>) -> bool {
+    password_hash
+        .and_then(|hash| PasswordHash::new(hash).ok())
+        .map(|parsed_hash| {
+            Argon2::default()
+                .verify_password(password.as_bytes(), &parsed_hash)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+fn contains_user_info(lowered_password: &str, username: &str, email: &str) -> bool {
+    let local_part = email.split('@').next().unwrap_or_default();
+
+    [username, email, local_part]
+        .iter()
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| value.chars().count() >= MIN_USER_INFO_LENGTH)
+        .any(|value| lowered_password.contains(&value))
+}
+
+fn is_common_password(lowered_password: &str) -> bool {
+    COMMON_PASSWORDS
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .any(|line| line.eq_ignore_ascii_case(lowered_password))
+}
+
+fn policy_error(code: &'static str, message: String) -> ValidationError {
+    ValidationError::new(code).with_message(Cow::Owned(message))
+}
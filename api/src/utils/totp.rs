@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+
+// RFC 6238 defaults, these are the only values every authenticator app supports
+const SECRET_BYTES: usize = 20;
+const STEP_SECS: i64 = 30;
+const DIGITS: u32 = 6;
+// accept one step before and after the current one to allow for clock drift
+const ALLOWED_SKEW: i64 = 1;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_PART_LENGTH: usize = 5;
+
+pub fn generate_secret() -> String {
+    let mut secret = [0u8; SECRET_BYTES];
+    rand::thread_rng().fill_bytes(&mut secret);
+    BASE32_NOPAD.encode(&secret)
+}
+
+pub fn otpauth_uri(secret: &str, account: &str, issuer: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        percent_encode(issuer),
+        percent_encode(account),
+        secret,
+        percent_encode(issuer),
+        DIGITS,
+        STEP_SECS
+    )
+}
+
+// returns the matched time step so callers can reject a code that was already used
+pub fn verify_code(
+    secret: &str,
+    code: &str,
+    now: DateTime<Utc>,
+    last_used_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim().replace(' ', "");
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let key = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    let current_step = now.timestamp() / STEP_SECS;
+
+    (-ALLOWED_SKEW..=ALLOWED_SKEW)
+        .map(|offset| current_step + offset)
+        .filter(|step| last_used_step.is_none_or(|last| *step > last))
+        .find(|step| generate_code(&key, *step).as_deref() == Some(code.as_str()))
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let raw: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(RECOVERY_CODE_PART_LENGTH * 2)
+                .map(|c| (c as char).to_ascii_lowercase())
+                .collect();
+            format!(
+                "{}-{}",
+                &raw[..RECOVERY_CODE_PART_LENGTH],
+                &raw[RECOVERY_CODE_PART_LENGTH..]
+            )
+        })
+        .collect()
+}
+
+// recovery codes are random enough that a fast hash is sufficient
+pub fn hash_recovery_code(code: &str) -> String {
+    let normalized = code.trim().to_ascii_lowercase();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+fn generate_code(key: &[u8], step: i64) -> Option<String> {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).ok()?;
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        digest[offset] & 0x7f,
+        digest[offset + 1],
+        digest[offset + 2],
+        digest[offset + 3],
+    ]);
+
+    Some(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS),
+        width = DIGITS as usize
+    ))
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the RFC 6238 SHA1 key "12345678901234567890"
+    const RFC_SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn matches_rfc_6238_test_vectors() {
+        // the RFC lists 8 digit codes, 6 digit codes are their last 6 digits
+        let vectors = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1111111111, "050471"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+            (20000000000, "353130"),
+        ];
+
+        for (timestamp, code) in vectors {
+            let key = BASE32_NOPAD.decode(RFC_SECRET.as_bytes()).unwrap();
+            assert_eq!(
+                generate_code(&key, timestamp / STEP_SECS).as_deref(),
+                Some(code)
+            );
+            assert_eq!(
+                verify_code(RFC_SECRET, code, at(timestamp), None),
+                Some(timestamp / STEP_SECS)
+            );
+        }
+    }
+
+    #[test]
+    fn accepts_one_step_of_clock_drift() {
+        // 287082 is the code of step 1
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(30), None), Some(1));
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(0), None), Some(1));
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(60), None), Some(1));
+
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(90), None), None);
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(-30), None), None);
+    }
+
+    #[test]
+    fn rejects_replayed_steps() {
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(59), Some(1)), None);
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(59), Some(2)), None);
+        assert_eq!(verify_code(RFC_SECRET, "287082", at(59), Some(0)), Some(1));
+    }
+
+    #[test]
+    fn normalizes_and_validates_the_code() {
+        assert_eq!(verify_code(RFC_SECRET, " 287 082 ", at(59), None), Some(1));
+
+        assert_eq!(verify_code(RFC_SECRET, "28708", at(59), None), None);
+        assert_eq!(verify_code(RFC_SECRET, "94287082", at(59), None), None);
+        assert_eq!(verify_code(RFC_SECRET, "28708a", at(59), None), None);
+        assert_eq!(verify_code("not base32!", "287082", at(59), None), None);
+    }
+}
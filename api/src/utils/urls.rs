@@ -1,4 +1,5 @@
 use actix_web::HttpRequest;
+use reqwest::Url;
 
 use crate::errors::CustomError;
 
@@ -10,5 +11,19 @@ pub fn extract_query_params(req: HttpRequest, wanted_param: &str) -> Result<Stri
         .map(|param| param.split('=').collect::<Vec<&str>>()[1].to_string())
         .ok_or(CustomError::InternalServerError)?;
 
-    return Ok(code);
+    Ok(code)
+}
+
+// relative paths stay on our own frontend, absolute urls must match an allowed origin
+pub fn is_allowed_redirect(redirect_to: &str, allowed_origins: &[String]) -> bool {
+    if redirect_to.starts_with('/') {
+        return !redirect_to.starts_with("//") && !redirect_to.contains('\\');
+    }
+
+    match Url::parse(redirect_to) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") => {
+            allowed_origins.contains(&url.origin().ascii_serialization())
+        }
+        _ => false,
+    }
 }
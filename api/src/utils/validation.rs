@@ -0,0 +1,31 @@
+use regex::Regex;
+use validator::ValidationError;
+
+pub fn validate_email(email: &str) -> Result<(), ValidationError> {
+    let email_rgx = Regex::new(r"^[a-zA-Z0-9._%+-]+@[a-zA-Z0-9.-]+\.[a-zA-Z]{2,}$").unwrap();
+    if !email_rgx.is_match(email) {
+        return Err(ValidationError::new("email must be a valid email address"));
+    }
+    Ok(())
+}
+
+pub fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let username_rgx = Regex::new(r"^[a-zA-Z0-9_.-]+$").unwrap();
+    if !username_rgx.is_match(username) {
+        return Err(ValidationError::new(
+            "username may only contain letters, numbers, '.', '_' and '-'",
+        ));
+    }
+    Ok(())
+}
+
+// prefix of issue keys such as ENG-12, used for org identifiers and team keys
+pub fn validate_key_prefix(key: &str) -> Result<(), ValidationError> {
+    let key_rgx = Regex::new(r"^[A-Z][A-Z0-9]{1,9}$").unwrap();
+    if !key_rgx.is_match(key) {
+        return Err(ValidationError::new(
+            "key must be 2 to 10 uppercase letters or digits starting with a letter",
+        ));
+    }
+    Ok(())
+}
@@ -2,4 +2,5 @@ export const CLIENT_BASE_URL = 'http://localhost:8070/v1';
 export const SERVER_BASE_URL = 'http://localhost:8070/v1';
 export const ACCESS_TOKEN = 'access_token';
 export const REFRESH_TOKEN = 'refresh_token';
+export const TWO_FACTOR_CHALLENGE = 'two_factor_challenge';
 export const isProduction = true;
@@ -34,3 +34,13 @@ export const userLoginSchema = z.object({
 
 export type UserLoginSchema = typeof userLoginSchema;
 export type UserLoginSchemaPayload = z.infer<typeof userLoginSchema>;
+
+export const twoFactorLoginSchema = z.object({
+	code: z
+		.string()
+		.trim()
+		.min(6, 'Code must be at least 6 characters')
+		.max(16, 'Code must be at most 16 characters')
+});
+
+export type TwoFactorLoginSchema = typeof twoFactorLoginSchema;
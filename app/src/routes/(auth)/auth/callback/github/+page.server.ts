@@ -1,42 +1,68 @@
 import { redirect } from '@sveltejs/kit';
 import type { PageServerLoad } from './$types';
 import { api } from '$lib/api';
-import { ACCESS_TOKEN, REFRESH_TOKEN, isProduction } from '$lib/constants';
+import { ACCESS_TOKEN, REFRESH_TOKEN, TWO_FACTOR_CHALLENGE, isProduction } from '$lib/constants';
 
-type LoginResponse = {
+type GithubLoginResponse = {
 	access_token: string;
 	refresh_token: string;
 
-	expires_in_refresh: number;
-	expires_in_access: number;
+	// minutes
+	access_token_expiration: number;
+	refresh_token_expiration: number;
+};
+
+type TwoFactorChallengeResponse = {
+	two_factor_required: true;
+	challenge_token: string;
+
+	// seconds
+	expires_in: number;
 };
 
 export const load: PageServerLoad = async ({ url, cookies }) => {
 	const code = url.searchParams.get('code');
+	const state = url.searchParams.get('state');
 
-	if (!code) {
+	if (!code || !state) {
 		return {
 			redirect: '/sign-in'
 		};
 	}
 
-	const response = await api.get(`auth/oauth/github/login?code=${code}`).json<LoginResponse>();
+	const response = await api
+		.get('auth/oauth/github/login', { searchParams: { code, state } })
+		.json<GithubLoginResponse | TwoFactorChallengeResponse>();
+
+	if ('two_factor_required' in response) {
+		// lax, the redirect below still belongs to the navigation coming from github
+		cookies.set(TWO_FACTOR_CHALLENGE, response.challenge_token, {
+			httpOnly: true,
+			secure: isProduction,
+			sameSite: 'lax',
+			path: '/',
+			maxAge: response.expires_in
+		});
+
+		redirect(307, '/sign-in/two-factor');
+	}
 
-	const { access_token, refresh_token, expires_in_refresh, expires_in_access } = response;
+	const { access_token, refresh_token, access_token_expiration, refresh_token_expiration } =
+		response;
 
 	cookies.set(ACCESS_TOKEN, access_token, {
 		httpOnly: true,
 		secure: isProduction,
 		sameSite: 'strict',
 		path: '/',
-		maxAge: expires_in_access
+		maxAge: access_token_expiration * 60
 	});
 	cookies.set(REFRESH_TOKEN, refresh_token, {
 		httpOnly: true,
 		secure: isProduction,
 		sameSite: 'strict',
 		path: '/',
-		maxAge: expires_in_refresh
+		maxAge: refresh_token_expiration * 60
 	});
 
 	redirect(307, '/');
@@ -0,0 +1,85 @@
+import { fail, redirect } from '@sveltejs/kit';
+import { HTTPError } from 'ky';
+import { setError, superValidate } from 'sveltekit-superforms';
+import { zod } from 'sveltekit-superforms/adapters';
+import { twoFactorLoginSchema } from '$lib/validators/auth.validator.js';
+import { ACCESS_TOKEN, REFRESH_TOKEN, TWO_FACTOR_CHALLENGE, isProduction } from '$lib/constants.js';
+import { api } from '$lib/api.js';
+
+type LoginResponse = {
+	access_token: string;
+	refresh_token: string;
+
+	expires_in_refresh: number;
+	expires_in_access: number;
+};
+
+export async function load({ cookies }) {
+	if (!cookies.get(TWO_FACTOR_CHALLENGE)) {
+		redirect(307, '/sign-in');
+	}
+
+	const form = await superValidate(zod(twoFactorLoginSchema));
+
+	return {
+		form
+	};
+}
+
+export const actions = {
+	verify: async ({ request, cookies }) => {
+		const form = await superValidate(request, zod(twoFactorLoginSchema));
+
+		if (!form.valid) {
+			return fail(400, { form });
+		}
+
+		const challenge_token = cookies.get(TWO_FACTOR_CHALLENGE);
+		if (!challenge_token) {
+			redirect(307, '/sign-in');
+		}
+
+		try {
+			const res = await api
+				.post<LoginResponse>('auth/2fa/login', {
+					json: { challenge_token, code: form.data.code }
+				})
+				.json();
+
+			const { access_token, refresh_token, expires_in_refresh, expires_in_access } = res;
+
+			cookies.delete(TWO_FACTOR_CHALLENGE, { path: '/' });
+			cookies.set(ACCESS_TOKEN, access_token, {
+				httpOnly: true,
+				secure: isProduction,
+				sameSite: 'strict',
+				path: '/',
+				maxAge: expires_in_access
+			});
+			cookies.set(REFRESH_TOKEN, refresh_token, {
+				httpOnly: true,
+				secure: isProduction,
+				sameSite: 'strict',
+				path: '/',
+				maxAge: expires_in_refresh
+			});
+		} catch (error) {
+			if (error instanceof HTTPError) {
+				if (error.response.status === 400) {
+					return setError(form, 'code', 'Invalid code');
+				}
+				if (error.response.status === 429) {
+					return setError(form, 'code', 'Too many attempts. Please try again later');
+				}
+				// the challenge expired or was used up, the login has to start over
+				if (error.response.status === 401) {
+					cookies.delete(TWO_FACTOR_CHALLENGE, { path: '/' });
+					redirect(307, '/sign-in');
+				}
+			}
+			return fail(500, { form });
+		}
+
+		redirect(307, '/');
+	}
+};